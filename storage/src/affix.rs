@@ -1,6 +1,9 @@
 #![allow(clippy::cast_possible_wrap)]
 
-use core::{alloc::Layout, convert::TryFrom, marker::PhantomData, mem, num::NonZeroUsize, ptr::NonNull};
+use core::{
+    alloc::Layout, convert::TryFrom, hint::unreachable_unchecked, marker::PhantomData, mem, num::NonZeroUsize,
+    ptr::NonNull,
+};
 
 use crate::{
     AllocErr, Handle, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
@@ -27,9 +30,52 @@ impl<const SIZE: usize, const ALIGN: usize> LayoutProvider for ConstLayoutProvid
     const ALIGN: usize = ALIGN;
 }
 
+/// The default hooks of an [`AffixStorage`], which don't do anything
+#[derive(Debug, Clone, Copy)]
+pub struct NoHooks;
+
+/// Initializes the affixes of every new allocation from an [`AffixStorage`]
+///
+/// Hooks are not run for storages without any affixes
+pub trait AffixInit {
+    const ENABLED: bool = true;
+
+    /// # Safety
+    ///
+    /// `prefix` and `suffix` must point to the uninitialized affixes of a new allocation
+    unsafe fn init(prefix: NonNull<u8>, suffix: NonNull<u8>);
+}
+
+/// Cleans up the affixes of every allocation from an [`AffixStorage`] before it's deallocated
+///
+/// Hooks are not run for storages without any affixes
+pub trait AffixDrop {
+    const ENABLED: bool = true;
+
+    /// # Safety
+    ///
+    /// `prefix` and `suffix` must point to the affixes of an allocation that is about to be
+    /// deallocated, which were initialized by [`AffixInit::init`]
+    unsafe fn drop(prefix: NonNull<u8>, suffix: NonNull<u8>);
+}
+
+impl AffixInit for NoHooks {
+    const ENABLED: bool = false;
+
+    #[inline]
+    unsafe fn init(_: NonNull<u8>, _: NonNull<u8>) {}
+}
+
+impl AffixDrop for NoHooks {
+    const ENABLED: bool = false;
+
+    #[inline]
+    unsafe fn drop(_: NonNull<u8>, _: NonNull<u8>) {}
+}
+
 #[repr(transparent)]
-pub struct AffixStorage<Pre, Suf, S: ?Sized> {
-    __: PhantomData<CoVariant<(Pre, Suf)>>,
+pub struct AffixStorage<Pre, Suf, S: ?Sized, Hooks = NoHooks> {
+    __: PhantomData<CoVariant<(Pre, Suf, Hooks)>>,
     pub inner: S,
 }
 
@@ -54,7 +100,12 @@ pub unsafe fn split<Pre: LayoutProvider, Suf: LayoutProvider>(
 
 impl<Pre, Suf, S> AffixStorage<Pre, Suf, S> {
     #[inline]
-    pub const fn new(storage: S) -> Self {
+    pub const fn new(storage: S) -> Self { Self::with_hooks(storage) }
+}
+
+impl<Pre, Suf, S, Hooks> AffixStorage<Pre, Suf, S, Hooks> {
+    #[inline]
+    pub const fn with_hooks(storage: S) -> Self {
         Self {
             inner: storage,
             __: PhantomData,
@@ -62,7 +113,7 @@ impl<Pre, Suf, S> AffixStorage<Pre, Suf, S> {
     }
 }
 
-impl<Pre: LayoutProvider, Suf: LayoutProvider, S> AffixStorage<Pre, Suf, S> {
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S, Hooks> AffixStorage<Pre, Suf, S, Hooks> {
     const NO_AFFIX: bool = Pre::SIZE == 0 && Pre::ALIGN == 1 && Suf::SIZE == 0 && Suf::ALIGN == 1;
 
     #[inline]
@@ -78,6 +129,14 @@ impl<Pre: LayoutProvider, Suf: LayoutProvider, S> AffixStorage<Pre, Suf, S> {
         Some((layout, offset, suffix))
     }
 
+    #[inline]
+    fn surround_dynamic(layout: Layout, len: usize) -> Option<(Layout, usize, usize, usize)> {
+        let (layout, prefix, suffix) = Self::surround(layout)?;
+        let (layout, len_offset) = layout.extend(Layout::new::<usize>()).ok()?;
+        let layout = Layout::from_size_align(layout.size().checked_add(len)?, layout.align()).ok()?;
+        Some((layout, prefix, suffix, len_offset))
+    }
+
     unsafe fn surround_unchecked(layout: Layout) -> (Layout, usize, usize) {
         match Self::surround(layout) {
             Some(x) => x,
@@ -95,7 +154,7 @@ impl<Pre: LayoutProvider, Suf: LayoutProvider, S> AffixStorage<Pre, Suf, S> {
     }
 }
 
-impl<Pre, Suf, S> AffixStorage<TypedLayoutProvider<Pre>, TypedLayoutProvider<Suf>, S> {
+impl<Pre, Suf, S, Hooks> AffixStorage<TypedLayoutProvider<Pre>, TypedLayoutProvider<Suf>, S, Hooks> {
     /// # Safety
     ///
     /// `ptr` must be aquired from `Self::*get*`
@@ -107,8 +166,8 @@ impl<Pre, Suf, S> AffixStorage<TypedLayoutProvider<Pre>, TypedLayoutProvider<Suf
     }
 }
 
-impl<Pre: LayoutProvider, Suf: LayoutProvider, S: Copy> Copy for AffixStorage<Pre, Suf, S> {}
-impl<Pre: LayoutProvider, Suf: LayoutProvider, S: Clone> Clone for AffixStorage<Pre, Suf, S> {
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S: Copy, Hooks> Copy for AffixStorage<Pre, Suf, S, Hooks> {}
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S: Clone, Hooks> Clone for AffixStorage<Pre, Suf, S, Hooks> {
     #[inline]
     fn clone(&self) -> Self {
         Self {
@@ -152,13 +211,15 @@ pub unsafe trait SharedOffsetHandle: OffsetHandle + SharedStorage {
     unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle;
 }
 
-unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedGetMut + OffsetHandle> SharedGetMut
-    for AffixStorage<Pre, Suf, S>
+unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedGetMut + OffsetHandle, Hooks: AffixInit + AffixDrop>
+    SharedGetMut for AffixStorage<Pre, Suf, S, Hooks>
 {
     unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.inner.shared_get_mut(handle.inner) }
 }
 
-unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage for AffixStorage<Pre, Suf, S> {
+unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle, Hooks: AffixInit + AffixDrop> Storage
+    for AffixStorage<Pre, Suf, S, Hooks>
+{
     type Handle = AffixHandle<Pre, Suf, S::Handle>;
 
     unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.inner.get(handle.inner) }
@@ -166,14 +227,15 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
     unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.inner.get_mut(handle.inner) }
 
     fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
+        let (layout, prefix, suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
 
         let memory_block = self
             .inner
             .allocate_nonempty(unsafe { NonEmptyLayout::new_unchecked(layout) })?;
+        unsafe { self.init_affixes(memory_block.handle, suffix) };
 
         Ok(NonEmptyMemoryBlock {
-            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+            size: unsafe { NonZeroUsize::new_unchecked(suffix - prefix) },
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.offset(memory_block.handle, prefix as isize) },
@@ -182,15 +244,16 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
     }
 
     unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
-        let (layout, prefix, _suffix) = Self::surround_unchecked(layout.into());
+        let (layout, prefix, suffix) = Self::surround_unchecked(layout.into());
         let prefix = prefix as isize;
         let handle = self.inner.offset(handle.inner, -prefix);
+        self.drop_affixes(handle, suffix);
         self.inner
             .deallocate_nonempty(handle, NonEmptyLayout::new_unchecked(layout))
     }
 
     fn allocate(&mut self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
+        let (layout, prefix, suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
 
         let memory_block = if Self::NO_AFFIX {
             self.inner.allocate(layout)
@@ -200,9 +263,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
                 .map(Into::into)
         };
         let memory_block = memory_block?;
+        unsafe { self.init_affixes(memory_block.handle, suffix) };
 
         Ok(MemoryBlock {
-            size: layout.size(),
+            size: suffix - prefix,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.offset(memory_block.handle, prefix as isize) },
@@ -211,9 +275,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
     }
 
     unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
-        let (layout, prefix, _suffix) = Self::surround_unchecked(layout);
+        let (layout, prefix, suffix) = Self::surround_unchecked(layout);
         let prefix = prefix as isize;
         let handle = self.inner.offset(handle.inner, -prefix);
+        self.drop_affixes(handle, suffix);
         if Self::NO_AFFIX {
             self.inner.deallocate(handle, layout)
         } else {
@@ -226,14 +291,15 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
         &mut self,
         layout: NonEmptyLayout,
     ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
+        let (layout, prefix, suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
 
         let memory_block = self
             .inner
             .allocate_nonempty_zeroed(unsafe { NonEmptyLayout::new_unchecked(layout) })?;
+        unsafe { self.init_affixes(memory_block.handle, suffix) };
 
         Ok(NonEmptyMemoryBlock {
-            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+            size: unsafe { NonZeroUsize::new_unchecked(suffix - prefix) },
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.offset(memory_block.handle, prefix as isize) },
@@ -242,7 +308,7 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
     }
 
     fn allocate_zeroed(&mut self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
+        let (layout, prefix, suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
 
         let memory_block = if Self::NO_AFFIX {
             self.inner.allocate_zeroed(layout)
@@ -252,9 +318,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
                 .map(Into::into)
         };
         let memory_block = memory_block?;
+        unsafe { self.init_affixes(memory_block.handle, suffix) };
 
         Ok(MemoryBlock {
-            size: layout.size(),
+            size: suffix - prefix,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.offset(memory_block.handle, prefix as isize) },
@@ -263,8 +330,8 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle> Storage f
     }
 }
 
-unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + OffsetHandle> ResizableStorage
-    for AffixStorage<Pre, Suf, S>
+unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + OffsetHandle, Hooks: AffixInit + AffixDrop>
+    ResizableStorage for AffixStorage<Pre, Suf, S, Hooks>
 {
     unsafe fn grow(
         &mut self,
@@ -283,9 +350,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + Offs
         }
 
         let (new, new_pre, new_suf) = Self::surround(new).ok_or_else(|| AllocErr::new(new))?;
-        let (old, _old_pre, old_suf) = Self::surround_unchecked(old);
+        let (old, old_pre, old_suf) = Self::surround_unchecked(old);
+        let handle = self.inner.offset(handle.inner, -(old_pre as isize));
 
-        let memory_block = self.inner.grow(handle.inner, old, new)?;
+        let memory_block = self.inner.grow(handle, old, new)?;
 
         if Suf::SIZE != 0 {
             let ptr = self.inner.get_mut(memory_block.handle).as_ptr();
@@ -293,7 +361,7 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + Offs
         }
 
         Ok(MemoryBlock {
-            size: new.size(),
+            size: new_suf - new_pre,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: self.inner.offset(memory_block.handle, new_pre as isize),
@@ -321,19 +389,22 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + Offs
         }
 
         let (new, new_pre, new_suf) = Self::surround(new).ok_or_else(|| AllocErr::new(new))?;
-        let (old, _old_pre, old_suf) = Self::surround_unchecked(old);
+        let old_end = old.size();
+        let (old, old_pre, old_suf) = Self::surround_unchecked(old);
+        let old_end = old_pre + old_end;
+        let handle = self.inner.offset(handle.inner, -(old_pre as isize));
 
-        let memory_block = self.inner.grow_zeroed(handle.inner, old, new)?;
+        let memory_block = self.inner.grow_zeroed(handle, old, new)?;
 
+        let ptr = self.inner.get_mut(memory_block.handle).as_ptr();
         if Suf::SIZE != 0 {
-            let ptr = self.inner.get_mut(memory_block.handle).as_ptr();
             ptr.add(old_suf).copy_to(ptr.add(new_suf), Suf::SIZE);
-            let zero_count = Suf::SIZE.min(new_suf - old_suf);
-            ptr.add(old_suf).write_bytes(0, zero_count);
         }
+        // only the bytes past the old suffix were zeroed by the inner storage
+        ptr.add(old_end).write_bytes(0, old.size().min(new_suf) - old_end);
 
         Ok(MemoryBlock {
-            size: new.size(),
+            size: new_suf - new_pre,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: self.inner.offset(memory_block.handle, new_pre as isize),
@@ -360,18 +431,19 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + Offs
                 })
         }
 
-        let (old, _old_pre, old_suf) = Self::surround_unchecked(old);
+        let (old, old_pre, old_suf) = Self::surround_unchecked(old);
         let (new, new_pre, new_suf) = Self::surround_unchecked(new);
+        let handle = self.inner.offset(handle.inner, -(old_pre as isize));
 
         if Suf::SIZE != 0 {
-            let ptr = self.inner.get_mut(handle.inner).as_ptr();
+            let ptr = self.inner.get_mut(handle).as_ptr();
             ptr.add(old_suf).copy_to(ptr.add(new_suf), Suf::SIZE);
         }
 
-        let memory_block = self.inner.shrink(handle.inner, old, new)?;
+        let memory_block = self.inner.shrink(handle, old, new)?;
 
         Ok(MemoryBlock {
-            size: new.size(),
+            size: new_suf - new_pre,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: self.inner.offset(memory_block.handle, new_pre as isize),
@@ -380,18 +452,19 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + Offs
     }
 }
 
-unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> SharedStorage
-    for AffixStorage<Pre, Suf, S>
+unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle, Hooks: AffixInit + AffixDrop> SharedStorage
+    for AffixStorage<Pre, Suf, S, Hooks>
 {
     fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
+        let (layout, prefix, suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
 
         let memory_block = self
             .inner
             .shared_allocate_nonempty(unsafe { NonEmptyLayout::new_unchecked(layout) })?;
+        unsafe { self.shared_init_affixes(memory_block.handle, suffix) };
 
         Ok(NonEmptyMemoryBlock {
-            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+            size: unsafe { NonZeroUsize::new_unchecked(suffix - prefix) },
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.shared_offset(memory_block.handle, prefix as isize) },
@@ -400,15 +473,16 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
     }
 
     unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
-        let (layout, prefix, _suffix) = Self::surround_unchecked(layout.into());
+        let (layout, prefix, suffix) = Self::surround_unchecked(layout.into());
         let prefix = prefix as isize;
         let handle = self.inner.shared_offset(handle.inner, -prefix);
+        self.shared_drop_affixes(handle, suffix);
         self.inner
             .shared_deallocate_nonempty(handle, NonEmptyLayout::new_unchecked(layout))
     }
 
     fn shared_allocate(&self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
+        let (layout, prefix, suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
 
         let memory_block = if Self::NO_AFFIX {
             self.inner.shared_allocate(layout)
@@ -418,9 +492,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
                 .map(Into::into)
         };
         let memory_block = memory_block?;
+        unsafe { self.shared_init_affixes(memory_block.handle, suffix) };
 
         Ok(MemoryBlock {
-            size: layout.size(),
+            size: suffix - prefix,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.shared_offset(memory_block.handle, prefix as isize) },
@@ -429,9 +504,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
     }
 
     unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
-        let (layout, prefix, _suffix) = Self::surround_unchecked(layout);
+        let (layout, prefix, suffix) = Self::surround_unchecked(layout);
         let prefix = prefix as isize;
         let handle = self.inner.shared_offset(handle.inner, -prefix);
+        self.shared_drop_affixes(handle, suffix);
         if Self::NO_AFFIX {
             self.inner.shared_deallocate(handle, layout)
         } else {
@@ -444,14 +520,15 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
         &self,
         layout: NonEmptyLayout,
     ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
+        let (layout, prefix, suffix) = Self::surround(layout.into()).ok_or_else(|| AllocErr::new(layout.into()))?;
 
         let memory_block = self
             .inner
             .shared_allocate_nonempty_zeroed(unsafe { NonEmptyLayout::new_unchecked(layout) })?;
+        unsafe { self.shared_init_affixes(memory_block.handle, suffix) };
 
         Ok(NonEmptyMemoryBlock {
-            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+            size: unsafe { NonZeroUsize::new_unchecked(suffix - prefix) },
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.shared_offset(memory_block.handle, prefix as isize) },
@@ -460,7 +537,7 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
     }
 
     fn shared_allocate_zeroed(&self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
-        let (layout, prefix, _suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
+        let (layout, prefix, suffix) = Self::surround(layout).ok_or_else(|| AllocErr::new(layout))?;
 
         let memory_block = if Self::NO_AFFIX {
             self.inner.shared_allocate_zeroed(layout)
@@ -470,9 +547,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
                 .map(Into::into)
         };
         let memory_block = memory_block?;
+        unsafe { self.shared_init_affixes(memory_block.handle, suffix) };
 
         Ok(MemoryBlock {
-            size: layout.size(),
+            size: suffix - prefix,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: unsafe { self.inner.shared_offset(memory_block.handle, prefix as isize) },
@@ -481,8 +559,12 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle> Sha
     }
 }
 
-unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedResizableStorage + SharedOffsetHandle>
-    SharedResizableStorage for AffixStorage<Pre, Suf, S>
+unsafe impl<
+        Pre: LayoutProvider,
+        Suf: LayoutProvider,
+        S: SharedResizableStorage + SharedOffsetHandle,
+        Hooks: AffixInit + AffixDrop,
+    > SharedResizableStorage for AffixStorage<Pre, Suf, S, Hooks>
 {
     unsafe fn shared_grow(
         &self,
@@ -504,9 +586,10 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedResizableStorage
         }
 
         let (new, new_pre, new_suf) = Self::surround(new).ok_or_else(|| AllocErr::new(new))?;
-        let (old, _old_pre, old_suf) = Self::surround_unchecked(old);
+        let (old, old_pre, old_suf) = Self::surround_unchecked(old);
+        let handle = self.inner.shared_offset(handle.inner, -(old_pre as isize));
 
-        let memory_block = self.inner.shared_grow(handle.inner, old, new)?;
+        let memory_block = self.inner.shared_grow(handle, old, new)?;
 
         if Suf::SIZE != 0 {
             let ptr = self.inner.shared_get_mut(memory_block.handle).as_ptr();
@@ -514,7 +597,7 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedResizableStorage
         }
 
         Ok(MemoryBlock {
-            size: new.size(),
+            size: new_suf - new_pre,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: self.inner.shared_offset(memory_block.handle, new_pre as isize),
@@ -542,19 +625,22 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedResizableStorage
         }
 
         let (new, new_pre, new_suf) = Self::surround(new).ok_or_else(|| AllocErr::new(new))?;
-        let (old, _old_pre, old_suf) = Self::surround_unchecked(old);
+        let old_end = old.size();
+        let (old, old_pre, old_suf) = Self::surround_unchecked(old);
+        let old_end = old_pre + old_end;
+        let handle = self.inner.shared_offset(handle.inner, -(old_pre as isize));
 
-        let memory_block = self.inner.shared_grow_zeroed(handle.inner, old, new)?;
+        let memory_block = self.inner.shared_grow_zeroed(handle, old, new)?;
 
+        let ptr = self.inner.shared_get_mut(memory_block.handle).as_ptr();
         if Suf::SIZE != 0 {
-            let ptr = self.inner.shared_get_mut(memory_block.handle).as_ptr();
             ptr.add(old_suf).copy_to(ptr.add(new_suf), Suf::SIZE);
-            let zero_count = Suf::SIZE.min(new_suf - old_suf);
-            ptr.add(old_suf).write_bytes(0, zero_count);
         }
+        // only the bytes past the old suffix were zeroed by the inner storage
+        ptr.add(old_end).write_bytes(0, old.size().min(new_suf) - old_end);
 
         Ok(MemoryBlock {
-            size: new.size(),
+            size: new_suf - new_pre,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: self.inner.shared_offset(memory_block.handle, new_pre as isize),
@@ -581,18 +667,19 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedResizableStorage
                 })
         }
 
-        let (old, _old_pre, old_suf) = Self::surround_unchecked(old);
+        let (old, old_pre, old_suf) = Self::surround_unchecked(old);
         let (new, new_pre, new_suf) = Self::surround_unchecked(new);
+        let handle = self.inner.shared_offset(handle.inner, -(old_pre as isize));
 
         if Suf::SIZE != 0 {
-            let ptr = self.inner.shared_get_mut(handle.inner).as_ptr();
+            let ptr = self.inner.shared_get_mut(handle).as_ptr();
             ptr.add(old_suf).copy_to(ptr.add(new_suf), Suf::SIZE);
         }
 
-        let memory_block = self.inner.shared_shrink(handle.inner, old, new)?;
+        let memory_block = self.inner.shared_shrink(handle, old, new)?;
 
         Ok(MemoryBlock {
-            size: new.size(),
+            size: new_suf - new_pre,
             handle: AffixHandle {
                 __: PhantomData,
                 inner: self.inner.shared_offset(memory_block.handle, new_pre as isize),
@@ -600,3 +687,313 @@ unsafe impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedResizableStorage
         })
     }
 }
+
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle, Hooks: AffixInit + AffixDrop>
+    AffixStorage<Pre, Suf, S, Hooks>
+{
+    // `handle` points to the start of the allocation, and `suffix` is the offset of the suffix from there
+    #[inline]
+    unsafe fn init_affixes(&mut self, handle: S::Handle, suffix: usize) {
+        if <Hooks as AffixInit>::ENABLED && !Self::NO_AFFIX {
+            let ptr = self.inner.get_mut(handle);
+            Hooks::init(ptr, NonNull::new_unchecked(ptr.as_ptr().add(suffix)));
+        }
+    }
+
+    #[inline]
+    unsafe fn drop_affixes(&mut self, handle: S::Handle, suffix: usize) {
+        if <Hooks as AffixDrop>::ENABLED && !Self::NO_AFFIX {
+            let ptr = self.inner.get_mut(handle);
+            Hooks::drop(ptr, NonNull::new_unchecked(ptr.as_ptr().add(suffix)));
+        }
+    }
+}
+
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S: SharedOffsetHandle, Hooks: AffixInit + AffixDrop>
+    AffixStorage<Pre, Suf, S, Hooks>
+{
+    #[inline]
+    unsafe fn shared_init_affixes(&self, handle: S::Handle, suffix: usize) {
+        if <Hooks as AffixInit>::ENABLED && !Self::NO_AFFIX {
+            let ptr = self.inner.shared_get_mut(handle);
+            Hooks::init(ptr, NonNull::new_unchecked(ptr.as_ptr().add(suffix)));
+        }
+    }
+
+    #[inline]
+    unsafe fn shared_drop_affixes(&self, handle: S::Handle, suffix: usize) {
+        if <Hooks as AffixDrop>::ENABLED && !Self::NO_AFFIX {
+            let ptr = self.inner.shared_get_mut(handle);
+            Hooks::drop(ptr, NonNull::new_unchecked(ptr.as_ptr().add(suffix)));
+        }
+    }
+}
+
+// allocations with a dynamic suffix are laid out as
+// `[prefix][value][suffix][suffix length: usize][dynamic suffix: [u8; length]]`
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S: OffsetHandle, Hooks: AffixInit + AffixDrop>
+    AffixStorage<Pre, Suf, S, Hooks>
+{
+    // the full layout of an allocation with a dynamic suffix, and the offsets of its parts
+    unsafe fn dynamic_layout(&self, handle: S::Handle, layout: Layout) -> (Layout, usize, usize, usize) {
+        let (_, prefix, _, len_offset) = Self::surround_dynamic(layout, 0).unwrap_or_else(|| unreachable_unchecked());
+        let ptr = self.inner.get(handle).as_ptr().sub(prefix);
+        let len = ptr.add(len_offset).cast::<usize>().read_unaligned();
+        Self::surround_dynamic(layout, len).unwrap_or_else(|| unreachable_unchecked())
+    }
+
+    /// Allocate space for `layout`, followed by the affixes and a dynamic suffix of `len` bytes
+    ///
+    /// The allocation must be deallocated and resized with the `*_with_suffix` functions
+    pub fn allocate_with_suffix(
+        &mut self,
+        layout: Layout,
+        len: usize,
+    ) -> Result<MemoryBlock<AffixHandle<Pre, Suf, S::Handle>>, AllocErr> {
+        let (full, prefix, suffix, len_offset) =
+            Self::surround_dynamic(layout, len).ok_or_else(|| AllocErr::new(layout))?;
+
+        let memory_block = self
+            .inner
+            .allocate_nonempty(unsafe { NonEmptyLayout::new_unchecked(full) })?;
+
+        unsafe {
+            let ptr = self.inner.get_mut(memory_block.handle).as_ptr();
+            ptr.add(len_offset).cast::<usize>().write_unaligned(len);
+            self.init_affixes(memory_block.handle, suffix);
+
+            Ok(MemoryBlock {
+                size: suffix - prefix,
+                handle: AffixHandle {
+                    __: PhantomData,
+                    inner: self.inner.offset(memory_block.handle, prefix as isize),
+                },
+            })
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `handle` must have been allocated by `allocate_with_suffix` with `layout`
+    pub unsafe fn deallocate_with_suffix(&mut self, handle: AffixHandle<Pre, Suf, S::Handle>, layout: Layout) {
+        let (full, prefix, suffix, _) = self.dynamic_layout(handle.inner, layout);
+        let handle = self.inner.offset(handle.inner, -(prefix as isize));
+        self.drop_affixes(handle, suffix);
+        self.inner
+            .deallocate_nonempty(handle, NonEmptyLayout::new_unchecked(full));
+    }
+
+    /// Get the dynamic suffix of an allocation
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been allocated by `allocate_with_suffix` with `layout`
+    pub unsafe fn dynamic_suffix(&mut self, handle: AffixHandle<Pre, Suf, S::Handle>, layout: Layout) -> NonNull<[u8]> {
+        let (full, prefix, _, len_offset) = self.dynamic_layout(handle.inner, layout);
+        let ptr = self.inner.get_mut(handle.inner).as_ptr().sub(prefix);
+        let start = len_offset + mem::size_of::<usize>();
+        NonNull::slice_from_raw_parts(NonNull::new_unchecked(ptr.add(start)), full.size() - start)
+    }
+}
+
+impl<Pre: LayoutProvider, Suf: LayoutProvider, S: ResizableStorage + OffsetHandle, Hooks: AffixInit + AffixDrop>
+    AffixStorage<Pre, Suf, S, Hooks>
+{
+    /// Grow an allocation from `allocate_with_suffix`, moving its suffixes along with it
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been allocated by `allocate_with_suffix` with `old`,
+    /// and `new.size() >= old.size()`
+    pub unsafe fn grow_with_suffix(
+        &mut self,
+        handle: AffixHandle<Pre, Suf, S::Handle>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<AffixHandle<Pre, Suf, S::Handle>>, AllocErr> {
+        let (old_full, old_pre, old_suf, old_len) = self.dynamic_layout(handle.inner, old);
+        let tail = old_full.size() - old_len;
+        let (new_full, new_pre, new_suf, new_len) =
+            Self::surround_dynamic(new, tail - mem::size_of::<usize>()).ok_or_else(|| AllocErr::new(new))?;
+        let handle = self.inner.offset(handle.inner, -(old_pre as isize));
+
+        let memory_block = self.inner.grow(handle, old_full, new_full)?;
+
+        // everything moves to a higher address, so move the last part first
+        let ptr = self.inner.get_mut(memory_block.handle).as_ptr();
+        ptr.add(old_len).copy_to(ptr.add(new_len), tail);
+        ptr.add(old_suf).copy_to(ptr.add(new_suf), Suf::SIZE);
+
+        Ok(MemoryBlock {
+            size: new_suf - new_pre,
+            handle: AffixHandle {
+                __: PhantomData,
+                inner: self.inner.offset(memory_block.handle, new_pre as isize),
+            },
+        })
+    }
+
+    /// Shrink an allocation from `allocate_with_suffix`, moving its suffixes along with it
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been allocated by `allocate_with_suffix` with `old`,
+    /// and `new.size() <= old.size()`
+    pub unsafe fn shrink_with_suffix(
+        &mut self,
+        handle: AffixHandle<Pre, Suf, S::Handle>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<AffixHandle<Pre, Suf, S::Handle>>, AllocErr> {
+        let (old_full, old_pre, old_suf, old_len) = self.dynamic_layout(handle.inner, old);
+        let tail = old_full.size() - old_len;
+        let (new_full, new_pre, new_suf, new_len) =
+            Self::surround_dynamic(new, tail - mem::size_of::<usize>()).ok_or_else(|| AllocErr::new(new))?;
+        let handle = self.inner.offset(handle.inner, -(old_pre as isize));
+
+        // everything moves to a lower address, so move the first part first
+        let ptr = self.inner.get_mut(handle).as_ptr();
+        ptr.add(old_suf).copy_to(ptr.add(new_suf), Suf::SIZE);
+        ptr.add(old_len).copy_to(ptr.add(new_len), tail);
+
+        let memory_block = self.inner.shrink(handle, old_full, new_full)?;
+
+        Ok(MemoryBlock {
+            size: new_suf - new_pre,
+            handle: AffixHandle {
+                __: PhantomData,
+                inner: self.inner.offset(memory_block.handle, new_pre as isize),
+            },
+        })
+    }
+}
+
+#[test]
+fn dynamic_suffix() {
+    type Store<S> = AffixStorage<TypedLayoutProvider<u32>, TypedLayoutProvider<u16>, S>;
+
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = Store::new(&mock);
+
+    let old = Layout::new::<[u8; 3]>();
+    let new = Layout::new::<[u8; 64]>();
+
+    unsafe {
+        let memory_block = storage.allocate_with_suffix(old, 5).unwrap();
+        assert_eq!(storage.dynamic_suffix(memory_block.handle, old).len(), 5);
+        storage
+            .dynamic_suffix(memory_block.handle, old)
+            .cast::<u8>()
+            .as_ptr()
+            .copy_from(b"hello".as_ptr(), 5);
+        let ptr = storage.get_mut(memory_block.handle);
+        storage.split(ptr, old).1.as_ptr().write(0xbeef);
+
+        let memory_block = storage.grow_with_suffix(memory_block.handle, old, new).unwrap();
+        let ptr = storage.get(memory_block.handle);
+        assert_eq!(storage.split(ptr, new).1.as_ptr().read(), 0xbeef);
+        assert_eq!(storage.dynamic_suffix(memory_block.handle, new).as_ref(), b"hello");
+
+        let memory_block = storage.shrink_with_suffix(memory_block.handle, new, old).unwrap();
+        let ptr = storage.get(memory_block.handle);
+        assert_eq!(storage.split(ptr, old).1.as_ptr().read(), 0xbeef);
+        assert_eq!(storage.dynamic_suffix(memory_block.handle, old).as_ref(), b"hello");
+
+        storage.deallocate_with_suffix(memory_block.handle, old);
+    }
+
+    let (small, ..) = Store::<()>::surround_dynamic(old, 5).unwrap();
+    let (large, ..) = Store::<()>::surround_dynamic(new, 5).unwrap();
+    mock.assert_events(&[
+        crate::Event::Allocate(small),
+        crate::Event::Grow { old: small, new: large },
+        crate::Event::Shrink { old: large, new: small },
+        crate::Event::Deallocate(small),
+    ]);
+}
+
+#[test]
+fn affix_hooks() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+    struct Tag;
+
+    impl AffixInit for Tag {
+        unsafe fn init(prefix: NonNull<u8>, suffix: NonNull<u8>) {
+            prefix.cast::<u32>().as_ptr().write(0xdead_beef);
+            suffix.cast::<u16>().as_ptr().write_unaligned(0xbeef);
+            LIVE.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    impl AffixDrop for Tag {
+        unsafe fn drop(prefix: NonNull<u8>, suffix: NonNull<u8>) {
+            assert_eq!(prefix.cast::<u32>().as_ptr().read(), 0xdead_beef);
+            assert_eq!(suffix.cast::<u16>().as_ptr().read_unaligned(), 0xbeef);
+            LIVE.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut storage = AffixStorage::<TypedLayoutProvider<u32>, TypedLayoutProvider<u16>, _, Tag>::with_hooks(
+        crate::AllocatorStorage::new(std::alloc::System),
+    );
+
+    let layout = Layout::new::<[u8; 3]>();
+    let memory_block = storage.allocate(layout).unwrap();
+    assert_eq!(LIVE.load(Ordering::Relaxed), 1);
+
+    unsafe {
+        let ptr = storage.get(memory_block.handle);
+        assert_eq!(storage.split(ptr, layout).0.as_ptr().read(), 0xdead_beef);
+
+        // resizing moves the affixes without running the hooks again
+        let memory_block = storage
+            .grow(memory_block.handle, layout, Layout::new::<[u8; 32]>())
+            .unwrap();
+        assert_eq!(LIVE.load(Ordering::Relaxed), 1);
+        storage.deallocate(memory_block.handle, Layout::new::<[u8; 32]>());
+    }
+
+    assert_eq!(LIVE.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn affix_resize() {
+    type Affix =
+        AffixStorage<TypedLayoutProvider<u64>, TypedLayoutProvider<u32>, crate::AllocatorStorage<std::alloc::System>>;
+
+    let mut storage = Affix::new(crate::AllocatorStorage::new(std::alloc::System));
+    let small = Layout::from_size_align(16, 8).unwrap();
+    let large = Layout::from_size_align(56, 8).unwrap();
+
+    unsafe {
+        let memory_block = storage.allocate(small).unwrap();
+        // the size of the block doesn't include the prefix or suffix
+        assert_eq!(memory_block.size, 16);
+
+        // fill the block, the suffix, and the padding after it
+        let ptr = storage.get_mut(memory_block.handle);
+        ptr.as_ptr().write_bytes(0xff, 24);
+        let (prefix, suffix) = storage.split(ptr, small);
+        prefix.as_ptr().write(1);
+        suffix.as_ptr().write(2);
+
+        let memory_block = storage.grow_zeroed(memory_block.handle, small, large).unwrap();
+        assert_eq!(memory_block.size, 56);
+        let ptr = storage.get_mut(memory_block.handle);
+        let bytes = core::slice::from_raw_parts(ptr.as_ptr(), 56);
+        assert!(bytes[..16].iter().all(|&byte| byte == 0xff));
+        assert!(bytes[16..].iter().all(|&byte| byte == 0));
+        let (prefix, suffix) = storage.split(ptr, large);
+        assert_eq!((prefix.as_ptr().read(), suffix.as_ptr().read()), (1, 2));
+
+        let memory_block = storage.shrink(memory_block.handle, large, small).unwrap();
+        assert_eq!(memory_block.size, 16);
+        let ptr = storage.get_mut(memory_block.handle);
+        let (prefix, suffix) = storage.split(ptr, small);
+        assert_eq!((prefix.as_ptr().read(), suffix.as_ptr().read()), (1, 2));
+        storage.deallocate(memory_block.handle, small);
+    }
+}
@@ -0,0 +1,367 @@
+use core::{alloc::Layout, mem, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    AlignGuarantee, AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const ADJUSTMENT: usize = mem::size_of::<usize>();
+
+/// A storage that accepts allocations aligned to more than the `MAX_ALIGN` that the underlying
+/// storage supports
+///
+/// Over-aligned allocations take up to `align + size_of::<usize>() - 1` extra bytes, and are moved
+/// forward to the requested alignment with [`OffsetHandle`]. How far they were moved is stored right
+/// before the allocation, so that the underlying allocation can be found again.
+///
+/// The adjustment is computed from the address of the underlying allocation when the allocation
+/// is made, so the underlying storage must not move its memory while over-aligned allocations
+/// are live. For example, an inline storage must not be moved.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct AlignUp<S, const MAX_ALIGN: usize> {
+    storage: S,
+}
+
+impl<S, const MAX_ALIGN: usize> AlignUp<S, MAX_ALIGN> {
+    const MAX_ALIGN_POW2: usize = MAX_ALIGN.next_power_of_two();
+
+    pub const fn new(storage: S) -> Self { Self { storage } }
+
+    pub fn into_inner(self) -> S { self.storage }
+
+    // zero-sized allocations are handled by the underlying storage's dangling handles
+    const fn is_over_aligned(layout: Layout) -> bool { layout.size() != 0 && layout.align() > Self::MAX_ALIGN_POW2 }
+
+    const fn is_plain(old: Layout, new: Layout) -> bool { !Self::is_over_aligned(old) && !Self::is_over_aligned(new) }
+
+    // there must be room for the adjustment before the aligned allocation, and
+    // the underlying allocation is at least aligned to `MAX_ALIGN_POW2`
+    fn padded(layout: NonEmptyLayout) -> Result<NonEmptyLayout, AllocErr> {
+        layout
+            .size()
+            .checked_add(ADJUSTMENT + layout.align() - 1)
+            .and_then(|size| Layout::from_size_align(size, Self::MAX_ALIGN_POW2).ok())
+            .and_then(NonEmptyLayout::new)
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    // the number of bytes from `ptr` to the next address that is aligned to `align`,
+    // with room for the adjustment before it
+    fn adjustment(ptr: NonNull<u8>, align: usize) -> usize {
+        let addr = ptr.as_ptr() as usize;
+        ((addr + ADJUSTMENT + align - 1) & !(align - 1)) - addr
+    }
+}
+
+impl<S: OffsetHandle, const MAX_ALIGN: usize> AlignUp<S, MAX_ALIGN> {
+    unsafe fn aligned(&mut self, handle: S::Handle, layout: NonEmptyLayout) -> NonEmptyMemoryBlock<S::Handle> {
+        let ptr = self.storage.get_mut(handle);
+        let adjustment = Self::adjustment(ptr, layout.align());
+        ptr.as_ptr()
+            .add(adjustment - ADJUSTMENT)
+            .cast::<usize>()
+            .write_unaligned(adjustment);
+
+        NonEmptyMemoryBlock {
+            handle: self.storage.offset(handle, adjustment.cast_signed()),
+            size: NonZeroUsize::new_unchecked(layout.size()),
+        }
+    }
+
+    unsafe fn underlying(&mut self, handle: S::Handle) -> S::Handle {
+        let ptr = self.storage.get(handle);
+        let adjustment = ptr.as_ptr().sub(ADJUSTMENT).cast::<usize>().read_unaligned();
+        self.storage.offset(handle, -adjustment.cast_signed())
+    }
+}
+
+impl<S: SharedOffsetHandle, const MAX_ALIGN: usize> AlignUp<S, MAX_ALIGN> {
+    unsafe fn shared_aligned(&self, handle: S::Handle, layout: NonEmptyLayout) -> NonEmptyMemoryBlock<S::Handle> {
+        let ptr = self.storage.shared_get_mut(handle);
+        let adjustment = Self::adjustment(ptr, layout.align());
+        ptr.as_ptr()
+            .add(adjustment - ADJUSTMENT)
+            .cast::<usize>()
+            .write_unaligned(adjustment);
+
+        NonEmptyMemoryBlock {
+            handle: self.storage.shared_offset(handle, adjustment.cast_signed()),
+            size: NonZeroUsize::new_unchecked(layout.size()),
+        }
+    }
+
+    unsafe fn shared_underlying(&self, handle: S::Handle) -> S::Handle {
+        let ptr = self.storage.get(handle);
+        let adjustment = ptr.as_ptr().sub(ADJUSTMENT).cast::<usize>().read_unaligned();
+        self.storage.shared_offset(handle, -adjustment.cast_signed())
+    }
+}
+
+unsafe impl<S: FromPtr + OffsetHandle, const MAX_ALIGN: usize> FromPtr for AlignUp<S, MAX_ALIGN> {
+    // handles always point at the allocation itself, so the underlying storage can find them
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut + OffsetHandle, const MAX_ALIGN: usize> SharedGetMut for AlignUp<S, MAX_ALIGN> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage + OffsetHandle, const MAX_ALIGN: usize> MultiStorage for AlignUp<S, MAX_ALIGN> {}
+
+// over-aligned allocations are aligned to more than the underlying storage ever could be,
+// and everything else is just the underlying allocation
+unsafe impl<S: AlignGuarantee + OffsetHandle, const MAX_ALIGN: usize> AlignGuarantee for AlignUp<S, MAX_ALIGN> {
+    const ALIGN: usize = S::ALIGN;
+}
+
+unsafe impl<S: OffsetHandle, const MAX_ALIGN: usize> Storage for AlignUp<S, MAX_ALIGN> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !Self::is_over_aligned(layout.into()) {
+            return self.storage.allocate_nonempty(layout)
+        }
+
+        let memory_block = self.storage.allocate_nonempty(Self::padded(layout)?)?;
+        Ok(unsafe { self.aligned(memory_block.handle, layout) })
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        if !Self::is_over_aligned(layout.into()) {
+            return self.storage.deallocate_nonempty(handle, layout)
+        }
+
+        let handle = self.underlying(handle);
+        // this succeeded when the allocation was made
+        let padded = Self::padded(layout).unwrap_unchecked();
+        self.storage.deallocate_nonempty(handle, padded);
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match NonEmptyLayout::new(layout) {
+            Some(layout) if Self::is_over_aligned(layout.into()) => self.allocate_nonempty(layout).map(Into::into),
+            _ => self.storage.allocate(layout),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        match NonEmptyLayout::new(layout) {
+            Some(layout) if Self::is_over_aligned(layout.into()) => self.deallocate_nonempty(handle, layout),
+            _ => self.storage.deallocate(handle, layout),
+        }
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !Self::is_over_aligned(layout.into()) {
+            return self.storage.allocate_nonempty_zeroed(layout)
+        }
+
+        let memory_block = self.storage.allocate_nonempty_zeroed(Self::padded(layout)?)?;
+        Ok(unsafe { self.aligned(memory_block.handle, layout) })
+    }
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match NonEmptyLayout::new(layout) {
+            Some(layout) if Self::is_over_aligned(layout.into()) => {
+                self.allocate_nonempty_zeroed(layout).map(Into::into)
+            }
+            _ => self.storage.allocate_zeroed(layout),
+        }
+    }
+}
+
+unsafe impl<S: ResizableStorage + MultiStorage + OffsetHandle, const MAX_ALIGN: usize> ResizableStorage
+    for AlignUp<S, MAX_ALIGN>
+{
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.storage.grow(handle, old, new)
+        } else {
+            crate::defaults::grow(self, handle, old, new)
+        }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.storage.grow_zeroed(handle, old, new)
+        } else {
+            crate::defaults::grow_zeroed(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.storage.shrink(handle, old, new)
+        } else {
+            crate::defaults::shrink(self, handle, old, new)
+        }
+    }
+}
+
+unsafe impl<S: SharedOffsetHandle, const MAX_ALIGN: usize> SharedStorage for AlignUp<S, MAX_ALIGN> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !Self::is_over_aligned(layout.into()) {
+            return self.storage.shared_allocate_nonempty(layout)
+        }
+
+        let memory_block = self.storage.shared_allocate_nonempty(Self::padded(layout)?)?;
+        Ok(unsafe { self.shared_aligned(memory_block.handle, layout) })
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        if !Self::is_over_aligned(layout.into()) {
+            return self.storage.shared_deallocate_nonempty(handle, layout)
+        }
+
+        let handle = self.shared_underlying(handle);
+        // this succeeded when the allocation was made
+        let padded = Self::padded(layout).unwrap_unchecked();
+        self.storage.shared_deallocate_nonempty(handle, padded);
+    }
+
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match NonEmptyLayout::new(layout) {
+            Some(layout) if Self::is_over_aligned(layout.into()) => {
+                self.shared_allocate_nonempty(layout).map(Into::into)
+            }
+            _ => self.storage.shared_allocate(layout),
+        }
+    }
+
+    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
+        match NonEmptyLayout::new(layout) {
+            Some(layout) if Self::is_over_aligned(layout.into()) => self.shared_deallocate_nonempty(handle, layout),
+            _ => self.storage.shared_deallocate(handle, layout),
+        }
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !Self::is_over_aligned(layout.into()) {
+            return self.storage.shared_allocate_nonempty_zeroed(layout)
+        }
+
+        let memory_block = self.storage.shared_allocate_nonempty_zeroed(Self::padded(layout)?)?;
+        Ok(unsafe { self.shared_aligned(memory_block.handle, layout) })
+    }
+
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match NonEmptyLayout::new(layout) {
+            Some(layout) if Self::is_over_aligned(layout.into()) => {
+                self.shared_allocate_nonempty_zeroed(layout).map(Into::into)
+            }
+            _ => self.storage.shared_allocate_zeroed(layout),
+        }
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + MultiStorage + SharedOffsetHandle, const MAX_ALIGN: usize>
+    SharedResizableStorage for AlignUp<S, MAX_ALIGN>
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.storage.shared_grow(handle, old, new)
+        } else {
+            crate::defaults::grow(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.storage.shared_grow_zeroed(handle, old, new)
+        } else {
+            crate::defaults::grow_zeroed(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.storage.shared_shrink(handle, old, new)
+        } else {
+            crate::defaults::shrink(self, handle, old, new)
+        }
+    }
+}
+
+#[test]
+fn align_up() {
+    let layout = Layout::from_size_align(32, 256).unwrap();
+
+    let mut bump = crate::BumpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 1024);
+    assert!(bump.allocate(layout).is_err());
+
+    let mut storage = AlignUp::<_, 8>::new(bump);
+    let small = storage.allocate(Layout::new::<u8>()).unwrap();
+    let memory_block = storage.allocate(layout).unwrap();
+
+    unsafe {
+        let ptr = storage.get_mut(memory_block.handle);
+        assert_eq!(ptr.as_ptr() as usize % 256, 0);
+        ptr.as_ptr().write_bytes(0xab, 32);
+
+        let memory_block = storage
+            .grow(memory_block.handle, layout, Layout::from_size_align(64, 512).unwrap())
+            .unwrap();
+        let ptr = storage.get(memory_block.handle);
+        assert_eq!(ptr.as_ptr() as usize % 512, 0);
+        assert_eq!(ptr.cast::<[u8; 32]>().read(), [0xab; 32]);
+
+        storage.deallocate(memory_block.handle, Layout::from_size_align(64, 512).unwrap());
+        storage.deallocate(small.handle, Layout::new::<u8>());
+    }
+
+    crate::storage_conformance!(
+        AlignUp::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System)),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
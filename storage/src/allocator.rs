@@ -0,0 +1,226 @@
+use core::{
+    alloc::{Allocator, Layout},
+    num::NonZeroUsize,
+    ptr::NonNull,
+};
+
+use crate::{
+    AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+#[derive(Default, Debug, Clone, Copy)]
+#[must_use = "storages don't do anything unless they are used"]
+pub struct AllocatorStorage<A> {
+    pub allocator: A,
+}
+
+#[cfg(feature = "std")]
+pub type SystemStorage = AllocatorStorage<std::alloc::System>;
+
+#[cfg(feature = "alloc")]
+pub type AllocGlobalStorage = AllocatorStorage<alloc::alloc::Global>;
+
+impl<A> AllocatorStorage<A> {
+    #[inline]
+    pub const fn new(allocator: A) -> Self { Self { allocator } }
+}
+
+const fn memory_block(ptr: NonNull<[u8]>) -> MemoryBlock<NonNull<u8>> {
+    MemoryBlock {
+        handle: ptr.cast(),
+        size: ptr.len(),
+    }
+}
+
+const fn non_empty_memory_block(ptr: NonNull<[u8]>) -> NonEmptyMemoryBlock<NonNull<u8>> {
+    NonEmptyMemoryBlock {
+        handle: ptr.cast(),
+        size: unsafe { NonZeroUsize::new_unchecked(ptr.len()) },
+    }
+}
+
+unsafe impl<A: Allocator> FromPtr for AllocatorStorage<A> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl<A: Allocator> OffsetHandle for AllocatorStorage<A> {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl<A: Allocator> SharedOffsetHandle for AllocatorStorage<A> {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl<A: Allocator> SharedGetMut for AllocatorStorage<A> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl<A: Allocator> MultiStorage for AllocatorStorage<A> {}
+
+unsafe impl<A: Allocator> Storage for AllocatorStorage<A> {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.shared_deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<A: Allocator> ResizableStorage for AllocatorStorage<A> {
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_shrink(handle, old, new)
+    }
+}
+
+unsafe impl<A: Allocator> SharedStorage for AllocatorStorage<A> {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocator
+            .allocate(layout.into())
+            .map(non_empty_memory_block)
+            .map_err(|_| AllocErr::new(layout.into()))
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.allocator.deallocate(handle, layout.into());
+    }
+
+    #[inline]
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocator
+            .allocate_zeroed(layout.into())
+            .map(non_empty_memory_block)
+            .map_err(|_| AllocErr::new(layout.into()))
+    }
+}
+
+unsafe impl<A: Allocator> SharedResizableStorage for AllocatorStorage<A> {
+    #[inline]
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        // zero-sized handles are dangling, so they were never allocated by `A`
+        let ptr = if old.size() == 0 {
+            self.allocator.allocate(new)
+        } else {
+            self.allocator.grow(handle, old, new)
+        };
+
+        ptr.map(memory_block).map_err(|_| AllocErr::new(new))
+    }
+
+    #[inline]
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let ptr = if old.size() == 0 {
+            self.allocator.allocate_zeroed(new)
+        } else {
+            self.allocator.grow_zeroed(handle, old, new)
+        };
+
+        ptr.map(memory_block).map_err(|_| AllocErr::new(new))
+    }
+
+    #[inline]
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if new.size() == 0 {
+            self.shared_deallocate(handle, old);
+
+            return Ok(MemoryBlock {
+                handle: Handle::dangling(new.align()),
+                size: 0,
+            })
+        }
+
+        self.allocator
+            .shrink(handle, old, new)
+            .map(memory_block)
+            .map_err(|_| AllocErr::new(new))
+    }
+}
+
+#[test]
+fn allocator_storage() {
+    crate::storage_conformance!(
+        AllocatorStorage::new(std::alloc::System),
+        resizable,
+        shared,
+        shared_resizable
+    );
+    crate::storage_conformance!(
+        AllocatorStorage::new(&std::alloc::System),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -0,0 +1,180 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    ptr::NonNull,
+};
+
+use allocator_api2::alloc as api2;
+
+use crate::{AllocatorStorage, FromPtr, NonEmptyLayout, SharedResizableStorage};
+
+pub type AllocatorApi2Storage<A> = AllocatorStorage<Api2Compat<A>>;
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Api2Compat<A>(pub A);
+
+unsafe impl<A: api2::Allocator> Allocator for Api2Compat<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.allocate(layout).map_err(|_| AllocError)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.allocate_zeroed(layout).map_err(|_| AllocError)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) { self.0.deallocate(ptr, layout) }
+
+    #[inline]
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.grow(ptr, old, new).map_err(|_| AllocError)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.grow_zeroed(ptr, old, new).map_err(|_| AllocError)
+    }
+
+    #[inline]
+    unsafe fn shrink(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.shrink(ptr, old, new).map_err(|_| AllocError)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+#[must_use = "storages don't do anything unless they are used"]
+pub struct StorageAllocator<S> {
+    pub storage: S,
+}
+
+impl<S> StorageAllocator<S> {
+    #[inline]
+    pub const fn new(storage: S) -> Self { Self { storage } }
+}
+
+const fn dangling(layout: Layout) -> NonNull<[u8]> {
+    let ptr = unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+    NonNull::slice_from_raw_parts(ptr, 0)
+}
+
+impl<S: SharedResizableStorage + FromPtr> StorageAllocator<S> {
+    #[inline]
+    unsafe fn slice(&self, handle: S::Handle, size: usize) -> NonNull<[u8]> {
+        NonNull::slice_from_raw_parts(self.storage.shared_get_mut(handle), size)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + FromPtr> api2::Allocator for StorageAllocator<S> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, api2::AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout))
+        }
+
+        let layout = unsafe { NonEmptyLayout::new_unchecked(layout) };
+        self.storage
+            .shared_allocate_nonempty(layout)
+            .map(|memory_block| unsafe { self.slice(memory_block.handle, memory_block.size.get()) })
+            .map_err(|_| api2::AllocError)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, api2::AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout))
+        }
+
+        let layout = unsafe { NonEmptyLayout::new_unchecked(layout) };
+        self.storage
+            .shared_allocate_nonempty_zeroed(layout)
+            .map(|memory_block| unsafe { self.slice(memory_block.handle, memory_block.size.get()) })
+            .map_err(|_| api2::AllocError)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if let Some(layout) = NonEmptyLayout::new(layout) {
+            let handle = self.storage.from_ptr(ptr, layout.into());
+            self.storage.shared_deallocate_nonempty(handle, layout);
+        }
+    }
+
+    #[inline]
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Result<NonNull<[u8]>, api2::AllocError> {
+        if old.size() == 0 {
+            return self.allocate(new)
+        }
+
+        let handle = self.storage.from_ptr(ptr, old);
+        self.storage
+            .shared_grow(handle, old, new)
+            .map(|memory_block| self.slice(memory_block.handle, memory_block.size))
+            .map_err(|_| api2::AllocError)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<NonNull<[u8]>, api2::AllocError> {
+        if old.size() == 0 {
+            return self.allocate_zeroed(new)
+        }
+
+        let handle = self.storage.from_ptr(ptr, old);
+        self.storage
+            .shared_grow_zeroed(handle, old, new)
+            .map(|memory_block| self.slice(memory_block.handle, memory_block.size))
+            .map_err(|_| api2::AllocError)
+    }
+
+    #[inline]
+    unsafe fn shrink(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Result<NonNull<[u8]>, api2::AllocError> {
+        if new.size() == 0 {
+            self.deallocate(ptr, old);
+            return Ok(dangling(new))
+        }
+
+        let handle = self.storage.from_ptr(ptr, old);
+        self.storage
+            .shared_shrink(handle, old, new)
+            .map(|memory_block| self.slice(memory_block.handle, memory_block.size))
+            .map_err(|_| api2::AllocError)
+    }
+}
+
+#[test]
+fn storage_allocator() {
+    use api2::Allocator as _;
+
+    let allocator = StorageAllocator::new(crate::LeakCheck::new(crate::AllocatorStorage::new(std::alloc::System)));
+    let layout = Layout::new::<[u32; 4]>();
+    let grown = Layout::new::<[u32; 16]>();
+
+    unsafe {
+        let ptr = allocator.allocate(layout).unwrap();
+        assert!(ptr.len() >= layout.size());
+        ptr.cast::<u32>().as_ptr().write(7);
+        assert_eq!(allocator.storage.live_allocations(), 1);
+
+        let ptr = allocator.grow(ptr.cast(), layout, grown).unwrap();
+        assert!(ptr.len() >= grown.size());
+        assert_eq!(ptr.cast::<u32>().as_ptr().read(), 7);
+        assert_eq!(allocator.storage.live_allocations(), 1);
+
+        allocator.deallocate(ptr.cast(), grown);
+    }
+
+    assert!(allocator.storage.check());
+
+    // and back into a storage
+    crate::storage_conformance!(
+        AllocatorApi2Storage::new(Api2Compat(&allocator)),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -0,0 +1,372 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    slice,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const BITS: usize = usize::BITS as usize;
+
+#[must_use = "storages don't do anything unless they are used"]
+pub struct BitmapStorage<S: Storage> {
+    storage: S,
+    // [usize; words] followed by [block; count]
+    start: S::Handle,
+    region: Layout,
+    block: NonEmptyLayout,
+    blocks: usize,
+    count: usize,
+    // the word where the last block was found, scanning starts here
+    hint: AtomicUsize,
+}
+
+#[derive(Clone, Copy)]
+pub struct BitmapHandle(usize);
+
+unsafe impl Handle for BitmapHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl BitmapHandle {
+    #[must_use = "`BitmapHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+}
+
+impl<S: Storage> BitmapStorage<S> {
+    pub fn new(storage: S, block: NonEmptyLayout, count: usize) -> Self {
+        Self::try_new(storage, block, count).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// # Panics
+    ///
+    /// if the region for `count` blocks overflows
+    pub fn try_new(mut storage: S, block: NonEmptyLayout, count: usize) -> Result<Self, AllocErr> {
+        let block = block.pad_to_align();
+        let words = count.div_ceil(BITS);
+        let bitmap = Layout::array::<usize>(words).unwrap();
+        let (region, blocks) = bitmap.extend(Layout::from(block).repeat(count).unwrap().0).unwrap();
+        let memory_block = storage.allocate(region)?;
+
+        unsafe {
+            let bitmap: *mut usize = storage.get_mut(memory_block.handle).as_ptr().cast();
+            bitmap.write_bytes(0, words);
+
+            // the bits past the last block are always marked as allocated
+            if !count.is_multiple_of(BITS) {
+                bitmap.add(words - 1).write(!0 << (count % BITS));
+            }
+        }
+
+        Ok(Self {
+            storage,
+            start: memory_block.handle,
+            region,
+            block,
+            blocks,
+            count,
+            hint: AtomicUsize::new(0),
+        })
+    }
+
+    pub const fn block(&self) -> NonEmptyLayout { self.block }
+
+    pub const fn capacity(&self) -> usize { self.count }
+
+    const fn words(&self) -> usize { self.count.div_ceil(BITS) }
+
+    const fn fits(&self, layout: Layout) -> bool {
+        layout.size() <= self.block.size() && layout.align() <= self.block.align()
+    }
+
+    const fn offset_of(&self, index: usize) -> usize { self.blocks + index * self.block.size() }
+
+    const fn index_of(&self, offset: usize) -> usize { (offset - self.blocks) / self.block.size() }
+
+    const unsafe fn bitmap_mut<'a>(&self, base: NonNull<u8>) -> &'a mut [usize] {
+        slice::from_raw_parts_mut(base.as_ptr().cast(), self.words())
+    }
+
+    const unsafe fn bitmap<'a>(&self, base: NonNull<u8>) -> &'a [AtomicUsize] {
+        slice::from_raw_parts(base.as_ptr().cast(), self.words())
+    }
+
+    fn claim_mut(&mut self, bitmap: &mut [usize]) -> Option<usize> {
+        let hint = *self.hint.get_mut();
+
+        for index in (hint..bitmap.len()).chain(0..hint) {
+            let word = &mut bitmap[index];
+
+            if *word != !0 {
+                let bit = (!*word).trailing_zeros() as usize;
+                *word |= 1 << bit;
+                *self.hint.get_mut() = index;
+                return Some(index * BITS + bit)
+            }
+        }
+
+        None
+    }
+
+    fn claim(&self, bitmap: &[AtomicUsize]) -> Option<usize> {
+        let hint = self.hint.load(Ordering::Relaxed);
+
+        for index in (hint..bitmap.len()).chain(0..hint) {
+            let word = &bitmap[index];
+            let mut current = word.load(Ordering::Relaxed);
+
+            while current != !0 {
+                let bit = (!current).trailing_zeros() as usize;
+
+                match word.compare_exchange_weak(current, current | 1 << bit, Ordering::Acquire, Ordering::Relaxed) {
+                    Ok(_) => {
+                        self.hint.store(index, Ordering::Relaxed);
+                        return Some(index * BITS + bit)
+                    }
+                    Err(next) => current = next,
+                }
+            }
+        }
+
+        None
+    }
+
+    fn memory_block(
+        &self,
+        index: Option<usize>,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<BitmapHandle>, AllocErr> {
+        index
+            .map(|index| NonEmptyMemoryBlock {
+                handle: BitmapHandle(self.offset_of(index)),
+                size: unsafe { NonZeroUsize::new_unchecked(self.block.size()) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn resize<T: Storage<Handle = BitmapHandle>>(
+        storage: &mut T,
+        block: NonEmptyLayout,
+        handle: BitmapHandle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<BitmapHandle>, AllocErr> {
+        if new.size() > block.size() || new.align() > block.align() {
+            Err(AllocErr::new(new))
+        } else if old.size() == 0 {
+            storage.allocate(new)
+        } else if new.size() == 0 {
+            storage.deallocate(handle, old);
+            Ok(MemoryBlock {
+                handle: BitmapHandle::dangling(new.align()),
+                size: 0,
+            })
+        } else {
+            Ok(MemoryBlock {
+                handle,
+                size: block.size(),
+            })
+        }
+    }
+}
+
+impl<S: Storage> Drop for BitmapStorage<S> {
+    fn drop(&mut self) { unsafe { self.storage.deallocate(self.start, self.region) } }
+}
+
+unsafe impl<S: Storage> OffsetHandle for BitmapStorage<S> {
+    unsafe fn offset(&mut self, BitmapHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        BitmapHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedOffsetHandle for BitmapStorage<S> {
+    unsafe fn shared_offset(&self, BitmapHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        BitmapHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: Storage> FromPtr for BitmapStorage<S> {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        let origin = self.storage.get(self.start);
+        BitmapHandle(ptr.as_ptr().offset_from(origin.as_ptr()) as usize)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for BitmapStorage<S> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.shared_get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+}
+
+impl<S: SharedGetMut> MultiStorage for BitmapStorage<S> {}
+
+unsafe impl<S: Storage> Storage for BitmapStorage<S> {
+    type Handle = BitmapHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !self.fits(layout.into()) {
+            return Err(AllocErr::new(layout.into()))
+        }
+
+        let index = unsafe {
+            let base = self.storage.get_mut(self.start);
+            let bitmap = self.bitmap_mut(base);
+            self.claim_mut(bitmap)
+        };
+
+        self.memory_block(index, layout)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, BitmapHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let index = self.index_of(offset);
+        let base = self.storage.get_mut(self.start);
+        self.bitmap_mut(base)[index / BITS] &= !(1 << (index % BITS));
+    }
+}
+
+unsafe impl<S: Storage> ResizableStorage for BitmapStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(self, self.block, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = Self::resize(self, self.block, handle, old, new)?;
+        let ptr = self.get_mut(memory_block.handle).as_ptr();
+        ptr.add(old.size()).write_bytes(0, memory_block.size - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(self, self.block, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedStorage for BitmapStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !self.fits(layout.into()) {
+            return Err(AllocErr::new(layout.into()))
+        }
+
+        let index = unsafe {
+            let base = self.storage.shared_get_mut(self.start);
+            self.claim(self.bitmap(base))
+        };
+
+        self.memory_block(index, layout)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, BitmapHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let index = self.index_of(offset);
+        let base = self.storage.shared_get_mut(self.start);
+        self.bitmap(base)[index / BITS].fetch_and(!(1 << (index % BITS)), Ordering::Release);
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedResizableStorage for BitmapStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(&mut &*self, self.block, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = Self::resize(&mut &*self, self.block, handle, old, new)?;
+        let ptr = self.shared_get_mut(memory_block.handle).as_ptr();
+        ptr.add(old.size()).write_bytes(0, memory_block.size - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(&mut &*self, self.block, handle, old, new)
+    }
+}
+
+#[test]
+fn bitmap() {
+    let layout = Layout::new::<u64>();
+    let block = NonEmptyLayout::new(layout).unwrap();
+    let mut storage = BitmapStorage::new(crate::AllocatorStorage::new(std::alloc::System), block, 100);
+    assert_eq!(core::mem::size_of::<usize>() * storage.words(), storage.blocks);
+
+    let blocks = (0..100)
+        .map(|_| storage.allocate(layout).unwrap())
+        .collect::<std::vec::Vec<_>>();
+    assert!(storage.allocate(layout).is_err());
+    assert!(storage.allocate(Layout::new::<u128>()).is_err());
+
+    unsafe {
+        storage.deallocate(blocks[70].handle, layout);
+        let small = storage.shared_allocate(Layout::new::<u8>()).unwrap();
+        assert_eq!(small.handle.0, blocks[70].handle.0);
+
+        for memory_block in blocks {
+            storage.deallocate(memory_block.handle, layout);
+        }
+    }
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
@@ -1,13 +1,28 @@
-use crate::{scope_guard::ScopeGuard, AllocErr, ResizableStorage, Storage};
+use crate::{
+    affix::{OffsetHandle, TypedLayoutProvider},
+    scope_guard::ScopeGuard,
+    vec::Vec,
+    AffixStorage, AllocErr, ResizableStorage, Storage, TryCloneIn,
+};
 use core::{
     alloc::Layout,
+    borrow::{Borrow, BorrowMut},
+    cmp::Ordering,
     fmt,
+    future::Future,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
     marker::{PhantomData, Unsize},
     mem::{self, ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
+    pin::Pin,
     ptr::{self, NonNull, Pointee, Thin},
+    str,
+    task::{Context, Poll},
 };
 
+type HeaderStore<H, S> = AffixStorage<TypedLayoutProvider<H>, TypedLayoutProvider<()>, S>;
+
 pub struct Box<T: ?Sized + Pointee, S: Storage = crate::Global> {
     handle: S::Handle,
     storage: S,
@@ -20,12 +35,12 @@ impl<T: ?Sized + Pointee, S: Storage> Drop for Box<T, S> {
         unsafe {
             let handle = self.handle;
             let ptr = self.storage.get(handle);
-            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast::<u8>(), self.meta);
             let layout = Layout::for_value(&*ptr);
             let mut scope =
                 ScopeGuard::with_extra(&mut self.storage, move |storage| storage.deallocate(handle, layout));
             let ptr = scope.extra_mut().get_mut(handle);
-            let ptr = ptr::from_raw_parts_mut::<T>(ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts_mut::<T>(ptr.as_ptr().cast::<u8>(), self.meta);
             ptr.drop_in_place()
         }
     }
@@ -37,7 +52,7 @@ impl<T: ?Sized + Pointee, S: Storage> Deref for Box<T, S> {
     fn deref(&self) -> &Self::Target {
         unsafe {
             let ptr = self.storage.get(self.handle);
-            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast::<u8>(), self.meta);
             &*ptr
         }
     }
@@ -47,7 +62,7 @@ impl<T: ?Sized + Pointee, S: Storage> DerefMut for Box<T, S> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe {
             let ptr = self.storage.get_mut(self.handle);
-            let ptr = ptr::from_raw_parts_mut::<T>(ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts_mut::<T>(ptr.as_ptr().cast::<u8>(), self.meta);
             &mut *ptr
         }
     }
@@ -99,6 +114,92 @@ impl<T, S: Storage> Box<[T], S> {
             meta: memory_block.size / mem::size_of::<T>(),
         })
     }
+
+    pub fn into_vec(this: Self) -> Vec<T, S> { Vec::from(this) }
+}
+
+impl<T> Box<[T]> {
+    pub fn new_slice_with(len: usize, f: impl FnMut(usize) -> T) -> Self {
+        Self::new_slice_with_in(len, f, crate::Global)
+    }
+}
+
+impl<T, S: Storage> Box<[T], S> {
+    pub fn new_slice_with_in(len: usize, f: impl FnMut(usize) -> T, storage: S) -> Self {
+        Self::try_new_slice_with_in(len, f, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Creates a slice of `len` elements, where the element at `i` is `f(i)`
+    ///
+    /// The elements are written straight into the allocation. If `f` panics,
+    /// the elements that were already written are dropped, and the allocation is freed.
+    pub fn try_new_slice_with_in(len: usize, mut f: impl FnMut(usize) -> T, mut storage: S) -> Result<Self, AllocErr> {
+        let layout = Layout::array::<T>(len).map_err(|_| AllocErr::new(Layout::new::<T>()))?;
+        let memory_block = storage.allocate(layout)?;
+        let handle = memory_block.handle;
+
+        let mut guard = ScopeGuard::with_extra((0, storage), move |(len, mut storage): (usize, S)| unsafe {
+            let ptr = storage.get_mut(handle).as_ptr().cast::<T>();
+            ptr::slice_from_raw_parts_mut(ptr, len).drop_in_place();
+            storage.deallocate(handle, layout);
+        });
+
+        while guard.extra_mut().0 < len {
+            let (index, storage) = guard.extra_mut();
+            unsafe {
+                let ptr = storage.get_mut(handle).as_ptr().cast::<T>();
+                ptr.add(*index).write(f(*index));
+            }
+            *index += 1;
+        }
+
+        let (len, storage) = unsafe { ptr::read(guard.extra_mut()) };
+        mem::forget(guard);
+
+        Ok(Self {
+            handle,
+            storage,
+            meta: len,
+            __: PhantomData,
+        })
+    }
+
+    pub fn from_iter_in<I: IntoIterator<Item = T>>(iter: I, storage: S) -> Self
+    where
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::try_from_iter_in(iter, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Collects an iterator that knows its length into a slice
+    ///
+    /// # Panics
+    ///
+    /// if the iterator yields fewer elements than it said it would, extra elements are ignored
+    pub fn try_from_iter_in<I: IntoIterator<Item = T>>(iter: I, storage: S) -> Result<Self, AllocErr>
+    where
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut iter = iter.into_iter();
+        Self::try_new_slice_with_in(
+            iter.len(),
+            |_| {
+                iter.next()
+                    .unwrap_or_else(|| panic!("the iterator yielded fewer elements than its length"))
+            },
+            storage,
+        )
+    }
+}
+
+impl<T: Clone, S: Storage> Box<[T], S> {
+    pub fn from_slice_in(slice: &[T], storage: S) -> Self {
+        Self::try_from_slice_in(slice, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_from_slice_in(slice: &[T], storage: S) -> Result<Self, AllocErr> {
+        Self::try_new_slice_with_in(slice.len(), |index| slice[index].clone(), storage)
+    }
 }
 
 impl<T: Thin> Box<T> {
@@ -160,7 +261,7 @@ impl<T: ?Sized + Pointee, S: Storage> Box<T, S> {
     {
         unsafe {
             let ptr = self.storage.get(self.handle);
-            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast::<u8>(), self.meta);
             let ptr: *const U = ptr;
 
             let meta = ptr::metadata(ptr);
@@ -169,6 +270,19 @@ impl<T: ?Sized + Pointee, S: Storage> Box<T, S> {
         }
     }
 
+    pub const fn handle(this: &Self) -> S::Handle { this.handle }
+
+    pub const fn storage(this: &Self) -> &S { &this.storage }
+
+    /// Clones the value into a new allocation from a clone of this box's storage
+    pub fn try_clone(this: &Self) -> Result<Self, AllocErr>
+    where
+        Self: TryCloneIn<S, Output = Self>,
+        S: Clone,
+    {
+        this.try_clone_in(this.storage.clone())
+    }
+
     pub fn into_raw_parts(this: Self) -> (S::Handle, T::Metadata, S) {
         unsafe {
             let this = ManuallyDrop::new(this);
@@ -269,6 +383,535 @@ impl<T: Copy, S: ResizableStorage> Box<[T], S> {
     }
 }
 
+impl<S: Storage> Box<str, S> {
+    pub fn from_str_in(string: &str, storage: S) -> Self {
+        Self::try_from_str_in(string, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_from_str_in(string: &str, mut storage: S) -> Result<Self, AllocErr> {
+        let memory_block = storage.allocate(Layout::for_value(string))?;
+
+        unsafe {
+            let ptr = storage.get_mut(memory_block.handle);
+            ptr.as_ptr().copy_from_nonoverlapping(string.as_ptr(), string.len());
+            Ok(Self::from_raw_parts(memory_block.handle, string.len(), storage))
+        }
+    }
+
+    /// # Errors
+    ///
+    /// If `bytes` isn't valid UTF-8, it's returned along with the error
+    pub fn from_utf8(bytes: Box<[u8], S>) -> Result<Self, (Box<[u8], S>, str::Utf8Error)> {
+        match str::from_utf8(&bytes) {
+            Ok(_) => Ok(unsafe { Self::from_utf8_unchecked(bytes) }),
+            Err(err) => Err((bytes, err)),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `bytes` must be valid UTF-8
+    pub unsafe fn from_utf8_unchecked(bytes: Box<[u8], S>) -> Self {
+        let (handle, len, storage) = Box::into_raw_parts(bytes);
+        Self::from_raw_parts(handle, len, storage)
+    }
+
+    pub fn into_boxed_bytes(this: Self) -> Box<[u8], S> {
+        let (handle, len, storage) = Self::into_raw_parts(this);
+        unsafe { Box::from_raw_parts(handle, len, storage) }
+    }
+}
+
+impl<S: Storage> From<Box<str, S>> for Box<[u8], S> {
+    fn from(string: Box<str, S>) -> Self { Box::into_boxed_bytes(string) }
+}
+
+impl<S: Storage, S2: Storage> TryCloneIn<S2> for Box<str, S> {
+    type Output = Box<str, S2>;
+
+    fn try_clone_in(&self, storage: S2) -> Result<Self::Output, AllocErr> { Box::try_from_str_in(self, storage) }
+}
+
+impl<T: Clone, S: Storage, S2: Storage> TryCloneIn<S2> for Box<T, S> {
+    type Output = Box<T, S2>;
+
+    fn try_clone_in(&self, storage: S2) -> Result<Self::Output, AllocErr> {
+        Ok(Box::write(Box::try_uninit_in(storage)?, T::clone(self)))
+    }
+}
+
+impl<T: Clone, S: Storage, S2: Storage> TryCloneIn<S2> for Box<[T], S> {
+    type Output = Box<[T], S2>;
+
+    fn try_clone_in(&self, storage: S2) -> Result<Self::Output, AllocErr> { Box::try_from_slice_in(self, storage) }
+}
+
+impl<T: ?Sized + Pointee, S: Storage + Clone> Clone for Box<T, S>
+where
+    Self: TryCloneIn<S, Output = Self>,
+{
+    fn clone(&self) -> Self { Self::try_clone(self).unwrap_or_else(AllocErr::handle) }
+}
+
+impl<T: ?Sized + Pointee, S: Storage> AsRef<T> for Box<T, S> {
+    fn as_ref(&self) -> &T { self }
+}
+
+impl<T: ?Sized + Pointee, S: Storage> AsMut<T> for Box<T, S> {
+    fn as_mut(&mut self) -> &mut T { self }
+}
+
+impl<T: ?Sized + Pointee, S: Storage> Borrow<T> for Box<T, S> {
+    fn borrow(&self) -> &T { self }
+}
+
+impl<T: ?Sized + Pointee, S: Storage> BorrowMut<T> for Box<T, S> {
+    fn borrow_mut(&mut self) -> &mut T { self }
+}
+
+impl<T: ?Sized + Pointee + PartialEq, S: Storage, S2: Storage> PartialEq<Box<T, S2>> for Box<T, S> {
+    fn eq(&self, other: &Box<T, S2>) -> bool { T::eq(self, other) }
+}
+
+impl<T: ?Sized + Pointee + Eq, S: Storage> Eq for Box<T, S> {}
+
+impl<T: ?Sized + Pointee + PartialOrd, S: Storage, S2: Storage> PartialOrd<Box<T, S2>> for Box<T, S> {
+    fn partial_cmp(&self, other: &Box<T, S2>) -> Option<Ordering> { T::partial_cmp(self, other) }
+}
+
+impl<T: ?Sized + Pointee + Ord, S: Storage> Ord for Box<T, S> {
+    fn cmp(&self, other: &Self) -> Ordering { T::cmp(self, other) }
+}
+
+impl<T: ?Sized + Pointee + Hash, S: Storage> Hash for Box<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) { T::hash(self, state) }
+}
+
+impl<I: ?Sized + Pointee + Iterator, S: Storage> Iterator for Box<I, S> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> { I::next(self) }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { I::size_hint(self) }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> { I::nth(self, n) }
+}
+
+impl<I: ?Sized + Pointee + DoubleEndedIterator, S: Storage> DoubleEndedIterator for Box<I, S> {
+    fn next_back(&mut self) -> Option<Self::Item> { I::next_back(self) }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> { I::nth_back(self, n) }
+}
+
+impl<I: ?Sized + Pointee + ExactSizeIterator, S: Storage> ExactSizeIterator for Box<I, S> {
+    fn len(&self) -> usize { I::len(self) }
+}
+
+impl<I: ?Sized + Pointee + FusedIterator, S: Storage> FusedIterator for Box<I, S> {}
+
+// A storage may keep the value inline, so moving the box can move the value, and
+// `Box` can't be `Unpin` unconditionally like `alloc`'s box. Only `Unpin` futures
+// can be polled through it.
+impl<F: ?Sized + Pointee + Future + Unpin, S: Storage> Future for Box<F, S> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: only the future is pinned, and it's `Unpin`
+        let this = unsafe { self.get_unchecked_mut() };
+        F::poll(Pin::new(this), cx)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<R: ?Sized + Pointee + std::io::Read, S: Storage> std::io::Read for Box<R, S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> { R::read(self, buf) }
+
+    fn read_to_end(&mut self, buf: &mut std::vec::Vec<u8>) -> std::io::Result<usize> { R::read_to_end(self, buf) }
+
+    fn read_to_string(&mut self, buf: &mut std::string::String) -> std::io::Result<usize> {
+        R::read_to_string(self, buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> { R::read_exact(self, buf) }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<W: ?Sized + Pointee + std::io::Write, S: Storage> std::io::Write for Box<W, S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { W::write(self, buf) }
+
+    fn flush(&mut self) -> std::io::Result<()> { W::flush(self) }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> { W::write_all(self, buf) }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> std::io::Result<()> { W::write_fmt(self, args) }
+}
+
+// Moving an unsized closure out of the box to call it needs `alloc`'s box, which is
+// the only place the compiler lets an unsized value be moved out of.
+#[cfg(any(test, feature = "alloc"))]
+struct CallOnce<S: Storage> {
+    storage: S,
+    handle: S::Handle,
+    layout: Layout,
+}
+
+#[cfg(any(test, feature = "alloc"))]
+impl<S: Storage> Drop for CallOnce<S> {
+    fn drop(&mut self) { unsafe { self.storage.deallocate(self.handle, self.layout) } }
+}
+
+#[cfg(any(test, feature = "alloc"))]
+unsafe impl<S: Storage> core::alloc::Allocator for CallOnce<S> {
+    fn allocate(&self, _: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> { Err(core::alloc::AllocError) }
+
+    // the block is returned to the storage when the `CallOnce` is dropped
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {}
+}
+
+#[cfg(any(test, feature = "alloc"))]
+impl<Args: core::marker::Tuple, F: ?Sized + Pointee + FnOnce<Args>, S: Storage> FnOnce<Args> for Box<F, S> {
+    type Output = F::Output;
+
+    extern "rust-call" fn call_once(self, args: Args) -> Self::Output {
+        let (handle, meta, storage) = Self::into_raw_parts(self);
+        let mut owner = CallOnce {
+            storage,
+            handle,
+            layout: Layout::new::<()>(),
+        };
+
+        unsafe {
+            let ptr = owner.storage.get_mut(handle);
+            let ptr = ptr::from_raw_parts_mut::<F>(ptr.as_ptr().cast::<u8>(), meta);
+            owner.layout = Layout::for_value(&*ptr);
+            let value = alloc::boxed::Box::from_raw_in(ptr, &owner);
+            <alloc::boxed::Box<F, &CallOnce<S>> as FnOnce<Args>>::call_once(value, args)
+        }
+    }
+}
+
+// without `alloc` a closure can't be moved out of the box, so only closures that can be called
+// through a unique reference are callable by value. They are then dropped in place with the box.
+#[cfg(not(any(test, feature = "alloc")))]
+impl<Args: core::marker::Tuple, F: ?Sized + Pointee + FnMut<Args>, S: Storage> FnOnce<Args> for Box<F, S> {
+    type Output = F::Output;
+
+    extern "rust-call" fn call_once(mut self, args: Args) -> Self::Output { F::call_mut(&mut self, args) }
+}
+
+impl<Args: core::marker::Tuple, F: ?Sized + Pointee + FnMut<Args>, S: Storage> FnMut<Args> for Box<F, S> {
+    extern "rust-call" fn call_mut(&mut self, args: Args) -> Self::Output { F::call_mut(self, args) }
+}
+
+impl<Args: core::marker::Tuple, F: ?Sized + Pointee + Fn<Args>, S: Storage> Fn<Args> for Box<F, S> {
+    extern "rust-call" fn call(&self, args: Args) -> Self::Output { F::call(self, args) }
+}
+
 impl<T: fmt::Debug + ?Sized, S: Storage> fmt::Debug for Box<T, S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { T::fmt(self, f) }
 }
+
+/// A [`Box`] that keeps a header right before its value, in the same allocation
+pub struct BoxWithHeader<H, T: ?Sized + Pointee, S: Storage + OffsetHandle = crate::Global> {
+    inner: Box<T, HeaderStore<H, S>>,
+}
+
+impl<H, T: ?Sized + Pointee, S: Storage + OffsetHandle> Drop for BoxWithHeader<H, T, S> {
+    fn drop(&mut self) {
+        // the value and the allocation are cleaned up by `inner`, even if the header panics
+        unsafe { self.header_ptr().as_ptr().drop_in_place() }
+    }
+}
+
+impl<H, T: Thin> BoxWithHeader<H, T> {
+    pub fn new(header: H, value: T) -> Self { Self::new_in(header, value, crate::Global) }
+}
+
+impl<H, T: Thin, S: Storage + OffsetHandle> BoxWithHeader<H, T, S> {
+    pub fn new_in(header: H, value: T, storage: S) -> Self {
+        Self::try_new_in(header, value, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_new_in(header: H, value: T, storage: S) -> Result<Self, AllocErr> {
+        let inner = Box::try_new_in(value, AffixStorage::new(storage))?;
+        let this = ManuallyDrop::new(Self { inner });
+        unsafe {
+            this.header_ptr().as_ptr().write(header);
+            Ok(ManuallyDrop::into_inner(this))
+        }
+    }
+}
+
+impl<H, T: ?Sized + Pointee, S: Storage + OffsetHandle> BoxWithHeader<H, T, S> {
+    fn header_ptr(&self) -> NonNull<H> {
+        unsafe {
+            let ptr = self.inner.storage.get(self.inner.handle);
+            let value = ptr::from_raw_parts::<T>(ptr.as_ptr().cast::<u8>(), self.inner.meta);
+            let (header, _) = self.inner.storage.split(ptr, Layout::for_value_raw(value));
+            header
+        }
+    }
+
+    pub fn header(&self) -> &H { unsafe { &*self.header_ptr().as_ptr() } }
+
+    pub fn header_mut(&mut self) -> &mut H {
+        unsafe {
+            let ptr = self.inner.storage.get_mut(self.inner.handle);
+            let value = ptr::from_raw_parts::<T>(ptr.as_ptr().cast::<u8>(), self.inner.meta);
+            let (header, _) = self.inner.storage.split(ptr, Layout::for_value_raw(value));
+            &mut *header.as_ptr()
+        }
+    }
+
+    pub fn cast<U: ?Sized>(self) -> BoxWithHeader<H, U, S>
+    where
+        T: Unsize<U>,
+    {
+        let this = ManuallyDrop::new(self);
+        BoxWithHeader {
+            inner: unsafe { ptr::read(&raw const this.inner) }.cast(),
+        }
+    }
+}
+
+impl<H, T: ?Sized + Pointee, S: Storage + OffsetHandle> Deref for BoxWithHeader<H, T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target { &self.inner }
+}
+
+impl<H, T: ?Sized + Pointee, S: Storage + OffsetHandle> DerefMut for BoxWithHeader<H, T, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target { &mut self.inner }
+}
+
+impl<H: fmt::Debug, T: fmt::Debug + ?Sized, S: Storage + OffsetHandle> fmt::Debug for BoxWithHeader<H, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxWithHeader")
+            .field("header", self.header())
+            .field("value", &&**self)
+            .finish()
+    }
+}
+
+#[test]
+fn box_with_header() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut bx = BoxWithHeader::new_in(3_usize, [1_u16, 2, 3], &mock);
+    assert_eq!(*bx.header(), 3);
+    *bx.header_mut() += 1;
+    bx[0] = 10;
+
+    let bx: BoxWithHeader<usize, [u16], _> = bx.cast();
+    assert_eq!(*bx.header(), 4);
+    assert_eq!(*bx, [10, 2, 3]);
+    drop(bx);
+
+    let layout = Layout::new::<usize>().extend(Layout::new::<[u16; 3]>()).unwrap().0;
+    mock.assert_events(&[crate::Event::Allocate(layout), crate::Event::Deallocate(layout)]);
+}
+
+#[test]
+fn boxed_str() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let string = Box::from_str_in("hello", &mock);
+    assert_eq!(&*string, "hello");
+    assert_eq!(string.len(), 5);
+
+    let bytes = Box::<[u8], _>::from(string);
+    assert_eq!(*bytes, *b"hello");
+    let string = Box::from_utf8(bytes).unwrap();
+    assert_eq!(&*string.try_clone_in(&mock).unwrap(), "hello");
+
+    let (bytes, _) = Box::from_utf8(crate::boxed_slice_in![0xff_u8, 0xfe; &mock]).unwrap_err();
+    assert_eq!(*bytes, [0xff, 0xfe]);
+
+    let empty = Box::from_str_in("", &mock);
+    assert!(empty.is_empty());
+
+    drop((string, bytes, empty));
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn boxed_slice_constructors() {
+    use core::cell::Cell;
+
+    struct Counted<'a>(&'a Cell<usize>);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let squares = Box::new_slice_with_in(5, |i| i * i, &mock);
+    assert_eq!(*squares, [0, 1, 4, 9, 16]);
+    let copied = Box::from_slice_in(&squares[1..], &mock);
+    assert_eq!(*copied, [1, 4, 9, 16]);
+    let collected = Box::from_iter_in(squares.iter().rev().map(|x| x + 1), &mock);
+    assert_eq!(*collected, [17, 10, 5, 2, 1]);
+    let empty = Box::<[u8], _>::from_iter_in(None, &mock);
+    assert!(empty.is_empty());
+    drop((squares, copied, collected, empty));
+    assert_eq!(mock.live_allocations(), 0);
+
+    // a panic part of the way through drops what was written, and frees the allocation
+    let drops = Cell::new(0);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        Box::new_slice_with_in(
+            4,
+            |i| {
+                assert!(i != 3);
+                Counted(&drops)
+            },
+            &mock,
+        )
+    }));
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 3);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn boxed_clone() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let value = Box::new_in(std::string::String::from("hello"), &mock);
+    let slice = Box::from_slice_in(&[1, 2, 3], &mock);
+    let string = Box::<str, _>::from_str_in("world", &mock);
+
+    let (value2, slice2, string2) = (value.clone(), slice.clone(), string.clone());
+    assert_eq!(*value2, "hello");
+    assert_eq!(*slice2, [1, 2, 3]);
+    assert_eq!(&*string2, "world");
+    assert_ne!(Box::handle(&slice), Box::handle(&slice2));
+    assert_eq!(mock.live_allocations(), 6);
+    drop((value, slice, string));
+    drop((value2, slice2, string2));
+    assert_eq!(mock.live_allocations(), 0);
+
+    // a full bump storage can't make room for the clone
+    let bump = crate::BumpStorage::<_, 8>::new(&mock, 16);
+    let full = Box::new_in([0_u64; 2], &bump);
+    assert!(Box::try_clone(&full).is_err());
+}
+
+#[test]
+fn boxed_cmp() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let bump = crate::BumpStorage::<_, 8>::new(&mock, 64);
+
+    let a = Box::new_in(1, &mock);
+    let b = Box::new_in(2, &bump);
+    assert_ne!(a, b);
+    assert!(a < b);
+    assert_eq!(a, Box::new_in(1, &bump));
+    assert_eq!(Box::new_in(3, &mock).cmp(&Box::new_in(2, &mock)), Ordering::Greater);
+
+    // boxes can be looked up by their pointee
+    let mut map = crate::collections::BTreeMap::new_in(&mock);
+    map.insert(Box::<str, _>::from_str_in("b", &mock), 2);
+    map.insert(Box::<str, _>::from_str_in("a", &mock), 1);
+    assert_eq!(map.get("a"), Some(&1));
+    assert!(map.iter().map(|(key, _)| key.as_ref()).eq(["a", "b"]));
+
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let set: std::collections::HashSet<_> = [1, 2, 1].iter().map(|&x| Box::new_in(x, system)).collect();
+    assert!(set.contains(&2));
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn boxed_forwarding() {
+    use std::io::{Read, Write};
+
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut iter: Box<dyn DoubleEndedIterator<Item = i32>, _> = Box::new_in(1..5, &mock).cast();
+    assert_eq!(iter.next(), Some(1));
+    assert_eq!(iter.next_back(), Some(4));
+    assert!(iter.by_ref().eq([2, 3]));
+
+    let mut exact = Box::new_in([1, 2, 3].iter(), &mock);
+    assert_eq!(exact.len(), 3);
+    assert_eq!(exact.nth(1), Some(&2));
+
+    let mut future: Box<dyn Future<Output = i32> + Unpin, _> = Box::new_in(core::future::ready(7), &mock).cast();
+    let mut cx = Context::from_waker(core::task::Waker::noop());
+    assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(7));
+
+    let mut reader: Box<dyn Read, _> = Box::new_in(&b"hello"[..], &mock).cast();
+    let mut read = std::string::String::new();
+    reader.read_to_string(&mut read).unwrap();
+    assert_eq!(read, "hello");
+
+    let mut writer = Box::new_in(std::vec::Vec::new(), &mock);
+    write!(writer, "{}-{}", 1, 2).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(*writer, b"1-2");
+
+    drop((iter, exact, future, reader, writer));
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn boxed_fn() {
+    use core::cell::Cell;
+
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let bump = crate::BumpStorage::<_, 8>::new(&mock, 256);
+
+    let add: Box<dyn Fn(i32, i32) -> i32, _> = Box::new_in(|a, b| a + b, &bump).cast();
+    assert_eq!(add(1, 2), 3);
+
+    let mut count = 0;
+    let mut counter: Box<dyn FnMut() -> i32, _> = Box::new_in(
+        || {
+            count += 1;
+            count
+        },
+        &bump,
+    )
+    .cast();
+    assert_eq!(counter(), 1);
+    assert_eq!(counter(), 2);
+    drop(counter);
+
+    // calling a boxed `FnOnce` consumes the closure, and gives the block back to the storage
+    let dropped = Cell::new(false);
+    let guard = ScopeGuard::new(|| dropped.set(true));
+    let once: Box<dyn FnOnce() -> usize, _> = Box::new_in(
+        move || {
+            let _guard = guard;
+            7
+        },
+        &mock,
+    )
+    .cast();
+    assert_eq!(mock.live_allocations(), 2);
+    assert_eq!(once(), 7);
+    assert!(dropped.get());
+    assert_eq!(mock.live_allocations(), 1);
+    drop(add);
+    drop(bump);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn boxed_fn_mut_on_stack() {
+    let mut total = 0;
+    let mut add: Box<dyn FnMut(i32) -> i32, _> = Box::new_in(
+        |x| {
+            total += x;
+            total
+        },
+        crate::SingleStackStorage::<[usize; 1]>::new(),
+    )
+    .cast();
+    assert_eq!(add(1), 1);
+    assert_eq!(add(2), 3);
+    assert_eq!(add(3), 6);
+    drop(add);
+    assert_eq!(total, 6);
+}
@@ -1,28 +1,84 @@
 use core::{
     alloc::Layout,
+    mem::ManuallyDrop,
     num::NonZeroUsize,
-    ptr::NonNull,
+    ptr::{self, NonNull},
     sync::atomic::{AtomicUsize, Ordering},
 };
 
 use crate::{
-    AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
-    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+    scope_guard::ScopeGuard, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 
 #[must_use = "storages don't do anything unless they are used"]
 pub struct BumpStorage<S: Storage, const MAX_ALIGN: usize> {
     storage: S,
     start: S::Handle,
+    // the size of the region, as returned by the underlying storage
+    capacity: usize,
     offset: AtomicUsize,
 }
 
+/// A point in a [`BumpStorage`]'s history, see [`BumpStorage::checkpoint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker(pub(crate) usize);
+
 impl<S: Storage, const MAX_ALIGN: usize> BumpStorage<S, MAX_ALIGN> {
-    pub unsafe fn reset(&mut self, max_offset: usize) { *self.offset.get_mut() = max_offset; }
+    /// Frees everything that was allocated from this storage
+    ///
+    /// # Safety
+    ///
+    /// handles to allocations made before the reset must not be used
+    pub unsafe fn reset(&mut self) { *self.offset.get_mut() = self.capacity; }
+
+    /// Records the current position, so that everything allocated after this
+    /// can be freed at once with [`BumpStorage::reset_to`]
+    pub fn checkpoint(&self) -> Marker { Marker(self.offset.load(Ordering::Relaxed)) }
+
+    /// Frees everything that was allocated after `marker` was created
+    ///
+    /// # Safety
+    ///
+    /// * `marker` must have been created by this storage
+    /// * the storage must not have been rewound past `marker` since it was created
+    /// * handles to allocations made after `marker` was created must not be used
+    pub unsafe fn reset_to(&mut self, Marker(offset): Marker) {
+        debug_assert!(
+            offset >= *self.offset.get_mut(),
+            "tried to reset to a marker from the future"
+        );
+        *self.offset.get_mut() = offset;
+    }
 
-    pub unsafe fn shared_reset_if_eq(&self, current_offset: usize, max_offset: usize) -> bool {
+    /// Frees everything that was allocated after `marker` was created, this is the same as [`BumpStorage::reset_to`]
+    ///
+    /// # Safety
+    ///
+    /// see [`BumpStorage::reset_to`]
+    pub unsafe fn rewind(&mut self, marker: Marker) { self.reset_to(marker) }
+
+    /// Runs `f` with a scope that allocates from this storage,
+    /// everything allocated in the scope is freed once `f` returns
+    pub fn scope<R>(&mut self, f: impl FnOnce(&mut BumpScope<'_, S, MAX_ALIGN>) -> R) -> R {
+        let marker = self.checkpoint();
+        // nothing allocated in the scope can outlive it, so it's always safe to reset
+        let mut guard = ScopeGuard::with_extra(self, move |bump| unsafe { bump.reset_to(marker) });
+        f(&mut BumpScope {
+            bump: guard.extra_mut(),
+        })
+    }
+
+    /// Frees everything that was allocated from this storage, but only if nothing
+    /// was allocated since `marker` was created, returns true if the storage was reset
+    ///
+    /// # Safety
+    ///
+    /// * `marker` must have been created by this storage
+    /// * handles to allocations made before the reset must not be used
+    pub unsafe fn shared_reset_if_eq(&self, Marker(offset): Marker) -> bool {
         self.offset
-            .compare_exchange(current_offset, max_offset, Ordering::SeqCst, Ordering::Relaxed)
+            .compare_exchange(offset, self.capacity, Ordering::SeqCst, Ordering::Relaxed)
             .is_ok()
     }
 }
@@ -32,7 +88,23 @@ impl<S: Storage, const MAX_ALIGN: usize> BumpStorage<S, MAX_ALIGN> {
 
     pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
 
-    pub fn remaining_space(&self) -> usize { self.offset.load(Ordering::Relaxed) }
+    /// The size of the region, this may be more than the space that was asked for
+    pub const fn capacity(&self) -> usize { self.capacity }
+
+    /// The number of bytes that have been allocated from this storage,
+    /// including any padding that was needed for alignment
+    ///
+    /// While the storage is shared this is only a snapshot, other threads may allocate at any time
+    pub fn used(&self) -> usize { self.capacity - self.remaining() }
+
+    /// The number of bytes that can still be allocated, before any padding for alignment,
+    /// this is always `capacity() - used()`
+    ///
+    /// While the storage is shared this is only a snapshot, other threads may allocate at any time
+    pub fn remaining(&self) -> usize { self.offset.load(Ordering::Relaxed) }
+
+    #[deprecated = "use `remaining` instead"]
+    pub fn remaining_space(&self) -> usize { self.remaining() }
 
     /// # Panics
     ///
@@ -41,14 +113,74 @@ impl<S: Storage, const MAX_ALIGN: usize> BumpStorage<S, MAX_ALIGN> {
         let memory_block = storage.allocate(Layout::from_size_align(space, Self::MAX_ALIGN_POW2).unwrap())?;
         Ok(Self {
             start: memory_block.handle,
+            capacity: memory_block.size,
             offset: AtomicUsize::new(memory_block.size),
             storage,
         })
     }
+
+    /// Hands the region back to the underlying storage, and returns the underlying storage
+    pub fn into_inner(self) -> S {
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            this.free_region();
+            ptr::read(ptr::addr_of!(this.storage))
+        }
+    }
+
+    unsafe fn free_region(&mut self) {
+        let layout = Layout::from_size_align_unchecked(self.capacity, Self::MAX_ALIGN_POW2);
+        self.storage.deallocate(self.start, layout);
+    }
+}
+
+impl<S: Storage, const MAX_ALIGN: usize> Drop for BumpStorage<S, MAX_ALIGN> {
+    fn drop(&mut self) { unsafe { self.free_region() } }
+}
+
+impl<S: SharedGetMut, const MAX_ALIGN: usize> BumpStorage<S, MAX_ALIGN> {
+    // the most recent allocation starts at the offset, so it can take the space below it,
+    // but since allocations grow downwards its contents have to be moved to the new start
+    unsafe fn grow_in_place(
+        &self,
+        BumpHandle(start): BumpHandle,
+        old: Layout,
+        new: Layout,
+    ) -> Option<MemoryBlock<BumpHandle>> {
+        if Self::MAX_ALIGN_POW2 < new.align() {
+            return None
+        }
+
+        let end = start.checked_add(old.size())?;
+        let new_start = end.checked_sub(new.size())? & !(new.align() - 1);
+        self.offset
+            .compare_exchange(start, new_start, Ordering::AcqRel, Ordering::Relaxed)
+            .ok()?;
+
+        let origin = self.storage.shared_get_mut(self.start).as_ptr();
+        ptr::copy(origin.add(start), origin.add(new_start), old.size());
+
+        Some(MemoryBlock {
+            handle: BumpHandle(new_start),
+            size: end - new_start,
+        })
+    }
+
+    // only the most recent allocation can be given back
+    fn deallocate_last(&self, BumpHandle(start): BumpHandle, layout: NonEmptyLayout) {
+        let _ = self
+            .offset
+            .compare_exchange(start, start + layout.size(), Ordering::AcqRel, Ordering::Relaxed);
+    }
+}
+
+/// A temporary view of a [`BumpStorage`], see [`BumpStorage::scope`]
+pub struct BumpScope<'a, S: Storage, const MAX_ALIGN: usize> {
+    bump: &'a mut BumpStorage<S, MAX_ALIGN>,
 }
 
 #[derive(Clone, Copy)]
-pub struct BumpHandle(usize);
+pub struct BumpHandle(pub(crate) usize);
 
 unsafe impl Handle for BumpHandle {
     unsafe fn dangling(_: usize) -> Self { Self(usize::MAX) }
@@ -113,8 +245,8 @@ unsafe impl<S: Storage, const MAX_ALIGN: usize> Storage for BumpStorage<S, MAX_A
         // between allocation and getting the pointer, otherwise
         // we would have to allocate more space than necessary
         // and offset the pointer each time to the correct alignment
-        // but this is more expensive, and could be layered on top
-        // if necessary
+        // but this is more expensive, and is layered on top
+        // by `OverAlignedBumpStorage`
         if Self::MAX_ALIGN_POW2 < layout.align() {
             return Err(AllocErr::new(layout))
         }
@@ -133,7 +265,12 @@ unsafe impl<S: Storage, const MAX_ALIGN: usize> Storage for BumpStorage<S, MAX_A
         })
     }
 
-    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, _: NonEmptyLayout) {}
+    unsafe fn deallocate_nonempty(&mut self, BumpHandle(start): Self::Handle, layout: NonEmptyLayout) {
+        let offset = self.offset.get_mut();
+        if *offset == start {
+            *offset += layout.size();
+        }
+    }
 }
 
 unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> ResizableStorage for BumpStorage<S, MAX_ALIGN> {
@@ -148,6 +285,8 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> ResizableStorage for BumpSt
                 size: old.size(),
                 handle,
             })
+        } else if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            Ok(memory_block)
         } else {
             crate::defaults::grow(self, handle, old, new)
         }
@@ -164,6 +303,10 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> ResizableStorage for BumpSt
                 size: old.size(),
                 handle,
             })
+        } else if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            let ptr = self.shared_get_mut(memory_block.handle).as_ptr();
+            ptr.add(old.size()).write_bytes(0, memory_block.size - old.size());
+            Ok(memory_block)
         } else {
             crate::defaults::grow_zeroed(self, handle, old, new)
         }
@@ -194,8 +337,8 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedStorage for BumpStora
         // between allocation and getting the pointer, otherwise
         // we would have to allocate more space than necessary
         // and offset the pointer each time to the correct alignment
-        // but this is more expensive, and could be layered on top
-        // if necessary
+        // but this is more expensive, and is layered on top
+        // by `OverAlignedBumpStorage`
         if Self::MAX_ALIGN_POW2 < layout.align() {
             return Err(AllocErr::new(layout))
         }
@@ -223,7 +366,9 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedStorage for BumpStora
         })
     }
 
-    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, _: NonEmptyLayout) {}
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.deallocate_last(handle, layout);
+    }
 }
 
 unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for BumpStorage<S, MAX_ALIGN> {
@@ -238,6 +383,8 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for
                 size: old.size(),
                 handle,
             })
+        } else if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            Ok(memory_block)
         } else {
             crate::defaults::grow(self, handle, old, new)
         }
@@ -254,6 +401,10 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for
                 size: old.size(),
                 handle,
             })
+        } else if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            let ptr = self.shared_get_mut(memory_block.handle).as_ptr();
+            ptr.add(old.size()).write_bytes(0, memory_block.size - old.size());
+            Ok(memory_block)
         } else {
             crate::defaults::grow_zeroed(self, handle, old, new)
         }
@@ -275,3 +426,206 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for
         }
     }
 }
+
+impl<S: Storage, const MAX_ALIGN: usize> BumpScope<'_, S, MAX_ALIGN> {
+    /// Runs `f` with a nested scope, everything allocated in it is freed once `f` returns
+    pub fn scope<R>(&mut self, f: impl FnOnce(&mut BumpScope<'_, S, MAX_ALIGN>) -> R) -> R { self.bump.scope(f) }
+
+    pub fn remaining(&self) -> usize { self.bump.remaining() }
+}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> OffsetHandle for BumpScope<'_, S, MAX_ALIGN> {
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        self.bump.offset(handle, offset)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedOffsetHandle for BumpScope<'_, S, MAX_ALIGN> {
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        self.bump.shared_offset(handle, offset)
+    }
+}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> FromPtr for BumpScope<'_, S, MAX_ALIGN> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.bump.from_ptr(ptr, layout) }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedGetMut for BumpScope<'_, S, MAX_ALIGN> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.bump.shared_get_mut(handle) }
+}
+
+impl<S: SharedGetMut, const MAX_ALIGN: usize> MultiStorage for BumpScope<'_, S, MAX_ALIGN> {}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> Storage for BumpScope<'_, S, MAX_ALIGN> {
+    type Handle = BumpHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.bump.get(handle) }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.bump.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.allocate_nonempty(layout)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.bump.deallocate_nonempty(handle, layout);
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> ResizableStorage for BumpScope<'_, S, MAX_ALIGN> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.grow(handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.grow_zeroed(handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.shrink(handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedStorage for BumpScope<'_, S, MAX_ALIGN> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.shared_allocate_nonempty(layout)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.bump.shared_deallocate_nonempty(handle, layout);
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for BumpScope<'_, S, MAX_ALIGN> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.shared_grow(handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.shared_grow_zeroed(handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.bump.shared_shrink(handle, old, new)
+    }
+}
+
+#[test]
+fn bump_drop() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let region = Layout::from_size_align(64, 8).unwrap();
+
+    let storage = BumpStorage::<_, 8>::new(&mock, 64);
+    drop(storage);
+
+    let storage = BumpStorage::<_, 8>::new(&mock, 64);
+    let _ = storage.into_inner();
+
+    mock.assert_events(&[
+        crate::Event::Allocate(region),
+        crate::Event::Deallocate(region),
+        crate::Event::Allocate(region),
+        crate::Event::Deallocate(region),
+    ]);
+}
+
+#[test]
+fn bump_reuse_last() {
+    let mut storage = BumpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 64);
+    let a = storage.allocate(Layout::new::<u64>()).unwrap();
+    let remaining = storage.remaining();
+
+    unsafe {
+        let b = storage.allocate(Layout::new::<[u64; 2]>()).unwrap();
+        storage.get_mut(b.handle).cast::<[u64; 2]>().as_ptr().write([1, 2]);
+
+        // the last allocation grows without leaving its old block behind
+        let b = storage
+            .grow(b.handle, Layout::new::<[u64; 2]>(), Layout::new::<[u64; 4]>())
+            .unwrap();
+        assert_eq!(storage.remaining(), remaining - 32);
+        assert_eq!(storage.get(b.handle).cast::<[u64; 2]>().as_ptr().read(), [1, 2]);
+
+        // only the last allocation can be given back
+        storage.deallocate(a.handle, Layout::new::<u64>());
+        assert_eq!(storage.remaining(), remaining - 32);
+        storage.deallocate(b.handle, Layout::new::<[u64; 4]>());
+        assert_eq!(storage.remaining(), remaining);
+    }
+}
+
+#[test]
+fn bump_reset() {
+    let mut storage = BumpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 64);
+    storage.allocate(Layout::new::<u64>()).unwrap();
+
+    let marker = storage.checkpoint();
+    let remaining = storage.remaining();
+    storage.allocate(Layout::new::<[u64; 4]>()).unwrap();
+    assert!(storage.allocate(Layout::new::<[u64; 4]>()).is_err());
+
+    unsafe { storage.reset_to(marker) }
+    assert_eq!(storage.remaining(), remaining);
+    storage.allocate(Layout::new::<[u64; 4]>()).unwrap();
+    unsafe { storage.rewind(marker) }
+    assert_eq!(storage.remaining(), remaining);
+    storage.allocate(Layout::new::<[u64; 4]>()).unwrap();
+
+    assert_eq!(storage.used(), 40);
+    assert_eq!(storage.used() + storage.remaining(), storage.capacity());
+    unsafe { storage.reset() }
+    assert_eq!(storage.used(), 0);
+}
+
+#[test]
+fn bump_scope() {
+    let mut storage = BumpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 64);
+    let remaining = storage.remaining();
+
+    let value = storage.scope(|scope| {
+        let outer = scope.allocate(Layout::new::<u64>()).unwrap().handle;
+        unsafe { scope.get_mut(outer).cast::<u64>().as_ptr().write(10) }
+
+        let inner = scope.scope(|scope| {
+            let inner = crate::boxed::Box::new_in([1_u64; 4], scope);
+            inner.iter().sum::<u64>()
+        });
+        assert_eq!(scope.remaining(), remaining - 8);
+
+        unsafe { scope.get(outer).cast::<u64>().as_ptr().read() + inner }
+    });
+
+    assert_eq!(value, 14);
+    assert_eq!(storage.remaining(), remaining);
+}
@@ -0,0 +1,352 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    AllocErr, FromPtr, Handle, Marker, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A bump storage that allocates upward from the start of its region
+///
+/// Unlike [`BumpStorage`](crate::BumpStorage), handles increase monotonically, and the
+/// last allocation can be grown or shrunk in place
+#[must_use = "storages don't do anything unless they are used"]
+pub struct BumpUpStorage<S: Storage, const MAX_ALIGN: usize> {
+    storage: S,
+    start: S::Handle,
+    capacity: usize,
+    offset: AtomicUsize,
+}
+
+#[derive(Clone, Copy)]
+pub struct BumpUpHandle(usize);
+
+unsafe impl Handle for BumpUpHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl BumpUpHandle {
+    #[must_use = "`BumpUpHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+
+    /// The offset of this allocation from the start of the region
+    pub const fn offset(self) -> usize { self.0 }
+}
+
+impl<S: Storage, const MAX_ALIGN: usize> BumpUpStorage<S, MAX_ALIGN> {
+    const MAX_ALIGN_POW2: usize = MAX_ALIGN.next_power_of_two();
+
+    pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
+
+    /// # Panics
+    ///
+    /// if `Layout::from_size_align(space, MAX_ALIGN.next_power_of_two())` returns Err
+    pub fn try_new(mut storage: S, space: usize) -> Result<Self, AllocErr> {
+        let memory_block = storage.allocate(Layout::from_size_align(space, Self::MAX_ALIGN_POW2).unwrap())?;
+        Ok(Self {
+            start: memory_block.handle,
+            capacity: memory_block.size,
+            offset: AtomicUsize::new(0),
+            storage,
+        })
+    }
+
+    pub fn remaining_space(&self) -> usize { self.capacity - self.offset.load(Ordering::Relaxed) }
+
+    /// Records the current position, so that everything allocated after this
+    /// can be freed at once with [`BumpUpStorage::rewind`]
+    pub fn checkpoint(&self) -> Marker { Marker(self.offset.load(Ordering::Relaxed)) }
+
+    /// Frees everything that was allocated after `marker` was created
+    ///
+    /// # Safety
+    ///
+    /// * `marker` must have been created by this storage
+    /// * the storage must not have been rewound past `marker` since it was created
+    /// * handles to allocations made after `marker` was created must not be used
+    pub unsafe fn rewind(&mut self, Marker(offset): Marker) {
+        debug_assert!(
+            offset <= *self.offset.get_mut(),
+            "tried to rewind to a marker from the future"
+        );
+        *self.offset.get_mut() = offset;
+    }
+
+    // the new end of the allocation, if it can be placed at `offset`
+    fn bump(&self, offset: usize, layout: Layout) -> Option<(usize, usize)> {
+        let start = offset.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        (end <= self.capacity).then_some((start, end))
+    }
+
+    // the new end of the region, if `handle` is the last allocation and can be resized in place
+    fn resize_last(&self, offset: usize, BumpUpHandle(start): BumpUpHandle, old: Layout, new: Layout) -> Option<usize> {
+        if start.wrapping_add(old.size()) != offset
+            || start & (new.align() - 1) != 0
+            || Self::MAX_ALIGN_POW2 < new.align()
+        {
+            return None
+        }
+
+        let end = start + new.size();
+        (end <= self.capacity).then_some(end)
+    }
+
+    fn shrink_in_place(&self, handle: BumpUpHandle, old: Layout, new: Layout) -> Option<MemoryBlock<BumpUpHandle>> {
+        if handle.0 & (new.align() - 1) != 0 {
+            return None
+        }
+
+        let current = self.offset.load(Ordering::Acquire);
+        let size = match self.resize_last(current, handle, old, new) {
+            Some(end)
+                if self
+                    .offset
+                    .compare_exchange(current, end, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok() =>
+            {
+                new.size()
+            }
+            _ => old.size(),
+        };
+
+        Some(MemoryBlock { handle, size })
+    }
+
+    fn grow_in_place(&self, handle: BumpUpHandle, old: Layout, new: Layout) -> Option<MemoryBlock<BumpUpHandle>> {
+        let current = self.offset.load(Ordering::Acquire);
+        let end = self.resize_last(current, handle, old, new)?;
+        self.offset
+            .compare_exchange(current, end, Ordering::AcqRel, Ordering::Relaxed)
+            .ok()?;
+        Some(MemoryBlock {
+            handle,
+            size: new.size(),
+        })
+    }
+}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> OffsetHandle for BumpUpStorage<S, MAX_ALIGN> {
+    unsafe fn offset(&mut self, BumpUpHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        BumpUpHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedOffsetHandle for BumpUpStorage<S, MAX_ALIGN> {
+    unsafe fn shared_offset(&self, BumpUpHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        BumpUpHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> FromPtr for BumpUpStorage<S, MAX_ALIGN> {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        let origin = self.storage.get(self.start);
+        BumpUpHandle(ptr.as_ptr().offset_from(origin.as_ptr()) as usize)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedGetMut for BumpUpStorage<S, MAX_ALIGN> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.shared_get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+}
+
+impl<S: SharedGetMut, const MAX_ALIGN: usize> MultiStorage for BumpUpStorage<S, MAX_ALIGN> {}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> Storage for BumpUpStorage<S, MAX_ALIGN> {
+    type Handle = BumpUpHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+
+        // the region is only aligned to `MAX_ALIGN`, see `BumpStorage`
+        if Self::MAX_ALIGN_POW2 < layout.align() {
+            return Err(AllocErr::new(layout))
+        }
+
+        let offset = *self.offset.get_mut();
+        let (start, end) = self.bump(offset, layout).ok_or_else(|| AllocErr::new(layout))?;
+        *self.offset.get_mut() = end;
+
+        Ok(NonEmptyMemoryBlock {
+            handle: BumpUpHandle(start),
+            size: unsafe { NonZeroUsize::new_unchecked(end - start) },
+        })
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> ResizableStorage for BumpUpStorage<S, MAX_ALIGN> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            return Ok(memory_block)
+        }
+
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            let ptr = self.get_mut(handle).as_ptr();
+            ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+            return Ok(memory_block)
+        }
+
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.shrink_in_place(handle, old, new) {
+            return Ok(memory_block)
+        }
+
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedStorage for BumpUpStorage<S, MAX_ALIGN> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+
+        // the region is only aligned to `MAX_ALIGN`, see `BumpStorage`
+        if Self::MAX_ALIGN_POW2 < layout.align() {
+            return Err(AllocErr::new(layout))
+        }
+
+        let mut start = 0;
+        let mut end = 0;
+        self.offset
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |offset| {
+                (start, end) = self.bump(offset, layout)?;
+                Some(end)
+            })
+            .map_err(|_| AllocErr::new(layout))?;
+
+        Ok(NonEmptyMemoryBlock {
+            handle: BumpUpHandle(start),
+            size: unsafe { NonZeroUsize::new_unchecked(end - start) },
+        })
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for BumpUpStorage<S, MAX_ALIGN> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            return Ok(memory_block)
+        }
+
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.grow_in_place(handle, old, new) {
+            let ptr = self.shared_get_mut(handle).as_ptr();
+            ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+            return Ok(memory_block)
+        }
+
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.shrink_in_place(handle, old, new) {
+            return Ok(memory_block)
+        }
+
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn bump_up() {
+    let mut storage = BumpUpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 64);
+
+    let first = storage.allocate(Layout::new::<u8>()).unwrap().handle;
+    let second = storage.allocate(Layout::new::<u64>()).unwrap().handle;
+    assert_eq!(first.offset(), 0);
+    assert_eq!(second.offset(), 8);
+
+    let grown = unsafe {
+        storage
+            .grow(second, Layout::new::<u64>(), Layout::new::<[u64; 2]>())
+            .unwrap()
+    };
+    assert_eq!(grown.handle.offset(), 8);
+    assert_eq!(storage.remaining_space(), 40);
+
+    let marker = storage.checkpoint();
+    storage.allocate(Layout::new::<[u64; 5]>()).unwrap();
+    assert!(storage.allocate(Layout::new::<u8>()).is_err());
+    unsafe { storage.rewind(marker) }
+    assert_eq!(storage.remaining_space(), 40);
+
+    let storage = BumpUpStorage::<_, 16>::new(crate::AllocatorStorage::new(std::alloc::System), 4096);
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
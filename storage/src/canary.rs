@@ -0,0 +1,262 @@
+use core::{alloc::Layout, mem, ptr::NonNull};
+
+use crate::{
+    AffixHandle, AffixStorage, AllocErr, ConstLayoutProvider, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const CANARY: u64 = 0x5AFE_C0DE_5AFE_C0DE;
+
+// the canaries are byte aligned, so the suffix starts right after the allocation
+type Guard = ConstLayoutProvider<{ mem::size_of::<u64>() }, 1>;
+type Guarded<S> = AffixStorage<Guard, Guard, S>;
+
+fn panic_on_corruption(layout: Layout) { panic!("the canaries around an allocation of {:?} were overwritten", layout) }
+
+/// A storage that surrounds every allocation with canaries, and checks them whenever the allocation
+/// is deallocated or resized
+///
+/// This turns silent buffer overruns (and underruns) into loud failures. When a canary was overwritten,
+/// the hook is called with the layout of the allocation, by default it panics.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct CanaryStorage<S> {
+    storage: Guarded<S>,
+    hook: fn(Layout),
+}
+
+impl<S> CanaryStorage<S> {
+    pub const fn new(storage: S) -> Self { Self::with_hook(storage, panic_on_corruption) }
+
+    pub const fn with_hook(storage: S, hook: fn(Layout)) -> Self {
+        Self {
+            storage: AffixStorage::new(storage),
+            hook,
+        }
+    }
+
+    unsafe fn write_canaries(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (prefix, suffix) = self.storage.split_untyped(ptr, layout);
+        prefix.as_ptr().cast::<u64>().write_unaligned(CANARY);
+        suffix.as_ptr().cast::<u64>().write_unaligned(CANARY);
+    }
+
+    unsafe fn check_canaries(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (prefix, suffix) = self.storage.split_untyped(ptr, layout);
+
+        if prefix.as_ptr().cast::<u64>().read_unaligned() != CANARY
+            || suffix.as_ptr().cast::<u64>().read_unaligned() != CANARY
+        {
+            (self.hook)(layout);
+        }
+    }
+}
+
+unsafe impl<S: SharedGetMut + OffsetHandle> SharedGetMut for CanaryStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+unsafe impl<S: OffsetHandle> Storage for CanaryStorage<S> {
+    type Handle = AffixHandle<Guard, Guard, S::Handle>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        unsafe {
+            let ptr = self.storage.get_mut(memory_block.handle);
+            self.write_canaries(ptr, layout.into());
+        }
+        Ok(memory_block)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.check_canaries(self.storage.get(handle), layout.into());
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_nonempty_zeroed(layout)?;
+        unsafe {
+            let ptr = self.storage.get_mut(memory_block.handle);
+            self.write_canaries(ptr, layout.into());
+        }
+        Ok(memory_block)
+    }
+
+    // zero-sized allocations get canaries too, so they aren't dangling
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate(layout)?;
+        unsafe {
+            let ptr = self.storage.get_mut(memory_block.handle);
+            self.write_canaries(ptr, layout);
+        }
+        Ok(memory_block)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.check_canaries(self.storage.get(handle), layout);
+        self.storage.deallocate(handle, layout);
+    }
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_zeroed(layout)?;
+        unsafe {
+            let ptr = self.storage.get_mut(memory_block.handle);
+            self.write_canaries(ptr, layout);
+        }
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: ResizableStorage + OffsetHandle> ResizableStorage for CanaryStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.check_canaries(self.storage.get(handle), old);
+        let memory_block = self.storage.grow(handle, old, new)?;
+        self.write_canaries(self.storage.get(memory_block.handle), new);
+        Ok(memory_block)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.check_canaries(self.storage.get(handle), old);
+        let memory_block = self.storage.grow_zeroed(handle, old, new)?;
+        self.write_canaries(self.storage.get(memory_block.handle), new);
+        Ok(memory_block)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.check_canaries(self.storage.get(handle), old);
+        let memory_block = self.storage.shrink(handle, old, new)?;
+        self.write_canaries(self.storage.get(memory_block.handle), new);
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: SharedOffsetHandle> SharedStorage for CanaryStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        unsafe { self.write_canaries(self.storage.shared_get_mut(memory_block.handle), layout.into()) }
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.check_canaries(self.storage.shared_get_mut(handle), layout.into());
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_nonempty_zeroed(layout)?;
+        unsafe { self.write_canaries(self.storage.shared_get_mut(memory_block.handle), layout.into()) }
+        Ok(memory_block)
+    }
+
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate(layout)?;
+        unsafe { self.write_canaries(self.storage.shared_get_mut(memory_block.handle), layout) }
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.check_canaries(self.storage.shared_get_mut(handle), layout);
+        self.storage.shared_deallocate(handle, layout);
+    }
+
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_zeroed(layout)?;
+        unsafe { self.write_canaries(self.storage.shared_get_mut(memory_block.handle), layout) }
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + SharedOffsetHandle> SharedResizableStorage for CanaryStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.check_canaries(self.storage.shared_get_mut(handle), old);
+        let memory_block = self.storage.shared_grow(handle, old, new)?;
+        self.write_canaries(self.storage.shared_get_mut(memory_block.handle), new);
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.check_canaries(self.storage.shared_get_mut(handle), old);
+        let memory_block = self.storage.shared_grow_zeroed(handle, old, new)?;
+        self.write_canaries(self.storage.shared_get_mut(memory_block.handle), new);
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.check_canaries(self.storage.shared_get_mut(handle), old);
+        let memory_block = self.storage.shared_shrink(handle, old, new)?;
+        self.write_canaries(self.storage.shared_get_mut(memory_block.handle), new);
+        Ok(memory_block)
+    }
+}
+
+#[test]
+fn canary() {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    static CORRUPTED: AtomicBool = AtomicBool::new(false);
+
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = CanaryStorage::with_hook(system, |_| CORRUPTED.store(true, Ordering::Relaxed));
+
+    let layout = Layout::new::<[u8; 10]>();
+    let memory_block = storage.allocate(layout).unwrap();
+    assert_eq!(memory_block.size, 10);
+
+    unsafe {
+        let ptr = storage.get_mut(memory_block.handle).as_ptr();
+        ptr.write_bytes(0xff, 10);
+        let memory_block = storage
+            .grow(memory_block.handle, layout, Layout::new::<[u8; 20]>())
+            .unwrap();
+        assert!(!CORRUPTED.load(Ordering::Relaxed));
+
+        // one byte past the end
+        storage.get_mut(memory_block.handle).as_ptr().add(20).write(0);
+        storage.deallocate(memory_block.handle, Layout::new::<[u8; 20]>());
+        assert!(CORRUPTED.load(Ordering::Relaxed));
+    }
+
+    crate::storage_conformance!(CanaryStorage::new(system), resizable, shared, shared_resizable);
+}
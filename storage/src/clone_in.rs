@@ -0,0 +1,52 @@
+use crate::{AllocErr, Storage};
+
+pub trait TryCloneIn<S: Storage> {
+    type Output;
+
+    fn try_clone_in(&self, storage: S) -> Result<Self::Output, AllocErr>;
+}
+
+pub trait CloneIn<S: Storage>: TryCloneIn<S> {
+    fn clone_in(&self, storage: S) -> Self::Output;
+}
+
+impl<S: Storage, T: ?Sized + TryCloneIn<S>> CloneIn<S> for T {
+    #[inline]
+    fn clone_in(&self, storage: S) -> Self::Output { self.try_clone_in(storage).unwrap_or_else(AllocErr::handle) }
+}
+
+#[test]
+fn clone_in() {
+    use crate::{boxed::Box, rc::Rc, vec::Vec, Event};
+
+    let source = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let target = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let boxed = Box::new_in(7_u32, &source);
+    let vec = Vec::from_slice_in(&[1_u16, 2, 3], &source);
+    let rc = Rc::new_in(11_u64, &source);
+    assert_eq!(source.events().len(), 3);
+
+    let cloned_box = boxed.clone_in(&target);
+    let cloned_vec = vec.clone_in(&target);
+    let cloned_rc = rc.clone_in(&target);
+    assert_eq!(*cloned_box, 7);
+    assert_eq!(*cloned_vec, [1, 2, 3]);
+    assert_eq!(*cloned_rc, 11);
+
+    // every clone was allocated in the target storage
+    assert_eq!(source.events().len(), 3);
+    assert_eq!(target.events().len(), 3);
+    assert!(target.events().iter().all(|event| matches!(event, Event::Allocate(_))));
+    assert_eq!(target.events()[0], Event::Allocate(core::alloc::Layout::new::<u32>()));
+    assert_eq!(
+        target.events()[1],
+        Event::Allocate(core::alloc::Layout::new::<[u16; 3]>())
+    );
+
+    drop((boxed, vec, rc));
+    assert_eq!(source.live_allocations(), 0);
+    assert_eq!(target.live_allocations(), 3);
+    drop((cloned_box, cloned_vec, cloned_rc));
+    assert_eq!(target.live_allocations(), 0);
+}
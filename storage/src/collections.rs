@@ -0,0 +1,7 @@
+pub mod binary_heap;
+pub mod btree_map;
+pub mod linked_list;
+
+pub use binary_heap::BinaryHeap;
+pub use btree_map::BTreeMap;
+pub use linked_list::LinkedList;
@@ -0,0 +1,171 @@
+use crate::{vec::Vec, AllocErr, ResizableStorage, Storage};
+
+/// A max-heap stored in a [`Vec`]
+pub struct BinaryHeap<T, S: Storage = crate::Global> {
+    data: Vec<T, S>,
+}
+
+impl<T: Ord> BinaryHeap<T> {
+    pub const fn new() -> Self { Self { data: Vec::new() } }
+}
+
+impl<T: Ord, S: Storage + Default> Default for BinaryHeap<T, S> {
+    fn default() -> Self { Self::new_in(S::default()) }
+}
+
+impl<T: Ord, S: Storage> BinaryHeap<T, S> {
+    pub fn new_in(storage: S) -> Self {
+        Self {
+            data: Vec::new_in(storage),
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, storage: S) -> Self {
+        Self::try_with_capacity_in(capacity, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_with_capacity_in(capacity: usize, storage: S) -> Result<Self, AllocErr<S>> {
+        Ok(Self {
+            data: Vec::try_with_capacity_in(capacity, storage)?,
+        })
+    }
+
+    pub fn len(&self) -> usize { self.data.len() }
+
+    pub fn is_empty(&self) -> bool { self.data.is_empty() }
+
+    pub fn capacity(&self) -> usize { self.data.capacity() }
+
+    pub fn clear(&mut self) { self.data.clear() }
+
+    /// The elements in an unspecified order
+    pub fn as_slice(&self) -> &[T] { &self.data }
+
+    /// The greatest element
+    pub fn peek(&self) -> Option<&T> { self.data.first() }
+
+    /// Removes the greatest element
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len();
+        if len > 1 {
+            self.data.swap(0, len - 1);
+        }
+
+        let value = self.data.try_pop()?;
+        self.sift_down(0, self.len());
+        Some(value)
+    }
+
+    /// Pushes `value` if there's space for it without growing, otherwise returns it back
+    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        self.data.try_push(value)?;
+        self.sift_up(self.len() - 1);
+        Ok(())
+    }
+
+    pub fn into_vec(self) -> Vec<T, S> { self.data }
+
+    /// Converts the heap into a vector sorted from least to greatest
+    pub fn into_sorted_vec(mut self) -> Vec<T, S> {
+        let mut end = self.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            self.sift_down(0, end);
+        }
+
+        self.data
+    }
+
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if self.data[index] <= self.data[parent] {
+                break
+            }
+
+            self.data.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    // only looks at the elements before `end`
+    fn sift_down(&mut self, mut index: usize, end: usize) {
+        loop {
+            let left = 2 * index + 1;
+            if left >= end {
+                break
+            }
+
+            let right = left + 1;
+            let child = if right < end && self.data[right] > self.data[left] {
+                right
+            } else {
+                left
+            };
+
+            if self.data[index] >= self.data[child] {
+                break
+            }
+
+            self.data.swap(index, child);
+            index = child;
+        }
+    }
+}
+
+impl<T: Ord, S: ResizableStorage> BinaryHeap<T, S> {
+    pub fn reserve(&mut self, additional: usize) { self.data.reserve(additional) }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocErr> { self.data.try_reserve(additional) }
+
+    pub fn push(&mut self, value: T) { self.try_push(value).unwrap_or_else(AllocErr::handle) }
+
+    /// Pushes `value`, growing the heap if there isn't space for it
+    ///
+    /// If the heap can't grow, `value` is returned in the error
+    pub fn try_push(&mut self, value: T) -> Result<(), AllocErr<T>> {
+        self.data.try_push_grow(value)?;
+        self.sift_up(self.len() - 1);
+        Ok(())
+    }
+}
+
+impl<T: Ord, S: Storage> From<Vec<T, S>> for BinaryHeap<T, S> {
+    fn from(data: Vec<T, S>) -> Self {
+        let mut heap = Self { data };
+        let len = heap.len();
+        for index in (0..len / 2).rev() {
+            heap.sift_down(index, len);
+        }
+        heap
+    }
+}
+
+impl<T: Ord, S: Storage> From<BinaryHeap<T, S>> for Vec<T, S> {
+    fn from(heap: BinaryHeap<T, S>) -> Self { heap.data }
+}
+
+#[test]
+fn binary_heap() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut heap = BinaryHeap::new_in(&mock);
+    for value in [5, 1, 8, 3, 9, 2, 8] {
+        heap.push(value);
+    }
+    assert_eq!(heap.peek(), Some(&9));
+    assert_eq!(heap.pop(), Some(9));
+    assert_eq!(heap.pop(), Some(8));
+    assert_eq!(*heap.into_sorted_vec(), [1, 2, 3, 5, 8]);
+
+    let mut heap = BinaryHeap::from(crate::vec_in![4, 7, 1, 0, 7, 3; &mock]);
+    assert!(core::iter::from_fn(|| heap.pop()).eq([7, 7, 4, 3, 1, 0]));
+
+    // a fixed size pool can still be used through `push_within_capacity`
+    let mut heap = BinaryHeap::with_capacity_in(2, crate::SingleStackStorage::<[u32; 2]>::new());
+    assert_eq!(heap.push_within_capacity(1), Ok(()));
+    assert_eq!(heap.push_within_capacity(2), Ok(()));
+    assert_eq!(heap.push_within_capacity(3), Err(3));
+    assert_eq!(heap.pop(), Some(2));
+}
@@ -0,0 +1,780 @@
+use core::{
+    alloc::Layout,
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr,
+};
+
+use crate::{AllocErr, MultiStorage, Storage};
+
+const B: usize = 6;
+const CAPACITY: usize = 2 * B - 1;
+const MIN_LEN: usize = B - 1;
+
+// leaves and internal nodes share a layout, leaves just never initialize their edges
+struct Node<K, V, H> {
+    len: usize,
+    keys: [MaybeUninit<K>; CAPACITY],
+    values: [MaybeUninit<V>; CAPACITY],
+    // internal nodes have `len + 1` edges
+    edges: [MaybeUninit<H>; CAPACITY + 1],
+}
+
+enum Target<'a, Q: ?Sized> {
+    Key(&'a Q),
+    First,
+    Last,
+}
+
+impl<Q: ?Sized> Clone for Target<'_, Q> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<Q: ?Sized> Copy for Target<'_, Q> {}
+
+/// An ordered map stored in a B-tree
+///
+/// Nodes refer to each other by their storage handles, not by pointers, so the map can
+/// live in storages that move their memory around, or that map it at different addresses
+/// in different processes. Pointers to nodes are only held while nothing is allocated
+/// or deallocated.
+pub struct BTreeMap<K, V, S: Storage = crate::Global> {
+    root: Option<S::Handle>,
+    height: usize,
+    len: usize,
+    storage: S,
+    __: PhantomData<(K, V)>,
+}
+
+impl<K, V> BTreeMap<K, V> {
+    pub const fn new() -> Self { Self::new_in(crate::Global) }
+}
+
+impl<K, V, S: Storage + Default> Default for BTreeMap<K, V, S> {
+    fn default() -> Self { Self::new_in(S::default()) }
+}
+
+impl<K, V, S: Storage> BTreeMap<K, V, S> {
+    pub const fn new_in(storage: S) -> Self {
+        Self {
+            root: None,
+            height: 0,
+            len: 0,
+            storage,
+            __: PhantomData,
+        }
+    }
+
+    pub const fn len(&self) -> usize { self.len }
+
+    pub const fn is_empty(&self) -> bool { self.len == 0 }
+}
+
+const unsafe fn slice_insert<T>(slice: &mut [MaybeUninit<T>], len: usize, index: usize, value: T) {
+    let ptr = slice.as_mut_ptr();
+    ptr.add(index + 1).copy_from(ptr.add(index), len - index);
+    ptr.add(index).write(MaybeUninit::new(value));
+}
+
+const unsafe fn slice_remove<T>(slice: &mut [MaybeUninit<T>], len: usize, index: usize) -> T {
+    let ptr = slice.as_mut_ptr();
+    let value = ptr.add(index).read().assume_init();
+    ptr.add(index).copy_from(ptr.add(index + 1), len - index - 1);
+    value
+}
+
+impl<K, V, H: Copy> Node<K, V, H> {
+    const unsafe fn key(&self, index: usize) -> &K { self.keys[index].assume_init_ref() }
+
+    const unsafe fn value(&self, index: usize) -> &V { self.values[index].assume_init_ref() }
+
+    const unsafe fn value_mut(&mut self, index: usize) -> &mut V { self.values[index].assume_init_mut() }
+
+    const unsafe fn edge(&self, index: usize) -> H { self.edges[index].assume_init() }
+
+    const unsafe fn insert(&mut self, index: usize, key: K, value: V) {
+        slice_insert(&mut self.keys, self.len, index, key);
+        slice_insert(&mut self.values, self.len, index, value);
+        self.len += 1;
+    }
+
+    const unsafe fn remove(&mut self, index: usize) -> (K, V) {
+        let key = slice_remove(&mut self.keys, self.len, index);
+        let value = slice_remove(&mut self.values, self.len, index);
+        self.len -= 1;
+        (key, value)
+    }
+
+    const unsafe fn replace(&mut self, index: usize, key: K, value: V) -> (K, V) {
+        (
+            mem::replace(self.keys[index].assume_init_mut(), key),
+            mem::replace(self.values[index].assume_init_mut(), value),
+        )
+    }
+
+    // must be called before the key that goes with the edge is inserted
+    const unsafe fn insert_edge(&mut self, index: usize, edge: H) {
+        slice_insert(&mut self.edges, self.len + 1, index, edge);
+    }
+
+    // must be called after the key that goes with the edge is removed
+    const unsafe fn remove_edge(&mut self, index: usize) -> H { slice_remove(&mut self.edges, self.len + 2, index) }
+
+    fn search<Q: Ord + ?Sized>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+    {
+        for index in 0..self.len {
+            match key.cmp(unsafe { self.key(index) }.borrow()) {
+                Ordering::Greater => (),
+                Ordering::Equal => return Ok(index),
+                Ordering::Less => return Err(index),
+            }
+        }
+
+        Err(self.len)
+    }
+}
+
+impl<K, V, S: MultiStorage> BTreeMap<K, V, S> {
+    const LAYOUT: Layout = Layout::new::<Node<K, V, S::Handle>>();
+
+    unsafe fn node(&self, handle: S::Handle) -> &Node<K, V, S::Handle> { &*self.storage.get(handle).as_ptr().cast() }
+
+    unsafe fn node_ptr(&self, handle: S::Handle) -> *mut Node<K, V, S::Handle> {
+        self.storage.shared_get_mut(handle).as_ptr().cast()
+    }
+
+    fn allocate_node(&mut self) -> Result<S::Handle, AllocErr> {
+        let handle = self.storage.allocate(Self::LAYOUT)?.handle;
+        unsafe { ptr::addr_of_mut!((*self.node_ptr(handle)).len).write(0) }
+        Ok(handle)
+    }
+
+    unsafe fn deallocate_node(&mut self, handle: S::Handle) { self.storage.deallocate(handle, Self::LAYOUT) }
+
+    pub fn clear(&mut self) {
+        if let Some(root) = self.root.take() {
+            // if a destructor panics, the rest of the map is leaked
+            let height = self.height;
+            self.len = 0;
+            self.height = 0;
+            unsafe { drop_subtree::<K, V, S>(&mut self.storage, root, height) }
+        }
+    }
+
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    pub fn get_key_value<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        let (handle, index) = self.find(key)?;
+        unsafe {
+            let node = self.node(handle);
+            Some((node.key(index), node.value(index)))
+        }
+    }
+
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        let (handle, index) = self.find(key)?;
+        unsafe { Some((*self.node_ptr(handle)).value_mut(index)) }
+    }
+
+    fn find<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(S::Handle, usize)>
+    where
+        K: Borrow<Q>,
+    {
+        let mut handle = self.root?;
+        let mut height = self.height;
+
+        loop {
+            let node = unsafe { self.node(handle) };
+            match node.search(key) {
+                Ok(index) => return Some((handle, index)),
+                Err(_) if height == 0 => return None,
+                Err(index) => {
+                    handle = unsafe { node.edge(index) };
+                    height -= 1;
+                }
+            }
+        }
+    }
+
+    // the first entry after `bound`, or the first entry if there's no bound
+    fn find_after(&self, bound: Option<&K>) -> Option<(&K, &V)>
+    where
+        K: Ord,
+    {
+        let mut handle = self.root?;
+        let mut height = self.height;
+        let mut found = None;
+
+        loop {
+            let node = unsafe { self.node(handle) };
+            let index = bound.map_or(0, |bound| {
+                (0..node.len)
+                    .find(|&index| unsafe { node.key(index) } > bound)
+                    .unwrap_or(node.len)
+            });
+
+            if index < node.len {
+                found = Some(unsafe { (node.key(index), node.value(index)) });
+            }
+
+            if height == 0 {
+                return found
+            }
+
+            handle = unsafe { node.edge(index) };
+            height -= 1;
+        }
+    }
+
+    // the last entry before `bound`, or the last entry if there's no bound
+    fn find_before(&self, bound: Option<&K>) -> Option<(&K, &V)>
+    where
+        K: Ord,
+    {
+        let mut handle = self.root?;
+        let mut height = self.height;
+        let mut found = None;
+
+        loop {
+            let node = unsafe { self.node(handle) };
+            let index = bound.map_or(node.len, |bound| {
+                (0..node.len)
+                    .find(|&index| unsafe { node.key(index) } >= bound)
+                    .unwrap_or(node.len)
+            });
+
+            if index > 0 {
+                found = Some(unsafe { (node.key(index - 1), node.value(index - 1)) });
+            }
+
+            if height == 0 {
+                return found
+            }
+
+            handle = unsafe { node.edge(index) };
+            height -= 1;
+        }
+    }
+}
+
+impl<K: Ord, V, S: MultiStorage> BTreeMap<K, V, S> {
+    pub const fn iter(&self) -> Iter<'_, K, V, S> {
+        Iter {
+            map: self,
+            front: None,
+            back: None,
+            remaining: self.len,
+        }
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> { self.find_after(None) }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> { self.find_before(None) }
+
+    /// Inserts `value` under `key`, and returns the value that was there before
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.try_insert(key, value).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Inserts `value` under `key`, and returns the value that was there before
+    ///
+    /// If a node can't be allocated, the map is left unchanged and the
+    /// key and value are returned in the error
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, AllocErr<(K, V)>> {
+        let root = if let Some(root) = self.root {
+            root
+        } else {
+            let root = match self.allocate_node() {
+                Ok(root) => root,
+                Err(err) => return Err(err.with((key, value))),
+            };
+            self.root = Some(root);
+            self.height = 0;
+            root
+        };
+
+        let mut handle = root;
+
+        // nodes are split on the way down, so there's always space to move a key up into the parent
+        if unsafe { self.node(root).len } == CAPACITY {
+            let (new_root, sibling) = match self.allocate_node() {
+                Err(err) => return Err(err.with((key, value))),
+                Ok(new_root) => match self.allocate_node() {
+                    Ok(sibling) => (new_root, sibling),
+                    Err(err) => {
+                        unsafe { self.deallocate_node(new_root) }
+                        return Err(err.with((key, value)))
+                    }
+                },
+            };
+
+            unsafe {
+                (*self.node_ptr(new_root)).edges[0] = MaybeUninit::new(root);
+                self.split_child(new_root, 0, sibling, self.height != 0);
+            }
+
+            self.root = Some(new_root);
+            self.height += 1;
+            handle = new_root;
+        }
+
+        let mut height = self.height;
+
+        unsafe {
+            loop {
+                let mut index = match self.node(handle).search(&key) {
+                    Ok(index) => return Ok(Some(mem::replace((*self.node_ptr(handle)).value_mut(index), value))),
+                    Err(index) => index,
+                };
+
+                if height == 0 {
+                    (*self.node_ptr(handle)).insert(index, key, value);
+                    self.len += 1;
+                    return Ok(None)
+                }
+
+                let child = self.node(handle).edge(index);
+                if self.node(child).len == CAPACITY {
+                    let sibling = match self.allocate_node() {
+                        Ok(sibling) => sibling,
+                        Err(err) => return Err(err.with((key, value))),
+                    };
+
+                    self.split_child(handle, index, sibling, height > 1);
+
+                    match key.cmp(self.node(handle).key(index)) {
+                        Ordering::Less => (),
+                        Ordering::Equal => {
+                            return Ok(Some(mem::replace((*self.node_ptr(handle)).value_mut(index), value)))
+                        }
+                        Ordering::Greater => index += 1,
+                    }
+                }
+
+                handle = self.node(handle).edge(index);
+                height -= 1;
+            }
+        }
+    }
+
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+    {
+        self.remove_target(Target::Key(key))
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> { self.remove_target(Target::<K>::First) }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> { self.remove_target(Target::<K>::Last) }
+
+    fn remove_target<Q: Ord + ?Sized>(&mut self, target: Target<'_, Q>) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+    {
+        let root = self.root?;
+
+        unsafe {
+            let entry = self.remove_from(root, self.height, target);
+            if entry.is_some() {
+                self.len -= 1;
+            }
+
+            // merging the root's only two children, or removing the last
+            // entry, can leave the root empty
+            if self.node(root).len == 0 {
+                if self.height == 0 {
+                    self.root = None;
+                } else {
+                    self.root = Some(self.node(root).edge(0));
+                    self.height -= 1;
+                }
+
+                self.deallocate_node(root);
+            }
+
+            entry
+        }
+    }
+
+    // every node that this descends into has more than `MIN_LEN` entries, so removing
+    // an entry from it or moving one of its entries into a child can't leave it too small
+    unsafe fn remove_from<Q: Ord + ?Sized>(
+        &mut self,
+        mut handle: S::Handle,
+        mut height: usize,
+        target: Target<'_, Q>,
+    ) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+    {
+        loop {
+            let len = self.node(handle).len;
+            let (found, index) = match target {
+                Target::Key(key) => match self.node(handle).search(key) {
+                    Ok(index) => (true, index),
+                    Err(index) => (false, index),
+                },
+                Target::First => (height == 0, 0),
+                Target::Last if height == 0 => (true, len - 1),
+                Target::Last => (false, len),
+            };
+
+            if height == 0 {
+                return if found {
+                    Some((*self.node_ptr(handle)).remove(index))
+                } else {
+                    None
+                }
+            }
+
+            let internal = height > 1;
+
+            if found {
+                let left = self.node(handle).edge(index);
+                let right = self.node(handle).edge(index + 1);
+
+                // replace the entry with its predecessor or successor, whichever can be spared
+                let neighbor = if self.node(left).len > MIN_LEN {
+                    Some(self.remove_from(left, height - 1, Target::<Q>::Last))
+                } else if self.node(right).len > MIN_LEN {
+                    Some(self.remove_from(right, height - 1, Target::<Q>::First))
+                } else {
+                    None
+                };
+
+                if let Some(neighbor) = neighbor {
+                    let (key, value) = neighbor?;
+                    return Some((*self.node_ptr(handle)).replace(index, key, value))
+                }
+
+                handle = self.merge(handle, index, internal);
+            } else {
+                handle = self.fill_child(handle, index, internal);
+            }
+
+            height -= 1;
+        }
+    }
+
+    // moves the upper half of the full child at `index` into `sibling`, and its middle entry into the parent
+    unsafe fn split_child(&mut self, parent: S::Handle, index: usize, sibling: S::Handle, internal: bool) {
+        let parent = self.node_ptr(parent);
+        let child = self.node_ptr((*parent).edge(index));
+        let sibling_handle = sibling;
+        let sibling = self.node_ptr(sibling);
+
+        (*sibling)
+            .keys
+            .as_mut_ptr()
+            .copy_from_nonoverlapping((*child).keys.as_ptr().add(B), MIN_LEN);
+        (*sibling)
+            .values
+            .as_mut_ptr()
+            .copy_from_nonoverlapping((*child).values.as_ptr().add(B), MIN_LEN);
+        if internal {
+            (*sibling)
+                .edges
+                .as_mut_ptr()
+                .copy_from_nonoverlapping((*child).edges.as_ptr().add(B), B);
+        }
+        (*sibling).len = MIN_LEN;
+
+        (*child).len = B;
+        let (key, value) = (*child).remove(B - 1);
+        (*parent).insert_edge(index + 1, sibling_handle);
+        (*parent).insert(index, key, value);
+    }
+
+    // moves the entry at `index` and everything in the child after it into the child before it
+    unsafe fn merge(&mut self, parent: S::Handle, index: usize, internal: bool) -> S::Handle {
+        let parent = self.node_ptr(parent);
+        let left_handle = (*parent).edge(index);
+        let right_handle = (*parent).edge(index + 1);
+        let left = self.node_ptr(left_handle);
+        let right = self.node_ptr(right_handle);
+
+        let (key, value) = (*parent).remove(index);
+        (*parent).remove_edge(index + 1);
+
+        let left_len = (*left).len;
+        let right_len = (*right).len;
+        (*left).keys[left_len] = MaybeUninit::new(key);
+        (*left).values[left_len] = MaybeUninit::new(value);
+
+        (*left)
+            .keys
+            .as_mut_ptr()
+            .add(left_len + 1)
+            .copy_from_nonoverlapping((*right).keys.as_ptr(), right_len);
+        (*left)
+            .values
+            .as_mut_ptr()
+            .add(left_len + 1)
+            .copy_from_nonoverlapping((*right).values.as_ptr(), right_len);
+        if internal {
+            (*left)
+                .edges
+                .as_mut_ptr()
+                .add(left_len + 1)
+                .copy_from_nonoverlapping((*right).edges.as_ptr(), right_len + 1);
+        }
+        (*left).len = left_len + 1 + right_len;
+
+        self.deallocate_node(right_handle);
+        left_handle
+    }
+
+    // makes sure the child at `index` can spare an entry, and returns the child to descend into
+    unsafe fn fill_child(&mut self, parent: S::Handle, index: usize, internal: bool) -> S::Handle {
+        let len = self.node(parent).len;
+        let child = self.node(parent).edge(index);
+
+        if self.node(child).len > MIN_LEN {
+            return child
+        }
+
+        if index > 0 && self.node(self.node(parent).edge(index - 1)).len > MIN_LEN {
+            self.rotate_right(parent, index - 1, internal);
+            child
+        } else if index < len && self.node(self.node(parent).edge(index + 1)).len > MIN_LEN {
+            self.rotate_left(parent, index, internal);
+            child
+        } else if index < len {
+            self.merge(parent, index, internal)
+        } else {
+            self.merge(parent, index - 1, internal)
+        }
+    }
+
+    // moves the last entry of the child at `index` up into the parent, and the parent's entry down into the next child
+    unsafe fn rotate_right(&mut self, parent: S::Handle, index: usize, internal: bool) {
+        let parent = self.node_ptr(parent);
+        let left = self.node_ptr((*parent).edge(index));
+        let right = self.node_ptr((*parent).edge(index + 1));
+
+        let (key, value) = (*left).remove((*left).len - 1);
+        let (key, value) = (*parent).replace(index, key, value);
+
+        if internal {
+            let edge = (*left).remove_edge((*left).len + 1);
+            (*right).insert_edge(0, edge);
+        }
+        (*right).insert(0, key, value);
+    }
+
+    // moves the first entry of the child after `index` up into the parent, and the parent's entry down into the child
+    unsafe fn rotate_left(&mut self, parent: S::Handle, index: usize, internal: bool) {
+        let parent = self.node_ptr(parent);
+        let left = self.node_ptr((*parent).edge(index));
+        let right = self.node_ptr((*parent).edge(index + 1));
+
+        let (key, value) = (*right).remove(0);
+        let (key, value) = (*parent).replace(index, key, value);
+
+        if internal {
+            let edge = (*right).remove_edge(0);
+            (*left).insert_edge((*left).len + 1, edge);
+        }
+        let left_len = (*left).len;
+        (*left).insert(left_len, key, value);
+    }
+}
+
+impl<K, V, S: Storage> Drop for BTreeMap<K, V, S> {
+    fn drop(&mut self) {
+        if let Some(root) = self.root {
+            unsafe { drop_subtree::<K, V, S>(&mut self.storage, root, self.height) }
+        }
+    }
+}
+
+// `Drop` can't require `MultiStorage`, but there's never more than one node in use at a time here
+unsafe fn drop_subtree<K, V, S: Storage>(storage: &mut S, handle: S::Handle, height: usize) {
+    let node = storage.get_mut(handle).as_ptr().cast::<Node<K, V, S::Handle>>();
+    let len = (*node).len;
+
+    if height != 0 {
+        for index in 0..=len {
+            let node = storage.get_mut(handle).as_ptr().cast::<Node<K, V, S::Handle>>();
+            drop_subtree::<K, V, S>(storage, (*node).edge(index), height - 1);
+        }
+    }
+
+    let node = storage.get_mut(handle).as_ptr().cast::<Node<K, V, S::Handle>>();
+    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+        (*node).keys.as_mut_ptr().cast::<K>(),
+        len,
+    ));
+    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+        (*node).values.as_mut_ptr().cast::<V>(),
+        len,
+    ));
+    storage.deallocate(handle, Layout::new::<Node<K, V, S::Handle>>());
+}
+
+impl<K: fmt::Debug + Ord, V: fmt::Debug, S: MultiStorage> fmt::Debug for BTreeMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.debug_map().entries(self.iter()).finish() }
+}
+
+/// An iterator over the entries of a [`BTreeMap`] in order, created by [`BTreeMap::iter`]
+pub struct Iter<'a, K, V, S: Storage = crate::Global> {
+    map: &'a BTreeMap<K, V, S>,
+    front: Option<&'a K>,
+    back: Option<&'a K>,
+    remaining: usize,
+}
+
+impl<'a, K: Ord, V, S: MultiStorage> Iterator for Iter<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None
+        }
+
+        let (key, value) = self.map.find_after(self.front)?;
+        self.front = Some(key);
+        self.remaining -= 1;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.remaining, Some(self.remaining)) }
+}
+
+impl<K: Ord, V, S: MultiStorage> DoubleEndedIterator for Iter<'_, K, V, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None
+        }
+
+        let (key, value) = self.map.find_before(self.back)?;
+        self.back = Some(key);
+        self.remaining -= 1;
+        Some((key, value))
+    }
+}
+
+impl<K: Ord, V, S: MultiStorage> ExactSizeIterator for Iter<'_, K, V, S> {}
+
+impl<K: Ord, V, S: MultiStorage> FusedIterator for Iter<'_, K, V, S> {}
+
+impl<'a, K: Ord, V, S: MultiStorage> IntoIterator for &'a BTreeMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, S>;
+
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+#[test]
+fn btree_map() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut map = BTreeMap::new_in(&mock);
+
+    // visit the keys out of order, so that every kind of split, merge and rotation happens
+    let keys = (0..1000_u32).map(|i| i * 7919 % 1000);
+    for key in keys.clone() {
+        assert_eq!(map.insert(key, key * 2), None);
+    }
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.insert(500, 0), Some(1000));
+    *map.get_mut(&500).unwrap() = 1000;
+
+    assert!(map
+        .iter()
+        .map(|(&key, &value)| (key, value))
+        .eq((0..1000).map(|key| (key, key * 2))));
+    assert!(map.iter().rev().map(|(&key, _)| key).eq((0..1000).rev()));
+
+    for key in keys.filter(|key| key % 3 != 0) {
+        assert_eq!(map.remove(&key), Some(key * 2));
+    }
+    assert_eq!(map.remove(&1), None);
+    assert!(map.iter().map(|(&key, _)| key).eq((0..1000).step_by(3)));
+
+    assert_eq!(map.pop_first(), Some((0, 0)));
+    assert_eq!(map.pop_last(), Some((999, 1998)));
+    assert_eq!(map.first_key_value(), Some((&3, &6)));
+    assert_eq!(map.get(&300), Some(&600));
+    assert!(!map.contains_key(&301));
+
+    while let Some((key, _)) = map.pop_last() {
+        assert!(!map.contains_key(&key));
+    }
+    assert!(map.is_empty());
+    assert_eq!(mock.live_allocations(), 0);
+
+    for key in 0..100 {
+        map.insert(key, key);
+    }
+    drop(map);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn btree_map_handles() {
+    use std::string::{String, ToString};
+
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    {
+        // bump handles are offsets into the bump's buffer, not pointers
+        let mut map = BTreeMap::new_in(crate::BumpStorage::<_, 8>::new(&mock, 1 << 16));
+        for key in (0..200_u32).rev() {
+            map.insert(key, key.to_string());
+        }
+        assert!(map
+            .iter()
+            .map(|(key, value)| (*key, value.parse::<u32>().unwrap()))
+            .eq((0..200).map(|key| (key, key))));
+
+        for key in 50..150 {
+            assert_eq!(map.remove(&key), Some(key.to_string()));
+        }
+        assert_eq!(map.len(), 100);
+        map.clear();
+        assert!(map.is_empty());
+        map.insert(1, String::from("a"));
+    }
+    assert_eq!(mock.live_allocations(), 0);
+
+    // running out of space leaves the map as it was
+    let mut map = BTreeMap::new_in(crate::BumpStorage::<_, 8>::new(&mock, 4096));
+    let mut inserted = 0_u32;
+    while map.try_insert(inserted, inserted).is_ok() {
+        inserted += 1;
+    }
+    assert_eq!(map.len(), inserted as usize);
+    assert!(map.iter().map(|(&key, _)| key).eq(0..inserted));
+}
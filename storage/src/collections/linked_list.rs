@@ -0,0 +1,346 @@
+use core::{alloc::Layout, fmt, iter::FusedIterator, marker::PhantomData};
+
+use crate::{AllocErr, MultiStorage, Storage};
+
+struct Node<T, H> {
+    value: T,
+    prev: Option<H>,
+    next: Option<H>,
+}
+
+/// A doubly linked list
+///
+/// Nodes link to each other by their storage handles, not by pointers, so the list stays
+/// valid in storages that move their memory around, or that map it at different addresses
+/// in different processes.
+pub struct LinkedList<T, S: Storage = crate::Global> {
+    head: Option<S::Handle>,
+    tail: Option<S::Handle>,
+    len: usize,
+    storage: S,
+    __: PhantomData<T>,
+}
+
+impl<T> LinkedList<T> {
+    pub const fn new() -> Self { Self::new_in(crate::Global) }
+}
+
+impl<T, S: Storage + Default> Default for LinkedList<T, S> {
+    fn default() -> Self { Self::new_in(S::default()) }
+}
+
+impl<T, S: Storage> LinkedList<T, S> {
+    pub const fn new_in(storage: S) -> Self {
+        Self {
+            head: None,
+            tail: None,
+            len: 0,
+            storage,
+            __: PhantomData,
+        }
+    }
+
+    pub const fn len(&self) -> usize { self.len }
+
+    pub const fn is_empty(&self) -> bool { self.len == 0 }
+
+    unsafe fn node(&self, handle: S::Handle) -> &Node<T, S::Handle> { &*self.storage.get(handle).as_ptr().cast() }
+
+    unsafe fn node_mut(&mut self, handle: S::Handle) -> &mut Node<T, S::Handle> {
+        &mut *self.storage.get_mut(handle).as_ptr().cast()
+    }
+
+    // unlinks and deallocates a node, only one node is looked at at a time so this doesn't need `MultiStorage`
+    unsafe fn take(&mut self, handle: S::Handle) -> T {
+        let node = self
+            .storage
+            .get_mut(handle)
+            .as_ptr()
+            .cast::<Node<T, S::Handle>>()
+            .read();
+
+        match node.prev {
+            Some(prev) => self.node_mut(prev).next = node.next,
+            None => self.head = node.next,
+        }
+
+        match node.next {
+            Some(next) => self.node_mut(next).prev = node.prev,
+            None => self.tail = node.prev,
+        }
+
+        self.len -= 1;
+        self.storage.deallocate(handle, Layout::new::<Node<T, S::Handle>>());
+        node.value
+    }
+
+    pub fn front(&self) -> Option<&T> { self.head.map(|head| unsafe { &self.node(head).value }) }
+
+    pub fn back(&self) -> Option<&T> { self.tail.map(|tail| unsafe { &self.node(tail).value }) }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        let head = self.head?;
+        unsafe { Some(&mut self.node_mut(head).value) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        let tail = self.tail?;
+        unsafe { Some(&mut self.node_mut(tail).value) }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let head = self.head?;
+        unsafe { Some(self.take(head)) }
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let tail = self.tail?;
+        unsafe { Some(self.take(tail)) }
+    }
+
+    pub fn clear(&mut self) {
+        while let Some(head) = self.head {
+            // if a destructor panics, the rest of the list is still in the list
+            drop(unsafe { self.take(head) });
+        }
+    }
+
+    pub const fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            list: self,
+            front: self.head,
+            back: self.tail,
+            remaining: self.len,
+        }
+    }
+
+    fn allocate_node(
+        &mut self,
+        value: T,
+        prev: Option<S::Handle>,
+        next: Option<S::Handle>,
+    ) -> Result<S::Handle, AllocErr<T>> {
+        let handle = match self.storage.allocate(Layout::new::<Node<T, S::Handle>>()) {
+            Ok(memory_block) => memory_block.handle,
+            Err(err) => return Err(err.with(value)),
+        };
+
+        unsafe {
+            self.storage
+                .get_mut(handle)
+                .as_ptr()
+                .cast::<Node<T, S::Handle>>()
+                .write(Node { value, prev, next });
+        }
+
+        Ok(handle)
+    }
+
+    pub fn push_front(&mut self, value: T) { self.try_push_front(value).unwrap_or_else(AllocErr::handle) }
+
+    pub fn push_back(&mut self, value: T) { self.try_push_back(value).unwrap_or_else(AllocErr::handle) }
+
+    /// Pushes `value` onto the front of the list
+    ///
+    /// If the node can't be allocated, `value` is returned in the error
+    pub fn try_push_front(&mut self, value: T) -> Result<(), AllocErr<T>> {
+        let head = self.head;
+        let handle = self.allocate_node(value, None, head)?;
+
+        match head {
+            Some(head) => unsafe { self.node_mut(head).prev = Some(handle) },
+            None => self.tail = Some(handle),
+        }
+
+        self.head = Some(handle);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Pushes `value` onto the back of the list
+    ///
+    /// If the node can't be allocated, `value` is returned in the error
+    pub fn try_push_back(&mut self, value: T) -> Result<(), AllocErr<T>> {
+        let tail = self.tail;
+        let handle = self.allocate_node(value, tail, None)?;
+
+        match tail {
+            Some(tail) => unsafe { self.node_mut(tail).next = Some(handle) },
+            None => self.head = Some(handle),
+        }
+
+        self.tail = Some(handle);
+        self.len += 1;
+        Ok(())
+    }
+}
+
+impl<T, S: MultiStorage> LinkedList<T, S> {
+    pub const fn iter_mut(&mut self) -> IterMut<'_, T, S> {
+        IterMut {
+            front: self.head,
+            back: self.tail,
+            remaining: self.len,
+            list: self,
+        }
+    }
+}
+
+impl<T, S: Storage> Drop for LinkedList<T, S> {
+    fn drop(&mut self) { self.clear() }
+}
+
+impl<T: fmt::Debug, S: Storage> fmt::Debug for LinkedList<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.debug_list().entries(self.iter()).finish() }
+}
+
+/// An iterator over the elements of a [`LinkedList`], created by [`LinkedList::iter`]
+pub struct Iter<'a, T, S: Storage = crate::Global> {
+    list: &'a LinkedList<T, S>,
+    front: Option<S::Handle>,
+    back: Option<S::Handle>,
+    remaining: usize,
+}
+
+impl<'a, T, S: Storage> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None
+        }
+
+        let node = unsafe { self.list.node(self.front?) };
+        self.front = node.next;
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.remaining, Some(self.remaining)) }
+}
+
+impl<T, S: Storage> DoubleEndedIterator for Iter<'_, T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None
+        }
+
+        let node = unsafe { self.list.node(self.back?) };
+        self.back = node.prev;
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+}
+
+impl<T, S: Storage> ExactSizeIterator for Iter<'_, T, S> {}
+
+impl<T, S: Storage> FusedIterator for Iter<'_, T, S> {}
+
+impl<'a, T, S: Storage> IntoIterator for &'a LinkedList<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+/// An iterator over mutable references to the elements of a [`LinkedList`], created by [`LinkedList::iter_mut`]
+///
+/// The references it yields can be alive at the same time, so this needs a [`MultiStorage`]
+pub struct IterMut<'a, T, S: MultiStorage = crate::Global> {
+    list: &'a mut LinkedList<T, S>,
+    front: Option<S::Handle>,
+    back: Option<S::Handle>,
+    remaining: usize,
+}
+
+impl<'a, T, S: MultiStorage> IterMut<'a, T, S> {
+    unsafe fn node(&self, handle: S::Handle) -> &'a mut Node<T, S::Handle> {
+        &mut *self.list.storage.shared_get_mut(handle).as_ptr().cast()
+    }
+}
+
+impl<'a, T, S: MultiStorage> Iterator for IterMut<'a, T, S> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.remaining == 0 {
+            return None
+        }
+
+        let node = unsafe { self.node(self.front?) };
+        self.front = node.next;
+        self.remaining -= 1;
+        Some(&mut node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.remaining, Some(self.remaining)) }
+}
+
+impl<T, S: MultiStorage> DoubleEndedIterator for IterMut<'_, T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None
+        }
+
+        let node = unsafe { self.node(self.back?) };
+        self.back = node.prev;
+        self.remaining -= 1;
+        Some(&mut node.value)
+    }
+}
+
+impl<T, S: MultiStorage> ExactSizeIterator for IterMut<'_, T, S> {}
+
+impl<T, S: MultiStorage> FusedIterator for IterMut<'_, T, S> {}
+
+impl<'a, T, S: MultiStorage> IntoIterator for &'a mut LinkedList<T, S> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter { self.iter_mut() }
+}
+
+#[test]
+fn linked_list() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    {
+        // bump handles are offsets into the bump's buffer, not pointers
+        let mut list = LinkedList::new_in(crate::BumpStorage::<_, 8>::new(&mock, 1024));
+        list.push_back(2);
+        list.push_back(3);
+        list.push_front(1);
+        list.push_front(0);
+        assert!(list.iter().eq(&[0, 1, 2, 3]));
+        assert!(list.iter().rev().eq(&[3, 2, 1, 0]));
+
+        for value in &mut list {
+            *value *= 10;
+        }
+        *list.back_mut().unwrap() += 1;
+        assert_eq!(list.front(), Some(&0));
+        assert_eq!(list.back(), Some(&31));
+
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(31));
+        assert_eq!(list.len(), 2);
+        assert!(list.iter_mut().rev().map(|value| *value).eq([20, 10]));
+    }
+    assert_eq!(mock.live_allocations(), 0);
+
+    let mut list = LinkedList::new_in(&mock);
+    list.push_back(std::string::String::from("a"));
+    list.push_back(std::string::String::from("b"));
+    assert_eq!(std::format!("{list:?}"), "[\"a\", \"b\"]");
+    drop(list);
+    assert_eq!(mock.live_allocations(), 0);
+
+    // running out of space hands the value back
+    let mut list = LinkedList::new_in(crate::BumpStorage::<_, 8>::new(&mock, 64));
+    let mut pushed = 0;
+    while list.try_push_back(pushed).is_ok() {
+        pushed += 1;
+    }
+    assert_eq!(list.try_push_front(100).map_err(AllocErr::defuse), Err(100));
+    assert!(list.iter().copied().eq(0..pushed));
+}
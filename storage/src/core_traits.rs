@@ -28,6 +28,17 @@ pub unsafe trait SharedGetMut: Storage {
 
 pub trait MultiStorage: SharedGetMut {}
 
+/// A storage whose non-empty allocations are always aligned to at least `ALIGN`,
+/// whatever alignment was asked for
+///
+/// # Safety
+///
+/// `ALIGN` must be a power of two, and every pointer to a non-empty allocation
+/// must be aligned to at least `ALIGN`
+pub unsafe trait AlignGuarantee: Storage {
+    const ALIGN: usize;
+}
+
 pub unsafe trait Storage {
     type Handle: Handle;
 
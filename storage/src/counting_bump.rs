@@ -9,27 +9,33 @@ use crate::{
     OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 
+/// A [`BumpStorage`] that counts its live allocations, and frees everything
+/// once the last of them is deallocated
 #[must_use = "storages don't do anything unless they are used"]
 pub struct CountingBumpStorage<S: Storage, const MAX_ALIGN: usize> {
     bump: BumpStorage<S, MAX_ALIGN>,
-    max_offset: usize,
     count: AtomicUsize,
 }
 
 impl<S: Storage, const MAX_ALIGN: usize> CountingBumpStorage<S, MAX_ALIGN> {
     pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
 
-    pub fn remaining_space(&self) -> usize { self.bump.remaining_space() }
+    pub const fn capacity(&self) -> usize { self.bump.capacity() }
+
+    pub fn used(&self) -> usize { self.bump.used() }
+
+    pub fn remaining(&self) -> usize { self.bump.remaining() }
+
+    #[deprecated = "use `remaining` instead"]
+    pub fn remaining_space(&self) -> usize { self.remaining() }
 
     /// # Panics
     ///
     /// if `Layout::from_size_align(space, MAX_ALIGN.next_power_of_two())` returns Err
     pub fn try_new(storage: S, space: usize) -> Result<Self, AllocErr> {
-        let bump = BumpStorage::try_new(storage, space)?;
         Ok(Self {
+            bump: BumpStorage::try_new(storage, space)?,
             count: AtomicUsize::new(0),
-            max_offset: bump.remaining_space(),
-            bump,
         })
     }
 }
@@ -48,8 +54,12 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedOffsetHandle for Coun
 
 unsafe impl<S: Storage, const MAX_ALIGN: usize> FromPtr for CountingBumpStorage<S, MAX_ALIGN> {
     #[inline]
-    #[allow(clippy::cast_sign_loss)]
     unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.bump.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.bump.from_ptr_mut(ptr, layout)
+    }
 }
 
 unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedGetMut for CountingBumpStorage<S, MAX_ALIGN> {
@@ -75,7 +85,7 @@ unsafe impl<S: Storage, const MAX_ALIGN: usize> Storage for CountingBumpStorage<
         let count = self.count.get_mut();
         *count -= 1;
         if *count == 0 {
-            self.bump.reset(self.max_offset)
+            self.bump.reset();
         }
     }
 }
@@ -117,9 +127,9 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedStorage for CountingB
     }
 
     unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, _: NonEmptyLayout) {
-        let current_offset = self.bump.remaining_space();
+        let marker = self.bump.checkpoint();
         if 1 == self.count.fetch_sub(1, Ordering::Relaxed) {
-            self.bump.shared_reset_if_eq(current_offset, self.max_offset);
+            self.bump.shared_reset_if_eq(marker);
         }
     }
 }
@@ -152,3 +162,21 @@ unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for
         self.bump.shared_shrink(handle, old, new)
     }
 }
+
+#[test]
+fn counting_bump() {
+    let mut storage = CountingBumpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 64);
+
+    let a = storage.allocate(Layout::new::<u64>()).unwrap();
+    let b = storage.shared_allocate(Layout::new::<[u64; 2]>()).unwrap();
+    assert_eq!(storage.used(), 24);
+
+    unsafe {
+        storage.deallocate(a.handle, Layout::new::<u64>());
+        assert_eq!(storage.used(), 24);
+        storage.shared_deallocate(b.handle, Layout::new::<[u64; 2]>());
+    }
+
+    // the last deallocation frees everything
+    assert_eq!(storage.used(), 0);
+}
@@ -1,7 +1,7 @@
 use core::{
     alloc::Layout,
     ptr::NonNull,
-    sync::atomic::{AtomicU8, Ordering},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use crate::{
@@ -9,20 +9,26 @@ use crate::{
     ResizableStorage, SharedFlush, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 
-const THRESHOLD: u8 = 128;
-
+/// A storage that flushes the underlying storage after every `THRESHOLD` operations
+///
+/// If `BYTES` is set, it instead flushes after every `THRESHOLD` bytes that were deallocated
+/// (or given back by shrinking), which follows how much memory is waiting to be flushed
+/// much more closely than the number of operations does.
 #[must_use = "storages don't do anything unless they are used"]
-pub struct CountingFlushStorage<S> {
+pub struct CountingFlushStorage<S, const THRESHOLD: usize = 128, const BYTES: bool = false> {
     pub storage: S,
-    count: AtomicU8,
+    count: AtomicUsize,
 }
 
-impl<S: Storage + Flush> CountingFlushStorage<S> {
+/// A [`CountingFlushStorage`] that flushes after every `THRESHOLD` bytes deallocated
+pub type ByteCountingFlushStorage<S, const THRESHOLD: usize> = CountingFlushStorage<S, THRESHOLD, true>;
+
+impl<S: Storage + Flush, const THRESHOLD: usize, const BYTES: bool> CountingFlushStorage<S, THRESHOLD, BYTES> {
     #[inline]
     pub fn new(storage: S) -> Self {
         Self {
             storage,
-            count: AtomicU8::new(0),
+            count: AtomicUsize::new(0),
         }
     }
 
@@ -39,30 +45,50 @@ impl<S: Storage + Flush> CountingFlushStorage<S> {
         self.storage.shared_flush()
     }
 
+    // how much an operation that gave back `freed` bytes counts towards the threshold
+    #[inline]
+    const fn amount(freed: usize) -> usize {
+        if BYTES {
+            freed
+        } else {
+            1
+        }
+    }
+
     #[inline]
-    fn count(&mut self) {
+    fn count(&mut self, amount: usize) {
+        if amount == 0 {
+            return
+        }
+
         let count = self.count.get_mut();
-        if *count > THRESHOLD {
+        *count = count.saturating_add(amount);
+        if *count >= THRESHOLD {
             *count = 0;
             self.flush_slow()
-        } else {
-            *count += 1;
         }
     }
 
     #[inline]
-    fn shared_count(&self)
+    fn shared_count(&self, amount: usize)
     where
         S: SharedFlush,
     {
-        if self.count.fetch_add(1, Ordering::Relaxed) > THRESHOLD {
-            self.count.fetch_sub(THRESHOLD, Ordering::Relaxed);
+        if amount == 0 {
+            return
+        }
+
+        // only the thread that takes the whole count flushes, the counts that race with
+        // it may be lost, but that only delays the next flush a bit
+        if self.count.fetch_add(amount, Ordering::Relaxed).saturating_add(amount) >= THRESHOLD
+            && self.count.swap(0, Ordering::Relaxed) >= THRESHOLD
+        {
             self.shared_flush_slow()
         }
     }
 }
 
-impl<S: Flush> Flush for CountingFlushStorage<S> {
+impl<S: Flush, const THRESHOLD: usize, const BYTES: bool> Flush for CountingFlushStorage<S, THRESHOLD, BYTES> {
     #[inline]
     fn try_flush(&mut self) -> bool {
         *self.count.get_mut() = 0;
@@ -76,7 +102,9 @@ impl<S: Flush> Flush for CountingFlushStorage<S> {
     }
 }
 
-impl<S: SharedFlush> SharedFlush for CountingFlushStorage<S> {
+impl<S: SharedFlush, const THRESHOLD: usize, const BYTES: bool> SharedFlush
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     fn try_shared_flush(&self) -> bool {
         self.count.store(0, Ordering::Relaxed);
@@ -90,36 +118,51 @@ impl<S: SharedFlush> SharedFlush for CountingFlushStorage<S> {
     }
 }
 
-unsafe impl<S: OffsetHandle + Flush> OffsetHandle for CountingFlushStorage<S> {
+unsafe impl<S: OffsetHandle + Flush, const THRESHOLD: usize, const BYTES: bool> OffsetHandle
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
         self.storage.offset(handle, offset)
     }
 }
 
-unsafe impl<S: SharedOffsetHandle + SharedFlush> SharedOffsetHandle for CountingFlushStorage<S> {
+unsafe impl<S: SharedOffsetHandle + SharedFlush, const THRESHOLD: usize, const BYTES: bool> SharedOffsetHandle
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
         self.storage.shared_offset(handle, offset)
     }
 }
 
-unsafe impl<S: FromPtr + Flush> FromPtr for CountingFlushStorage<S> {
+unsafe impl<S: FromPtr + Flush, const THRESHOLD: usize, const BYTES: bool> FromPtr
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
 
     #[inline]
-    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr_mut(ptr, layout) }
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
 }
 
-unsafe impl<S: SharedGetMut + Flush> SharedGetMut for CountingFlushStorage<S> {
+unsafe impl<S: SharedGetMut + Flush, const THRESHOLD: usize, const BYTES: bool> SharedGetMut
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
 }
 
-impl<S: MultiStorage + Flush> MultiStorage for CountingFlushStorage<S> {}
+impl<S: MultiStorage + Flush, const THRESHOLD: usize, const BYTES: bool> MultiStorage
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
+}
 
-unsafe impl<S: Storage + Flush> Storage for CountingFlushStorage<S> {
+unsafe impl<S: Storage + Flush, const THRESHOLD: usize, const BYTES: bool> Storage
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     type Handle = S::Handle;
 
     #[inline]
@@ -130,26 +173,26 @@ unsafe impl<S: Storage + Flush> Storage for CountingFlushStorage<S> {
 
     #[inline]
     fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.count();
+        self.count(Self::amount(0));
         self.storage.allocate_nonempty(layout)
     }
 
     #[inline]
     unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
         self.storage.deallocate_nonempty(handle, layout);
-        self.count();
+        self.count(Self::amount(layout.size()));
     }
 
     #[inline]
     fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.count();
+        self.count(Self::amount(0));
         self.storage.allocate(layout)
     }
 
     #[inline]
     unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
         self.storage.deallocate(handle, layout);
-        self.count();
+        self.count(Self::amount(layout.size()));
     }
 
     #[inline]
@@ -157,18 +200,20 @@ unsafe impl<S: Storage + Flush> Storage for CountingFlushStorage<S> {
         &mut self,
         layout: NonEmptyLayout,
     ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.count();
+        self.count(Self::amount(0));
         self.storage.allocate_nonempty_zeroed(layout)
     }
 
     #[inline]
     fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.count();
+        self.count(Self::amount(0));
         self.storage.allocate_zeroed(layout)
     }
 }
 
-unsafe impl<S: ResizableStorage + Flush> ResizableStorage for CountingFlushStorage<S> {
+unsafe impl<S: ResizableStorage + Flush, const THRESHOLD: usize, const BYTES: bool> ResizableStorage
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     unsafe fn grow(
         &mut self,
@@ -177,7 +222,7 @@ unsafe impl<S: ResizableStorage + Flush> ResizableStorage for CountingFlushStora
         new: Layout,
     ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         let memory_block = self.storage.grow(handle, old, new);
-        self.count();
+        self.count(Self::amount(0));
         memory_block
     }
 
@@ -189,7 +234,7 @@ unsafe impl<S: ResizableStorage + Flush> ResizableStorage for CountingFlushStora
         new: Layout,
     ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         let memory_block = self.storage.grow_zeroed(handle, old, new);
-        self.count();
+        self.count(Self::amount(0));
         memory_block
     }
 
@@ -201,34 +246,36 @@ unsafe impl<S: ResizableStorage + Flush> ResizableStorage for CountingFlushStora
         new: Layout,
     ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         let memory_block = self.storage.shrink(handle, old, new);
-        self.count();
+        self.count(Self::amount(old.size() - new.size()));
         memory_block
     }
 }
 
-unsafe impl<S: SharedStorage + SharedFlush> SharedStorage for CountingFlushStorage<S> {
+unsafe impl<S: SharedStorage + SharedFlush, const THRESHOLD: usize, const BYTES: bool> SharedStorage
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.shared_count();
+        self.shared_count(Self::amount(0));
         self.storage.shared_allocate_nonempty(layout)
     }
 
     #[inline]
     unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
         self.storage.shared_deallocate_nonempty(handle, layout);
-        self.shared_count();
+        self.shared_count(Self::amount(layout.size()));
     }
 
     #[inline]
     fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.shared_count();
+        self.shared_count(Self::amount(0));
         self.storage.shared_allocate(layout)
     }
 
     #[inline]
     unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
         self.storage.shared_deallocate(handle, layout);
-        self.shared_count();
+        self.shared_count(Self::amount(layout.size()));
     }
 
     #[inline]
@@ -236,18 +283,20 @@ unsafe impl<S: SharedStorage + SharedFlush> SharedStorage for CountingFlushStora
         &self,
         layout: NonEmptyLayout,
     ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.shared_count();
+        self.shared_count(Self::amount(0));
         self.storage.shared_allocate_nonempty_zeroed(layout)
     }
 
     #[inline]
     fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.shared_count();
+        self.shared_count(Self::amount(0));
         self.storage.shared_allocate_zeroed(layout)
     }
 }
 
-unsafe impl<S: SharedResizableStorage + SharedFlush> SharedResizableStorage for CountingFlushStorage<S> {
+unsafe impl<S: SharedResizableStorage + SharedFlush, const THRESHOLD: usize, const BYTES: bool> SharedResizableStorage
+    for CountingFlushStorage<S, THRESHOLD, BYTES>
+{
     #[inline]
     unsafe fn shared_grow(
         &self,
@@ -256,7 +305,7 @@ unsafe impl<S: SharedResizableStorage + SharedFlush> SharedResizableStorage for
         new: Layout,
     ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         let memory_block = self.storage.shared_grow(handle, old, new);
-        self.shared_count();
+        self.shared_count(Self::amount(0));
         memory_block
     }
 
@@ -268,7 +317,7 @@ unsafe impl<S: SharedResizableStorage + SharedFlush> SharedResizableStorage for
         new: Layout,
     ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         let memory_block = self.storage.shared_grow_zeroed(handle, old, new);
-        self.shared_count();
+        self.shared_count(Self::amount(0));
         memory_block
     }
 
@@ -280,7 +329,52 @@ unsafe impl<S: SharedResizableStorage + SharedFlush> SharedResizableStorage for
         new: Layout,
     ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         let memory_block = self.storage.shared_shrink(handle, old, new);
-        self.shared_count();
+        self.shared_count(Self::amount(old.size() - new.size()));
         memory_block
     }
 }
+
+#[test]
+fn counting_flush() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = CountingFlushStorage::<_>::new(crate::DeferredFreeStorage::new(crate::FlushBarrier::new(&mock)));
+
+    let layout = Layout::new::<u64>();
+    for _ in 0..128 {
+        let memory_block = storage.allocate(layout).unwrap();
+        unsafe { storage.deallocate(memory_block.handle, layout) }
+    }
+
+    // the deallocations were flushed once enough operations were made
+    assert!(mock.live_allocations() < 128);
+
+    storage.flush();
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn byte_counting_flush() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage =
+        ByteCountingFlushStorage::<_, 256>::new(crate::DeferredFreeStorage::new(crate::FlushBarrier::new(&mock)));
+
+    // allocating doesn't count, so nothing is flushed until 256 bytes are deallocated
+    let small = Layout::new::<[u8; 64]>();
+    let blocks = [(); 3].map(|()| storage.allocate(small).unwrap());
+    for memory_block in blocks {
+        unsafe { storage.deallocate(memory_block.handle, small) }
+    }
+    assert_eq!(mock.live_allocations(), 3);
+
+    let large = Layout::new::<[u8; 128]>();
+    let memory_block = storage.allocate(large).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, large) }
+    assert_eq!(mock.live_allocations(), 0);
+
+    crate::storage_conformance!(
+        ByteCountingFlushStorage::<_, 256>::new(crate::DeferredFreeStorage::new(crate::FlushBarrier::new(&mock))),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
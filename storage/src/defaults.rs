@@ -1,12 +1,29 @@
-use crate::{AllocErr, MemoryBlock, MultiStorage};
+use crate::{AllocErr, Handle, MemoryBlock, MultiStorage};
 use core::alloc::Layout;
 
+// zero-sized allocations never need the underlying storage
+fn dangling<H: Handle>(layout: Layout) -> MemoryBlock<H> {
+    MemoryBlock {
+        handle: unsafe { H::dangling(layout.align()) },
+        size: 0,
+    }
+}
+
 pub unsafe fn grow<S: MultiStorage>(
     mut storage: S,
     handle: S::Handle,
     old: Layout,
     new: Layout,
 ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+    if new.size() == 0 {
+        return Ok(dangling(new))
+    }
+
+    // there's nothing to copy out of a zero-sized allocation, or to deallocate
+    if old.size() == 0 {
+        return storage.allocate(new)
+    }
+
     let memory_block = storage.allocate(new)?;
     let old_ptr = storage.get(handle);
     let new_ptr = storage.shared_get_mut(memory_block.handle);
@@ -21,6 +38,14 @@ pub unsafe fn grow_zeroed<S: MultiStorage>(
     old: Layout,
     new: Layout,
 ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+    if new.size() == 0 {
+        return Ok(dangling(new))
+    }
+
+    if old.size() == 0 {
+        return storage.allocate_zeroed(new)
+    }
+
     let memory_block = storage.allocate_zeroed(new)?;
     let old_ptr = storage.get(handle);
     let new_ptr = storage.shared_get_mut(memory_block.handle);
@@ -35,6 +60,11 @@ pub unsafe fn shrink<S: MultiStorage>(
     old: Layout,
     new: Layout,
 ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+    if new.size() == 0 {
+        storage.deallocate(handle, old);
+        return Ok(dangling(new))
+    }
+
     let memory_block = storage.allocate_zeroed(new)?;
     let old_ptr = storage.get(handle);
     let new_ptr = storage.shared_get_mut(memory_block.handle);
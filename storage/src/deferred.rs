@@ -0,0 +1,317 @@
+use core::{alloc::Layout, cell::UnsafeCell, mem, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+// deferred blocks are linked together through their own memory
+struct Node<H> {
+    next: Option<H>,
+    layout: NonEmptyLayout,
+}
+
+struct Epochs<H> {
+    epoch: usize,
+    // blocks deallocated in the current epoch
+    current: Option<H>,
+    // blocks deallocated in the previous epoch, freed once the current one ends
+    previous: Option<H>,
+}
+
+/// A storage that defers frees until the end of the next epoch
+///
+/// Deallocating only queues up the block, it is freed once [`advance`](Self::advance) has been
+/// called twice. So anyone who could see the block when it was deallocated has until the end of
+/// the following epoch to stop using it, which is the grace period that lock-free data structures
+/// need before memory is reused.
+///
+/// Every allocation is made large enough to hold the link in the queue.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct DeferredFreeStorage<S: Storage> {
+    storage: S,
+    epochs: UnsafeCell<Epochs<S::Handle>>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Storage + Send> Send for DeferredFreeStorage<S> where S::Handle: Send {}
+unsafe impl<S: Storage + Sync> Sync for DeferredFreeStorage<S> where S::Handle: Send {}
+
+impl<S: Storage> DeferredFreeStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            epochs: UnsafeCell::new(Epochs {
+                epoch: 0,
+                current: None,
+                previous: None,
+            }),
+            lock: SpinLock::new(),
+        }
+    }
+
+    /// The number of times the epoch was advanced
+    pub fn epoch(&self) -> usize {
+        let _guard = self.lock.lock();
+        unsafe { (*self.epochs.get()).epoch }
+    }
+
+    fn padded(layout: Layout) -> Result<NonEmptyLayout, AllocErr> {
+        let node = Layout::new::<Node<S::Handle>>();
+
+        Layout::from_size_align(layout.size().max(node.size()), layout.align().max(node.align()))
+            .ok()
+            .and_then(NonEmptyLayout::new)
+            .ok_or_else(|| AllocErr::new(layout))
+    }
+
+    // the layout was already padded when the block was allocated
+    unsafe fn padded_unchecked(layout: Layout) -> NonEmptyLayout {
+        Self::padded(layout).unwrap_or_else(|_| core::hint::unreachable_unchecked())
+    }
+
+    const unsafe fn push(ptr: NonNull<u8>, head: &mut Option<S::Handle>, handle: S::Handle, layout: NonEmptyLayout) {
+        ptr.as_ptr().cast::<Node<S::Handle>>().write(Node {
+            next: head.replace(handle),
+            layout,
+        });
+    }
+
+    // frees every block in the list starting at `head`
+    unsafe fn free_list(&mut self, mut head: Option<S::Handle>) {
+        while let Some(handle) = head {
+            let node = self.storage.get_mut(handle).as_ptr().cast::<Node<S::Handle>>().read();
+            head = node.next;
+            self.storage.deallocate_nonempty(handle, node.layout);
+        }
+    }
+
+    /// Frees every deferred block, this doesn't need a grace period because there are
+    /// no other references to the storage
+    pub fn free_all(&mut self) {
+        let epochs = self.epochs.get_mut();
+        let current = epochs.current.take();
+        let previous = epochs.previous.take();
+
+        unsafe {
+            self.free_list(previous);
+            self.free_list(current);
+        }
+    }
+}
+
+impl<S: SharedStorage> DeferredFreeStorage<S> {
+    unsafe fn shared_free_list(&self, mut head: Option<S::Handle>) {
+        while let Some(handle) = head {
+            let node = self
+                .storage
+                .shared_get_mut(handle)
+                .as_ptr()
+                .cast::<Node<S::Handle>>()
+                .read();
+            head = node.next;
+            self.storage.shared_deallocate_nonempty(handle, node.layout);
+        }
+    }
+
+    /// Ends the current epoch, and frees the blocks that were deallocated in the previous one
+    pub fn advance(&self) {
+        let expired = {
+            let _guard = self.lock.lock();
+            let epochs = unsafe { &mut *self.epochs.get() };
+            epochs.epoch = epochs.epoch.wrapping_add(1);
+            mem::replace(&mut epochs.previous, epochs.current.take())
+        };
+
+        unsafe { self.shared_free_list(expired) }
+    }
+}
+
+impl<S: Storage> Drop for DeferredFreeStorage<S> {
+    fn drop(&mut self) { self.free_all() }
+}
+
+impl<S: Storage + Flush> Flush for DeferredFreeStorage<S> {
+    fn try_flush(&mut self) -> bool {
+        self.free_all();
+        self.storage.try_flush()
+    }
+
+    fn flush(&mut self) {
+        self.free_all();
+        self.storage.flush();
+    }
+}
+
+/// Flushing through a shared reference only advances the epoch, because
+/// the most recently deallocated blocks may still be in use
+impl<S: SharedStorage + SharedFlush> SharedFlush for DeferredFreeStorage<S> {
+    fn try_shared_flush(&self) -> bool {
+        self.advance();
+        self.storage.try_shared_flush()
+    }
+
+    fn shared_flush(&self) {
+        self.advance();
+        self.storage.shared_flush();
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for DeferredFreeStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr(ptr, Self::padded_unchecked(layout).into())
+    }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, Self::padded_unchecked(layout).into())
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for DeferredFreeStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for DeferredFreeStorage<S> {}
+
+unsafe impl<S: Storage> Storage for DeferredFreeStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.allocate_nonempty(Self::padded(layout.into())?)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let layout = Self::padded_unchecked(layout.into());
+        let ptr = self.storage.get_mut(handle);
+        Self::push(ptr, &mut self.epochs.get_mut().current, handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.allocate_nonempty_zeroed(Self::padded(layout.into())?)
+    }
+}
+
+// the old block has to outlive its grace period, so resizing always moves the allocation
+unsafe impl<S: MultiStorage> ResizableStorage for DeferredFreeStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for DeferredFreeStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_allocate_nonempty(Self::padded(layout.into())?)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let layout = Self::padded_unchecked(layout.into());
+        let ptr = self.storage.shared_get_mut(handle);
+        let _guard = self.lock.lock();
+        Self::push(ptr, &mut (*self.epochs.get()).current, handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage
+            .shared_allocate_nonempty_zeroed(Self::padded(layout.into())?)
+    }
+}
+
+unsafe impl<S: SharedStorage + MultiStorage> SharedResizableStorage for DeferredFreeStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn deferred() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let storage = DeferredFreeStorage::new(&mock);
+
+    let layout = Layout::new::<u8>();
+    // the block is padded to fit the link in the queue
+    let padded = Layout::new::<Node<NonNull<u8>>>();
+    let handle = storage.shared_allocate(layout).unwrap().handle;
+    unsafe { storage.shared_deallocate(handle, layout) }
+
+    // the block survives the end of the epoch it was deallocated in
+    storage.advance();
+    assert_eq!(storage.epoch(), 1);
+    assert_eq!(mock.live_allocations(), 1);
+
+    storage.advance();
+    assert_eq!(mock.live_allocations(), 0);
+
+    let handle = storage.shared_allocate(layout).unwrap().handle;
+    unsafe { storage.shared_deallocate(handle, layout) }
+    drop(storage);
+
+    mock.assert_events(&[
+        crate::Event::Allocate(padded),
+        crate::Event::Deallocate(padded),
+        crate::Event::Allocate(padded),
+        crate::Event::Deallocate(padded),
+    ]);
+
+    crate::storage_conformance!(DeferredFreeStorage::new(mock), resizable, shared, shared_resizable);
+}
@@ -0,0 +1,244 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+use crate::{
+    AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage,
+    SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that logs everything it does with [`defmt`]
+///
+/// Successful operations are logged at the `trace` level, and failed allocations at the `warn` level,
+/// so which of them make it into the binary is decided at compile time by `DEFMT_LOG`. Allocations are
+/// identified by their address.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct DefmtStorage<S> {
+    storage: S,
+}
+
+impl<S> DefmtStorage<S> {
+    pub const fn new(storage: S) -> Self { Self { storage } }
+}
+
+impl<S: Storage> DefmtStorage<S> {
+    fn allocated(
+        &self,
+        layout: NonEmptyLayout,
+        result: Result<NonEmptyMemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<NonEmptyMemoryBlock<S::Handle>, AllocErr> {
+        if let Ok(memory_block) = &result {
+            let ptr = unsafe { self.storage.get(memory_block.handle) };
+            defmt::trace!(
+                "allocated {=usize} bytes (align {=usize}) at {=usize:#x}",
+                memory_block.size.get(),
+                layout.align(),
+                ptr.as_ptr() as usize
+            );
+        } else {
+            defmt::warn!(
+                "failed to allocate {=usize} bytes (align {=usize})",
+                layout.size(),
+                layout.align()
+            );
+        }
+
+        result
+    }
+
+    fn resized(
+        &self,
+        old: Layout,
+        new: Layout,
+        result: Result<MemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+        match &result {
+            // shrinking to zero bytes leaves a dangling handle, which can't be read
+            Ok(memory_block) if memory_block.size == 0 => {
+                defmt::trace!("resized {=usize} bytes to 0 bytes", old.size());
+            }
+            Ok(memory_block) => {
+                let ptr = unsafe { self.storage.get(memory_block.handle) };
+                defmt::trace!(
+                    "resized {=usize} bytes to {=usize} bytes (align {=usize}) at {=usize:#x}",
+                    old.size(),
+                    memory_block.size,
+                    new.align(),
+                    ptr.as_ptr() as usize
+                );
+            }
+            Err(_) => defmt::warn!(
+                "failed to resize {=usize} bytes to {=usize} bytes (align {=usize})",
+                old.size(),
+                new.size(),
+                new.align()
+            ),
+        }
+
+        result
+    }
+
+    fn deallocated(ptr: NonNull<u8>, layout: NonEmptyLayout) {
+        defmt::trace!(
+            "deallocated {=usize} bytes (align {=usize}) at {=usize:#x}",
+            layout.size(),
+            layout.align(),
+            ptr.as_ptr() as usize
+        );
+    }
+}
+
+impl<S: Flush> Flush for DefmtStorage<S> {
+    fn try_flush(&mut self) -> bool {
+        let flushed = self.storage.try_flush();
+        defmt::trace!("flushed: {=bool}", flushed);
+        flushed
+    }
+
+    fn flush(&mut self) {
+        self.storage.flush();
+        defmt::trace!("flushed");
+    }
+}
+
+impl<S: SharedFlush> SharedFlush for DefmtStorage<S> {
+    fn try_shared_flush(&self) -> bool {
+        let flushed = self.storage.try_shared_flush();
+        defmt::trace!("flushed: {=bool}", flushed);
+        flushed
+    }
+
+    fn shared_flush(&self) {
+        self.storage.shared_flush();
+        defmt::trace!("flushed");
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for DefmtStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for DefmtStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for DefmtStorage<S> {}
+
+unsafe impl<S: Storage> Storage for DefmtStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.allocate_nonempty(layout);
+        self.allocated(layout, result)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        Self::deallocated(self.storage.get(handle), layout);
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.allocate_nonempty_zeroed(layout);
+        self.allocated(layout, result)
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for DefmtStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.grow(handle, old, new);
+        self.resized(old, new, result)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.grow_zeroed(handle, old, new);
+        self.resized(old, new, result)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.shrink(handle, old, new);
+        self.resized(old, new, result)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for DefmtStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocated(layout, self.storage.shared_allocate_nonempty(layout))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        Self::deallocated(self.storage.get(handle), layout);
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocated(layout, self.storage.shared_allocate_nonempty_zeroed(layout))
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for DefmtStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.resized(old, new, self.storage.shared_grow(handle, old, new))
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.resized(old, new, self.storage.shared_grow_zeroed(handle, old, new))
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.resized(old, new, self.storage.shared_shrink(handle, old, new))
+    }
+}
+
+#[test]
+fn defmt_storage() {
+    let storage = DefmtStorage::new(crate::LeakCheck::new(crate::AllocatorStorage::new(std::alloc::System)));
+    crate::storage_conformance!(&storage, resizable, shared, shared_resizable);
+    drop(storage);
+}
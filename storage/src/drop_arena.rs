@@ -0,0 +1,145 @@
+use crate::{AllocErr, BumpHandle, BumpStorage, Marker, SharedGetMut, SharedStorage, Storage};
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+};
+
+// values that need to be dropped are allocated after a header, which links them together
+#[derive(Clone, Copy)]
+struct Header {
+    next: Option<BumpHandle>,
+    drop: unsafe fn(NonNull<u8>),
+}
+
+#[repr(C)]
+struct Node<T> {
+    header: Header,
+    value: T,
+}
+
+unsafe fn drop_node<T>(node: NonNull<u8>) {
+    ptr::drop_in_place(ptr::addr_of_mut!((*node.cast::<Node<T>>().as_ptr()).value));
+}
+
+/// An arena over a [`BumpStorage`] that runs the destructors of the values allocated in it
+///
+/// Destructors are run in reverse allocation order, when the arena is [reset](DropArena::reset)
+/// or dropped. Values that don't need to be dropped take no more space than in the bump storage.
+pub struct DropArena<S: Storage, const MAX_ALIGN: usize> {
+    bump: BumpStorage<S, MAX_ALIGN>,
+    start: Marker,
+    drops: Cell<Option<BumpHandle>>,
+    // the arena may hold values of any type, so it can't be sent to another thread
+    __: PhantomData<*mut ()>,
+}
+
+impl<S: Storage, const MAX_ALIGN: usize> DropArena<S, MAX_ALIGN> {
+    pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
+
+    /// # Panics
+    ///
+    /// if `Layout::from_size_align(space, MAX_ALIGN.next_power_of_two())` returns Err
+    pub fn try_new(storage: S, space: usize) -> Result<Self, AllocErr> {
+        let bump = BumpStorage::try_new(storage, space)?;
+
+        Ok(Self {
+            start: bump.checkpoint(),
+            bump,
+            drops: Cell::new(None),
+            __: PhantomData,
+        })
+    }
+
+    pub const fn storage(&self) -> &BumpStorage<S, MAX_ALIGN> { &self.bump }
+
+    /// Drops every value in the arena, and frees all of their memory
+    pub fn reset(&mut self) {
+        self.run_drops();
+        unsafe { self.bump.reset_to(self.start) }
+    }
+
+    fn run_drops(&mut self) {
+        while let Some(handle) = *self.drops.get_mut() {
+            unsafe {
+                let node = self.bump.get_mut(handle);
+                let header = node.cast::<Header>().as_ptr().read();
+                *self.drops.get_mut() = header.next;
+                (header.drop)(node);
+            }
+        }
+    }
+}
+
+impl<S: SharedGetMut, const MAX_ALIGN: usize> DropArena<S, MAX_ALIGN> {
+    pub fn alloc<T: 'static>(&self, value: T) -> &mut T { self.try_alloc(value).unwrap_or_else(AllocErr::handle) }
+
+    /// Moves `value` into the arena, it will be dropped when the arena is reset or dropped
+    ///
+    /// Values can't borrow anything, because they may be dropped long after they are allocated
+    ///
+    /// # Errors
+    ///
+    /// If the arena is out of space
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T: 'static>(&self, value: T) -> Result<&mut T, AllocErr> {
+        if !mem::needs_drop::<T>() {
+            let ptr: NonNull<T> = if mem::size_of::<T>() == 0 {
+                NonNull::dangling()
+            } else {
+                let handle = self.bump.shared_allocate(Layout::new::<T>())?.handle;
+                unsafe { self.bump.shared_get_mut(handle).cast() }
+            };
+
+            unsafe {
+                ptr.as_ptr().write(value);
+                return Ok(&mut *ptr.as_ptr())
+            }
+        }
+
+        let handle = self.bump.shared_allocate(Layout::new::<Node<T>>())?.handle;
+
+        unsafe {
+            let node = self.bump.shared_get_mut(handle).cast::<Node<T>>().as_ptr();
+            node.write(Node {
+                header: Header {
+                    next: self.drops.get(),
+                    drop: drop_node::<T>,
+                },
+                value,
+            });
+            self.drops.set(Some(handle));
+            Ok(&mut (*node).value)
+        }
+    }
+}
+
+impl<S: Storage, const MAX_ALIGN: usize> Drop for DropArena<S, MAX_ALIGN> {
+    fn drop(&mut self) { self.run_drops() }
+}
+
+#[test]
+fn drop_arena() {
+    use std::{rc::Rc, vec::Vec};
+
+    let counter = Rc::new(());
+    let mut arena = DropArena::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 1024);
+
+    let first = arena.alloc(Rc::clone(&counter));
+    assert_eq!(Rc::strong_count(first), 2);
+    arena.alloc(10_u64);
+    arena.alloc(());
+    arena.alloc(Vec::from([Rc::clone(&counter), Rc::clone(&counter)]));
+    assert_eq!(Rc::strong_count(&counter), 4);
+
+    let remaining = arena.storage().remaining();
+    arena.reset();
+    assert_eq!(Rc::strong_count(&counter), 1);
+    assert!(arena.storage().remaining() > remaining);
+
+    arena.alloc(Rc::clone(&counter));
+    drop(arena);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
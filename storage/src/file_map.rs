@@ -0,0 +1,295 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{fs::File, io};
+
+use crate::{
+    os, AllocErr, Flush, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedFlush, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A bump storage over a memory mapped file
+///
+/// Handles are offsets from the start of the file, so they stay meaningful
+/// after the file is mapped again. Flushing writes all changes back to the file.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct FileMapStorage {
+    start: NonNull<u8>,
+    len: usize,
+    offset: AtomicUsize,
+}
+
+unsafe impl Send for FileMapStorage {}
+unsafe impl Sync for FileMapStorage {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMapHandle(usize);
+
+unsafe impl Handle for FileMapHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl FileMapHandle {
+    /// A handle to the memory at `offset` bytes from the start of the file
+    pub const fn new(offset: usize) -> Self { Self(offset) }
+
+    #[must_use = "`FileMapHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+
+    /// The offset of this allocation from the start of the file
+    pub const fn offset(self) -> usize { self.0 }
+}
+
+impl FileMapStorage {
+    /// Maps the first `len` bytes of `file`, extending it if it is shorter than that
+    ///
+    /// The mapping starts out empty, see [`FileMapStorage::set_used`] to keep data that
+    /// was allocated before the file was mapped.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be extended or mapped
+    pub fn new(file: &File, len: usize) -> io::Result<Self> {
+        let file_len = file.metadata()?.len();
+
+        if file_len < len as u64 {
+            file.set_len(len as u64)?;
+        }
+
+        let start = unsafe { os::map_file(file, len) }.ok_or_else(io::Error::last_os_error)?;
+
+        Ok(Self {
+            start,
+            len,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    pub const fn len(&self) -> usize { self.len }
+
+    pub const fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// The number of bytes from the start of the file that are allocated
+    pub fn used(&self) -> usize { self.offset.load(Ordering::Relaxed) }
+
+    /// Marks the first `used` bytes of the file as allocated
+    ///
+    /// # Safety
+    ///
+    /// `used` must not be larger than `len`, and there must not be any
+    /// live allocations past `used`
+    pub unsafe fn set_used(&mut self, used: usize) {
+        debug_assert!(used <= self.len);
+        *self.offset.get_mut() = used;
+    }
+
+    // the new end of the allocation, if it can be placed at `offset`
+    fn bump(&self, offset: usize, layout: Layout) -> Option<(usize, usize)> {
+        // the mapping is only page aligned
+        if layout.align() > os::page_size() {
+            return None
+        }
+
+        let start = offset.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        (end <= self.len).then_some((start, end))
+    }
+
+    fn sync(&self) -> bool { unsafe { os::sync(self.start, self.len) } }
+}
+
+impl Drop for FileMapStorage {
+    fn drop(&mut self) { unsafe { os::unmap_file(self.start, self.len) } }
+}
+
+impl Flush for FileMapStorage {
+    fn try_flush(&mut self) -> bool { self.sync() }
+}
+
+impl SharedFlush for FileMapStorage {
+    fn try_shared_flush(&self) -> bool { self.sync() }
+}
+
+unsafe impl OffsetHandle for FileMapStorage {
+    unsafe fn offset(&mut self, FileMapHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        FileMapHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for FileMapStorage {
+    unsafe fn shared_offset(&self, FileMapHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        FileMapHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl FromPtr for FileMapStorage {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        FileMapHandle(ptr.as_ptr().offset_from(self.start.as_ptr()) as usize)
+    }
+}
+
+unsafe impl SharedGetMut for FileMapStorage {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
+}
+
+impl MultiStorage for FileMapStorage {}
+
+unsafe impl Storage for FileMapStorage {
+    type Handle = FileMapHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        NonNull::new_unchecked(self.start.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+        let offset = *self.offset.get_mut();
+        let (start, end) = self.bump(offset, layout).ok_or_else(|| AllocErr::new(layout))?;
+        *self.offset.get_mut() = end;
+
+        Ok(NonEmptyMemoryBlock {
+            handle: FileMapHandle(start),
+            size: unsafe { NonZeroUsize::new_unchecked(end - start) },
+        })
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl ResizableStorage for FileMapStorage {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for FileMapStorage {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+
+        let mut start = 0;
+        let mut end = 0;
+        self.offset
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |offset| {
+                (start, end) = self.bump(offset, layout)?;
+                Some(end)
+            })
+            .map_err(|_| AllocErr::new(layout))?;
+
+        Ok(NonEmptyMemoryBlock {
+            handle: FileMapHandle(start),
+            size: unsafe { NonZeroUsize::new_unchecked(end - start) },
+        })
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl SharedResizableStorage for FileMapStorage {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn file_map() {
+    let path = std::env::temp_dir().join(std::format!("storage-file-map-{}", std::process::id()));
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+
+    let mut storage = FileMapStorage::new(&file, 4096).unwrap();
+    storage.allocate(Layout::new::<u8>()).unwrap();
+    let handle = storage.allocate(Layout::new::<u64>()).unwrap().handle;
+    assert_eq!(handle.offset(), 8);
+    unsafe {
+        storage
+            .get_mut(handle)
+            .cast::<u64>()
+            .as_ptr()
+            .write(0x0123_4567_89ab_cdef);
+    }
+    storage.flush();
+    drop(storage);
+
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(bytes.len(), 4096);
+    assert_eq!(bytes[8..16], 0x0123_4567_89ab_cdef_u64.to_ne_bytes());
+
+    // mapping the file again keeps the data, and the same handle refers to it
+    let mut storage = FileMapStorage::new(&file, 4096).unwrap();
+    unsafe { storage.set_used(16) }
+    assert_eq!(
+        unsafe { storage.get(handle).cast::<u64>().as_ptr().read() },
+        0x0123_4567_89ab_cdef
+    );
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+
+    drop(file);
+    std::fs::remove_file(path).unwrap();
+}
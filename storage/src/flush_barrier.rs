@@ -201,3 +201,20 @@ unsafe impl<S: SharedResizableStorage> SharedResizableStorage for FlushBarrier<S
         self.storage.shared_shrink(handle, old, new)
     }
 }
+
+#[test]
+fn flush_barrier() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = FlushBarrier::new(crate::DeferredFreeStorage::new(&mock));
+
+    let layout = Layout::new::<u64>();
+    let memory_block = storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, layout) }
+
+    // flushes don't make it past the barrier
+    storage.flush();
+    assert_eq!(mock.live_allocations(), 1);
+
+    storage.storage.free_all();
+    assert_eq!(mock.live_allocations(), 0);
+}
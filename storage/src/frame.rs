@@ -0,0 +1,433 @@
+use core::{alloc::Layout, cell::UnsafeCell, num::NonZeroUsize, ops::Range, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const BITS: usize = usize::BITS as usize;
+
+#[derive(Clone, Copy)]
+struct Bitmap(*mut usize);
+
+impl Bitmap {
+    unsafe fn is_used(self, frame: usize) -> bool { *self.0.add(frame / BITS) & (1 << (frame % BITS)) != 0 }
+
+    unsafe fn is_free(self, mut frames: Range<usize>) -> bool { frames.all(|frame| !self.is_used(frame)) }
+
+    unsafe fn set(self, frames: Range<usize>, used: bool) {
+        for frame in frames {
+            let word = &mut *self.0.add(frame / BITS);
+            let bit = 1 << (frame % BITS);
+            debug_assert_ne!(*word & bit != 0, used, "frame {frame} was already in this state");
+
+            if used {
+                *word |= bit;
+            } else {
+                *word &= !bit;
+            }
+        }
+    }
+
+    // finds and claims `count` free frames in a row
+    unsafe fn claim(self, total: usize, count: usize) -> Option<usize> {
+        let mut run = 0;
+        let mut frame = 0;
+
+        while frame < total {
+            if run == 0 && frame % BITS == 0 && *self.0.add(frame / BITS) == !0 {
+                frame += BITS;
+                continue
+            }
+
+            if self.is_used(frame) {
+                run = 0;
+            } else {
+                run += 1;
+
+                if run == count {
+                    let first = frame + 1 - count;
+                    self.set(first..frame + 1, true);
+                    return Some(first)
+                }
+            }
+
+            frame += 1;
+        }
+
+        None
+    }
+}
+
+/// A storage that hands out whole frames of a physical memory range, tracked by a bitmap
+///
+/// Only layouts whose size is a multiple of `PAGE_SIZE`, and whose alignment is at most
+/// `PAGE_SIZE` are accepted, every other layout is rejected. This is meant to be the
+/// frame allocator of a kernel, with the kernel's heap layered on top of it.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct FrameStorage<'a, const PAGE_SIZE: usize = 4096> {
+    start: NonNull<u8>,
+    physical_start: usize,
+    frames: usize,
+    bitmap: &'a UnsafeCell<[usize]>,
+    lock: SpinLock,
+}
+
+unsafe impl<const PAGE_SIZE: usize> Send for FrameStorage<'_, PAGE_SIZE> {}
+unsafe impl<const PAGE_SIZE: usize> Sync for FrameStorage<'_, PAGE_SIZE> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHandle(usize);
+
+unsafe impl Handle for FrameHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl FrameHandle {
+    #[must_use = "`FrameHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+
+    /// The offset of this allocation from the start of the physical range
+    pub const fn offset(self) -> usize { self.0 }
+}
+
+impl<'a, const PAGE_SIZE: usize> FrameStorage<'a, PAGE_SIZE> {
+    /// Creates a storage over `frames` frames, starting at the physical address `physical_start`,
+    /// which is mapped at `start`, with every frame free
+    ///
+    /// # Safety
+    ///
+    /// `start` must be valid for reads and writes of `frames * PAGE_SIZE` bytes for `'a`,
+    /// and nothing else may access that memory
+    ///
+    /// # Panics
+    ///
+    /// * if `PAGE_SIZE` isn't a power of two
+    /// * if `start` or `physical_start` isn't page aligned
+    /// * if `bitmap` doesn't have a bit for every frame
+    pub unsafe fn new(start: NonNull<u8>, physical_start: usize, frames: usize, bitmap: &'a mut [usize]) -> Self {
+        assert!(PAGE_SIZE.is_power_of_two(), "the page size must be a power of two");
+        assert_eq!(start.as_ptr().align_offset(PAGE_SIZE), 0, "frames must be page aligned");
+        assert_eq!(physical_start % PAGE_SIZE, 0, "frames must be page aligned");
+        assert!(
+            bitmap.len() * BITS >= frames,
+            "the bitmap is too small for {} frames",
+            frames
+        );
+
+        bitmap.fill(0);
+
+        // the bits past the last frame are always marked as used
+        for (i, word) in bitmap.iter_mut().enumerate() {
+            let first = i * BITS;
+
+            if first >= frames {
+                *word = !0;
+            } else if frames - first < BITS {
+                *word = !0 << (frames - first);
+            }
+        }
+
+        Self {
+            start,
+            physical_start,
+            frames,
+            bitmap: UnsafeCell::from_mut(bitmap),
+            lock: SpinLock::new(),
+        }
+    }
+
+    pub const fn page_size(&self) -> usize { PAGE_SIZE }
+
+    /// The number of frames in the physical range
+    pub const fn frames(&self) -> usize { self.frames }
+
+    /// The physical address of the memory behind `handle`
+    pub const fn physical_address(&self, handle: FrameHandle) -> usize { self.physical_start + handle.0 }
+
+    /// Marks `frames` as used, so they are never handed out,
+    /// for example because the kernel image or a memory mapped device lives there
+    ///
+    /// # Panics
+    ///
+    /// if `frames` is out of bounds
+    pub fn reserve(&mut self, frames: Range<usize>) {
+        assert!(frames.end <= self.frames, "{:?} is out of bounds", frames);
+
+        for frame in frames {
+            unsafe {
+                if !self.bitmap().is_used(frame) {
+                    self.bitmap().set(frame..frame + 1, true);
+                }
+            }
+        }
+    }
+
+    const fn bitmap(&self) -> Bitmap { Bitmap(self.bitmap.get().cast()) }
+
+    // the number of frames for `layout`, if it's page granular
+    const fn frame_count(layout: Layout) -> Option<usize> {
+        if layout.align() <= PAGE_SIZE && layout.size().is_multiple_of(PAGE_SIZE) {
+            Some(layout.size() / PAGE_SIZE)
+        } else {
+            None
+        }
+    }
+
+    // must be called with exclusive access to the bitmap
+    unsafe fn claim(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<FrameHandle>, AllocErr> {
+        Self::frame_count(layout.into())
+            .and_then(|count| self.bitmap().claim(self.frames, count))
+            .map(|frame| NonEmptyMemoryBlock {
+                handle: FrameHandle(frame * PAGE_SIZE),
+                size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    // must be called with exclusive access to the bitmap
+    unsafe fn release(&self, FrameHandle(offset): FrameHandle, layout: NonEmptyLayout) {
+        let first = offset / PAGE_SIZE;
+        self.bitmap().set(first..first + layout.size() / PAGE_SIZE, false);
+    }
+
+    // must be called with exclusive access to the bitmap
+    unsafe fn resize_in_place(&self, FrameHandle(offset): FrameHandle, old: Layout, new: Layout) -> bool {
+        let (Some(old), Some(new)) = (Self::frame_count(old), Self::frame_count(new)) else {
+            return false
+        };
+
+        let first = offset / PAGE_SIZE;
+
+        if new <= old {
+            self.bitmap().set(first + new..first + old, false);
+            true
+        } else if first + new <= self.frames && self.bitmap().is_free(first + old..first + new) {
+            self.bitmap().set(first + old..first + new, true);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> OffsetHandle for FrameStorage<'_, PAGE_SIZE> {
+    unsafe fn offset(&mut self, FrameHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        FrameHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> SharedOffsetHandle for FrameStorage<'_, PAGE_SIZE> {
+    unsafe fn shared_offset(&self, FrameHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        FrameHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> FromPtr for FrameStorage<'_, PAGE_SIZE> {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        FrameHandle(ptr.as_ptr().offset_from(self.start.as_ptr()) as usize)
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> SharedGetMut for FrameStorage<'_, PAGE_SIZE> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
+}
+
+impl<const PAGE_SIZE: usize> MultiStorage for FrameStorage<'_, PAGE_SIZE> {}
+
+unsafe impl<const PAGE_SIZE: usize> Storage for FrameStorage<'_, PAGE_SIZE> {
+    type Handle = FrameHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        NonNull::new_unchecked(self.start.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        unsafe { self.claim(layout) }
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.release(handle, layout);
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> ResizableStorage for FrameStorage<'_, PAGE_SIZE> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.resize_in_place(handle, old, new) {
+            return Ok(MemoryBlock {
+                handle,
+                size: new.size(),
+            })
+        }
+
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.grow(handle, old, new)?;
+        let ptr = self.get(memory_block.handle).as_ptr();
+        ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if new.size() == 0 {
+            self.deallocate(handle, old);
+
+            return Ok(MemoryBlock {
+                handle: Handle::dangling(new.align()),
+                size: 0,
+            })
+        }
+
+        if self.resize_in_place(handle, old, new) {
+            return Ok(MemoryBlock {
+                handle,
+                size: new.size(),
+            })
+        }
+
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> SharedStorage for FrameStorage<'_, PAGE_SIZE> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let _guard = self.lock.lock();
+        unsafe { self.claim(layout) }
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let _guard = self.lock.lock();
+        self.release(handle, layout);
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize> SharedResizableStorage for FrameStorage<'_, PAGE_SIZE> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 {
+            let _guard = self.lock.lock();
+
+            if self.resize_in_place(handle, old, new) {
+                return Ok(MemoryBlock {
+                    handle,
+                    size: new.size(),
+                })
+            }
+        }
+
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.shared_grow(handle, old, new)?;
+        let ptr = self.get(memory_block.handle).as_ptr();
+        ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if new.size() == 0 {
+            self.shared_deallocate(handle, old);
+
+            return Ok(MemoryBlock {
+                handle: Handle::dangling(new.align()),
+                size: 0,
+            })
+        }
+
+        {
+            let _guard = self.lock.lock();
+
+            if self.resize_in_place(handle, old, new) {
+                return Ok(MemoryBlock {
+                    handle,
+                    size: new.size(),
+                })
+            }
+        }
+
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn frame() {
+    let layout = Layout::from_size_align(4096 * 8, 4096).unwrap();
+    let mut memory = crate::AllocatorStorage::new(std::alloc::System);
+    let region = memory.allocate(layout).unwrap().handle;
+    let mut bitmap = [0; 1];
+
+    let mut storage = unsafe { FrameStorage::<4096>::new(region, 0x10_0000, 8, &mut bitmap) };
+    storage.reserve(0..1);
+
+    let page = Layout::from_size_align(4096, 4096).unwrap();
+    let pages = Layout::from_size_align(4096 * 3, 4096).unwrap();
+    let more_pages = Layout::from_size_align(4096 * 6, 4096).unwrap();
+
+    // only whole pages can be allocated
+    assert!(storage.allocate(Layout::new::<u64>()).is_err());
+    assert!(storage.allocate(Layout::from_size_align(4096, 8192).unwrap()).is_err());
+
+    let first = storage.allocate(page).unwrap().handle;
+    assert_eq!(storage.physical_address(first), 0x10_1000);
+
+    let second = storage.allocate(pages).unwrap().handle;
+    assert_eq!(second.offset(), 4096 * 2);
+
+    unsafe {
+        // the frames after `second` are free, so it grows in place
+        let grown = storage.grow(second, pages, more_pages);
+        assert_eq!(grown.unwrap().handle, second);
+        assert!(storage.allocate(page).is_err());
+
+        storage.deallocate(first, page);
+        storage.deallocate(second, more_pages);
+        assert_eq!(storage.allocate(more_pages).unwrap().handle.offset(), 4096);
+
+        memory.deallocate(region, layout);
+    }
+}
@@ -1,4 +1,13 @@
-use core::{alloc::{Layout, LayoutError}, cell::Cell, mem::MaybeUninit, num::NonZeroUsize, ptr::NonNull, slice, sync::atomic::{AtomicU8, Ordering}};
+use core::{
+    alloc::{Layout, LayoutError},
+    cell::Cell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    slice,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
 
 use crate::{
     AllocErr, FromPtr, Handle, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
@@ -22,18 +31,90 @@ struct FreeListItem<H> {
     handle: Cell<H>,
 }
 
-pub struct FreeListStorage<S: Storage> {
+/// A storage that caches deallocated blocks, and hands them out again for later allocations
+///
+/// The cache is split into lanes by the size class of each block (its size rounded up to a power of two),
+/// so allocations look at the blocks of their own size class first, and only scan the rest of the cache
+/// if none of them fit. A cached block fits an allocation if it is at least as aligned, and at least as large
+/// but no more than the slack factor times larger (see [`FreeListStorage::with_slack`]).
+///
+/// Which of the fitting blocks is handed out is decided by the [`FitPolicy`] `P`.
+///
+/// The cache holds a fixed number of blocks, unless it is allowed to grow with
+/// [`FreeListStorage::with_ceiling`].
+pub struct FreeListStorage<S: Storage, P = FirstFit> {
     max_length: NonZeroUsize,
+    ceiling: usize,
+    // the number of blocks that didn't fit in the cache since it last grew
+    overflows: AtomicUsize,
+    slack: usize,
+    // the number of blocks that were handed out for a different layout, and still have a slot in the cache
+    lent: AtomicUsize,
     storage: S,
     items: S::Handle,
+    policy: PhantomData<P>,
+}
+
+/// How a [`FreeListStorage`] picks between the cached blocks that fit an allocation
+pub trait FitPolicy {
+    /// If the first block that is accepted should be handed out, without looking for a better one
+    const FIRST: bool;
+
+    /// Returns how good of a fit a cached block with the layout `item` is for `layout`,
+    /// lower is better, and `None` if it shouldn't be handed out at all
+    ///
+    /// This is only called for blocks that fit `layout`
+    fn fit(item: Layout, layout: NonEmptyLayout) -> Option<usize>;
+}
+
+/// Hands out the first cached block that fits
+#[derive(Default, Debug, Clone, Copy)]
+pub struct FirstFit;
+
+/// Hands out the smallest cached block that fits, this has to look at every cached block
+/// unless one of exactly the requested size is found
+#[derive(Default, Debug, Clone, Copy)]
+pub struct BestFit;
+
+/// Only hands out cached blocks of exactly the requested size
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ExactFit;
+
+impl FitPolicy for FirstFit {
+    const FIRST: bool = true;
+
+    #[inline]
+    fn fit(item: Layout, layout: NonEmptyLayout) -> Option<usize> { Some(item.size() - layout.size()) }
+}
+
+impl FitPolicy for BestFit {
+    const FIRST: bool = false;
+
+    #[inline]
+    fn fit(item: Layout, layout: NonEmptyLayout) -> Option<usize> { Some(item.size() - layout.size()) }
+}
+
+impl FitPolicy for ExactFit {
+    const FIRST: bool = true;
+
+    #[inline]
+    fn fit(item: Layout, layout: NonEmptyLayout) -> Option<usize> {
+        if item.size() == layout.size() {
+            Some(0)
+        } else {
+            None
+        }
+    }
 }
 
-impl<S: Storage> Drop for FreeListStorage<S> {
+impl<S: Storage, P> Drop for FreeListStorage<S, P> {
     fn drop(&mut self) {
+        // hand every cached block back before the free list goes away
+        self.shallow_flush();
+
         unsafe {
-            let (layout, ..) = unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length.get()));
-            self.storage
-                .deallocate_nonempty(self.items, NonEmptyLayout::new_unchecked(layout));
+            let (layout, ..) = unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length));
+            self.storage.deallocate_nonempty(self.items, layout);
         }
     }
 }
@@ -43,11 +124,45 @@ const MASK_STATUS: u8 = !SINGLE_LOCK;
 const SINGLE_LOCK: u8 = 0b1000_0000;
 const SINGLE_STATUS: u8 = 1;
 
-fn free_list_layout<H>(max_size: usize) -> Result<(Layout, usize, usize), LayoutError> {
+const SIZE_CLASSES: usize = usize::BITS as usize;
+
+const DEFAULT_SLACK: usize = 2;
+
+// a larger block can be handed out, but only if not too much of it would be wasted
+const fn fits(item: Layout, layout: NonEmptyLayout, slack: usize) -> bool {
+    item.align() >= layout.align() && item.size() >= layout.size() && item.size() / slack <= layout.size()
+}
+
+fn rank<P: FitPolicy>(item: Layout, layout: NonEmptyLayout, slack: usize) -> Option<usize> {
+    if fits(item, layout, slack) {
+        P::fit(item, layout)
+    } else {
+        None
+    }
+}
+
+// the buckets are interleaved between the lanes, and each size class is assigned a lane,
+// the lane of the layout's size class is looked at first, and the rest of the buckets only after that
+fn buckets(layout: NonEmptyLayout, buckets: usize) -> impl Iterator<Item = usize> {
+    let lanes = buckets.min(SIZE_CLASSES);
+    let lane = layout.size().next_power_of_two().trailing_zeros() as usize % lanes;
+    (lane..buckets)
+        .step_by(lanes)
+        .chain((0..buckets).filter(move |i| i % lanes != lane))
+}
+
+// the bitflags hold the status of each bucket, and are followed by a bit for each slot
+// that is kept for a block that was handed out for a different layout
+fn free_list_layout<H>(max_size: NonZeroUsize) -> Result<(NonEmptyLayout, usize, usize), LayoutError> {
+    let max_size = max_size.get();
     let bitflags_len = (max_size / 7) + usize::from(max_size % 7 != 0);
-    let fl = Layout::new::<FreeListItem<H>>().repeat(max_size)?.0;
-    let bf = Layout::new::<AtomicU8>().repeat(bitflags_len)?.0;
-    fl.extend(bf).map(|(layout, bitflags)| (layout, bitflags, bitflags_len))
+    let fl = Layout::array::<FreeListItem<H>>(max_size)?;
+    let bf = NonEmptyLayout::from_size_align(
+        unsafe { NonZeroUsize::new_unchecked(bitflags_len * 2) },
+        core::mem::align_of::<AtomicU8>(),
+    )?;
+    bf.extend_after(fl)
+        .map(|(layout, bitflags)| (layout, bitflags.get(), bitflags_len))
 }
 
 #[allow(clippy::missing_const_for_fn)]
@@ -66,9 +181,21 @@ impl<S: Storage> FreeListStorage<S> {
     /// # Panics
     ///
     /// * If layout could not be computed TODO
-    pub fn try_new(max_size: NonZeroUsize, mut storage: S) -> Result<Self, AllocErr<S>> {
-        let (layout, freelist, freelist_len) = free_list_layout::<S::Handle>(max_size.get()).unwrap();
-        let layout = unsafe { NonEmptyLayout::new_unchecked(layout) };
+    pub fn try_new(max_size: NonZeroUsize, storage: S) -> Result<Self, AllocErr<S>> {
+        Self::try_with_policy(max_size, storage)
+    }
+}
+
+impl<S: Storage, P: FitPolicy> FreeListStorage<S, P> {
+    pub fn with_policy(max_size: NonZeroUsize, storage: S) -> Self {
+        Self::try_with_policy(max_size, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// # Panics
+    ///
+    /// * If layout could not be computed TODO
+    pub fn try_with_policy(max_size: NonZeroUsize, mut storage: S) -> Result<Self, AllocErr<S>> {
+        let (layout, freelist, freelist_len) = free_list_layout::<S::Handle>(max_size).unwrap();
         let meta = match storage.allocate_nonempty(layout) {
             Ok(x) => x.handle,
             Err(err) => return Err(err.with(storage)),
@@ -86,22 +213,52 @@ impl<S: Storage> FreeListStorage<S> {
         }
 
         let bitflags = unsafe {
-            slice::from_raw_parts_mut(items_ptr.as_ptr().cast::<MaybeUninit<u8>>().add(freelist), freelist_len)
+            slice::from_raw_parts_mut(
+                items_ptr.as_ptr().cast::<MaybeUninit<u8>>().add(freelist),
+                freelist_len * 2,
+            )
         };
         bitflags.fill(MaybeUninit::new(0));
 
         Ok(Self {
             max_length: max_size,
+            ceiling: max_size.get(),
+            overflows: AtomicUsize::new(0),
+            slack: DEFAULT_SLACK,
+            lent: AtomicUsize::new(0),
             storage,
             items: meta,
+            policy: PhantomData,
         })
     }
+
+    /// Sets how many times larger than the request a cached block may be to be handed out,
+    /// a slack of 1 only reuses blocks of exactly the requested size (the default is 2)
+    ///
+    /// A block that is handed out for a different layout keeps its slot in the cache until it is deallocated,
+    /// and is then put back in it with the layout it was allocated with, so it is only ever handed back to
+    /// the underlying storage with that layout. The reported size of the block is its real size.
+    #[must_use]
+    pub const fn with_slack(mut self, slack: NonZeroUsize) -> Self {
+        self.slack = slack.get();
+        self
+    }
+
+    /// Lets the cache grow up to `ceiling` blocks once it keeps overflowing (by default it never grows)
+    ///
+    /// The cache is doubled every time as many blocks didn't fit in it as it can hold,
+    /// but it can only grow while the storage is used through a unique reference.
+    #[must_use]
+    pub fn with_ceiling(mut self, ceiling: NonZeroUsize) -> Self {
+        self.ceiling = ceiling.get().max(self.max_length.get());
+        self
+    }
 }
 
-impl<S: Storage> FreeListStorage<S> {
-    fn free_list(&self) -> (&[FreeListItem<S::Handle>], &[AtomicU8]) {
-        let (_, bitflags, bitflags_len) =
-            unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length.get())) };
+impl<S: Storage, P> FreeListStorage<S, P> {
+    #[allow(clippy::type_complexity)]
+    fn free_list(&self) -> (&[FreeListItem<S::Handle>], &[AtomicU8], &[AtomicU8]) {
+        let (_, bitflags, bitflags_len) = unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length)) };
         let meta_array = unsafe { self.storage.get(self.items) };
         let free_list = meta_array.cast::<FreeListItem<S::Handle>>().as_ptr();
         unsafe {
@@ -109,14 +266,24 @@ impl<S: Storage> FreeListStorage<S> {
             (
                 slice::from_raw_parts(free_list, self.max_length.get()),
                 slice::from_raw_parts(bitflags, bitflags_len),
+                slice::from_raw_parts(bitflags.add(bitflags_len), bitflags_len),
             )
         }
     }
 
-    fn free_list_mut(&mut self) -> (&mut [FreeListItem<S::Handle>], &mut [u8]) {
-        let (_, bitflags, bitflags_len) =
-            unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length.get())) };
-        unsafe { self.free_list_mut_at(bitflags, bitflags_len) }
+    #[allow(clippy::type_complexity)]
+    fn free_list_mut(&mut self) -> (&mut [FreeListItem<S::Handle>], &mut [u8], &mut [u8]) {
+        let (_, bitflags, bitflags_len) = unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length)) };
+        let meta_array = unsafe { self.storage.get_mut(self.items) };
+        let free_list = meta_array.cast::<FreeListItem<S::Handle>>().as_ptr();
+        unsafe {
+            let bitflags = free_list.cast::<u8>().add(bitflags);
+            (
+                slice::from_raw_parts_mut(free_list, self.max_length.get()),
+                slice::from_raw_parts_mut(bitflags, bitflags_len),
+                slice::from_raw_parts_mut(bitflags.add(bitflags_len), bitflags_len),
+            )
+        }
     }
 
     unsafe fn free_list_at(&self, bitflags: usize, bitflags_len: usize) -> (&[FreeListItem<S::Handle>], &[AtomicU8]) {
@@ -143,58 +310,104 @@ impl<S: Storage> FreeListStorage<S> {
         )
     }
 
+    // returns the block, and if it was lent out for a different layout
     fn attempt_allocate(
         free_list: &mut [FreeListItem<S::Handle>],
         bitflags: &mut [u8],
+        lent: &mut [u8],
         layout: NonEmptyLayout,
-    ) -> Option<NonEmptyMemoryBlock<S::Handle>> {
-        for (i, owned) in bitflags.iter_mut().enumerate() {
+        slack: usize,
+    ) -> Option<(NonEmptyMemoryBlock<S::Handle>, bool)>
+    where
+        P: FitPolicy,
+    {
+        // the index and rank of the best block so far
+        let mut best = None::<(usize, usize)>;
+
+        'search: for i in buckets(layout, bitflags.len()) {
+            let owned = unsafe { *bitflags.get_unchecked(i) };
+
             // if all of the slots are empty, skip this bucket
             // NOTE: because we have `&mut self`, the free list can't be locked
-            if *owned == 0 {
+            if owned == 0 {
                 continue
             }
 
             for j in 0..7 {
                 let status_bit = SINGLE_STATUS << j;
-                if (*owned & status_bit) != 0 {
+                if (owned & status_bit) != 0 {
                     let index = i * 7 + j;
-                    let free_list = unsafe { free_list.get_unchecked_mut(index) };
-                    let item_layout = free_list.layout.get();
+                    let free_list = unsafe { free_list.get_unchecked(index) };
 
-                    if item_layout.align() == layout.align() && item_layout.size() >= layout.size() {
-                        *owned &= !status_bit;
+                    match rank::<P>(free_list.layout.get(), layout, slack) {
+                        Some(rank) if best.map_or(usize::MAX, |(_, best)| best) > rank => {
+                            best = Some((index, rank));
 
-                        return Some(NonEmptyMemoryBlock {
-                            handle: free_list.handle.get(),
-                            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
-                        })
+                            if P::FIRST || rank == 0 {
+                                break 'search
+                            }
+                        }
+                        _ => (),
                     }
                 }
             }
         }
 
-        None
+        let (index, _) = best?;
+        unsafe {
+            let status_bit = SINGLE_STATUS << (index % 7);
+            *bitflags.get_unchecked_mut(index / 7) &= !status_bit;
+
+            let (memory_block, is_lent) = Self::lend(free_list.get_unchecked(index), layout);
+            if is_lent {
+                *lent.get_unchecked_mut(index / 7) |= status_bit;
+            }
+            Some((memory_block, is_lent))
+        }
+    }
+
+    // hands out the cached block, and returns if it has a different layout than `layout`,
+    // in which case its slot must be kept until it comes back
+    fn lend(free_list: &FreeListItem<S::Handle>, layout: NonEmptyLayout) -> (NonEmptyMemoryBlock<S::Handle>, bool) {
+        let item = free_list.layout.get();
+
+        let memory_block = NonEmptyMemoryBlock {
+            handle: free_list.handle.get(),
+            size: unsafe { NonZeroUsize::new_unchecked(item.size()) },
+        };
+
+        (memory_block, item != Layout::from(layout))
     }
 
     fn attempt_deallocate(
         free_list: &mut [FreeListItem<S::Handle>],
         bitflags: &mut [u8],
+        lent: &[u8],
         handle: S::Handle,
         layout: NonEmptyLayout,
     ) -> bool {
-        for (i, owned) in bitflags.iter_mut().enumerate() {
+        for i in buckets(layout, bitflags.len()) {
+            let owned = unsafe { bitflags.get_unchecked_mut(i) };
+            // the slots of blocks that are lent out are kept for them
+            let taken = *owned | unsafe { *lent.get_unchecked(i) };
+
             // if all of the slots are full, skip this bucket
             // NOTE: because we have `&mut self`, the free list can't be locked
-            if *owned == MASK_STATUS {
+            if taken == MASK_STATUS {
                 continue
             }
 
             for j in 0..7 {
                 let status_bit = SINGLE_STATUS << j;
-                if (*owned & status_bit) == 0 {
-                    *owned |= status_bit;
+                if (taken & status_bit) == 0 {
                     let index = i * 7 + j;
+
+                    // the last bucket may be partially filled
+                    if index >= free_list.len() {
+                        break
+                    }
+
+                    *owned |= status_bit;
                     let free_list = unsafe { free_list.get_unchecked_mut(index) };
                     free_list.layout = Cell::new(layout.into());
                     free_list.handle = Cell::new(handle);
@@ -207,14 +420,95 @@ impl<S: Storage> FreeListStorage<S> {
     }
 }
 
-impl<S: SharedStorage> FreeListStorage<S> {
+impl<S: Storage, P> FreeListStorage<S, P> {
+    fn attempt_find_lent(&self, ptr: NonNull<u8>, put_back: bool, was_blocked: &mut bool) -> bool {
+        let (free_list, bitflags, lent) = self.free_list();
+
+        for (i, (owned, lent)) in bitflags.iter().zip(lent).enumerate() {
+            // the lent bits only change while the bucket is locked, but the bit of a block that is
+            // being looked for was set before it was handed out, so it's fine to look at them without the lock
+            if lent.load(Ordering::Relaxed) == 0 {
+                continue
+            }
+
+            // try to aquire the lock
+            let status = owned.fetch_or(SINGLE_LOCK, Ordering::Acquire);
+
+            // if someone else locked the bucket
+            if status & SINGLE_LOCK != 0 {
+                *was_blocked = true;
+                continue
+            }
+
+            let lent_bits = lent.load(Ordering::Relaxed);
+
+            for j in 0..7 {
+                let status_bit = SINGLE_STATUS << j;
+                if (lent_bits & status_bit) != 0 {
+                    let free_list = unsafe { free_list.get_unchecked(i * 7 + j) };
+
+                    if unsafe { self.storage.get(free_list.handle.get()) } == ptr {
+                        if put_back {
+                            lent.store(lent_bits & !status_bit, Ordering::Relaxed);
+                            // clear lock and mark this slot as full
+                            owned.store(status | status_bit, Ordering::Release);
+                        } else {
+                            // clear lock
+                            owned.store(status, Ordering::Release);
+                        }
+
+                        return true
+                    }
+                }
+            }
+
+            // clear lock
+            owned.store(status, Ordering::Release);
+        }
+
+        false
+    }
+
+    // checks if the block was lent out for a different layout than it was allocated with,
+    // and if `put_back` is set, puts it back in its slot in the cache
+    unsafe fn find_lent(&self, handle: S::Handle, put_back: bool) -> bool {
+        if self.lent.load(Ordering::Relaxed) == 0 {
+            return false
+        }
+
+        let ptr = self.storage.get(handle);
+
+        loop {
+            let mut was_blocked = false;
+            if self.attempt_find_lent(ptr, put_back, &mut was_blocked) {
+                if put_back {
+                    self.lent.fetch_sub(1, Ordering::Relaxed);
+                }
+                return true
+            }
+            if !was_blocked {
+                return false
+            }
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl<S: SharedStorage, P: FitPolicy> FreeListStorage<S, P> {
     fn attempt_shared_allocate(
         free_list: &[FreeListItem<S::Handle>],
         bitflags: &[AtomicU8],
+        lent: &[AtomicU8],
         layout: NonEmptyLayout,
+        slack: usize,
         was_blocked: &mut bool,
-    ) -> Option<NonEmptyMemoryBlock<S::Handle>> {
-        for (i, owned) in bitflags.iter().enumerate() {
+    ) -> Option<(NonEmptyMemoryBlock<S::Handle>, bool)> {
+        // the index and rank of the best block so far, and the status of its bucket
+        // the bucket of the best block stays locked, so that it can't be taken by anyone else
+        let mut best = None::<(usize, usize, u8)>;
+
+        for i in buckets(layout, bitflags.len()) {
+            let owned = unsafe { bitflags.get_unchecked(i) };
             let fetch = owned.load(Ordering::Relaxed);
 
             // if the bucket is locked or all of the slots are empty, skip this bucket
@@ -233,42 +527,70 @@ impl<S: SharedStorage> FreeListStorage<S> {
             }
 
             let status = locked;
+            let mut found = None::<(usize, usize)>;
+            let mut bound = best.map_or(usize::MAX, |(_, rank, _)| rank);
 
             for j in 0..7 {
                 let status_bit = SINGLE_STATUS << j;
                 if (status & status_bit) != 0 {
                     let index = i * 7 + j;
                     let free_list = unsafe { free_list.get_unchecked(index) };
-                    let item_layout = free_list.layout.get();
-
-                    if item_layout.align() == layout.align() && item_layout.size() >= layout.size() {
-                        let handle = free_list.handle.get();
-                        // clear lock and mark this slot as empty
-                        owned.store(status & !status_bit, Ordering::Release);
 
-                        return Some(NonEmptyMemoryBlock {
-                            handle,
-                            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
-                        })
+                    match rank::<P>(free_list.layout.get(), layout, slack) {
+                        Some(rank) if bound > rank => {
+                            found = Some((index, rank));
+                            bound = rank;
+                        }
+                        _ => (),
                     }
                 }
             }
 
-            // clear lock
-            owned.store(status, Ordering::Release);
+            if let Some((index, rank)) = found {
+                // release the bucket of the previous best block
+                if let Some((index, _, status)) = best {
+                    unsafe { bitflags.get_unchecked(index / 7) }.store(status, Ordering::Release);
+                }
+
+                best = Some((index, rank, status));
+
+                if P::FIRST || rank == 0 {
+                    break
+                }
+            } else {
+                // clear lock
+                owned.store(status, Ordering::Release);
+            }
         }
 
-        None
+        let (index, _, status) = best?;
+        unsafe {
+            let status_bit = SINGLE_STATUS << (index % 7);
+
+            let (memory_block, is_lent) = Self::lend(free_list.get_unchecked(index), layout);
+            if is_lent {
+                lent.get_unchecked(index / 7).fetch_or(status_bit, Ordering::Relaxed);
+            }
+
+            // clear lock and mark this slot as empty
+            bitflags
+                .get_unchecked(index / 7)
+                .store(status & !status_bit, Ordering::Release);
+
+            Some((memory_block, is_lent))
+        }
     }
 
     fn attempt_shared_deallocate(
         free_list: &[FreeListItem<S::Handle>],
         bitflags: &[AtomicU8],
+        lent: &[AtomicU8],
         handle: S::Handle,
         layout: NonEmptyLayout,
         was_blocked: &mut bool,
     ) -> bool {
-        for (i, owned) in bitflags.iter().enumerate() {
+        for i in buckets(layout, bitflags.len()) {
+            let owned = unsafe { bitflags.get_unchecked(i) };
             let fetch = owned.load(Ordering::Relaxed);
 
             // if the bucket is locked or all of the slots are full, skip this bucket
@@ -287,11 +609,19 @@ impl<S: SharedStorage> FreeListStorage<S> {
             }
 
             let status = locked;
+            // the slots of blocks that are lent out are kept for them
+            let taken = status | unsafe { lent.get_unchecked(i) }.load(Ordering::Relaxed);
 
             for j in 0..7 {
                 let status_bit = SINGLE_STATUS << j;
-                if (status & status_bit) == 0 {
+                if (taken & status_bit) == 0 {
                     let index = i * 7 + j;
+
+                    // the last bucket may be partially filled
+                    if index >= free_list.len() {
+                        break
+                    }
+
                     let free_list = unsafe { free_list.get_unchecked(index) };
                     free_list.layout.set(layout.into());
                     free_list.handle.set(handle);
@@ -310,11 +640,9 @@ impl<S: SharedStorage> FreeListStorage<S> {
     }
 }
 
-unsafe impl<S: FromPtr> FromPtr for FreeListStorage<S> {
+unsafe impl<S: FromPtr, P: FitPolicy> FromPtr for FreeListStorage<S, P> {
     #[inline]
-    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
-        self.storage.from_ptr(ptr, layout)
-    }
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
 
     #[inline]
     unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
@@ -322,13 +650,13 @@ unsafe impl<S: FromPtr> FromPtr for FreeListStorage<S> {
     }
 }
 
-unsafe impl<S: SharedGetMut> SharedGetMut for FreeListStorage<S> {
+unsafe impl<S: SharedGetMut, P: FitPolicy> SharedGetMut for FreeListStorage<S, P> {
     unsafe fn shared_get_mut(&self, handle: Self::Handle) -> core::ptr::NonNull<u8> {
         self.storage.shared_get_mut(handle)
     }
 }
 
-unsafe impl<S: Storage> Storage for FreeListStorage<S> {
+unsafe impl<S: Storage, P: FitPolicy> Storage for FreeListStorage<S, P> {
     type Handle = S::Handle;
 
     unsafe fn get(&self, handle: Self::Handle) -> core::ptr::NonNull<u8> { self.storage.get(handle) }
@@ -339,10 +667,16 @@ unsafe impl<S: Storage> Storage for FreeListStorage<S> {
         &mut self,
         layout: NonEmptyLayout,
     ) -> Result<crate::NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        let (free_list, bitflags) = self.free_list_mut();
+        let slack = self.slack;
+        let (free_list, bitflags, lent) = self.free_list_mut();
         #[allow(clippy::single_match_else)]
-        match Self::attempt_allocate(free_list, bitflags, layout) {
-            Some(memory_block) => Ok(memory_block),
+        match Self::attempt_allocate(free_list, bitflags, lent, layout, slack) {
+            Some((memory_block, is_lent)) => {
+                if is_lent {
+                    *self.lent.get_mut() += 1;
+                }
+                Ok(memory_block)
+            }
             None => {
                 let memory = self.storage.allocate_nonempty(layout)?;
                 Ok(NonEmptyMemoryBlock {
@@ -354,24 +688,42 @@ unsafe impl<S: Storage> Storage for FreeListStorage<S> {
     }
 
     unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
-        let (free_list, bitflags) = self.free_list_mut();
-        if !Self::attempt_deallocate(free_list, bitflags, handle, layout) {
-            self.storage.deallocate_nonempty(handle, layout)
+        if self.find_lent(handle, true) {
+            return
+        }
+
+        let (free_list, bitflags, lent) = self.free_list_mut();
+        if Self::attempt_deallocate(free_list, bitflags, lent, handle, layout) {
+            return
         }
+
+        if self.overflow() {
+            let (free_list, bitflags, lent) = self.free_list_mut();
+            if Self::attempt_deallocate(free_list, bitflags, lent, handle, layout) {
+                return
+            }
+        }
+
+        self.storage.deallocate_nonempty(handle, layout);
     }
 }
 
-unsafe impl<S: SharedStorage> SharedStorage for FreeListStorage<S> {
+unsafe impl<S: SharedStorage, P: FitPolicy> SharedStorage for FreeListStorage<S, P> {
     fn shared_allocate_nonempty(
         &self,
         layout: NonEmptyLayout,
     ) -> Result<crate::NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        let (free_list, bitflags) = self.free_list();
+        let (free_list, bitflags, lent) = self.free_list();
 
         let waiter = crate::backoff::Backoff::new();
         while waiter.spin() {
             let mut was_blocked = false;
-            if let Some(memory_block) = Self::attempt_shared_allocate(free_list, bitflags, layout, &mut was_blocked) {
+            if let Some((memory_block, is_lent)) =
+                Self::attempt_shared_allocate(free_list, bitflags, lent, layout, self.slack, &mut was_blocked)
+            {
+                if is_lent {
+                    self.lent.fetch_add(1, Ordering::Relaxed);
+                }
                 return Ok(memory_block)
             }
             if !was_blocked {
@@ -387,12 +739,16 @@ unsafe impl<S: SharedStorage> SharedStorage for FreeListStorage<S> {
     }
 
     unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
-        let (free_list, bitflags) = self.free_list();
+        if self.find_lent(handle, true) {
+            return
+        }
+
+        let (free_list, bitflags, lent) = self.free_list();
 
         let waiter = crate::backoff::Backoff::new();
         while waiter.spin() {
             let mut was_blocked = false;
-            if Self::attempt_shared_deallocate(free_list, bitflags, handle, layout, &mut was_blocked) {
+            if Self::attempt_shared_deallocate(free_list, bitflags, lent, handle, layout, &mut was_blocked) {
                 return
             }
             if !was_blocked {
@@ -400,18 +756,89 @@ unsafe impl<S: SharedStorage> SharedStorage for FreeListStorage<S> {
             }
         }
 
+        // the cache can't grow through a shared reference, but the overflow is remembered for later
+        self.overflows.fetch_add(1, Ordering::Relaxed);
         self.storage.shared_deallocate_nonempty(handle, layout)
     }
 }
 
-impl<S: Storage + Flush> FreeListStorage<S> {
-    fn shallow_flush(&mut self) {
+impl<S: Storage, P> FreeListStorage<S, P> {
+    // records that a block didn't fit in the cache, and grows the cache if that happened too often
+    fn overflow(&mut self) -> bool {
+        if self.max_length.get() >= self.ceiling {
+            return false
+        }
+
+        let overflows = self.overflows.get_mut();
+        *overflows += 1;
+        *overflows >= self.max_length.get() && self.grow_free_list()
+    }
+
+    fn grow_free_list(&mut self) -> bool {
+        let old_length = self.max_length;
+        let new_length = old_length.get().saturating_mul(2).min(self.ceiling);
+        let new_length = unsafe { NonZeroUsize::new_unchecked(new_length) };
+
+        let (old_layout, old_bitflags, old_bitflags_len) =
+            unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(old_length)) };
+        let Ok((new_layout, new_bitflags, new_bitflags_len)) = free_list_layout::<S::Handle>(new_length) else {
+            return false
+        };
+
+        let Ok(NonEmptyMemoryBlock { handle: items, .. }) = self.storage.allocate_nonempty(new_layout) else {
+            return false
+        };
+
+        unsafe {
+            let old_ptr = self.storage.get_mut(self.items).as_ptr();
+            let new_ptr = self.storage.get_mut(items).as_ptr();
+
+            // the cached blocks keep their slots, so the bitflags can be copied over as is
+            let old_items = old_ptr.cast::<FreeListItem<S::Handle>>();
+            let new_items = new_ptr.cast::<FreeListItem<S::Handle>>();
+            new_items.copy_from_nonoverlapping(old_items, old_length.get());
+
+            let dangling = Handle::dangling(1);
+            for i in old_length.get()..new_length.get() {
+                new_items.add(i).write(FreeListItem {
+                    layout: Cell::new(Layout::new::<()>()),
+                    handle: Cell::new(dangling),
+                });
+            }
+
+            // the status of each bucket, and then the slots that are kept for lent out blocks
+            let new_bitflags = new_ptr.add(new_bitflags);
+            let old_bitflags = old_ptr.add(old_bitflags);
+            new_bitflags.write_bytes(0, new_bitflags_len * 2);
+            new_bitflags.copy_from_nonoverlapping(old_bitflags, old_bitflags_len);
+            new_bitflags
+                .add(new_bitflags_len)
+                .copy_from_nonoverlapping(old_bitflags.add(old_bitflags_len), old_bitflags_len);
+
+            self.storage.deallocate_nonempty(self.items, old_layout);
+        }
+
+        self.items = items;
+        self.max_length = new_length;
+        *self.overflows.get_mut() = 0;
+
+        true
+    }
+
+    fn shallow_flush(&mut self) { self.shallow_flush_where(usize::MAX, |_| true); }
+
+    // hands back at most `limit` of the cached blocks that `filter` accepts, and returns how many were handed back
+    fn shallow_flush_where(&mut self, mut limit: usize, mut filter: impl FnMut(Layout) -> bool) -> usize {
         type ScratchSpace<H> = crate::SingleStackStorage<[(H, Layout); 7]>;
 
-        let (_, bitflags, bitflags_len) =
-            unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length.get())) };
+        let (_, bitflags, bitflags_len) = unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length)) };
+        let mut flushed = 0;
 
         for i in 0..bitflags_len {
+            if limit == 0 {
+                break
+            }
+
             let (freelist, bitflags) = unsafe { self.free_list_mut_at(bitflags, bitflags_len) };
 
             let flags = unsafe { bitflags.get_unchecked_mut(i) };
@@ -423,30 +850,47 @@ impl<S: Storage + Flush> FreeListStorage<S> {
 
             let mut vec = crate::vec::Vec::new_in(ScratchSpace::<S::Handle>::new());
 
-            let flags = core::mem::take(flags);
             let index = i * 7;
             for j in 0..7 {
-                let flag = flags & (1 << j);
+                let flag = *flags & (1 << j);
 
-                if flag != 0 {
+                if flag != 0 && limit != 0 {
                     let index = index + j;
                     let freelist = unsafe { freelist.get_unchecked_mut(index) };
 
-                    unsafe {
-                        vec.push_unchecked((freelist.handle.get(), freelist.layout.get()));
+                    if filter(freelist.layout.get()) {
+                        *flags &= !flag;
+                        limit -= 1;
+
+                        unsafe {
+                            vec.push_unchecked((freelist.handle.get(), freelist.layout.get()));
+                        }
                     }
                 }
             }
 
             while let Some((handle, layout)) = vec.try_pop() {
+                flushed += 1;
                 unsafe {
                     self.storage
                         .deallocate_nonempty(handle, NonEmptyLayout::new_unchecked(layout))
                 }
             }
         }
+
+        flushed
     }
 
+    /// Hands every cached block that is larger than `size` bytes back to the underlying storage,
+    /// and returns how many blocks were handed back
+    pub fn flush_larger_than(&mut self, size: usize) -> usize {
+        self.shallow_flush_where(usize::MAX, |layout| layout.size() > size)
+    }
+
+    /// Hands at most `count` cached blocks back to the underlying storage,
+    /// and returns how many blocks were handed back
+    pub fn flush_at_most(&mut self, count: usize) -> usize { self.shallow_flush_where(count, |_| true) }
+
     fn shared_shallow_flush(&self, force_retry: bool) -> bool
     where
         S: SharedStorage,
@@ -455,8 +899,7 @@ impl<S: Storage + Flush> FreeListStorage<S> {
 
         let mut completed = true;
 
-        let (_, bitflags, bitflags_len) =
-            unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length.get())) };
+        let (_, bitflags, bitflags_len) = unsafe { unwrap_unchecked(free_list_layout::<S::Handle>(self.max_length)) };
 
         let (freelist, bitflags) = unsafe { self.free_list_at(bitflags, bitflags_len) };
         'main_loop: for (i, flags) in bitflags.iter().enumerate() {
@@ -520,7 +963,7 @@ impl<S: Storage + Flush> FreeListStorage<S> {
     }
 }
 
-impl<S: Storage + Flush> Flush for FreeListStorage<S> {
+impl<S: Storage + Flush, P: FitPolicy> Flush for FreeListStorage<S, P> {
     fn try_flush(&mut self) -> bool {
         self.shallow_flush();
         self.storage.try_flush()
@@ -532,7 +975,7 @@ impl<S: Storage + Flush> Flush for FreeListStorage<S> {
     }
 }
 
-impl<S: SharedStorage + SharedFlush> SharedFlush for FreeListStorage<S> {
+impl<S: SharedStorage + SharedFlush, P: FitPolicy> SharedFlush for FreeListStorage<S, P> {
     fn try_shared_flush(&self) -> bool {
         let shallow = self.shared_shallow_flush(false);
         let storage = self.storage.try_shared_flush();
@@ -545,7 +988,44 @@ impl<S: SharedStorage + SharedFlush> SharedFlush for FreeListStorage<S> {
     }
 }
 
-unsafe impl<S: ResizableStorage> ResizableStorage for FreeListStorage<S> {
+// a block that was lent out for a different layout can't be resized by the underlying storage,
+// so it is moved to a new block instead, and put back in the cache
+impl<S: Storage, P: FitPolicy> FreeListStorage<S, P> {
+    unsafe fn move_lent(
+        &mut self,
+        handle: S::Handle,
+        old: Layout,
+        memory_block: Result<crate::MemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<crate::MemoryBlock<S::Handle>, AllocErr> {
+        let memory_block = memory_block?;
+        let src = self.get(handle);
+        let dst = self.get_mut(memory_block.handle);
+        dst.as_ptr()
+            .copy_from_nonoverlapping(src.as_ptr(), old.size().min(memory_block.size));
+        self.deallocate(handle, old);
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_move_lent(
+        &self,
+        handle: S::Handle,
+        old: Layout,
+        memory_block: Result<crate::MemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<crate::MemoryBlock<S::Handle>, AllocErr>
+    where
+        S: SharedStorage,
+    {
+        let memory_block = memory_block?;
+        let src = self.get(handle);
+        let dst = self.shared_get_mut(memory_block.handle);
+        dst.as_ptr()
+            .copy_from_nonoverlapping(src.as_ptr(), old.size().min(memory_block.size));
+        self.shared_deallocate(handle, old);
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: ResizableStorage, P: FitPolicy> ResizableStorage for FreeListStorage<S, P> {
     #[inline]
     unsafe fn grow(
         &mut self,
@@ -553,6 +1033,11 @@ unsafe impl<S: ResizableStorage> ResizableStorage for FreeListStorage<S> {
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.find_lent(handle, false) {
+            let memory_block = self.allocate(new);
+            return self.move_lent(handle, old, memory_block)
+        }
+
         self.storage.grow(handle, old, new)
     }
 
@@ -563,6 +1048,11 @@ unsafe impl<S: ResizableStorage> ResizableStorage for FreeListStorage<S> {
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.find_lent(handle, false) {
+            let memory_block = self.allocate_zeroed(new);
+            return self.move_lent(handle, old, memory_block)
+        }
+
         self.storage.grow_zeroed(handle, old, new)
     }
 
@@ -573,11 +1063,16 @@ unsafe impl<S: ResizableStorage> ResizableStorage for FreeListStorage<S> {
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.find_lent(handle, false) {
+            let memory_block = self.allocate(new);
+            return self.move_lent(handle, old, memory_block)
+        }
+
         self.storage.shrink(handle, old, new)
     }
 }
 
-unsafe impl<S: SharedResizableStorage> SharedResizableStorage for FreeListStorage<S> {
+unsafe impl<S: SharedResizableStorage, P: FitPolicy> SharedResizableStorage for FreeListStorage<S, P> {
     #[inline]
     unsafe fn shared_grow(
         &self,
@@ -585,6 +1080,11 @@ unsafe impl<S: SharedResizableStorage> SharedResizableStorage for FreeListStorag
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.find_lent(handle, false) {
+            let memory_block = self.shared_allocate(new);
+            return self.shared_move_lent(handle, old, memory_block)
+        }
+
         self.storage.shared_grow(handle, old, new)
     }
 
@@ -595,6 +1095,11 @@ unsafe impl<S: SharedResizableStorage> SharedResizableStorage for FreeListStorag
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.find_lent(handle, false) {
+            let memory_block = self.shared_allocate_zeroed(new);
+            return self.shared_move_lent(handle, old, memory_block)
+        }
+
         self.storage.shared_grow_zeroed(handle, old, new)
     }
 
@@ -605,6 +1110,266 @@ unsafe impl<S: SharedResizableStorage> SharedResizableStorage for FreeListStorag
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() != 0 && self.find_lent(handle, false) {
+            let memory_block = self.shared_allocate(new);
+            return self.shared_move_lent(handle, old, memory_block)
+        }
+
         self.storage.shared_shrink(handle, old, new)
     }
 }
+
+#[test]
+fn freelist_drop() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = FreeListStorage::new(NonZeroUsize::new(8).unwrap(), &mock);
+
+    let layout = Layout::new::<[u64; 4]>();
+    let a = storage.allocate(layout).unwrap();
+    let b = storage.allocate(layout).unwrap();
+
+    unsafe {
+        storage.deallocate(a.handle, layout);
+        storage.deallocate(b.handle, layout);
+    }
+
+    // the blocks are cached, along with the free list itself
+    assert_eq!(mock.live_allocations(), 3);
+
+    drop(storage);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn freelist_size_classes() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = FreeListStorage::new(NonZeroUsize::new(8).unwrap(), &mock);
+
+    let small = Layout::new::<[u64; 4]>();
+    let large = Layout::new::<[u64; 32]>();
+    let a = storage.allocate(large).unwrap();
+    let b = storage.allocate(small).unwrap();
+
+    unsafe {
+        storage.deallocate(a.handle, large);
+        storage.deallocate(b.handle, small);
+
+        // the block from the same size class is preferred, even though the larger block would fit
+        let c = storage.allocate(small).unwrap();
+        assert_eq!(storage.get(c.handle), storage.get(b.handle));
+        storage.deallocate(c.handle, small);
+    }
+}
+
+#[test]
+fn freelist_slack() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let large = Layout::from_size_align(64, 16).unwrap();
+    let medium = Layout::from_size_align(48, 8).unwrap();
+    let small = Layout::from_size_align(16, 8).unwrap();
+
+    // a slack of 1 only reuses blocks of exactly the requested size
+    let mut storage = FreeListStorage::new(NonZeroUsize::new(8).unwrap(), crate::ValidatingStorage::new(system))
+        .with_slack(NonZeroUsize::new(1).unwrap());
+    let a = storage.allocate(large).unwrap();
+
+    unsafe {
+        storage.deallocate(a.handle, large);
+
+        let b = storage.allocate(medium).unwrap();
+        assert_ne!(storage.get(b.handle), storage.get(a.handle));
+        storage.deallocate(b.handle, medium);
+    }
+
+    let mut storage = FreeListStorage::new(NonZeroUsize::new(8).unwrap(), crate::ValidatingStorage::new(system));
+    let a = storage.allocate(large).unwrap();
+
+    unsafe {
+        storage.deallocate(a.handle, large);
+
+        // too much of the cached block would be wasted
+        let b = storage.allocate(small).unwrap();
+        assert_ne!(storage.get(b.handle), storage.get(a.handle));
+
+        // but a less aligned request can use it, and sees the real size of the block
+        let c = storage.allocate(medium).unwrap();
+        assert_eq!(storage.get(c.handle), storage.get(a.handle));
+        assert_eq!(c.size, large.size());
+
+        storage.deallocate(b.handle, small);
+        // the block goes back in the cache with the layout it was allocated with
+        storage.deallocate(c.handle, medium);
+
+        let reused = storage.allocate(large).unwrap();
+        assert_eq!(storage.get(reused.handle), storage.get(a.handle));
+        storage.deallocate(reused.handle, large);
+
+        // a lent out block is moved when it is resized
+        let grown = Layout::from_size_align(56, 8).unwrap();
+        let lent = storage.allocate(medium).unwrap();
+        storage.get_mut(lent.handle).as_ptr().write(0xab);
+        let moved = storage.grow(lent.handle, medium, grown).unwrap();
+        assert_ne!(storage.get(moved.handle), storage.get(a.handle));
+        assert_eq!(storage.get(moved.handle).as_ptr().read(), 0xab);
+        storage.deallocate(moved.handle, grown);
+    }
+
+    // the validating storage checks every block that is handed back to it
+    drop(storage);
+}
+
+#[test]
+fn freelist_policy() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let large = Layout::from_size_align(64, 8).unwrap();
+    let medium = Layout::from_size_align(40, 8).unwrap();
+    let small = Layout::from_size_align(32, 8).unwrap();
+
+    let mut storage = FreeListStorage::<_, BestFit>::with_policy(
+        NonZeroUsize::new(8).unwrap(),
+        crate::ValidatingStorage::new(system),
+    );
+    let a = storage.allocate(large).unwrap();
+    let b = storage.allocate(medium).unwrap();
+
+    unsafe {
+        storage.deallocate(a.handle, large);
+        storage.deallocate(b.handle, medium);
+
+        // both blocks fit, but the medium block wastes less
+        let c = storage.allocate(small).unwrap();
+        assert_eq!(storage.get(c.handle), storage.get(b.handle));
+        storage.deallocate(c.handle, small);
+    }
+
+    let mut storage = FreeListStorage::<_, ExactFit>::with_policy(
+        NonZeroUsize::new(8).unwrap(),
+        crate::ValidatingStorage::new(system),
+    );
+    let a = storage.allocate(medium).unwrap();
+
+    unsafe {
+        storage.deallocate(a.handle, medium);
+
+        // the cached block is larger than requested
+        let b = storage.allocate(small).unwrap();
+        assert_ne!(storage.get(b.handle), storage.get(a.handle));
+
+        let c = storage.allocate(medium).unwrap();
+        assert_eq!(storage.get(c.handle), storage.get(a.handle));
+
+        storage.deallocate(b.handle, small);
+        storage.deallocate(c.handle, medium);
+    }
+
+    crate::storage_conformance!(
+        FreeListStorage::<_, BestFit>::with_policy(
+            NonZeroUsize::new(8).unwrap(),
+            crate::ValidatingStorage::new(system)
+        ),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
+
+#[test]
+fn freelist_ceiling() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage =
+        FreeListStorage::new(NonZeroUsize::new(1).unwrap(), &mock).with_ceiling(NonZeroUsize::new(4).unwrap());
+
+    let layout = Layout::new::<[u8; 64]>();
+    let blocks = [(); 4].map(|()| storage.allocate(layout).unwrap());
+
+    unsafe {
+        for memory_block in &blocks {
+            storage.deallocate(memory_block.handle, layout);
+        }
+    }
+
+    // the cache doubled twice, so only one block was handed back to the mock storage
+    assert_eq!(mock.live_allocations(), 4);
+
+    let reused = [(); 3].map(|()| storage.allocate(layout).unwrap());
+    assert_eq!(mock.live_allocations(), 4);
+
+    unsafe {
+        for memory_block in &reused {
+            storage.deallocate(memory_block.handle, layout);
+        }
+    }
+
+    drop(storage);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn freelist_partial_flush() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = FreeListStorage::new(NonZeroUsize::new(8).unwrap(), &mock);
+
+    let small = Layout::new::<[u8; 16]>();
+    let large = Layout::new::<[u8; 256]>();
+    let blocks = [small, large, small, large, small].map(|layout| (storage.allocate(layout).unwrap(), layout));
+
+    unsafe {
+        for (memory_block, layout) in &blocks {
+            storage.deallocate(memory_block.handle, *layout);
+        }
+    }
+
+    // the free list itself is allocated from the mock storage too
+    assert_eq!(mock.live_allocations(), 6);
+
+    assert_eq!(storage.flush_larger_than(16), 2);
+    assert_eq!(mock.live_allocations(), 4);
+
+    assert_eq!(storage.flush_at_most(2), 2);
+    assert_eq!(mock.live_allocations(), 2);
+
+    assert_eq!(storage.flush_at_most(2), 1);
+    assert_eq!(storage.flush_larger_than(0), 0);
+    assert_eq!(mock.live_allocations(), 1);
+}
+
+#[test]
+fn freelist_partial_bucket() {
+    struct Counting<'a> {
+        storage: crate::AllocatorStorage<std::alloc::System>,
+        deallocated: &'a Cell<usize>,
+    }
+
+    unsafe impl Storage for Counting<'_> {
+        type Handle = NonNull<u8>;
+
+        unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+        unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+        fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+            self.storage.allocate_nonempty(layout)
+        }
+
+        unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+            self.deallocated.set(self.deallocated.get() + 1);
+            self.storage.deallocate_nonempty(handle, layout);
+        }
+    }
+
+    let deallocated = Cell::new(0);
+    let counting = Counting {
+        storage: crate::AllocatorStorage::new(std::alloc::System),
+        deallocated: &deallocated,
+    };
+    let mut storage = FreeListStorage::new(NonZeroUsize::new(3).unwrap(), counting);
+    let layout = Layout::new::<[u64; 4]>();
+
+    let blocks: [_; 5] = core::array::from_fn(|_| storage.allocate(layout).unwrap().handle);
+    for block in blocks {
+        unsafe { storage.deallocate(block, layout) }
+    }
+
+    // the cache only has room for three blocks, even though its last bucket of flags has room for seven
+    assert_eq!(deallocated.get(), 2);
+}
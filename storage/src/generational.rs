@@ -0,0 +1,331 @@
+use core::{alloc::Layout, mem, ptr::NonNull};
+
+use crate::{
+    AllocErr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    Storage,
+};
+
+const NIL: usize = usize::MAX;
+
+struct Slot<H> {
+    handle: H,
+    generation: usize,
+    // the next free slot, only meaningful while this slot is free
+    next: usize,
+}
+
+/// A storage that detects uses of handles after they were deallocated or resized
+///
+/// Every allocation gets a slot, which records the generation it was allocated in.
+/// Deallocating or resizing an allocation bumps the generation, so stale handles
+/// can be detected even after the slot is reused.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct GenerationalStorage<S: Storage> {
+    storage: S,
+    // [Slot<S::Handle>; capacity]
+    slots: S::Handle,
+    len: usize,
+    capacity: usize,
+    free: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationalHandle {
+    index: usize,
+    generation: usize,
+}
+
+unsafe impl Handle for GenerationalHandle {
+    unsafe fn dangling(align: usize) -> Self {
+        Self {
+            index: NIL,
+            generation: align,
+        }
+    }
+}
+
+impl GenerationalHandle {
+    #[must_use = "`GenerationalHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.index == NIL }
+
+    pub const fn index(self) -> usize { self.index }
+
+    pub const fn generation(self) -> usize { self.generation }
+}
+
+impl<S: Storage> GenerationalStorage<S> {
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            slots: unsafe { Handle::dangling(mem::align_of::<Slot<S::Handle>>()) },
+            len: 0,
+            capacity: 0,
+            free: NIL,
+        }
+    }
+
+    /// Returns true if `handle` is dangling, or refers to an allocation that is still live
+    pub fn is_live(&self, handle: GenerationalHandle) -> bool { handle.is_dangling() || self.slot(handle).is_some() }
+
+    /// A checked version of `Storage::get`, returns `None` if `handle` is not live
+    pub fn try_get(&self, handle: GenerationalHandle) -> Option<NonNull<u8>> {
+        if handle.is_dangling() {
+            return Some(unsafe { NonNull::new_unchecked(handle.generation as *mut u8) })
+        }
+
+        self.slot(handle).map(|inner| unsafe { self.storage.get(inner) })
+    }
+
+    /// A checked version of `Storage::get_mut`, returns `None` if `handle` is not live
+    pub fn try_get_mut(&mut self, handle: GenerationalHandle) -> Option<NonNull<u8>> {
+        if handle.is_dangling() {
+            return Some(unsafe { NonNull::new_unchecked(handle.generation as *mut u8) })
+        }
+
+        self.slot(handle).map(|inner| unsafe { self.storage.get_mut(inner) })
+    }
+
+    fn slot(&self, handle: GenerationalHandle) -> Option<S::Handle> {
+        if handle.index >= self.len {
+            return None
+        }
+
+        let slot = unsafe { &*self.slots().add(handle.index) };
+        (slot.generation == handle.generation).then_some(slot.handle)
+    }
+
+    fn check(&self, handle: GenerationalHandle) -> S::Handle { self.slot(handle).unwrap_or_else(|| stale(handle)) }
+
+    fn slots(&self) -> *const Slot<S::Handle> { unsafe { self.storage.get(self.slots).as_ptr().cast() } }
+
+    fn slots_mut(&mut self) -> *mut Slot<S::Handle> { unsafe { self.storage.get_mut(self.slots).as_ptr().cast() } }
+
+    // bumps the generation of the slot, so that all existing handles to it are stale
+    fn bump(&mut self, index: usize, inner: S::Handle) -> GenerationalHandle {
+        let slot = unsafe { &mut *self.slots_mut().add(index) };
+        slot.handle = inner;
+        slot.generation = slot.generation.wrapping_add(1);
+        GenerationalHandle {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    unsafe fn slots_layout(capacity: usize) -> Layout {
+        Layout::array::<Slot<S::Handle>>(capacity).unwrap_or_else(|_| core::hint::unreachable_unchecked())
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn stale(handle: GenerationalHandle) -> ! { panic!("{:?} was used after it was deallocated or resized", handle) }
+
+impl<S: ResizableStorage> GenerationalStorage<S> {
+    #[cold]
+    #[inline(never)]
+    fn reserve_slot(&mut self, layout: Layout) -> Result<(), AllocErr> {
+        let capacity = self.capacity.max(2) * 2;
+        let old = unsafe { Self::slots_layout(self.capacity) };
+        let new = Layout::array::<Slot<S::Handle>>(capacity).map_err(|_| AllocErr::new(layout))?;
+        let memory_block = unsafe { self.storage.grow(self.slots, old, new)? };
+        self.slots = memory_block.handle;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    fn insert(
+        &mut self,
+        layout: NonEmptyLayout,
+        allocate: impl FnOnce(&mut S) -> Result<NonEmptyMemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<NonEmptyMemoryBlock<GenerationalHandle>, AllocErr> {
+        if self.free == NIL && self.len == self.capacity {
+            self.reserve_slot(layout.into())?;
+        }
+
+        let memory_block = allocate(&mut self.storage)?;
+
+        let index = if self.free == NIL {
+            let index = self.len;
+            unsafe {
+                self.slots_mut().add(index).write(Slot {
+                    handle: memory_block.handle,
+                    generation: 0,
+                    next: NIL,
+                });
+            }
+            self.len += 1;
+            index
+        } else {
+            let index = self.free;
+            let slot = unsafe { &mut *self.slots_mut().add(index) };
+            slot.handle = memory_block.handle;
+            self.free = slot.next;
+            index
+        };
+
+        let generation = unsafe { (*self.slots().add(index)).generation };
+
+        Ok(NonEmptyMemoryBlock {
+            handle: GenerationalHandle { index, generation },
+            size: memory_block.size,
+        })
+    }
+}
+
+impl<S: Storage> Drop for GenerationalStorage<S> {
+    fn drop(&mut self) { unsafe { self.storage.deallocate(self.slots, Self::slots_layout(self.capacity)) } }
+}
+
+unsafe impl<S: ResizableStorage + SharedGetMut> SharedGetMut for GenerationalStorage<S> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.generation as *mut u8)
+        }
+
+        self.storage.shared_get_mut(self.check(handle))
+    }
+}
+
+impl<S: ResizableStorage + SharedGetMut> MultiStorage for GenerationalStorage<S> {}
+
+unsafe impl<S: ResizableStorage> Storage for GenerationalStorage<S> {
+    type Handle = GenerationalHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.generation as *mut u8)
+        }
+
+        self.storage.get(self.check(handle))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.generation as *mut u8)
+        }
+
+        let inner = self.check(handle);
+        self.storage.get_mut(inner)
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.insert(layout, |storage| storage.allocate_nonempty(layout))
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.insert(layout, |storage| storage.allocate_nonempty_zeroed(layout))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let inner = self.check(handle);
+        self.storage.deallocate_nonempty(inner, layout);
+        self.bump(handle.index, inner);
+
+        let free = self.free;
+        (*self.slots_mut().add(handle.index)).next = free;
+        self.free = handle.index;
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for GenerationalStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() == 0 {
+            return self.allocate(new)
+        }
+
+        let memory_block = self.storage.grow(self.check(handle), old, new)?;
+        Ok(MemoryBlock {
+            handle: self.bump(handle.index, memory_block.handle),
+            size: memory_block.size,
+        })
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() == 0 {
+            return self.allocate_zeroed(new)
+        }
+
+        let memory_block = self.storage.grow_zeroed(self.check(handle), old, new)?;
+        Ok(MemoryBlock {
+            handle: self.bump(handle.index, memory_block.handle),
+            size: memory_block.size,
+        })
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if new.size() == 0 {
+            self.deallocate(handle, old);
+            return Ok(MemoryBlock {
+                handle: GenerationalHandle::dangling(new.align()),
+                size: 0,
+            })
+        }
+
+        let memory_block = self.storage.shrink(self.check(handle), old, new)?;
+        Ok(MemoryBlock {
+            handle: self.bump(handle.index, memory_block.handle),
+            size: memory_block.size,
+        })
+    }
+}
+
+#[test]
+fn generational() {
+    let mut storage = GenerationalStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let layout = Layout::new::<u64>();
+
+    let first = storage.allocate(layout).unwrap().handle;
+    let second = storage.allocate(layout).unwrap().handle;
+    assert!(storage.is_live(first));
+
+    unsafe { storage.deallocate(first, layout) }
+    assert!(!storage.is_live(first));
+    assert!(storage.try_get(first).is_none());
+
+    let third = storage.allocate(layout).unwrap().handle;
+    assert_eq!(third.index(), first.index());
+    assert!(!storage.is_live(first));
+    assert!(storage.try_get_mut(third).is_some());
+
+    let grown = unsafe { storage.grow(second, layout, Layout::new::<[u64; 4]>()).unwrap().handle };
+    assert!(!storage.is_live(second));
+    assert!(storage.is_live(grown));
+
+    unsafe {
+        storage.deallocate(third, layout);
+        storage.deallocate(grown, Layout::new::<[u64; 4]>());
+    }
+
+    crate::storage_conformance!(storage, resizable);
+}
+
+#[test]
+#[should_panic = "was used after it was deallocated or resized"]
+fn generational_use_after_free() {
+    let mut storage = GenerationalStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let layout = Layout::new::<u64>();
+    let memory_block = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(memory_block.handle, layout);
+        storage.allocate(layout).unwrap();
+        storage.get(memory_block.handle);
+    }
+}
@@ -1,15 +1,17 @@
 use core::{
     alloc::Layout,
+    cell::UnsafeCell,
+    mem::{self, MaybeUninit},
     ptr::NonNull,
     sync::atomic::{
         AtomicU8,
-        Ordering::{Relaxed, SeqCst},
+        Ordering::{Acquire, Relaxed, SeqCst},
     },
 };
 
 use crate::{
-    AllocErr, FromPtr, MultiStorage, NonEmptyLayout, OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle,
-    SharedResizableStorage, SharedStorage, Storage,
+    backoff::Backoff, AllocErr, FromPtr, MultiStorage, NonEmptyLayout, OffsetHandle, ResizableStorage, SharedGetMut,
+    SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 
 pub trait GlobalStorage: SharedResizableStorage + Send + Sync + 'static {}
@@ -20,42 +22,163 @@ pub struct Global;
 
 pub type GlobalStorageImp = &'static dyn GlobalStorage<Handle = NonNull<u8>>;
 
-static mut GLOBAL: GlobalStorageImp = &crate::no_op::NoOpStorage;
-static INITIALIZER_STATE: AtomicU8 = AtomicU8::new(UNINIT);
+// the lazy initializer is stored in place, so that it doesn't have to be boxed
+type LazyInit = MaybeUninit<[usize; 4]>;
+
+static GLOBAL: GlobalCell = GlobalCell::new();
 
 const UNINIT: u8 = 0;
 const WRITING: u8 = 1;
 const INIT: u8 = 2;
+const LAZY: u8 = 3;
+const POISONED: u8 = 4;
+
+struct GlobalCell {
+    state: AtomicU8,
+    global: UnsafeCell<GlobalStorageImp>,
+    lazy: UnsafeCell<LazyInit>,
+    call_lazy: UnsafeCell<unsafe fn(*mut LazyInit) -> GlobalStorageImp>,
+}
+
+// the cells are only written while the state is `WRITING`, which only one thread can see at a time
+unsafe impl Sync for GlobalCell {}
+
+unsafe fn call_lazy<F: FnOnce() -> GlobalStorageImp>(lazy: *mut LazyInit) -> GlobalStorageImp {
+    lazy.cast::<F>().read()()
+}
 
-pub fn set_global_storage_with(global: impl FnOnce() -> GlobalStorageImp) -> bool {
-    if INITIALIZER_STATE.load(Relaxed) != UNINIT
-        || INITIALIZER_STATE
-            .compare_exchange(UNINIT, WRITING, SeqCst, Relaxed)
-            .is_err()
-    {
-        return false
+// sets the state if the initializer panics
+struct ResetOnUnwind<'a>(&'a AtomicU8, u8);
+
+impl Drop for ResetOnUnwind<'_> {
+    fn drop(&mut self) { self.0.store(self.1, SeqCst); }
+}
+
+impl GlobalCell {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(UNINIT),
+            global: UnsafeCell::new(&crate::no_op::NoOpStorage),
+            lazy: UnsafeCell::new(MaybeUninit::uninit()),
+            call_lazy: UnsafeCell::new(|_| &crate::no_op::NoOpStorage),
+        }
     }
 
-    unsafe {
-        GLOBAL = global();
+    fn start_writing(&self) -> bool {
+        self.state.load(Relaxed) == UNINIT && self.state.compare_exchange(UNINIT, WRITING, SeqCst, Relaxed).is_ok()
+    }
+
+    fn set_with(&self, global: impl FnOnce() -> GlobalStorageImp) -> bool {
+        if !self.start_writing() {
+            return false
+        }
+
+        // the initializer can be tried again if it panics
+        let reset = ResetOnUnwind(&self.state, UNINIT);
+        unsafe {
+            *self.global.get() = global();
+        }
+        mem::forget(reset);
+
+        self.state.store(INIT, SeqCst);
+
+        true
+    }
+
+    fn set_lazy<F: FnOnce() -> GlobalStorageImp + Send + 'static>(&self, global: F) -> bool {
+        const {
+            assert!(
+                mem::size_of::<F>() <= mem::size_of::<LazyInit>()
+                    && mem::align_of::<F>() <= mem::align_of::<LazyInit>(),
+                "the lazy global storage initializer can only capture up to 4 pointers"
+            );
+        }
+
+        if !self.start_writing() {
+            return false
+        }
+
+        unsafe {
+            self.lazy.get().cast::<F>().write(global);
+            *self.call_lazy.get() = call_lazy::<F>;
+        }
+
+        self.state.store(LAZY, SeqCst);
+
+        true
     }
 
-    INITIALIZER_STATE.store(INIT, SeqCst);
+    #[inline]
+    fn get(&self) -> GlobalStorageImp {
+        match self.state.load(Acquire) {
+            INIT => unsafe { *self.global.get() },
+            UNINIT => &crate::no_op::NoOpStorage,
+            _ => self.init_lazy(),
+        }
+    }
 
-    true
+    #[cold]
+    fn init_lazy(&self) -> GlobalStorageImp {
+        let backoff = Backoff::new();
+
+        loop {
+            match self.state.compare_exchange(LAZY, WRITING, SeqCst, Acquire) {
+                Ok(_) => break,
+                Err(INIT) => return unsafe { *self.global.get() },
+                Err(UNINIT) => return &crate::no_op::NoOpStorage,
+                Err(POISONED) => panic!("the lazy global storage initializer panicked"),
+                // another thread is setting up the global storage, or running the initializer
+                // if this thread is running the initializer, then this never finishes
+                Err(_) => {
+                    if !backoff.spin() {
+                        core::hint::spin_loop();
+                    }
+                }
+            }
+        }
+
+        // the initializer was moved out, so it can't be tried again if it panics
+        let poison = ResetOnUnwind(&self.state, POISONED);
+        unsafe {
+            *self.global.get() = (*self.call_lazy.get())(self.lazy.get());
+        }
+        mem::forget(poison);
+
+        self.state.store(INIT, SeqCst);
+
+        unsafe { *self.global.get() }
+    }
 }
 
+/// Sets the global storage to the one returned by `global`, which is called right away
+///
+/// If other threads use [`Global`] while `global` is running, they wait for it to finish.
+/// So `global` must not use [`Global`] itself, or it will never finish. If `global` panics
+/// the global storage is left unset.
+///
+/// Returns false if the global storage was already set
+pub fn set_global_storage_with(global: impl FnOnce() -> GlobalStorageImp) -> bool { GLOBAL.set_with(global) }
+
 pub fn set_global_storage(global: GlobalStorageImp) -> bool { set_global_storage_with(move || global) }
 
-#[inline]
-fn global() -> GlobalStorageImp {
-    if INITIALIZER_STATE.load(Relaxed) == INIT {
-        unsafe { GLOBAL }
-    } else {
-        &crate::no_op::NoOpStorage
-    }
+/// Sets the global storage to the one returned by `global`, which is only called
+/// when [`Global`] is first used
+///
+/// This way, the global storage doesn't have to be set up before anything can be allocated.
+/// If other threads use [`Global`] while `global` is running, they wait for it to finish.
+/// So `global` must not use [`Global`] itself, or it will never finish. If `global` panics,
+/// every later use of [`Global`] panics too.
+///
+/// `global` is stored without allocating, so it may only capture up to 4 pointers worth of data.
+///
+/// Returns false if the global storage was already set
+pub fn set_global_storage_lazy<F: FnOnce() -> GlobalStorageImp + Send + 'static>(global: F) -> bool {
+    GLOBAL.set_lazy(global)
 }
 
+#[inline]
+fn global() -> GlobalStorageImp { GLOBAL.get() }
+
 unsafe impl FromPtr for Global {
     #[inline]
     unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
@@ -224,3 +347,54 @@ unsafe impl SharedResizableStorage for Global {
         global().shrink(handle, old, new)
     }
 }
+
+#[test]
+fn global_lazy() {
+    use std::sync::atomic::AtomicUsize;
+
+    static SYSTEM: crate::AllocatorStorage<std::alloc::System> = crate::AllocatorStorage::new(std::alloc::System);
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let cell = GlobalCell::new();
+    assert!(cell.set_lazy(|| {
+        CALLS.fetch_add(1, Relaxed);
+        // give the other threads time to see that the initializer is running
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        &SYSTEM
+    }));
+    assert!(!cell.set_lazy(|| -> GlobalStorageImp { &SYSTEM }));
+    assert!(!cell.set_with(|| &SYSTEM));
+    assert_eq!(CALLS.load(Relaxed), 0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                // every thread waits for the initializer, instead of seeing the unset global storage
+                let layout = Layout::new::<u64>();
+                let memory_block = cell.get().shared_allocate(layout).unwrap();
+                unsafe { cell.get().shared_deallocate(memory_block.handle, layout) }
+            });
+        }
+    });
+
+    assert_eq!(CALLS.load(Relaxed), 1);
+}
+
+#[test]
+fn global_lazy_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    static SYSTEM: crate::AllocatorStorage<std::alloc::System> = crate::AllocatorStorage::new(std::alloc::System);
+
+    let cell = GlobalCell::new();
+    assert!(cell.set_lazy(|| -> GlobalStorageImp { panic!("initializer") }));
+    assert!(catch_unwind(AssertUnwindSafe(|| cell.get())).is_err());
+    // the initializer is gone, so later uses panic instead of waiting forever
+    assert!(catch_unwind(AssertUnwindSafe(|| cell.get())).is_err());
+
+    // but an eager initializer can be tried again
+    let cell = GlobalCell::new();
+    assert!(catch_unwind(AssertUnwindSafe(|| cell.set_with(|| panic!("initializer")))).is_err());
+    assert!(cell.set_with(|| &SYSTEM));
+    assert!(!cell.set_lazy(|| -> GlobalStorageImp { &SYSTEM }));
+}
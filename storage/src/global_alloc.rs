@@ -0,0 +1,85 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::{self, NonNull},
+};
+
+use crate::{macros::MbR, FromPtr, SharedResizableStorage};
+
+#[must_use = "storages don't do anything unless they are used"]
+pub struct StorageGlobalAlloc<S> {
+    pub storage: S,
+}
+
+impl<S> StorageGlobalAlloc<S> {
+    #[inline]
+    pub const fn new(storage: S) -> Self { Self { storage } }
+}
+
+impl<S: SharedResizableStorage> StorageGlobalAlloc<S> {
+    #[inline]
+    unsafe fn as_ptr(&self, memory_block: MbR<S::Handle>) -> *mut u8 {
+        memory_block.map_or(ptr::null_mut(), |memory_block| {
+            self.storage.shared_get_mut(memory_block.handle).as_ptr()
+        })
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + FromPtr> GlobalAlloc for StorageGlobalAlloc<S> {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 { self.as_ptr(self.storage.shared_allocate(layout)) }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let handle = self.storage.from_ptr(NonNull::new_unchecked(ptr), layout);
+        self.storage.shared_deallocate(handle, layout);
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.as_ptr(self.storage.shared_allocate_zeroed(layout))
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = Layout::from_size_align_unchecked(new_size, layout.align());
+        let handle = self.storage.from_ptr(NonNull::new_unchecked(ptr), layout);
+
+        let memory_block = if new_size >= layout.size() {
+            self.storage.shared_grow(handle, layout, new)
+        } else {
+            self.storage.shared_shrink(handle, layout, new)
+        };
+
+        self.as_ptr(memory_block)
+    }
+}
+
+#[test]
+fn storage_global_alloc() {
+    let global = StorageGlobalAlloc::new(crate::LeakCheck::new(crate::AllocatorStorage::new(std::alloc::System)));
+    let layout = Layout::new::<[u32; 4]>();
+    let grown = Layout::new::<[u32; 16]>();
+    let shrunk = Layout::new::<[u32; 2]>();
+
+    unsafe {
+        let ptr = global.alloc(layout);
+        assert!(!ptr.is_null());
+        ptr.write(7);
+        assert_eq!(global.storage.live_allocations(), 1);
+
+        let ptr = global.realloc(ptr, layout, grown.size());
+        assert_eq!(ptr.read(), 7);
+        let ptr = global.realloc(ptr, grown, shrunk.size());
+        assert_eq!(ptr.read(), 7);
+        assert_eq!(global.storage.live_allocations(), 1);
+        global.dealloc(ptr, shrunk);
+
+        let ptr = global.alloc_zeroed(layout);
+        assert!(core::slice::from_raw_parts(ptr, layout.size())
+            .iter()
+            .all(|&byte| byte == 0));
+        global.dealloc(ptr, layout);
+    }
+
+    assert!(global.storage.check());
+}
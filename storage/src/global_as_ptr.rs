@@ -1,21 +1,65 @@
 use crate::{
     core_traits::FromPtr,
     macros::{map_mbr, map_nembr},
-    MultiStorage, OffsetHandle, PointerHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle,
+    GlobalStorage, MultiStorage, OffsetHandle, PointerHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle,
     SharedResizableStorage, SharedStorage, Storage,
 };
 use core::{alloc::Layout, ptr::NonNull};
 
 fn to_ptr<H: PointerHandle>(handle: H) -> NonNull<u8> { unsafe { handle.get_mut() } }
 
+/// A storage that uses the pointers to its allocations as the handles
+///
+/// This lets any storage with [`PointerHandle`] handles be installed as the global storage,
+/// which needs `NonNull<u8>` handles. The handles are turned back into the underlying storage's
+/// handles with [`FromPtr`], so the pointers have to stay put for as long as the allocation lives,
+/// even if the `GlobalAsPtrStorage` is moved.
+///
+/// [`GlobalAsPtrStorage::from_static`] is always fine, because a storage behind a `&'static`
+/// never moves. Otherwise, the storage must not keep its allocations inline (like a
+/// [`SingleStackStorage`](crate::SingleStackStorage)), but somewhere that outlives the storage
+/// itself, like the heap or a `static`.
 pub struct GlobalAsPtrStorage<S> {
     inner: S,
 }
 
 impl<S: 'static> GlobalAsPtrStorage<S> {
+    /// # Safety
+    ///
+    /// The pointers to `inner`'s allocations must not change when `inner` is moved,
+    /// and must be valid until they are deallocated
     pub const unsafe fn new(inner: S) -> Self { Self { inner } }
 }
 
+impl<S: GlobalStorage + FromPtr + ?Sized> GlobalAsPtrStorage<&'static S> {
+    pub const fn from_static(inner: &'static S) -> Self { Self { inner } }
+}
+
+impl<S: FromPtr> GlobalAsPtrStorage<S> {
+    // turns a pointer back into `S`'s handle, and checks that it names the same allocation
+    #[inline]
+    unsafe fn handle(&self, ptr: NonNull<u8>, layout: Layout) -> S::Handle {
+        let handle = self.inner.from_ptr(ptr, layout);
+        debug_assert_eq!(
+            self.inner.get(handle),
+            ptr,
+            "`FromPtr::from_ptr` didn't give back the handle to the allocation"
+        );
+        handle
+    }
+
+    #[inline]
+    unsafe fn handle_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> S::Handle {
+        let handle = self.inner.from_ptr_mut(ptr, layout);
+        debug_assert_eq!(
+            self.inner.get(handle),
+            ptr,
+            "`FromPtr::from_ptr` didn't give back the handle to the allocation"
+        );
+        handle
+    }
+}
+
 unsafe impl<S: FromPtr> FromPtr for GlobalAsPtrStorage<S>
 where
     S::Handle: PointerHandle,
@@ -65,7 +109,7 @@ where
 
     #[inline]
     unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: crate::NonEmptyLayout) {
-        let handle = self.inner.from_ptr_mut(handle, layout.into());
+        let handle = self.handle_mut(handle, layout.into());
         S::deallocate_nonempty(&mut self.inner, handle, layout)
     }
 
@@ -76,7 +120,7 @@ where
 
     #[inline]
     unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
-        let handle = self.inner.from_ptr_mut(handle, layout);
+        let handle = self.handle_mut(handle, layout);
         S::deallocate(&mut self.inner, handle, layout)
     }
 
@@ -112,7 +156,7 @@ where
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        let handle = self.inner.from_ptr_mut(handle, old);
+        let handle = self.handle_mut(handle, old);
         map_mbr(S::grow(&mut self.inner, handle, old, new), to_ptr)
     }
 
@@ -123,7 +167,7 @@ where
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        let handle = self.inner.from_ptr_mut(handle, old);
+        let handle = self.handle_mut(handle, old);
         map_mbr(S::grow_zeroed(&mut self.inner, handle, old, new), to_ptr)
     }
 
@@ -134,7 +178,7 @@ where
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        let handle = self.inner.from_ptr_mut(handle, old);
+        let handle = self.handle_mut(handle, old);
         map_mbr(S::shrink(&mut self.inner, handle, old, new), to_ptr)
     }
 }
@@ -153,7 +197,7 @@ where
 
     #[inline]
     unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: crate::NonEmptyLayout) {
-        let handle = self.inner.from_ptr(handle, layout.into());
+        let handle = self.handle(handle, layout.into());
         S::shared_deallocate_nonempty(&self.inner, handle, layout)
     }
 
@@ -164,7 +208,7 @@ where
 
     #[inline]
     unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
-        let handle = self.inner.from_ptr(handle, layout);
+        let handle = self.handle(handle, layout);
         S::shared_deallocate(&self.inner, handle, layout)
     }
 
@@ -193,7 +237,7 @@ where
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        let handle = self.inner.from_ptr(handle, old);
+        let handle = self.handle(handle, old);
         map_mbr(S::shared_grow(&self.inner, handle, old, new), to_ptr)
     }
 
@@ -204,7 +248,7 @@ where
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        let handle = self.inner.from_ptr(handle, old);
+        let handle = self.handle(handle, old);
         map_mbr(S::shared_grow_zeroed(&self.inner, handle, old, new), to_ptr)
     }
 
@@ -215,7 +259,16 @@ where
         old: Layout,
         new: Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        let handle = self.inner.from_ptr(handle, old);
+        let handle = self.handle(handle, old);
         map_mbr(S::shared_shrink(&self.inner, handle, old, new), to_ptr)
     }
 }
+
+#[test]
+fn global_as_ptr() {
+    static STORAGE: crate::AllocatorStorage<std::alloc::System> = crate::AllocatorStorage::new(std::alloc::System);
+    static GLOBAL: GlobalAsPtrStorage<&crate::AllocatorStorage<std::alloc::System>> =
+        GlobalAsPtrStorage::from_static(&STORAGE);
+
+    crate::storage_conformance!(&GLOBAL, resizable, shared, shared_resizable);
+}
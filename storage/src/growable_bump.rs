@@ -0,0 +1,258 @@
+use core::{alloc::Layout, mem, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    AllocErr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle, ResizableStorage,
+    SharedGetMut, Storage,
+};
+
+type Chunk<H> = (H, Layout);
+
+#[must_use = "storages don't do anything unless they are used"]
+pub struct GrowableBumpStorage<S: Storage, const CHUNK: usize> {
+    storage: S,
+    // [Chunk<S::Handle>; capacity]
+    chunks: S::Handle,
+    len: usize,
+    capacity: usize,
+    align: usize,
+    offset: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct GrowableBumpHandle {
+    chunk: usize,
+    offset: usize,
+}
+
+unsafe impl Handle for GrowableBumpHandle {
+    unsafe fn dangling(align: usize) -> Self {
+        Self {
+            chunk: usize::MAX,
+            offset: align,
+        }
+    }
+}
+
+impl GrowableBumpHandle {
+    #[must_use = "`GrowableBumpHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.chunk == usize::MAX }
+}
+
+impl<S: Storage, const CHUNK: usize> GrowableBumpStorage<S, CHUNK> {
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            chunks: unsafe { Handle::dangling(mem::align_of::<Chunk<S::Handle>>()) },
+            len: 0,
+            capacity: 0,
+            align: 0,
+            offset: 0,
+        }
+    }
+
+    pub const fn chunk_count(&self) -> usize { self.len }
+
+    /// the space left in the current chunk
+    pub const fn remaining_space(&self) -> usize { self.offset }
+
+    unsafe fn chunk(&self, index: usize) -> Chunk<S::Handle> {
+        let chunks = self.storage.get(self.chunks).cast::<Chunk<S::Handle>>();
+        chunks.as_ptr().add(index).read()
+    }
+
+    unsafe fn chunks_layout(capacity: usize) -> Layout {
+        Layout::array::<Chunk<S::Handle>>(capacity).unwrap_or_else(|_| core::hint::unreachable_unchecked())
+    }
+}
+
+impl<S: ResizableStorage, const CHUNK: usize> GrowableBumpStorage<S, CHUNK> {
+    #[cold]
+    #[inline(never)]
+    fn add_chunk(&mut self, layout: Layout) -> Result<(), AllocErr> {
+        if self.len == self.capacity {
+            let capacity = self.capacity.max(2) * 2;
+            let old = unsafe { Self::chunks_layout(self.capacity) };
+            let new = Layout::array::<Chunk<S::Handle>>(capacity).map_err(|_| AllocErr::new(layout))?;
+            let memory_block = unsafe { self.storage.grow(self.chunks, old, new)? };
+            self.chunks = memory_block.handle;
+            self.capacity = capacity;
+        }
+
+        let chunk = Layout::from_size_align(layout.size().max(CHUNK), layout.align().max(mem::align_of::<usize>()))
+            .map_err(|_| AllocErr::new(layout))?;
+        let memory_block = self.storage.allocate(chunk)?;
+
+        unsafe {
+            let chunks = self.storage.get_mut(self.chunks).cast::<Chunk<S::Handle>>();
+            chunks.as_ptr().add(self.len).write((memory_block.handle, chunk));
+        }
+
+        self.len += 1;
+        self.align = chunk.align();
+        self.offset = memory_block.size;
+
+        Ok(())
+    }
+}
+
+impl<S: Storage, const CHUNK: usize> Drop for GrowableBumpStorage<S, CHUNK> {
+    fn drop(&mut self) {
+        unsafe {
+            for i in 0..self.len {
+                let (handle, layout) = self.chunk(i);
+                self.storage.deallocate(handle, layout);
+            }
+
+            self.storage.deallocate(self.chunks, Self::chunks_layout(self.capacity));
+        }
+    }
+}
+
+unsafe impl<S: ResizableStorage, const CHUNK: usize> OffsetHandle for GrowableBumpStorage<S, CHUNK> {
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        GrowableBumpHandle {
+            chunk: handle.chunk,
+            offset: handle.offset.wrapping_add(offset),
+        }
+    }
+}
+
+unsafe impl<S: ResizableStorage + SharedGetMut, const CHUNK: usize> SharedGetMut for GrowableBumpStorage<S, CHUNK> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.offset as *mut u8)
+        }
+
+        let (chunk, _) = self.chunk(handle.chunk);
+        let ptr = self.storage.shared_get_mut(chunk);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.offset))
+    }
+}
+
+impl<S: ResizableStorage + SharedGetMut, const CHUNK: usize> MultiStorage for GrowableBumpStorage<S, CHUNK> {}
+
+unsafe impl<S: ResizableStorage, const CHUNK: usize> Storage for GrowableBumpStorage<S, CHUNK> {
+    type Handle = GrowableBumpHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.offset as *mut u8)
+        }
+
+        let (chunk, _) = self.chunk(handle.chunk);
+        let ptr = self.storage.get(chunk);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.offset))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.offset as *mut u8)
+        }
+
+        let (chunk, _) = self.chunk(handle.chunk);
+        let ptr = self.storage.get_mut(chunk);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.offset))
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+
+        // chunks are only aligned to the largest alignment they were created for
+        // so larger alignments need a new chunk, just like larger sizes
+        if self.align < layout.align() || self.offset < layout.size() {
+            self.add_chunk(layout)?;
+        }
+
+        let start = self.offset;
+        let offset = (start - layout.size()) & !layout.align().wrapping_sub(1);
+        self.offset = offset;
+
+        let size = unsafe { NonZeroUsize::new_unchecked(start.wrapping_sub(offset)) };
+
+        Ok(NonEmptyMemoryBlock {
+            handle: GrowableBumpHandle {
+                chunk: self.len - 1,
+                offset,
+            },
+            size,
+        })
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl<S: ResizableStorage + SharedGetMut, const CHUNK: usize> ResizableStorage for GrowableBumpStorage<S, CHUNK> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old == new {
+            Ok(MemoryBlock {
+                size: old.size(),
+                handle,
+            })
+        } else {
+            crate::defaults::grow(self, handle, old, new)
+        }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old == new {
+            Ok(MemoryBlock {
+                size: old.size(),
+                handle,
+            })
+        } else {
+            crate::defaults::grow_zeroed(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old == new {
+            Ok(MemoryBlock {
+                size: old.size(),
+                handle,
+            })
+        } else {
+            crate::defaults::shrink(self, handle, old, new)
+        }
+    }
+}
+
+#[test]
+fn growable_bump() {
+    let mut storage = GrowableBumpStorage::<_, 64>::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let handles = (0..32_u64)
+        .map(|i| {
+            let memory_block = storage.allocate(Layout::new::<u64>()).unwrap();
+            unsafe { storage.get_mut(memory_block.handle).cast::<u64>().as_ptr().write(i) }
+            memory_block.handle
+        })
+        .collect::<std::vec::Vec<_>>();
+
+    assert_eq!(storage.chunk_count(), 4);
+
+    for (i, &handle) in handles.iter().enumerate() {
+        assert_eq!(unsafe { storage.get(handle).cast::<u64>().as_ptr().read() }, i as u64);
+    }
+
+    storage.allocate(Layout::new::<[u8; 100]>()).unwrap();
+    assert_eq!(storage.chunk_count(), 5);
+
+    crate::storage_conformance!(storage, resizable);
+}
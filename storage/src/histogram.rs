@@ -0,0 +1,240 @@
+use core::{
+    alloc::Layout,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// The number of buckets in a [`HistogramStorage`], bucket `i` holds the requests for
+/// more than `2^(i - 1)` and at most `2^i` bytes
+pub const HISTOGRAM_BUCKETS: usize = usize::BITS as usize;
+
+/// The requests that fell into one bucket of a [`HistogramStorage`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// The number of requests
+    pub count: usize,
+    /// The total number of bytes requested
+    pub bytes: usize,
+}
+
+struct Counters {
+    count: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+/// A storage that buckets allocation requests by their size, rounded up to a power of two
+///
+/// Both allocations and resizes are recorded, a resize counts as a request for its new size.
+/// Deallocations aren't recorded, so the histogram describes the requests that were made,
+/// not the memory that is live.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct HistogramStorage<S> {
+    storage: S,
+    buckets: [Counters; HISTOGRAM_BUCKETS],
+}
+
+impl<S> HistogramStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            buckets: [const {
+                Counters {
+                    count: AtomicUsize::new(0),
+                    bytes: AtomicUsize::new(0),
+                }
+            }; HISTOGRAM_BUCKETS],
+        }
+    }
+
+    /// A copy of every bucket, indexed by the log2 of the bucket's size
+    pub fn snapshot(&self) -> [HistogramBucket; HISTOGRAM_BUCKETS] {
+        let mut snapshot = [HistogramBucket::default(); HISTOGRAM_BUCKETS];
+
+        for (bucket, counters) in snapshot.iter_mut().zip(&self.buckets) {
+            bucket.count = counters.count.load(Ordering::Relaxed);
+            bucket.bytes = counters.bytes.load(Ordering::Relaxed);
+        }
+
+        snapshot
+    }
+
+    /// Empties every bucket
+    pub fn reset(&self) {
+        for counters in &self.buckets {
+            counters.count.store(0, Ordering::Relaxed);
+            counters.bytes.store(0, Ordering::Relaxed);
+        }
+    }
+
+    // zero-sized requests never reach the underlying storage, so they aren't recorded
+    fn record(&self, size: usize) {
+        if size == 0 {
+            return
+        }
+
+        let counters = &self.buckets[size.next_power_of_two().trailing_zeros() as usize];
+        counters.count.fetch_add(1, Ordering::Relaxed);
+        counters.bytes.fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for HistogramStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for HistogramStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for HistogramStorage<S> {}
+
+unsafe impl<S: Storage> Storage for HistogramStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(layout.size());
+        self.storage.allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(layout.size());
+        self.storage.allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for HistogramStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(new.size());
+        self.storage.grow(handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(new.size());
+        self.storage.grow_zeroed(handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(new.size());
+        self.storage.shrink(handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for HistogramStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(layout.size());
+        self.storage.shared_allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(layout.size());
+        self.storage.shared_allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for HistogramStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(new.size());
+        self.storage.shared_grow(handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(new.size());
+        self.storage.shared_grow_zeroed(handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(new.size());
+        self.storage.shared_shrink(handle, old, new)
+    }
+}
+
+#[test]
+fn histogram() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = HistogramStorage::new(system);
+
+    let a = storage.allocate(Layout::new::<[u8; 3]>()).unwrap();
+    let b = storage.allocate(Layout::new::<[u8; 4]>()).unwrap();
+
+    unsafe {
+        let a = storage
+            .grow(a.handle, Layout::new::<[u8; 3]>(), Layout::new::<[u8; 100]>())
+            .unwrap();
+        storage.deallocate(a.handle, Layout::new::<[u8; 100]>());
+        storage.deallocate(b.handle, Layout::new::<[u8; 4]>());
+    }
+
+    let snapshot = storage.snapshot();
+    assert_eq!(snapshot[2], HistogramBucket { count: 2, bytes: 7 });
+    assert_eq!(snapshot[7], HistogramBucket { count: 1, bytes: 100 });
+    assert_eq!(snapshot.iter().map(|bucket| bucket.count).sum::<usize>(), 3);
+
+    storage.reset();
+    assert_eq!(storage.snapshot(), [HistogramBucket::default(); HISTOGRAM_BUCKETS]);
+
+    crate::storage_conformance!(HistogramStorage::new(system), resizable, shared, shared_resizable);
+}
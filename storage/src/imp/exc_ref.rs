@@ -1,6 +1,6 @@
 use crate::{
-    Flush, FromPtr, MultiStorage, OffsetHandle, ResizableStorage, SharedFlush, SharedGetMut, SharedOffsetHandle,
-    SharedResizableStorage, SharedStorage, Storage,
+    AlignGuarantee, Flush, FromPtr, MultiStorage, OffsetHandle, ResizableStorage, SharedFlush, SharedGetMut,
+    SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 use core::{alloc::Layout, ptr::NonNull};
 
@@ -37,6 +37,10 @@ unsafe impl<S: SharedOffsetHandle + ?Sized> SharedOffsetHandle for &mut S {
 }
 
 impl<S: MultiStorage + ?Sized> MultiStorage for &mut S {}
+unsafe impl<S: AlignGuarantee + ?Sized> AlignGuarantee for &mut S {
+    const ALIGN: usize = S::ALIGN;
+}
+
 unsafe impl<S: Storage + ?Sized> Storage for &mut S {
     type Handle = S::Handle;
 
@@ -1,8 +1,8 @@
 use core::{alloc::Layout, ptr::NonNull};
 
 use crate::{
-    Flush, FromPtr, MultiStorage, OffsetHandle, ResizableStorage, SharedFlush, SharedGetMut, SharedOffsetHandle,
-    SharedResizableStorage, SharedStorage, Storage,
+    AlignGuarantee, Flush, FromPtr, MultiStorage, OffsetHandle, ResizableStorage, SharedFlush, SharedGetMut,
+    SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 
 impl<S: SharedFlush + ?Sized> Flush for &S {
@@ -41,6 +41,10 @@ unsafe impl<S: SharedOffsetHandle + ?Sized> SharedOffsetHandle for &S {
 
 impl<S: MultiStorage + SharedStorage + ?Sized> MultiStorage for &S {}
 
+unsafe impl<S: AlignGuarantee + SharedStorage + ?Sized> AlignGuarantee for &S {
+    const ALIGN: usize = S::ALIGN;
+}
+
 unsafe impl<S: SharedStorage + ?Sized> Storage for &S {
     type Handle = S::Handle;
 
@@ -0,0 +1,403 @@
+use core::{alloc::Layout, cell::UnsafeCell, mem, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock,
+    AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage,
+    SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+// cached blocks are linked together through their own memory,
+// they may be less aligned than a node, so nodes are always read and written unaligned
+struct Node<H> {
+    next: Option<H>,
+    layout: NonEmptyLayout,
+}
+
+/// A storage that caches deallocated blocks in a free list that is threaded through the blocks themselves
+///
+/// Unlike [`FreeListStorage`](crate::FreeListStorage) there is no table of cached blocks, so nothing is
+/// allocated up front, and there is no limit on how many blocks are cached. Blocks that are too small
+/// to hold the link in the list are never cached, and go straight back to the underlying storage.
+///
+/// A cached block is only handed out for an allocation with exactly the layout it was deallocated with,
+/// so it is always handed back to the underlying storage with a layout that the storage accepts.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct IntrusiveFreeListStorage<S: Storage> {
+    storage: S,
+    head: UnsafeCell<Option<S::Handle>>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Storage + Send> Send for IntrusiveFreeListStorage<S> where S::Handle: Send {}
+unsafe impl<S: Storage + Sync> Sync for IntrusiveFreeListStorage<S> where S::Handle: Send {}
+
+impl<S: Storage> IntrusiveFreeListStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            head: UnsafeCell::new(None),
+            lock: SpinLock::new(),
+        }
+    }
+
+    const fn can_cache(layout: NonEmptyLayout) -> bool { layout.size() >= mem::size_of::<Node<S::Handle>>() }
+
+    // unlinks the first block in the list starting at `head` that was deallocated with `layout`
+    unsafe fn take(
+        head: &mut Option<S::Handle>,
+        layout: NonEmptyLayout,
+        mut get: impl FnMut(S::Handle) -> NonNull<u8>,
+    ) -> Option<NonEmptyMemoryBlock<S::Handle>> {
+        let mut prev = None::<S::Handle>;
+        let mut current = *head;
+
+        while let Some(handle) = current {
+            let node = get(handle).as_ptr().cast::<Node<S::Handle>>().read_unaligned();
+
+            if node.layout == layout {
+                match prev {
+                    None => *head = node.next,
+                    Some(prev) => {
+                        let prev = get(prev).as_ptr().cast::<Node<S::Handle>>();
+                        let mut prev_node = prev.read_unaligned();
+                        prev_node.next = node.next;
+                        prev.write_unaligned(prev_node);
+                    }
+                }
+
+                return Some(NonEmptyMemoryBlock {
+                    handle,
+                    size: NonZeroUsize::new_unchecked(layout.size()),
+                })
+            }
+
+            prev = current;
+            current = node.next;
+        }
+
+        None
+    }
+
+    const unsafe fn push(ptr: NonNull<u8>, head: &mut Option<S::Handle>, handle: S::Handle, layout: NonEmptyLayout) {
+        ptr.as_ptr().cast::<Node<S::Handle>>().write_unaligned(Node {
+            next: head.replace(handle),
+            layout,
+        });
+    }
+
+    /// Hands every cached block back to the underlying storage
+    pub fn free_all(&mut self) {
+        let mut head = self.head.get_mut().take();
+
+        while let Some(handle) = head {
+            unsafe {
+                let node = self
+                    .storage
+                    .get_mut(handle)
+                    .as_ptr()
+                    .cast::<Node<S::Handle>>()
+                    .read_unaligned();
+                head = node.next;
+                self.storage.deallocate_nonempty(handle, node.layout);
+            }
+        }
+    }
+}
+
+impl<S: SharedStorage> IntrusiveFreeListStorage<S> {
+    fn shared_free_all(&self) {
+        let mut head = {
+            let _guard = self.lock.lock();
+            unsafe { (*self.head.get()).take() }
+        };
+
+        while let Some(handle) = head {
+            unsafe {
+                let node = self
+                    .storage
+                    .shared_get_mut(handle)
+                    .as_ptr()
+                    .cast::<Node<S::Handle>>()
+                    .read_unaligned();
+                head = node.next;
+                self.storage.shared_deallocate_nonempty(handle, node.layout);
+            }
+        }
+    }
+
+    fn shared_take(&self, layout: NonEmptyLayout) -> Option<NonEmptyMemoryBlock<S::Handle>> {
+        let _guard = self.lock.lock();
+        unsafe {
+            Self::take(&mut *self.head.get(), layout, |handle| {
+                self.storage.shared_get_mut(handle)
+            })
+        }
+    }
+}
+
+impl<S: Storage> Drop for IntrusiveFreeListStorage<S> {
+    fn drop(&mut self) { self.free_all() }
+}
+
+impl<S: Storage + Flush> Flush for IntrusiveFreeListStorage<S> {
+    fn try_flush(&mut self) -> bool {
+        self.free_all();
+        self.storage.try_flush()
+    }
+
+    fn flush(&mut self) {
+        self.free_all();
+        self.storage.flush();
+    }
+}
+
+impl<S: SharedStorage + SharedFlush> SharedFlush for IntrusiveFreeListStorage<S> {
+    fn try_shared_flush(&self) -> bool {
+        self.shared_free_all();
+        self.storage.try_shared_flush()
+    }
+
+    fn shared_flush(&self) {
+        self.shared_free_all();
+        self.storage.shared_flush();
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for IntrusiveFreeListStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for IntrusiveFreeListStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for IntrusiveFreeListStorage<S> {}
+
+unsafe impl<S: Storage> Storage for IntrusiveFreeListStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let storage = &mut self.storage;
+        let cached = unsafe {
+            Self::take(self.head.get_mut(), layout, |handle| {
+                storage.get_mut(handle)
+            })
+        };
+
+        if let Some(memory_block) = cached {
+            return Ok(memory_block)
+        }
+
+        self.storage.allocate_nonempty(layout)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        if Self::can_cache(layout) {
+            let ptr = self.storage.get_mut(handle);
+            Self::push(ptr, self.head.get_mut(), handle, layout);
+        } else {
+            self.storage.deallocate_nonempty(handle, layout);
+        }
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let storage = &mut self.storage;
+        let cached = unsafe {
+            Self::take(self.head.get_mut(), layout, |handle| {
+                storage.get_mut(handle)
+            })
+        };
+
+        if let Some(memory_block) = cached {
+            unsafe {
+                let ptr = self.storage.get_mut(memory_block.handle);
+                ptr.as_ptr().write_bytes(0, memory_block.size.get());
+            }
+            return Ok(memory_block)
+        }
+
+        self.storage.allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for IntrusiveFreeListStorage<S> {
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shrink(handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for IntrusiveFreeListStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.shared_take(layout) {
+            return Ok(memory_block)
+        }
+
+        self.storage.shared_allocate_nonempty(layout)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        if Self::can_cache(layout) {
+            let ptr = self.storage.shared_get_mut(handle);
+            let _guard = self.lock.lock();
+            Self::push(ptr, &mut *self.head.get(), handle, layout);
+        } else {
+            self.storage.shared_deallocate_nonempty(handle, layout);
+        }
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if let Some(memory_block) = self.shared_take(layout) {
+            unsafe {
+                let ptr = self.storage.shared_get_mut(memory_block.handle);
+                ptr.as_ptr().write_bytes(0, memory_block.size.get());
+            }
+            return Ok(memory_block)
+        }
+
+        self.storage.shared_allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for IntrusiveFreeListStorage<S> {
+    #[inline]
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_shrink(handle, old, new)
+    }
+}
+
+#[test]
+fn intrusive_freelist() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = IntrusiveFreeListStorage::new(&mock);
+
+    let layout = Layout::new::<[usize; 8]>();
+    // too small to hold the link in the list
+    let small = Layout::new::<u8>();
+
+    let blocks = [(); 4].map(|()| storage.allocate(layout).unwrap());
+    let tiny = storage.allocate(small).unwrap();
+
+    unsafe {
+        for memory_block in &blocks {
+            storage.deallocate(memory_block.handle, layout);
+        }
+        storage.deallocate(tiny.handle, small);
+    }
+
+    // every block is reused, most recently deallocated first
+    for memory_block in blocks.iter().rev() {
+        let reused = storage.allocate(layout).unwrap();
+        assert_eq!(unsafe { storage.get(reused.handle) }, unsafe {
+            storage.get(memory_block.handle)
+        });
+    }
+
+    unsafe {
+        for memory_block in &blocks {
+            storage.deallocate(memory_block.handle, layout);
+        }
+    }
+
+    drop(storage);
+    assert_eq!(mock.live_allocations(), 0);
+
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    crate::storage_conformance!(
+        IntrusiveFreeListStorage::new(crate::ValidatingStorage::new(system)),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
+
+#[test]
+fn intrusive_freelist_exact_layout() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = IntrusiveFreeListStorage::new(crate::ValidatingStorage::new(system));
+
+    let large = Layout::from_size_align(64, 16).unwrap();
+    let a = storage.allocate(large).unwrap();
+    unsafe { storage.deallocate(a.handle, large) }
+
+    unsafe {
+        // a smaller or less aligned block would be handed back to the underlying storage with the wrong layout
+        let medium = Layout::from_size_align(48, 8).unwrap();
+        let b = storage.allocate(medium).unwrap();
+        assert_ne!(storage.get(b.handle), storage.get(a.handle));
+        storage.deallocate(b.handle, medium);
+
+        let c = storage.allocate(large).unwrap();
+        assert_eq!(storage.get(c.handle), storage.get(a.handle));
+        storage.deallocate(c.handle, large);
+    }
+}
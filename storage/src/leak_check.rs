@@ -0,0 +1,34 @@
+use crate::mock::TrackingStorage;
+
+/// A storage adapter that keeps track of all live allocations, and panics
+/// if any of them were not deallocated when it is dropped
+///
+/// The check on drop needs `std`, to skip it while the thread is unwinding,
+/// without it leaks are only reported by [`report`](TrackingStorage::report) and [`check`](TrackingStorage::check).
+pub type LeakCheck<S> = TrackingStorage<S, true>;
+
+impl<S> LeakCheck<S> {
+    #[inline]
+    pub const fn new(storage: S) -> Self { Self::with_tracker(storage) }
+}
+
+#[test]
+#[should_panic = "leaked 1 allocations"]
+fn leak_check() {
+    let storage = LeakCheck::new(crate::AllocatorStorage::new(std::alloc::System));
+    let a = crate::boxed::Box::new_in(0_u32, &storage);
+    let b = crate::boxed::Box::new_in([0_u64; 4], &storage);
+    drop(a);
+    core::mem::forget(b);
+    assert!(!storage.check());
+    storage.report();
+}
+
+#[test]
+#[should_panic = "unwinding"]
+fn leak_check_unwinding() {
+    let storage = LeakCheck::new(crate::AllocatorStorage::new(std::alloc::System));
+    core::mem::forget(crate::boxed::Box::new_in(0_u32, &storage));
+    // dropping the storage while unwinding doesn't panic again, which would abort
+    panic!("unwinding");
+}
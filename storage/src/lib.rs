@@ -1,5 +1,15 @@
 #![no_std]
-#![feature(core_intrinsics, ptr_metadata, unsize, layout_for_ptr, alloc_layout_extra)]
+#![feature(
+    core_intrinsics,
+    ptr_metadata,
+    unsize,
+    layout_for_ptr,
+    alloc_layout_extra,
+    allocator_api,
+    unboxed_closures,
+    fn_traits,
+    tuple_trait
+)]
 #![deny(clippy::pedantic, clippy::perf)]
 #![warn(clippy::nursery)]
 #![allow(
@@ -14,29 +24,90 @@
 #[allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
 pub mod macros;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "alloc"))]
+extern crate alloc;
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
 mod core_traits;
 
 mod backoff;
+mod spin_lock;
+mod clone_in;
 mod non_empty_layout;
 
 mod affix;
+mod align_up;
+mod allocator;
+#[cfg(feature = "allocator-api2")]
+mod api2;
+mod bitmap;
 mod bump;
+mod bump_up;
+mod canary;
 mod counting_bump;
 mod counting_flush;
+mod deferred;
+#[cfg(feature = "defmt")]
+mod defmt_log;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod file_map;
 mod flush_barrier;
+mod frame;
+mod generational;
 mod global;
+mod global_alloc;
 mod global_as_ptr;
+mod growable_bump;
+mod histogram;
 mod imp;
+mod intrusive_freelist;
+#[cfg(any(test, feature = "alloc"))]
+mod leak_check;
+#[cfg(all(feature = "libc", unix))]
+mod libc_malloc;
+mod linker_region;
+#[cfg(any(test, feature = "alloc"))]
+mod mock;
 mod no_op;
 mod null;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod os;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod os_vm;
+mod over_aligned_bump;
 mod pad;
+#[cfg(any(test, feature = "std"))]
+mod periodic_flush;
 mod picker;
+mod poison;
+mod quarantine;
+mod quota;
+mod record;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod reserve_commit;
+mod ring;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod shm;
 mod single;
 mod single_ref;
+mod size_class;
+mod slab;
+mod small;
+#[cfg(any(test, feature = "std"))]
+mod thread_cache;
+#[cfg(any(test, feature = "std"))]
+mod thread_local_global;
+mod tlsf;
+#[cfg(feature = "tracing")]
+mod traced;
+#[cfg(any(test, feature = "alloc"))]
+mod validating;
+#[cfg(target_arch = "wasm32")]
+mod wasm_memory;
+mod watermark;
 mod zero_sized;
+mod zeroize;
 
 mod freelist;
 
@@ -45,34 +116,110 @@ pub mod defaults;
 mod alloc_error_handler;
 
 pub mod boxed;
+pub mod collections;
+pub mod drop_arena;
+pub mod pool;
 pub mod rc;
+pub mod string;
+pub mod testing;
 pub mod vec;
 
 mod scope_guard;
+#[cfg(any(test, feature = "alloc"))]
+mod tracker;
 
 pub use core_traits::{
-    FromPtr, Handle, MultiStorage, PointerHandle, ResizableStorage, SharedGetMut, SharedResizableStorage,
-    SharedStorage, Storage,
+    AlignGuarantee, FromPtr, Handle, MultiStorage, PointerHandle, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
 };
 
+pub use clone_in::{CloneIn, TryCloneIn};
+
 pub use alloc_error_handler::{handle_alloc_error, set_alloc_error_handler};
 
 pub use affix::{
-    AffixHandle, AffixStorage, ConstLayoutProvider, OffsetHandle, SharedOffsetHandle, TypedLayoutProvider,
+    AffixDrop, AffixHandle, AffixInit, AffixStorage, ConstLayoutProvider, NoHooks, OffsetHandle, SharedOffsetHandle,
+    TypedLayoutProvider,
 };
-pub use bump::{BumpHandle, BumpStorage};
+pub use align_up::AlignUp;
+pub use allocator::AllocatorStorage;
+#[cfg(feature = "allocator-api2")]
+pub use api2::{AllocatorApi2Storage, Api2Compat, StorageAllocator};
+#[cfg(feature = "alloc")]
+pub use allocator::AllocGlobalStorage;
+#[cfg(feature = "std")]
+pub use allocator::SystemStorage;
+pub use bitmap::{BitmapHandle, BitmapStorage};
+pub use bump::{BumpHandle, BumpScope, BumpStorage, Marker};
+pub use bump_up::{BumpUpHandle, BumpUpStorage};
+pub use canary::CanaryStorage;
 pub use counting_bump::CountingBumpStorage;
-pub use counting_flush::CountingFlushStorage;
+pub use counting_flush::{ByteCountingFlushStorage, CountingFlushStorage};
+pub use deferred::DeferredFreeStorage;
+#[cfg(feature = "defmt")]
+pub use defmt_log::DefmtStorage;
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use file_map::{FileMapHandle, FileMapStorage};
 pub use flush_barrier::FlushBarrier;
-pub use freelist::{Flush, FreeListStorage, SharedFlush};
-pub use global::{set_global_storage, set_global_storage_with, Global, GlobalStorage};
+pub use frame::{FrameHandle, FrameStorage};
+pub use freelist::{BestFit, ExactFit, FirstFit, FitPolicy, Flush, FreeListStorage, SharedFlush};
+pub use generational::{GenerationalHandle, GenerationalStorage};
+pub use global::{set_global_storage, set_global_storage_lazy, set_global_storage_with, Global, GlobalStorage};
+pub use global_alloc::StorageGlobalAlloc;
 pub use global_as_ptr::GlobalAsPtrStorage;
+pub use growable_bump::{GrowableBumpHandle, GrowableBumpStorage};
+pub use histogram::{HistogramBucket, HistogramStorage, HISTOGRAM_BUCKETS};
+pub use intrusive_freelist::IntrusiveFreeListStorage;
+#[cfg(any(test, feature = "alloc"))]
+pub use leak_check::LeakCheck;
+#[cfg(all(feature = "libc", unix))]
+pub use libc_malloc::LibcStorage;
+pub use linker_region::LinkerRegionStorage;
+#[cfg(any(test, feature = "alloc"))]
+pub use mock::{Event, MockStorage};
 pub use no_op::NoOpStorage;
 pub use null::NullStorage;
-pub use picker::{AndC, Choose, MaxAlign, MaxSize, MinAlign, MinSize, NotC, OrC, Picker};
-pub use single::{OffsetSingleStackStorage, SingleStackStorage};
-pub use single_ref::{OffsetSingleRefStorage, SingleRefStorage};
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use os_vm::OsVmStorage;
+pub use over_aligned_bump::{OverAlignedBumpHandle, OverAlignedBumpStorage};
+pub use pad::{CacheLinePad, Pad, PowerOfTwoPad};
+#[cfg(any(test, feature = "std"))]
+pub use periodic_flush::PeriodicFlushStorage;
+pub use picker::{
+    AlignEq, AndC, Choose, DynChoose, EitherHandle, FnChoose, MaxAlign, MaxSize, MinAlign, MinSize, NotC, OrC, Picker,
+    PickerChain, SizeEq, SizeInRange, TaggedHandle, TaggedPicker,
+};
+pub use poison::PoisonStorage;
+pub use quarantine::QuarantineStorage;
+pub use quota::QuotaStorage;
+pub use record::{Record, RecordOp, RecordingStorage, ReplayStorage};
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use reserve_commit::ReserveCommitStorage;
+pub use ring::{RingHandle, RingStorage};
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use shm::{ShmHandle, ShmStorage};
+pub use single::{
+    Aligned, AlignedSingleStackStorage, Alignment, OffsetSingleHandle, OffsetSingleStackStorage, SingleStackStorage,
+    ValidAlignment,
+};
+pub use single_ref::{MultiRefStorage, OffsetSingleRefStorage, SingleRefStorage};
+pub use size_class::SizeClassStorage;
+pub use slab::{SlabHandle, SlabStorage};
+pub use small::{InlineBytes, SmallStorage, SpillHandle, SpillStorage};
+#[cfg(any(test, feature = "std"))]
+pub use thread_cache::ThreadCachedStorage;
+#[cfg(any(test, feature = "std"))]
+pub use thread_local_global::{set_thread_storage, ThreadLocalGlobal, ThreadStorageImp};
+pub use tlsf::{TlsfHandle, TlsfStorage};
+#[cfg(feature = "tracing")]
+pub use traced::TracedStorage;
+#[cfg(any(test, feature = "alloc"))]
+pub use validating::ValidatingStorage;
+#[cfg(target_arch = "wasm32")]
+pub use wasm_memory::WasmMemoryStorage;
+pub use watermark::WatermarkStorage;
 pub use zero_sized::ZeroSizedStorage;
+pub use zeroize::ZeroizeStorage;
 
 use core::{alloc::Layout, num::NonZeroUsize, ptr::NonNull};
 pub use non_empty_layout::NonEmptyLayout;
@@ -166,10 +313,12 @@ fn test() {
     rc::Rc::new_in(0xdead_beef_usize, alloc.clone());
 
     let x = BumpStorage::<_, 4096>::new(Zst, 0);
-    assert_eq!(x.remaining_space(), (1 << 24));
+    assert_eq!(x.remaining(), (1 << 24));
     x.shared_allocate(Layout::new::<[usize; 32]>()).unwrap();
-    assert_eq!(x.remaining_space(), (1 << 24) - 8 * 32);
-    assert_eq!(core::mem::size_of_val(&x), 8);
+    assert_eq!(x.used(), 8 * 32);
+    assert_eq!(x.remaining(), (1 << 24) - 8 * 32);
+    // the offset, and the size of the region so it can be handed back
+    assert_eq!(core::mem::size_of_val(&x), 16);
 }
 
 #[test]
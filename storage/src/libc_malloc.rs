@@ -0,0 +1,249 @@
+use core::{
+    alloc::Layout,
+    mem,
+    num::NonZeroUsize,
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+// the alignment that `malloc` guarantees, the same as the one `std` assumes
+const MIN_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+/// A storage backed by libc's `malloc`, `realloc` and `free`, with `posix_memalign`
+/// for layouts that are aligned more than `malloc` guarantees
+#[derive(Default, Debug, Clone, Copy)]
+#[must_use = "storages don't do anything unless they are used"]
+pub struct LibcStorage;
+
+impl LibcStorage {
+    #[inline]
+    pub const fn new() -> Self { Self }
+
+    // `malloc` only guarantees alignments up to the allocation's size
+    const fn is_malloc_aligned(layout: Layout) -> bool {
+        layout.align() <= MIN_ALIGN && layout.align() <= layout.size()
+    }
+
+    unsafe fn alloc(layout: Layout) -> *mut u8 {
+        if Self::is_malloc_aligned(layout) {
+            return libc::malloc(layout.size()).cast()
+        }
+
+        let mut ptr = ptr::null_mut();
+        // `posix_memalign` requires the alignment to be at least the size of a pointer
+        let align = layout.align().max(mem::size_of::<usize>());
+
+        if libc::posix_memalign(ptr::addr_of_mut!(ptr), align, layout.size()) == 0 {
+            ptr.cast()
+        } else {
+            ptr::null_mut()
+        }
+    }
+
+    fn memory_block(ptr: *mut u8, layout: Layout) -> Result<NonEmptyMemoryBlock<NonNull<u8>>, AllocErr> {
+        let handle = NonNull::new(ptr).ok_or_else(|| AllocErr::new(layout))?;
+
+        Ok(NonEmptyMemoryBlock {
+            handle,
+            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+        })
+    }
+
+    unsafe fn realloc(handle: NonNull<u8>, old: Layout, new: Layout) -> Option<MemoryBlock<NonNull<u8>>> {
+        // `realloc` only keeps the alignment that `malloc` guarantees
+        if old.size() == 0 || old.align() != new.align() || !Self::is_malloc_aligned(new) {
+            return None
+        }
+
+        let handle = NonNull::new(libc::realloc(handle.as_ptr().cast(), new.size()).cast())?;
+
+        Some(MemoryBlock {
+            handle,
+            size: new.size(),
+        })
+    }
+}
+
+unsafe impl FromPtr for LibcStorage {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl OffsetHandle for LibcStorage {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for LibcStorage {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedGetMut for LibcStorage {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl MultiStorage for LibcStorage {}
+
+unsafe impl Storage for LibcStorage {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.shared_deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl ResizableStorage for LibcStorage {
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_shrink(handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for LibcStorage {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+        Self::memory_block(unsafe { Self::alloc(layout) }, layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, _: NonEmptyLayout) {
+        libc::free(handle.as_ptr().cast());
+    }
+
+    #[inline]
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+
+        if Self::is_malloc_aligned(layout) {
+            return Self::memory_block(unsafe { libc::calloc(layout.size(), 1).cast() }, layout)
+        }
+
+        let memory_block = Self::memory_block(unsafe { Self::alloc(layout) }, layout)?;
+        unsafe { memory_block.handle.as_ptr().write_bytes(0, layout.size()) }
+        Ok(memory_block)
+    }
+}
+
+unsafe impl SharedResizableStorage for LibcStorage {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::realloc(handle, old, new).map_or_else(|| crate::defaults::grow(self, handle, old, new), Ok)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let Some(memory_block) = Self::realloc(handle, old, new) else {
+            return crate::defaults::grow_zeroed(self, handle, old, new)
+        };
+
+        let ptr = memory_block.handle.as_ptr();
+        ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if new.size() == 0 {
+            self.shared_deallocate(handle, old);
+
+            return Ok(MemoryBlock {
+                handle: Handle::dangling(new.align()),
+                size: 0,
+            })
+        }
+
+        Self::realloc(handle, old, new).map_or_else(|| crate::defaults::shrink(self, handle, old, new), Ok)
+    }
+}
+
+#[test]
+fn libc_malloc() {
+    let mut storage = LibcStorage::new();
+
+    let layout = Layout::from_size_align(64, 256).unwrap();
+    let memory_block = storage.allocate_zeroed(layout).unwrap();
+    assert_eq!(memory_block.handle.as_ptr() as usize % 256, 0);
+
+    unsafe {
+        let big = Layout::from_size_align(1024, 256).unwrap();
+        let memory_block = storage.grow_zeroed(memory_block.handle, layout, big).unwrap();
+        assert_eq!(memory_block.handle.as_ptr() as usize % 256, 0);
+
+        let ptr = memory_block.handle.as_ptr();
+        assert!((0..memory_block.size).all(|i| *ptr.add(i) == 0));
+        storage.deallocate(memory_block.handle, big);
+    }
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
@@ -0,0 +1,163 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    AllocErr, FromPtr, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock, SharedGetMut, SharedStorage, Storage,
+};
+
+/// A storage that hands out a region of memory reserved by the linker, all in one allocation
+///
+/// This is meant to sit underneath another storage, like [`BumpStorage`](crate::BumpStorage)
+/// or [`FreeListStorage`](crate::FreeListStorage), see [`linker_region`](crate::linker_region)
+/// to declare one from a pair of linker symbols.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct LinkerRegionStorage {
+    start: NonNull<u8>,
+    len: usize,
+    allocated: AtomicBool,
+}
+
+unsafe impl Send for LinkerRegionStorage {}
+unsafe impl Sync for LinkerRegionStorage {}
+
+impl LinkerRegionStorage {
+    /// # Safety
+    ///
+    /// `start..end` must be valid for reads and writes for the rest of the program,
+    /// and nothing else may access it, including other `LinkerRegionStorage`s
+    ///
+    /// # Panics
+    ///
+    /// if `start` is null or `end` is before `start`
+    #[allow(clippy::cast_sign_loss)]
+    pub unsafe fn new(start: *mut u8, end: *mut u8) -> Self {
+        let start = NonNull::new(start).expect("the start of a linker region can't be null");
+        let len = end.offset_from(start.as_ptr());
+        assert!(len >= 0, "the end of a linker region can't be before its start");
+
+        Self {
+            start,
+            len: len as usize,
+            allocated: AtomicBool::new(false),
+        }
+    }
+
+    pub const fn len(&self) -> usize { self.len }
+
+    pub const fn is_empty(&self) -> bool { self.len == 0 }
+
+    fn fits(&self, layout: Layout) -> bool {
+        self.len >= layout.size() && self.start.as_ptr().align_offset(layout.align()) == 0
+    }
+
+    fn aquire(&self) -> bool {
+        self.allocated
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+unsafe impl FromPtr for LinkerRegionStorage {
+    unsafe fn from_ptr(&self, _: NonNull<u8>, _: Layout) -> Self::Handle {}
+}
+
+unsafe impl SharedGetMut for LinkerRegionStorage {
+    unsafe fn shared_get_mut(&self, (): Self::Handle) -> NonNull<u8> { self.start }
+}
+
+unsafe impl Storage for LinkerRegionStorage {
+    type Handle = ();
+
+    #[inline]
+    unsafe fn get(&self, (): Self::Handle) -> NonNull<u8> { self.start }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, (): Self::Handle) -> NonNull<u8> { self.start }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !*self.allocated.get_mut() && self.fits(layout.into()) {
+            *self.allocated.get_mut() = true;
+            Ok(NonEmptyMemoryBlock {
+                size: unsafe { NonZeroUsize::new_unchecked(self.len) },
+                handle: (),
+            })
+        } else {
+            Err(AllocErr::new(layout.into()))
+        }
+    }
+
+    #[inline]
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if !*self.allocated.get_mut() && self.fits(layout) {
+            *self.allocated.get_mut() |= layout.size() != 0;
+            Ok(MemoryBlock {
+                size: self.len,
+                handle: (),
+            })
+        } else {
+            Err(AllocErr::new(layout))
+        }
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, (): Self::Handle, _: NonEmptyLayout) { *self.allocated.get_mut() = false; }
+
+    #[inline]
+    unsafe fn deallocate(&mut self, (): Self::Handle, layout: Layout) {
+        *self.allocated.get_mut() &= layout.size() == 0;
+    }
+}
+
+unsafe impl SharedStorage for LinkerRegionStorage {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if self.fits(layout.into()) && self.aquire() {
+            Ok(NonEmptyMemoryBlock {
+                size: unsafe { NonZeroUsize::new_unchecked(self.len) },
+                handle: (),
+            })
+        } else {
+            Err(AllocErr::new(layout.into()))
+        }
+    }
+
+    #[inline]
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if self.fits(layout) && (layout.size() == 0 || self.aquire()) {
+            Ok(MemoryBlock {
+                size: self.len,
+                handle: (),
+            })
+        } else {
+            Err(AllocErr::new(layout))
+        }
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, (): Self::Handle, _: NonEmptyLayout) {
+        self.allocated.store(false, Ordering::Release);
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate(&self, (): Self::Handle, layout: Layout) {
+        self.allocated.fetch_and(layout.size() == 0, Ordering::Release);
+    }
+}
+
+#[test]
+fn linker_region() {
+    static mut REGION: [u64; 32] = [0; 32];
+
+    let start = core::ptr::addr_of_mut!(REGION).cast::<u8>();
+    let storage = unsafe { LinkerRegionStorage::new(start, start.add(256)) };
+    assert_eq!(storage.len(), 256);
+
+    let mut storage = crate::BumpStorage::<_, 8>::new(storage, 256);
+    let handle = storage.allocate(Layout::new::<u64>()).unwrap().handle;
+    assert_eq!(unsafe { storage.get(handle) }.as_ptr(), start.wrapping_add(248));
+}
@@ -1,6 +1,11 @@
 mod zst_static_with;
 
+mod conformance;
+mod format_in;
+mod global_alloc;
 mod install_global;
+mod linker_region;
+mod vec_in;
 mod zst_static;
 
 pub use core;
@@ -0,0 +1,8 @@
+#[macro_export]
+macro_rules! storage_conformance {
+    ($storage:expr $(, $check:ident)* $(,)?) => {{
+        let mut storage = $storage;
+        $crate::testing::storage(&mut storage);
+        $($crate::testing::$check(&mut storage);)*
+    }};
+}
@@ -0,0 +1,17 @@
+/// Creates a [`String`](crate::string::String) in the given storage, like `format!`
+///
+/// ```
+/// let system = storage::AllocatorStorage::new(std::alloc::System);
+/// let string = storage::format_in!(&system, "{}-{}", 1, 2);
+/// assert_eq!(string, "1-2");
+/// ```
+///
+/// # Panics
+///
+/// if the string can't be allocated, or if a formatting trait implementation returns an error
+#[macro_export]
+macro_rules! format_in {
+    ($storage:expr, $($arg:tt)*) => {
+        $crate::string::String::format_in($storage, $crate::macros::core::format_args!($($arg)*))
+    };
+}
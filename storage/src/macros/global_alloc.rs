@@ -0,0 +1,8 @@
+#[macro_export]
+macro_rules! storage_global_allocator {
+    ($(#[$meta:meta])* $v:vis static $name:ident: $type:ty = $value:expr $(;)?) => {
+        $(#[$meta])*
+        #[global_allocator]
+        $v static $name: $crate::StorageGlobalAlloc<$type> = $crate::StorageGlobalAlloc::new($value);
+    };
+}
@@ -0,0 +1,36 @@
+/// Declares a [`LinkerRegionStorage`](crate::LinkerRegionStorage) over the memory
+/// between two linker symbols
+///
+/// ```ignore
+/// let region = storage::linker_region!(__heap_start, __heap_end).unwrap();
+/// let len = region.len();
+/// let bump = storage::BumpStorage::<_, 8>::new(region, len);
+/// ```
+///
+/// This evaluates to `None` if it was evaluated before, so each invocation
+/// can only produce one storage, but it's still up to you to make sure the
+/// symbols are correct, and that nothing else uses the region
+#[macro_export]
+macro_rules! linker_region {
+    ($start:ident, $end:ident $(,)?) => {{
+        extern "C" {
+            static mut $start: u8;
+            static mut $end: u8;
+        }
+
+        static TAKEN: $crate::macros::core::sync::atomic::AtomicBool =
+            $crate::macros::core::sync::atomic::AtomicBool::new(false);
+
+        if TAKEN.swap(true, $crate::macros::core::sync::atomic::Ordering::AcqRel) {
+            $crate::macros::core::option::Option::None
+        } else {
+            #[allow(unused_unsafe)]
+            $crate::macros::core::option::Option::Some(unsafe {
+                $crate::LinkerRegionStorage::new(
+                    $crate::macros::core::ptr::addr_of_mut!($start),
+                    $crate::macros::core::ptr::addr_of_mut!($end),
+                )
+            })
+        }
+    }};
+}
@@ -0,0 +1,39 @@
+/// Creates a [`Vec`](crate::vec::Vec) in the given storage, like `vec![]`
+///
+/// ```
+/// let system = storage::AllocatorStorage::new(std::alloc::System);
+///
+/// let bytes = storage::vec_in![0_u8; 1024; &system];
+/// assert_eq!(bytes.len(), 1024);
+///
+/// let vec = storage::vec_in![1, 2, 3; &system];
+/// assert_eq!(*vec, [1, 2, 3]);
+/// ```
+///
+/// The storage only needs to be resizable for the [`boxed_slice_in`] macro
+#[macro_export]
+macro_rules! vec_in {
+    (; $storage:expr) => {
+        $crate::vec::Vec::new_in($storage)
+    };
+    ($elem:expr; $len:expr; $storage:expr) => {
+        $crate::vec::Vec::from_elem_in($elem, $len, $storage)
+    };
+    ($($value:expr),+ $(,)?; $storage:expr) => {
+        $crate::vec::Vec::from_array_in([$($value),+], $storage)
+    };
+}
+
+/// Creates a boxed slice in the given storage, with the same syntax as [`vec_in`]
+///
+/// ```
+/// let system = storage::AllocatorStorage::new(std::alloc::System);
+/// let boxed = storage::boxed_slice_in![1, 2, 3; &system];
+/// assert_eq!(*boxed, [1, 2, 3]);
+/// ```
+#[macro_export]
+macro_rules! boxed_slice_in {
+    ($($tt:tt)*) => {
+        $crate::vec::Vec::into_boxed_slice($crate::vec_in![$($tt)*])
+    };
+}
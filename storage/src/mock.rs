@@ -0,0 +1,367 @@
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::{Ref, RefCell},
+    ptr::NonNull,
+};
+
+use crate::{
+    tracker::{Allocation, Tracker},
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle, ResizableStorage,
+    SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Allocate(Layout),
+    Deallocate(Layout),
+    Grow { old: Layout, new: Layout },
+    Shrink { old: Layout, new: Layout },
+}
+
+/// A storage that records every call made to it, and checks that
+///
+/// * only live allocations are deallocated or resized
+/// * allocations are deallocated or resized with a layout that fits the one they were allocated with
+pub type MockStorage<S> = TrackingStorage<S, false>;
+
+/// The storage behind [`MockStorage`] and [`LeakCheck`](crate::LeakCheck), with `LEAK_CHECK`
+/// it doesn't record any events, and checks for leaks when it's dropped
+#[must_use = "storages don't do anything unless they are used"]
+pub struct TrackingStorage<S, const LEAK_CHECK: bool> {
+    pub storage: S,
+    tracker: Tracker,
+    events: RefCell<Vec<Event>>,
+}
+
+impl<S> MockStorage<S> {
+    #[inline]
+    pub const fn new(storage: S) -> Self { Self::with_tracker(storage) }
+}
+
+impl<S, const LEAK_CHECK: bool> TrackingStorage<S, LEAK_CHECK> {
+    #[inline]
+    pub(crate) const fn with_tracker(storage: S) -> Self {
+        Self {
+            storage,
+            tracker: Tracker::new(),
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn events(&self) -> Ref<'_, [Event]> { Ref::map(self.events.borrow(), Vec::as_slice) }
+
+    pub fn clear_events(&mut self) { self.events.get_mut().clear() }
+
+    /// # Panics
+    ///
+    /// if the events recorded since the last call to `assert_events` or `clear_events` are not `expected`
+    pub fn assert_events(&mut self, expected: &[Event]) {
+        assert_eq!(*self.events.get_mut(), expected);
+        self.clear_events();
+    }
+
+    pub fn live_allocations(&self) -> usize { self.tracker.live().len() }
+
+    /// Returns true if every allocation has been deallocated
+    pub fn check(&self) -> bool { self.tracker.live().is_empty() }
+
+    /// # Panics
+    ///
+    /// if there are any live allocations
+    pub fn report(&self) {
+        let live = self.tracker.live();
+        assert!(live.is_empty(), "leaked {} allocations: {:#?}", live.len(), &*live);
+    }
+
+    fn record(&self, event: Event) {
+        if !LEAK_CHECK {
+            self.events.borrow_mut().push(event);
+        }
+    }
+}
+
+impl<S, const LEAK_CHECK: bool> Drop for TrackingStorage<S, LEAK_CHECK> {
+    fn drop(&mut self) {
+        // panicking while the thread is already unwinding would abort, and without `std`
+        // there's no way to tell if it is, so leaks are only reported by `report` there
+        #[cfg(any(test, feature = "std"))]
+        if LEAK_CHECK && !std::thread::panicking() {
+            self.report();
+        }
+    }
+}
+
+impl<S: Storage, const LEAK_CHECK: bool> TrackingStorage<S, LEAK_CHECK> {
+    fn track(&self, handle: S::Handle, layout: Layout, size: usize) {
+        self.tracker.track(unsafe { self.storage.get(handle) }, layout, size);
+    }
+
+    unsafe fn untrack(&self, handle: S::Handle, layout: Layout) -> Option<Allocation> {
+        self.tracker.untrack(self.storage.get(handle), layout)
+    }
+
+    fn resized(
+        &self,
+        old: Option<Allocation>,
+        new: Layout,
+        result: Result<MemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+        if let Ok(memory_block) = &result {
+            self.track(memory_block.handle, new, memory_block.size);
+        } else if let Some(old) = old {
+            self.tracker.retrack(old);
+        }
+
+        result
+    }
+}
+
+unsafe impl<S: FromPtr, const LEAK_CHECK: bool> FromPtr for TrackingStorage<S, LEAK_CHECK> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: OffsetHandle, const LEAK_CHECK: bool> OffsetHandle for TrackingStorage<S, LEAK_CHECK> {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        self.storage.offset(handle, offset)
+    }
+}
+
+unsafe impl<S: SharedOffsetHandle, const LEAK_CHECK: bool> SharedOffsetHandle for TrackingStorage<S, LEAK_CHECK> {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        self.storage.shared_offset(handle, offset)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const LEAK_CHECK: bool> SharedGetMut for TrackingStorage<S, LEAK_CHECK> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage, const LEAK_CHECK: bool> MultiStorage for TrackingStorage<S, LEAK_CHECK> {}
+
+unsafe impl<S: Storage, const LEAK_CHECK: bool> Storage for TrackingStorage<S, LEAK_CHECK> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout.into()));
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        self.track(memory_block.handle, layout.into(), memory_block.size.get());
+        Ok(memory_block)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.record(Event::Deallocate(layout.into()));
+        self.untrack(handle, layout.into());
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout));
+        let memory_block = self.storage.allocate(layout)?;
+        self.track(memory_block.handle, layout, memory_block.size);
+        Ok(memory_block)
+    }
+
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        self.record(Event::Deallocate(layout));
+        self.untrack(handle, layout);
+        self.storage.deallocate(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout.into()));
+        let memory_block = self.storage.allocate_nonempty_zeroed(layout)?;
+        self.track(memory_block.handle, layout.into(), memory_block.size.get());
+        Ok(memory_block)
+    }
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout));
+        let memory_block = self.storage.allocate_zeroed(layout)?;
+        self.track(memory_block.handle, layout, memory_block.size);
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: ResizableStorage, const LEAK_CHECK: bool> ResizableStorage for TrackingStorage<S, LEAK_CHECK> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Grow { old, new });
+        let allocation = self.untrack(handle, old);
+        let result = self.storage.grow(handle, old, new);
+        self.resized(allocation, new, result)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Grow { old, new });
+        let allocation = self.untrack(handle, old);
+        let result = self.storage.grow_zeroed(handle, old, new);
+        self.resized(allocation, new, result)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Shrink { old, new });
+        let allocation = self.untrack(handle, old);
+        let result = self.storage.shrink(handle, old, new);
+        self.resized(allocation, new, result)
+    }
+}
+
+unsafe impl<S: SharedStorage, const LEAK_CHECK: bool> SharedStorage for TrackingStorage<S, LEAK_CHECK> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout.into()));
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        self.track(memory_block.handle, layout.into(), memory_block.size.get());
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.record(Event::Deallocate(layout.into()));
+        self.untrack(handle, layout.into());
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout));
+        let memory_block = self.storage.shared_allocate(layout)?;
+        self.track(memory_block.handle, layout, memory_block.size);
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.record(Event::Deallocate(layout));
+        self.untrack(handle, layout);
+        self.storage.shared_deallocate(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout.into()));
+        let memory_block = self.storage.shared_allocate_nonempty_zeroed(layout)?;
+        self.track(memory_block.handle, layout.into(), memory_block.size.get());
+        Ok(memory_block)
+    }
+
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Allocate(layout));
+        let memory_block = self.storage.shared_allocate_zeroed(layout)?;
+        self.track(memory_block.handle, layout, memory_block.size);
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage, const LEAK_CHECK: bool> SharedResizableStorage
+    for TrackingStorage<S, LEAK_CHECK>
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Grow { old, new });
+        let allocation = self.untrack(handle, old);
+        let result = self.storage.shared_grow(handle, old, new);
+        self.resized(allocation, new, result)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Grow { old, new });
+        let allocation = self.untrack(handle, old);
+        let result = self.storage.shared_grow_zeroed(handle, old, new);
+        self.resized(allocation, new, result)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.record(Event::Shrink { old, new });
+        let allocation = self.untrack(handle, old);
+        let result = self.storage.shared_shrink(handle, old, new);
+        self.resized(allocation, new, result)
+    }
+}
+
+#[test]
+fn mock() {
+    let mut storage = MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = crate::vec::Vec::<u32, _>::new_in(&storage);
+    vec.reserve(4);
+    vec.reserve(16);
+    drop(vec);
+
+    let mut boxed = crate::boxed::Box::new_in(0_u64, &mut storage);
+    *boxed += 1;
+    drop(boxed);
+
+    assert_eq!(storage.live_allocations(), 0);
+    storage.assert_events(&[
+        Event::Allocate(Layout::new::<[u32; 0]>()),
+        Event::Grow {
+            old: Layout::new::<[u32; 0]>(),
+            new: Layout::new::<[u32; 4]>(),
+        },
+        Event::Grow {
+            old: Layout::new::<[u32; 4]>(),
+            new: Layout::new::<[u32; 16]>(),
+        },
+        Event::Deallocate(Layout::new::<[u32; 16]>()),
+        Event::Allocate(Layout::new::<u64>()),
+        Event::Deallocate(Layout::new::<u64>()),
+    ]);
+}
+
+#[test]
+#[should_panic = "double free"]
+fn mock_double_free() {
+    let mut storage = MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let layout = Layout::new::<u64>();
+    let memory_block = storage.allocate(layout).unwrap();
+    unsafe {
+        storage.deallocate(memory_block.handle, layout);
+        storage.deallocate(memory_block.handle, layout);
+    }
+}
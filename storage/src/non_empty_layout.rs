@@ -33,10 +33,37 @@ impl NonEmptyLayout {
         }
     }
 
+    pub const fn of<T>() -> Option<Self> { Self::new(Layout::new::<T>()) }
+
+    /// # Errors
+    ///
+    /// On arithmetic overflow, returns `LayoutError`.
+    pub fn array<T>(n: usize) -> Result<Option<Self>, LayoutError> { Layout::array::<T>(n).map(Self::new) }
+
+    /// # Errors
+    ///
+    /// If `align` is not a power of two, or `size` overflows
+    /// `isize::MAX` when rounded up to `align`, returns `LayoutError`.
+    pub fn from_size_align(size: NonZeroUsize, align: usize) -> Result<Self, LayoutError> {
+        Layout::from_size_align(size.get(), align).map(|layout| unsafe { Self::new_unchecked(layout) })
+    }
+
     pub const fn size(self) -> usize { self.size.get() }
 
     pub const fn align(self) -> usize { self.align.get() }
 
+    #[must_use = "calling `pad_to_align` without using result"]
+    pub fn pad_to_align(self) -> Self { unsafe { Self::new_unchecked(Layout::from(self).pad_to_align()) } }
+
+    /// # Errors
+    ///
+    /// On arithmetic overflow, returns `LayoutError`.
+    pub fn repeat(self, n: NonZeroUsize) -> Result<(Self, usize), LayoutError> {
+        Layout::from(self)
+            .repeat(n.get())
+            .map(|(layout, offset)| (unsafe { Self::new_unchecked(layout) }, offset))
+    }
+
     /// # Errors
     ///
     /// On arithmetic overflow, returns `LayoutError`.
@@ -61,3 +88,31 @@ impl From<NonEmptyLayout> for Layout {
         unsafe { Self::from_size_align_unchecked(layout.size(), layout.align()) }
     }
 }
+
+#[test]
+fn non_empty_layout() {
+    assert_eq!(NonEmptyLayout::new(Layout::new::<()>()), None);
+    assert_eq!(NonEmptyLayout::of::<()>(), None);
+    assert_eq!(NonEmptyLayout::of::<[u64; 0]>(), None);
+    assert_eq!(NonEmptyLayout::array::<u32>(0), Ok(None));
+    assert!(NonEmptyLayout::array::<u32>(usize::MAX).is_err());
+
+    let layout = NonEmptyLayout::of::<u64>().unwrap();
+    assert_eq!(Layout::from(layout), Layout::new::<u64>());
+    assert_eq!(NonEmptyLayout::new(Layout::new::<u64>()), Some(layout));
+
+    let array = NonEmptyLayout::array::<u32>(3).unwrap().unwrap();
+    assert_eq!(Layout::from(array), Layout::new::<[u32; 3]>());
+
+    let one = NonZeroUsize::new(1).unwrap();
+    assert!(NonEmptyLayout::from_size_align(one, 3).is_err());
+    let byte = NonEmptyLayout::from_size_align(one, 4).unwrap();
+    assert_eq!((byte.size(), byte.align()), (1, 4));
+    assert_eq!(
+        Layout::from(byte.pad_to_align()),
+        Layout::from_size_align(4, 4).unwrap()
+    );
+
+    let (repeated, stride) = byte.pad_to_align().repeat(NonZeroUsize::new(3).unwrap()).unwrap();
+    assert_eq!((repeated.size(), repeated.align(), stride), (12, 4, 4));
+}
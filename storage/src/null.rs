@@ -182,3 +182,17 @@ unsafe impl<H: Handle> SharedResizableStorage for NullStorage<H> {
         core::hint::unreachable_unchecked()
     }
 }
+
+#[test]
+fn null() {
+    let mut storage = NullStorage::<crate::BumpHandle>::with_handle();
+    assert!(storage.allocate(Layout::new::<u8>()).is_err());
+    assert!(storage.shared_allocate(Layout::new::<()>()).is_err());
+
+    crate::storage_conformance!(
+        NullStorage::<crate::BumpHandle>::with_handle(),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -0,0 +1,259 @@
+//! thin wrappers around the operating system's virtual memory api
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub use imp::*;
+
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+pub fn page_size() -> usize {
+    let page_size = PAGE_SIZE.load(Ordering::Relaxed);
+
+    if page_size != 0 {
+        return page_size
+    }
+
+    let page_size = unsafe { imp::page_size() };
+    PAGE_SIZE.store(page_size, Ordering::Relaxed);
+    page_size
+}
+
+/// rounds `size` up to a whole number of pages
+pub fn pages(size: usize) -> Option<usize> {
+    let page_size = page_size();
+    Some(size.checked_add(page_size - 1)? & !(page_size - 1))
+}
+
+#[cfg(unix)]
+mod imp {
+    use core::ptr::{self, NonNull};
+    use std::{
+        ffi::CString,
+        fs::File,
+        os::unix::io::{AsRawFd, FromRawFd},
+    };
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    pub(super) unsafe fn page_size() -> usize { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+
+    unsafe fn mmap(size: usize, prot: libc::c_int, flags: libc::c_int, fd: libc::c_int) -> Option<NonNull<u8>> {
+        let ptr = libc::mmap(ptr::null_mut(), size, prot, flags, fd, 0);
+
+        if ptr == libc::MAP_FAILED {
+            None
+        } else {
+            NonNull::new(ptr.cast())
+        }
+    }
+
+    pub unsafe fn map(size: usize) -> Option<NonNull<u8>> {
+        mmap(
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+        )
+    }
+
+    pub unsafe fn unmap(ptr: NonNull<u8>, size: usize) { libc::munmap(ptr.as_ptr().cast(), size); }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub unsafe fn remap(ptr: NonNull<u8>, old: usize, new: usize) -> Option<NonNull<u8>> {
+        let ptr = libc::mremap(ptr.as_ptr().cast(), old, new, libc::MREMAP_MAYMOVE);
+
+        if ptr == libc::MAP_FAILED {
+            None
+        } else {
+            NonNull::new(ptr.cast())
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub unsafe fn remap(_: NonNull<u8>, _: usize, _: usize) -> Option<NonNull<u8>> { None }
+
+    // unmapping the tail of a mapping is always allowed
+    pub unsafe fn shrink(ptr: NonNull<u8>, old: usize, new: usize) -> usize {
+        unmap(NonNull::new_unchecked(ptr.as_ptr().add(new)), old - new);
+        new
+    }
+
+    /// reserves address space without making it accessible, it must be released with `unmap`
+    pub unsafe fn reserve(size: usize) -> Option<NonNull<u8>> {
+        mmap(
+            size,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_NORESERVE,
+            -1,
+        )
+    }
+
+    /// makes part of a reservation accessible
+    pub unsafe fn commit(ptr: NonNull<u8>, size: usize) -> bool {
+        libc::mprotect(ptr.as_ptr().cast(), size, libc::PROT_READ | libc::PROT_WRITE) == 0
+    }
+
+    /// maps the first `size` bytes of `file`, writes are shared with the file, it must be released with `unmap_file`
+    pub unsafe fn map_file(file: &File, size: usize) -> Option<NonNull<u8>> {
+        mmap(
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+        )
+    }
+
+    pub unsafe fn unmap_file(ptr: NonNull<u8>, size: usize) { unmap(ptr, size) }
+
+    /// writes changes to a file mapping back to the file
+    pub unsafe fn sync(ptr: NonNull<u8>, size: usize) -> bool {
+        libc::msync(ptr.as_ptr().cast(), size, libc::MS_SYNC) == 0
+    }
+
+    fn shm_name(name: &str) -> Option<CString> { CString::new(std::format!("/{name}")).ok() }
+
+    /// maps the first `size` bytes of the named shared memory object, creating it if it doesn't exist,
+    /// it must be released with `unmap_file`
+    pub unsafe fn map_shared(name: &str, size: usize) -> Option<NonNull<u8>> {
+        let name = shm_name(name)?;
+        let fd = libc::shm_open(name.as_ptr(), libc::O_RDWR | libc::O_CREAT, 0o600);
+
+        if fd < 0 {
+            return None
+        }
+
+        // the mapping keeps the object alive after the file is closed
+        let file = File::from_raw_fd(fd);
+
+        if file.metadata().ok()?.len() < size as u64 {
+            file.set_len(size as u64).ok()?;
+        }
+
+        map_file(&file, size)
+    }
+
+    /// removes the name of a shared memory object, existing mappings stay valid
+    pub fn unlink_shared(name: &str) -> bool {
+        shm_name(name).is_some_and(|name| unsafe { libc::shm_unlink(name.as_ptr()) == 0 })
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use core::{
+        ffi::c_void,
+        mem::MaybeUninit,
+        ptr::{self, NonNull},
+    };
+    use std::{fs::File, os::windows::io::AsRawHandle, vec::Vec};
+
+    const MEM_COMMIT: u32 = 0x1000;
+    const MEM_RESERVE: u32 = 0x2000;
+    const MEM_RELEASE: u32 = 0x8000;
+    const PAGE_NOACCESS: u32 = 0x01;
+    const PAGE_READWRITE: u32 = 0x04;
+    const FILE_MAP_WRITE: u32 = 0x02;
+
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct SYSTEM_INFO {
+        wProcessorArchitecture: u16,
+        wReserved: u16,
+        dwPageSize: u32,
+        lpMinimumApplicationAddress: *mut c_void,
+        lpMaximumApplicationAddress: *mut c_void,
+        dwActiveProcessorMask: usize,
+        dwNumberOfProcessors: u32,
+        dwProcessorType: u32,
+        dwAllocationGranularity: u32,
+        wProcessorLevel: u16,
+        wProcessorRevision: u16,
+    }
+
+    extern "system" {
+        fn VirtualAlloc(address: *mut c_void, size: usize, allocation_type: u32, protect: u32) -> *mut c_void;
+        fn VirtualFree(address: *mut c_void, size: usize, free_type: u32) -> i32;
+        fn GetSystemInfo(info: *mut SYSTEM_INFO);
+        fn CreateFileMappingW(
+            file: *mut c_void,
+            attributes: *mut c_void,
+            protect: u32,
+            size_high: u32,
+            size_low: u32,
+            name: *const u16,
+        ) -> *mut c_void;
+        fn MapViewOfFile(
+            mapping: *mut c_void,
+            access: u32,
+            offset_high: u32,
+            offset_low: u32,
+            size: usize,
+        ) -> *mut c_void;
+        fn UnmapViewOfFile(address: *const c_void) -> i32;
+        fn FlushViewOfFile(address: *const c_void, size: usize) -> i32;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    pub(super) unsafe fn page_size() -> usize {
+        let mut info = MaybeUninit::<SYSTEM_INFO>::uninit();
+        GetSystemInfo(info.as_mut_ptr());
+        info.assume_init().dwPageSize as usize
+    }
+
+    pub unsafe fn map(size: usize) -> Option<NonNull<u8>> {
+        NonNull::new(VirtualAlloc(ptr::null_mut(), size, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE).cast())
+    }
+
+    pub unsafe fn unmap(ptr: NonNull<u8>, _: usize) { VirtualFree(ptr.as_ptr().cast(), 0, MEM_RELEASE); }
+
+    pub unsafe fn remap(_: NonNull<u8>, _: usize, _: usize) -> Option<NonNull<u8>> { None }
+
+    // a reservation can only be released all at once, so the whole mapping is kept
+    pub unsafe fn shrink(_: NonNull<u8>, old: usize, _: usize) -> usize { old }
+
+    /// reserves address space without making it accessible, it must be released with `unmap`
+    pub unsafe fn reserve(size: usize) -> Option<NonNull<u8>> {
+        NonNull::new(VirtualAlloc(ptr::null_mut(), size, MEM_RESERVE, PAGE_NOACCESS).cast())
+    }
+
+    /// makes part of a reservation accessible
+    pub unsafe fn commit(ptr: NonNull<u8>, size: usize) -> bool {
+        !VirtualAlloc(ptr.as_ptr().cast(), size, MEM_COMMIT, PAGE_READWRITE).is_null()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    unsafe fn map_view(handle: *mut c_void, name: *const u16, size: usize) -> Option<NonNull<u8>> {
+        let size_high = ((size as u64) >> 32) as u32;
+        let mapping = CreateFileMappingW(handle, ptr::null_mut(), PAGE_READWRITE, size_high, size as u32, name);
+
+        if mapping.is_null() {
+            return None
+        }
+
+        // the view keeps the mapping alive
+        let ptr = MapViewOfFile(mapping, FILE_MAP_WRITE, 0, 0, size);
+        CloseHandle(mapping);
+        NonNull::new(ptr.cast())
+    }
+
+    /// maps the first `size` bytes of `file`, writes are shared with the file, it must be released with `unmap_file`
+    pub unsafe fn map_file(file: &File, size: usize) -> Option<NonNull<u8>> {
+        map_view(file.as_raw_handle().cast(), ptr::null(), size)
+    }
+
+    pub unsafe fn unmap_file(ptr: NonNull<u8>, _: usize) { UnmapViewOfFile(ptr.as_ptr().cast()); }
+
+    /// writes changes to a file mapping back to the file
+    pub unsafe fn sync(ptr: NonNull<u8>, size: usize) -> bool { FlushViewOfFile(ptr.as_ptr().cast(), size) != 0 }
+
+    /// maps the first `size` bytes of the named shared memory object, creating it if it doesn't exist,
+    /// it must be released with `unmap_file`
+    pub unsafe fn map_shared(name: &str, size: usize) -> Option<NonNull<u8>> {
+        // backed by the page file instead of a real file
+        let invalid_handle_value = usize::MAX as *mut c_void;
+        let name = name.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+        map_view(invalid_handle_value, name.as_ptr(), size)
+    }
+
+    // named mappings are removed once the last view is unmapped
+    pub fn unlink_shared(_: &str) -> bool { true }
+}
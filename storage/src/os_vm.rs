@@ -0,0 +1,251 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    os, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that gets memory directly from the operating system's virtual memory,
+/// using `mmap` on unix and `VirtualAlloc` on windows
+///
+/// Every allocation is rounded up to a whole number of pages, and is page aligned,
+/// so this is meant to sit underneath other storages, not to be used for small allocations
+#[derive(Default, Debug, Clone, Copy)]
+#[must_use = "storages don't do anything unless they are used"]
+pub struct OsVmStorage;
+
+impl OsVmStorage {
+    #[inline]
+    pub const fn new() -> Self { Self }
+
+    pub fn page_size() -> usize { os::page_size() }
+
+    fn pages(size: usize) -> Option<usize> { os::pages(size) }
+
+    fn map(layout: Layout) -> Option<(NonNull<u8>, usize)> {
+        // mappings are only page aligned
+        if layout.align() > Self::page_size() {
+            return None
+        }
+
+        let size = Self::pages(layout.size())?;
+        let ptr = unsafe { os::map(size)? };
+        Some((ptr, size))
+    }
+}
+
+unsafe impl FromPtr for OsVmStorage {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl OffsetHandle for OsVmStorage {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for OsVmStorage {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedGetMut for OsVmStorage {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl MultiStorage for OsVmStorage {}
+
+unsafe impl Storage for OsVmStorage {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.shared_deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl ResizableStorage for OsVmStorage {
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_shrink(handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for OsVmStorage {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        Self::map(layout.into())
+            .map(|(handle, size)| NonEmptyMemoryBlock {
+                handle,
+                size: unsafe { NonZeroUsize::new_unchecked(size) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        // any size between the requested size and the returned size rounds up to the same number of pages
+        let size = Self::pages(layout.size()).unwrap_or_else(|| core::hint::unreachable_unchecked());
+        os::unmap(handle, size);
+    }
+
+    // fresh mappings are always zeroed
+    #[inline]
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty(layout)
+    }
+}
+
+unsafe impl SharedResizableStorage for OsVmStorage {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if old.size() == 0 {
+            return self.shared_allocate(new)
+        }
+
+        let old_size = Self::pages(old.size()).unwrap_or_else(|| core::hint::unreachable_unchecked());
+        let new_size = Self::pages(new.size()).ok_or_else(|| AllocErr::new(new))?;
+
+        if new.align() > Self::page_size() {
+            Err(AllocErr::new(new))
+        } else if old_size == new_size {
+            Ok(MemoryBlock { handle, size: old_size })
+        } else if let Some(handle) = os::remap(handle, old_size, new_size) {
+            Ok(MemoryBlock { handle, size: new_size })
+        } else {
+            crate::defaults::grow(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.shared_grow(handle, old, new)?;
+
+        // any new pages are already zeroed, but the end of the last old page may not be
+        let old_size = Self::pages(old.size()).unwrap_or_else(|| core::hint::unreachable_unchecked());
+        let ptr = memory_block.handle.as_ptr();
+        ptr::write_bytes(ptr.add(old.size()), 0, old_size.min(memory_block.size) - old.size());
+
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if new.size() == 0 {
+            self.shared_deallocate(handle, old);
+
+            return Ok(MemoryBlock {
+                handle: Handle::dangling(new.align()),
+                size: 0,
+            })
+        }
+
+        if new.align() > Self::page_size() {
+            return Err(AllocErr::new(new))
+        }
+
+        let old_size = Self::pages(old.size()).unwrap_or_else(|| core::hint::unreachable_unchecked());
+        let new_size = Self::pages(new.size()).unwrap_or_else(|| core::hint::unreachable_unchecked());
+
+        Ok(MemoryBlock {
+            handle,
+            size: if old_size == new_size {
+                old_size
+            } else {
+                os::shrink(handle, old_size, new_size)
+            },
+        })
+    }
+}
+
+#[test]
+fn os_vm() {
+    let mut storage = OsVmStorage::new();
+    let page_size = OsVmStorage::page_size();
+    assert!(page_size.is_power_of_two());
+
+    let layout = Layout::new::<[u8; 100]>();
+    let memory_block = storage.allocate_zeroed(layout).unwrap();
+    assert_eq!(memory_block.size, page_size);
+    assert_eq!(memory_block.handle.as_ptr() as usize % page_size, 0);
+
+    unsafe {
+        let big = Layout::from_size_align(page_size * 3, 1).unwrap();
+        let memory_block = storage.grow_zeroed(memory_block.handle, layout, big).unwrap();
+        assert_eq!(memory_block.size, page_size * 3);
+
+        let ptr = memory_block.handle.as_ptr();
+        assert!((0..memory_block.size).all(|i| *ptr.add(i) == 0));
+
+        let memory_block = storage.shrink(memory_block.handle, big, layout).unwrap();
+        storage.deallocate(memory_block.handle, layout);
+    }
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
@@ -0,0 +1,307 @@
+use core::{alloc::Layout, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    AllocErr, BumpHandle, BumpStorage, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A [`BumpStorage`] that also accepts allocations that are aligned to more than `MAX_ALIGN`
+///
+/// Over-aligned allocations take up to `align - MAX_ALIGN` extra bytes from the arena, and remember how
+/// far they had to be moved in their handle. So occasional cache-line or page aligned allocations
+/// don't force the whole arena to be aligned to them.
+///
+/// The fix-up is computed from the address of the arena when the allocation is made, so unlike a
+/// [`BumpStorage`] the underlying storage must not move its memory while over-aligned allocations are live.
+/// For example, an inline storage must not be moved.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct OverAlignedBumpStorage<S: Storage, const MAX_ALIGN: usize> {
+    bump: BumpStorage<S, MAX_ALIGN>,
+}
+
+#[derive(Clone, Copy)]
+pub struct OverAlignedBumpHandle {
+    handle: BumpHandle,
+    // the number of bytes between the start of the underlying allocation and `handle`,
+    // or the alignment if the handle is dangling
+    fixup: usize,
+}
+
+unsafe impl Handle for OverAlignedBumpHandle {
+    unsafe fn dangling(align: usize) -> Self {
+        Self {
+            handle: BumpHandle::dangling(align),
+            fixup: align,
+        }
+    }
+}
+
+impl OverAlignedBumpHandle {
+    #[must_use = "`OverAlignedBumpHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.handle.is_dangling() }
+
+    const fn new(handle: BumpHandle) -> Self { Self { handle, fixup: 0 } }
+}
+
+impl<S: Storage, const MAX_ALIGN: usize> OverAlignedBumpStorage<S, MAX_ALIGN> {
+    const MAX_ALIGN_POW2: usize = MAX_ALIGN.next_power_of_two();
+
+    pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
+
+    /// # Panics
+    ///
+    /// if `Layout::from_size_align(space, MAX_ALIGN.next_power_of_two())` returns Err
+    pub fn try_new(storage: S, space: usize) -> Result<Self, AllocErr> {
+        Ok(Self {
+            bump: BumpStorage::try_new(storage, space)?,
+        })
+    }
+
+    pub const fn capacity(&self) -> usize { self.bump.capacity() }
+
+    pub fn used(&self) -> usize { self.bump.used() }
+
+    pub fn remaining(&self) -> usize { self.bump.remaining() }
+
+    /// Returns the underlying bump storage, which can no longer allocate over-aligned memory
+    pub fn into_inner(self) -> BumpStorage<S, MAX_ALIGN> { self.bump }
+
+    // the underlying allocation is always aligned to `MAX_ALIGN_POW2`,
+    // so it needs at most `align - MAX_ALIGN_POW2` bytes to reach `align`
+    fn padded(layout: NonEmptyLayout) -> Result<NonEmptyLayout, AllocErr> {
+        layout
+            .size()
+            .checked_add(layout.align() - Self::MAX_ALIGN_POW2)
+            .and_then(|size| Layout::from_size_align(size, Self::MAX_ALIGN_POW2).ok())
+            .and_then(NonEmptyLayout::new)
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    // only the requested size is handed out, so that the padding can always be recomputed from the layout
+    fn aligned(&self, layout: NonEmptyLayout, handle: BumpHandle) -> NonEmptyMemoryBlock<OverAlignedBumpHandle> {
+        let BumpHandle(offset) = handle;
+        let fixup = unsafe { self.bump.get(handle) }.as_ptr().align_offset(layout.align());
+
+        NonEmptyMemoryBlock {
+            handle: OverAlignedBumpHandle {
+                handle: BumpHandle(offset + fixup),
+                fixup,
+            },
+            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+        }
+    }
+
+    // zero-sized allocations use this storage's dangling handles, so only
+    // resizes between non-empty and suitably aligned allocations can be passed through
+    fn is_plain(old: Layout, new: Layout) -> bool {
+        old.size() != 0 && new.size() != 0 && old.align().max(new.align()) <= Self::MAX_ALIGN_POW2
+    }
+
+    // the handle and layout of the underlying allocation
+    unsafe fn underlying(handle: OverAlignedBumpHandle, layout: NonEmptyLayout) -> (BumpHandle, NonEmptyLayout) {
+        if layout.align() <= Self::MAX_ALIGN_POW2 {
+            return (handle.handle, layout)
+        }
+
+        let BumpHandle(offset) = handle.handle;
+        // this succeeded when the allocation was made
+        let layout = Self::padded(layout).unwrap_unchecked();
+        (BumpHandle(offset - handle.fixup), layout)
+    }
+}
+
+const fn resized(MemoryBlock { handle, size }: MemoryBlock<BumpHandle>) -> MemoryBlock<OverAlignedBumpHandle> {
+    MemoryBlock {
+        handle: OverAlignedBumpHandle::new(handle),
+        size,
+    }
+}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> FromPtr for OverAlignedBumpStorage<S, MAX_ALIGN> {
+    // the fix-up can't be recovered from the pointer, but the underlying storage never
+    // gives back space that isn't at the top, so forgetting it only leaks the padding
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        OverAlignedBumpHandle::new(self.bump.from_ptr(ptr, layout))
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedGetMut for OverAlignedBumpStorage<S, MAX_ALIGN> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.fixup as *mut u8)
+        }
+
+        self.bump.shared_get_mut(handle.handle)
+    }
+}
+
+impl<S: SharedGetMut, const MAX_ALIGN: usize> MultiStorage for OverAlignedBumpStorage<S, MAX_ALIGN> {}
+
+unsafe impl<S: Storage, const MAX_ALIGN: usize> Storage for OverAlignedBumpStorage<S, MAX_ALIGN> {
+    type Handle = OverAlignedBumpHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.fixup as *mut u8)
+        }
+
+        self.bump.get(handle.handle)
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(handle.fixup as *mut u8)
+        }
+
+        self.bump.get_mut(handle.handle)
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if layout.align() <= Self::MAX_ALIGN_POW2 {
+            let memory_block = self.bump.allocate_nonempty(layout)?;
+            return Ok(NonEmptyMemoryBlock {
+                handle: OverAlignedBumpHandle::new(memory_block.handle),
+                size: memory_block.size,
+            })
+        }
+
+        let memory_block = self.bump.allocate_nonempty(Self::padded(layout)?)?;
+        Ok(self.aligned(layout, memory_block.handle))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let (handle, layout) = Self::underlying(handle, layout);
+        self.bump.deallocate_nonempty(handle, layout);
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> ResizableStorage for OverAlignedBumpStorage<S, MAX_ALIGN> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.bump.grow(handle.handle, old, new).map(resized)
+        } else {
+            crate::defaults::grow(self, handle, old, new)
+        }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.bump.grow_zeroed(handle.handle, old, new).map(resized)
+        } else {
+            crate::defaults::grow_zeroed(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.bump.shrink(handle.handle, old, new).map(resized)
+        } else {
+            crate::defaults::shrink(self, handle, old, new)
+        }
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedStorage for OverAlignedBumpStorage<S, MAX_ALIGN> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if layout.align() <= Self::MAX_ALIGN_POW2 {
+            let memory_block = self.bump.shared_allocate_nonempty(layout)?;
+            return Ok(NonEmptyMemoryBlock {
+                handle: OverAlignedBumpHandle::new(memory_block.handle),
+                size: memory_block.size,
+            })
+        }
+
+        let memory_block = self.bump.shared_allocate_nonempty(Self::padded(layout)?)?;
+        Ok(self.aligned(layout, memory_block.handle))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let (handle, layout) = Self::underlying(handle, layout);
+        self.bump.shared_deallocate_nonempty(handle, layout);
+    }
+}
+
+unsafe impl<S: SharedGetMut, const MAX_ALIGN: usize> SharedResizableStorage for OverAlignedBumpStorage<S, MAX_ALIGN> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.bump.shared_grow(handle.handle, old, new).map(resized)
+        } else {
+            crate::defaults::grow(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.bump.shared_grow_zeroed(handle.handle, old, new).map(resized)
+        } else {
+            crate::defaults::grow_zeroed(self, handle, old, new)
+        }
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if Self::is_plain(old, new) {
+            self.bump.shared_shrink(handle.handle, old, new).map(resized)
+        } else {
+            crate::defaults::shrink(self, handle, old, new)
+        }
+    }
+}
+
+#[test]
+fn over_aligned_bump() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = OverAlignedBumpStorage::<_, 8>::new(system, 4096);
+    let remaining = storage.remaining();
+
+    let layout = Layout::from_size_align(64, 256).unwrap();
+    let memory_block = storage.allocate(layout).unwrap();
+
+    unsafe {
+        let ptr = storage.get_mut(memory_block.handle);
+        assert_eq!(ptr.as_ptr() as usize % 256, 0);
+        ptr.as_ptr().write_bytes(0xa5, 64);
+
+        // the padding is given back along with the allocation
+        storage.deallocate(memory_block.handle, layout);
+    }
+
+    assert_eq!(storage.remaining(), remaining);
+
+    crate::storage_conformance!(
+        OverAlignedBumpStorage::<_, 8>::new(system, 4096),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
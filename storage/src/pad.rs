@@ -1,36 +1,77 @@
 use crate::{
-    FromPtr, MultiStorage, NonEmptyLayout, OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle,
-    SharedResizableStorage, SharedStorage, Storage,
+    AlignGuarantee, FromPtr, MultiStorage, NonEmptyLayout, OffsetHandle, ResizableStorage, SharedGetMut,
+    SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
 };
 use core::{alloc::Layout, ptr::NonNull};
 
+/// A storage that rounds every layout up to at least `SIZE` bytes and `ALIGN` alignment
+/// before passing it on to the underlying storage
+///
+/// If `POW2` is set, the size is also rounded up to the next power of two, so that blocks
+/// of similar sizes can be reused for each other (for example by a [`FreeListStorage`](crate::FreeListStorage)).
+///
+/// `ALIGN` must be a power of two
 #[repr(transparent)]
-pub struct Pad<S: ?Sized, const SIZE: usize, const ALIGN: usize> {
+#[must_use = "storages don't do anything unless they are used"]
+pub struct Pad<S: ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool = false> {
     pub storage: S,
 }
 
-fn pad<const SIZE: usize, const ALIGN: usize>(layout: Layout) -> Layout {
+/// A [`Pad`] that rounds every size up to a whole cache line, so that no two allocations share one
+pub type CacheLinePad<S> = Pad<S, CACHE_LINE, CACHE_LINE>;
+
+/// A [`Pad`] that rounds every size up to the next power of two
+pub type PowerOfTwoPad<S, const SIZE: usize = 0, const ALIGN: usize = 1> = Pad<S, SIZE, ALIGN, true>;
+
+// the size of the cache lines on the target, or rather the size that avoids false sharing,
+// which is two cache lines on targets that prefetch pairs of cache lines
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "powerpc64"))]
+const CACHE_LINE: usize = 128;
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "powerpc64")))]
+const CACHE_LINE: usize = 64;
+
+impl<S, const SIZE: usize, const ALIGN: usize, const POW2: bool> Pad<S, SIZE, ALIGN, POW2> {
+    #[inline]
+    pub const fn new(storage: S) -> Self { Self { storage } }
+}
+
+fn pad<const SIZE: usize, const ALIGN: usize, const POW2: bool>(layout: Layout) -> Layout {
     assert!(ALIGN.is_power_of_two());
-    Layout::from_size_align(layout.size().max(SIZE), layout.align().max(ALIGN))
+    let layout = Layout::from_size_align(layout.size().max(SIZE), layout.align().max(ALIGN))
         .unwrap()
-        .pad_to_align()
+        .pad_to_align();
+
+    if POW2 {
+        // the alignment is a power of two, so the padded size is still a multiple of it
+        let size = layout.size().checked_next_power_of_two().unwrap();
+        Layout::from_size_align(size, layout.align()).unwrap()
+    } else {
+        layout
+    }
 }
 
-unsafe fn pad_unchecked<const SIZE: usize, const ALIGN: usize>(layout: Layout) -> Layout {
-    Layout::from_size_align_unchecked(layout.size().max(SIZE), layout.align().max(ALIGN)).pad_to_align()
+unsafe fn pad_unchecked<const SIZE: usize, const ALIGN: usize, const POW2: bool>(layout: Layout) -> Layout {
+    let layout = Layout::from_size_align_unchecked(layout.size().max(SIZE), layout.align().max(ALIGN)).pad_to_align();
+
+    if POW2 {
+        Layout::from_size_align_unchecked(layout.size().next_power_of_two(), layout.align())
+    } else {
+        layout
+    }
 }
 
-impl<S: ?Sized, const SIZE: usize, const ALIGN: usize> Pad<S, SIZE, ALIGN> {
+impl<S: ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> Pad<S, SIZE, ALIGN, POW2> {
     fn pad_ne(layout: NonEmptyLayout) -> NonEmptyLayout {
-        unsafe { NonEmptyLayout::new_unchecked(pad::<SIZE, ALIGN>(layout.into())) }
+        // padding never makes the layout smaller, so it stays non-empty
+        unsafe { NonEmptyLayout::new_unchecked(pad::<SIZE, ALIGN, POW2>(layout.into())) }
     }
 
     unsafe fn pad_ne_unchecked(layout: NonEmptyLayout) -> NonEmptyLayout {
-        NonEmptyLayout::new_unchecked(pad_unchecked::<SIZE, ALIGN>(layout.into()))
+        NonEmptyLayout::new_unchecked(pad_unchecked::<SIZE, ALIGN, POW2>(layout.into()))
     }
 
     fn pad(layout: Layout) -> Result<Layout, NonEmptyLayout> {
-        let layout = pad::<SIZE, ALIGN>(layout);
+        let layout = pad::<SIZE, ALIGN, POW2>(layout);
         if SIZE == 0 {
             Ok(layout)
         } else {
@@ -39,7 +80,7 @@ impl<S: ?Sized, const SIZE: usize, const ALIGN: usize> Pad<S, SIZE, ALIGN> {
     }
 
     unsafe fn pad_unchecked(layout: Layout) -> Result<Layout, NonEmptyLayout> {
-        let layout = pad_unchecked::<SIZE, ALIGN>(layout);
+        let layout = pad_unchecked::<SIZE, ALIGN, POW2>(layout);
         if SIZE == 0 {
             Ok(layout)
         } else {
@@ -48,12 +89,25 @@ impl<S: ?Sized, const SIZE: usize, const ALIGN: usize> Pad<S, SIZE, ALIGN> {
     }
 
     // pad_nobranch
-    fn pad_nb(layout: Layout) -> Layout { pad::<SIZE, ALIGN>(layout) }
-
-    unsafe fn pad_nb_unchecked(layout: Layout) -> Layout { pad_unchecked::<SIZE, ALIGN>(layout) }
+    fn pad_nb(layout: Layout) -> Layout { pad::<SIZE, ALIGN, POW2>(layout) }
+
+    unsafe fn pad_nb_unchecked(layout: Layout) -> Layout { pad_unchecked::<SIZE, ALIGN, POW2>(layout) }
+
+    // the underlying storage only zeroes the memory past the padded layout,
+    // so the padding after the old allocation has to be cleared before growing
+    unsafe fn zero_padding(ptr: impl FnOnce() -> NonNull<u8>, old: Layout, padded: Layout) {
+        if old.size() < padded.size() {
+            ptr()
+                .as_ptr()
+                .add(old.size())
+                .write_bytes(0, padded.size() - old.size());
+        }
+    }
 }
 
-unsafe impl<S: FromPtr + ?Sized, const SIZE: usize, const ALIGN: usize> FromPtr for Pad<S, SIZE, ALIGN> {
+unsafe impl<S: FromPtr + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> FromPtr
+    for Pad<S, SIZE, ALIGN, POW2>
+{
     #[inline]
     unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
 
@@ -63,22 +117,37 @@ unsafe impl<S: FromPtr + ?Sized, const SIZE: usize, const ALIGN: usize> FromPtr
     }
 }
 
-unsafe impl<S: OffsetHandle + ?Sized, const SIZE: usize, const ALIGN: usize> OffsetHandle for Pad<S, SIZE, ALIGN> {
+unsafe impl<S: OffsetHandle + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> OffsetHandle
+    for Pad<S, SIZE, ALIGN, POW2>
+{
     unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
         S::offset(&mut self.storage, handle, offset)
     }
 }
 
-unsafe impl<S: SharedOffsetHandle + ?Sized, const SIZE: usize, const ALIGN: usize> SharedOffsetHandle
-    for Pad<S, SIZE, ALIGN>
+unsafe impl<S: SharedOffsetHandle + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> SharedOffsetHandle
+    for Pad<S, SIZE, ALIGN, POW2>
 {
     unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
         S::shared_offset(&self.storage, handle, offset)
     }
 }
 
-impl<S: MultiStorage + ?Sized, const SIZE: usize, const ALIGN: usize> MultiStorage for Pad<S, SIZE, ALIGN> {}
-unsafe impl<S: Storage + ?Sized, const SIZE: usize, const ALIGN: usize> Storage for Pad<S, SIZE, ALIGN> {
+impl<S: MultiStorage + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> MultiStorage
+    for Pad<S, SIZE, ALIGN, POW2>
+{
+}
+
+// every layout is padded to at least `ALIGN` before it reaches the underlying storage
+unsafe impl<S: Storage + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> AlignGuarantee
+    for Pad<S, SIZE, ALIGN, POW2>
+{
+    const ALIGN: usize = ALIGN;
+}
+
+unsafe impl<S: Storage + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> Storage
+    for Pad<S, SIZE, ALIGN, POW2>
+{
     type Handle = S::Handle;
 
     #[inline]
@@ -139,12 +208,14 @@ unsafe impl<S: Storage + ?Sized, const SIZE: usize, const ALIGN: usize> Storage
     }
 }
 
-unsafe impl<S: SharedGetMut + ?Sized, const SIZE: usize, const ALIGN: usize> SharedGetMut for Pad<S, SIZE, ALIGN> {
+unsafe impl<S: SharedGetMut + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> SharedGetMut
+    for Pad<S, SIZE, ALIGN, POW2>
+{
     unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { S::shared_get_mut(&self.storage, handle) }
 }
 
-unsafe impl<S: ResizableStorage + ?Sized, const SIZE: usize, const ALIGN: usize> ResizableStorage
-    for Pad<S, SIZE, ALIGN>
+unsafe impl<S: ResizableStorage + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> ResizableStorage
+    for Pad<S, SIZE, ALIGN, POW2>
 {
     #[inline]
     unsafe fn grow(
@@ -166,8 +237,9 @@ unsafe impl<S: ResizableStorage + ?Sized, const SIZE: usize, const ALIGN: usize>
         new: core::alloc::Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
         let new = Self::pad_nb(new);
-        let old = Self::pad_nb_unchecked(old);
-        S::grow_zeroed(&mut self.storage, handle, old, new)
+        let padded = Self::pad_nb_unchecked(old);
+        Self::zero_padding(|| S::get_mut(&mut self.storage, handle), old, padded);
+        S::grow_zeroed(&mut self.storage, handle, padded, new)
     }
 
     #[inline]
@@ -183,7 +255,9 @@ unsafe impl<S: ResizableStorage + ?Sized, const SIZE: usize, const ALIGN: usize>
     }
 }
 
-unsafe impl<S: SharedStorage + ?Sized, const SIZE: usize, const ALIGN: usize> SharedStorage for Pad<S, SIZE, ALIGN> {
+unsafe impl<S: SharedStorage + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool> SharedStorage
+    for Pad<S, SIZE, ALIGN, POW2>
+{
     #[inline]
     fn shared_allocate_nonempty(
         &self,
@@ -239,8 +313,8 @@ unsafe impl<S: SharedStorage + ?Sized, const SIZE: usize, const ALIGN: usize> Sh
     }
 }
 
-unsafe impl<S: SharedResizableStorage + ?Sized, const SIZE: usize, const ALIGN: usize> SharedResizableStorage
-    for Pad<S, SIZE, ALIGN>
+unsafe impl<S: SharedResizableStorage + ?Sized, const SIZE: usize, const ALIGN: usize, const POW2: bool>
+    SharedResizableStorage for Pad<S, SIZE, ALIGN, POW2>
 {
     #[inline]
     unsafe fn shared_grow(
@@ -262,8 +336,9 @@ unsafe impl<S: SharedResizableStorage + ?Sized, const SIZE: usize, const ALIGN:
         new: core::alloc::Layout,
     ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
         let new = Self::pad_nb(new);
-        let old = Self::pad_nb_unchecked(old);
-        S::shared_grow_zeroed(&self.storage, handle, old, new)
+        let padded = Self::pad_nb_unchecked(old);
+        Self::zero_padding(|| S::shared_get_mut(&self.storage, handle), old, padded);
+        S::shared_grow_zeroed(&self.storage, handle, padded, new)
     }
 
     #[inline]
@@ -278,3 +353,60 @@ unsafe impl<S: SharedResizableStorage + ?Sized, const SIZE: usize, const ALIGN:
         S::shared_shrink(&self.storage, handle, old, new)
     }
 }
+
+#[test]
+fn padded() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = Pad::<_, 16, 16>::new(&mock);
+
+    let memory_block = storage.allocate(Layout::new::<u8>()).unwrap();
+    assert!(memory_block.size >= 16);
+    unsafe { storage.deallocate(memory_block.handle, Layout::new::<u8>()) }
+
+    let padded = Layout::from_size_align(16, 16).unwrap();
+    mock.assert_events(&[crate::Event::Allocate(padded), crate::Event::Deallocate(padded)]);
+    mock.clear_events();
+
+    crate::storage_conformance!(Pad::<_, 16, 16>::new(&mock), resizable, shared, shared_resizable);
+}
+
+#[test]
+fn power_of_two_padded() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = PowerOfTwoPad::<_>::new(&mock);
+
+    let layout = Layout::new::<[u8; 40]>();
+    let memory_block = storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, layout) }
+
+    let padded = Layout::new::<[u8; 64]>();
+    mock.assert_events(&[crate::Event::Allocate(padded), crate::Event::Deallocate(padded)]);
+    mock.clear_events();
+
+    let mut storage = CacheLinePad::new(&mock);
+    let memory_block = storage.allocate(Layout::new::<u8>()).unwrap();
+    assert_eq!(
+        unsafe { storage.get(memory_block.handle) }.as_ptr() as usize % CACHE_LINE,
+        0
+    );
+    unsafe { storage.deallocate(memory_block.handle, Layout::new::<u8>()) }
+    mock.clear_events();
+
+    crate::storage_conformance!(PowerOfTwoPad::<_>::new(&mock), resizable, shared, shared_resizable);
+}
+
+#[test]
+fn pad_align_guarantee() {
+    fn guarantee<S: AlignGuarantee>(_: &S) -> usize { S::ALIGN }
+
+    let storage = crate::AllocatorStorage::new(std::alloc::System);
+    assert_eq!(guarantee(&Pad::<_, 0, 32>::new(&storage)), 32);
+    assert_eq!(
+        guarantee(&crate::AlignUp::<_, 8>::new(CacheLinePad::new(&storage))),
+        CACHE_LINE
+    );
+    assert_eq!(
+        guarantee(&crate::SingleStackStorage::<u64>::new()),
+        core::mem::align_of::<u64>()
+    );
+}
@@ -0,0 +1,337 @@
+use core::{
+    alloc::Layout,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use std::time::{Duration, Instant};
+
+use crate::{
+    AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedFlush, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that flushes the underlying storage once more than `period` has passed since the last flush
+///
+/// The time is only checked when allocating, so an idle storage isn't flushed until it's used again.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct PeriodicFlushStorage<S> {
+    pub storage: S,
+    period: u64,
+    start: Instant,
+    // nanoseconds from `start` to the last flush
+    last_flush: AtomicU64,
+}
+
+impl<S> PeriodicFlushStorage<S> {
+    #[inline]
+    pub fn new(storage: S, period: Duration) -> Self {
+        Self {
+            storage,
+            period: nanos(period),
+            start: Instant::now(),
+            last_flush: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub const fn period(&self) -> Duration { Duration::from_nanos(self.period) }
+
+    #[inline]
+    fn now(&self) -> u64 { nanos(self.start.elapsed()) }
+
+    #[inline]
+    fn reset(&self) { self.last_flush.store(self.now(), Ordering::Relaxed) }
+}
+
+impl<S: Storage + Flush> PeriodicFlushStorage<S> {
+    #[cold]
+    #[inline(never)]
+    fn flush_slow(&mut self, now: u64) {
+        *self.last_flush.get_mut() = now;
+        self.storage.flush();
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn shared_flush_slow(&self, last_flush: u64, now: u64)
+    where
+        S: SharedFlush,
+    {
+        // only one of the threads that notice the period is over flushes
+        if self
+            .last_flush
+            .compare_exchange(last_flush, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.storage.shared_flush();
+        }
+    }
+
+    #[inline]
+    fn tick(&mut self) {
+        let now = self.now();
+        if now.saturating_sub(*self.last_flush.get_mut()) > self.period {
+            self.flush_slow(now);
+        }
+    }
+
+    #[inline]
+    fn shared_tick(&self)
+    where
+        S: SharedFlush,
+    {
+        let now = self.now();
+        let last_flush = self.last_flush.load(Ordering::Relaxed);
+        if now.saturating_sub(last_flush) > self.period {
+            self.shared_flush_slow(last_flush, now);
+        }
+    }
+}
+
+// a `u64` of nanoseconds lasts for centuries
+fn nanos(duration: Duration) -> u64 { core::convert::TryFrom::try_from(duration.as_nanos()).unwrap_or(u64::MAX) }
+
+impl<S: Flush> Flush for PeriodicFlushStorage<S> {
+    #[inline]
+    fn try_flush(&mut self) -> bool {
+        self.reset();
+        self.storage.try_flush()
+    }
+
+    #[inline]
+    fn flush(&mut self) {
+        self.reset();
+        self.storage.flush();
+    }
+}
+
+impl<S: SharedFlush> SharedFlush for PeriodicFlushStorage<S> {
+    #[inline]
+    fn try_shared_flush(&self) -> bool {
+        self.reset();
+        self.storage.try_shared_flush()
+    }
+
+    #[inline]
+    fn shared_flush(&self) {
+        self.reset();
+        self.storage.shared_flush();
+    }
+}
+
+unsafe impl<S: OffsetHandle + Flush> OffsetHandle for PeriodicFlushStorage<S> {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        self.storage.offset(handle, offset)
+    }
+}
+
+unsafe impl<S: SharedOffsetHandle + SharedFlush> SharedOffsetHandle for PeriodicFlushStorage<S> {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        self.storage.shared_offset(handle, offset)
+    }
+}
+
+unsafe impl<S: FromPtr + Flush> FromPtr for PeriodicFlushStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut + Flush> SharedGetMut for PeriodicFlushStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage + Flush> MultiStorage for PeriodicFlushStorage<S> {}
+
+unsafe impl<S: Storage + Flush> Storage for PeriodicFlushStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.tick();
+        self.storage.allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.tick();
+        self.storage.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) { self.storage.deallocate(handle, layout); }
+
+    #[inline]
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.tick();
+        self.storage.allocate_nonempty_zeroed(layout)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.tick();
+        self.storage.allocate_zeroed(layout)
+    }
+}
+
+unsafe impl<S: ResizableStorage + Flush> ResizableStorage for PeriodicFlushStorage<S> {
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.tick();
+        self.storage.grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.tick();
+        self.storage.grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shrink(handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage + SharedFlush> SharedStorage for PeriodicFlushStorage<S> {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_tick();
+        self.storage.shared_allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_tick();
+        self.storage.shared_allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
+        self.storage.shared_deallocate(handle, layout);
+    }
+
+    #[inline]
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_tick();
+        self.storage.shared_allocate_nonempty_zeroed(layout)
+    }
+
+    #[inline]
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_tick();
+        self.storage.shared_allocate_zeroed(layout)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + SharedFlush> SharedResizableStorage for PeriodicFlushStorage<S> {
+    #[inline]
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_tick();
+        self.storage.shared_grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_tick();
+        self.storage.shared_grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_shrink(handle, old, new)
+    }
+}
+
+#[test]
+fn periodic_flush() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = PeriodicFlushStorage::new(
+        crate::DeferredFreeStorage::new(crate::FlushBarrier::new(&mock)),
+        Duration::from_millis(10),
+    );
+
+    let layout = Layout::new::<u64>();
+    let memory_block = storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, layout) }
+    assert_eq!(mock.live_allocations(), 1);
+
+    std::thread::sleep(Duration::from_millis(20));
+
+    // the period is over, so the next allocation flushes the deferred deallocation
+    let memory_block = storage.allocate(layout).unwrap();
+    assert_eq!(mock.live_allocations(), 1);
+    unsafe { storage.deallocate(memory_block.handle, layout) }
+
+    storage.flush();
+    assert_eq!(mock.live_allocations(), 0);
+
+    crate::storage_conformance!(
+        PeriodicFlushStorage::new(
+            crate::DeferredFreeStorage::new(crate::FlushBarrier::new(&mock)),
+            Duration::from_millis(1)
+        ),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
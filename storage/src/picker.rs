@@ -1,12 +1,18 @@
 use core::{alloc::Layout, ptr::NonNull};
 
+mod chain;
 mod choose;
+mod tagged;
 
-pub use choose::{AndC, Choose, MaxAlign, MaxSize, MinAlign, MinSize, NotC, OrC};
+pub use chain::PickerChain;
+pub use choose::{
+    AlignEq, AndC, Choose, DynChoose, FnChoose, MaxAlign, MaxSize, MinAlign, MinSize, NotC, OrC, SizeEq, SizeInRange,
+};
+pub use tagged::{TaggedHandle, TaggedPicker};
 
 use crate::{
-    FromPtr, MultiStorage, PointerHandle, ResizableStorage, SharedGetMut, SharedResizableStorage, SharedStorage,
-    Storage,
+    AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, PointerHandle,
+    ResizableStorage, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
 };
 
 pub struct Picker<F, A, B> {
@@ -15,135 +21,214 @@ pub struct Picker<F, A, B> {
     pub right: B,
 }
 
-unsafe impl<F: Choose, A: Storage, B: Storage<Handle = A::Handle>> SharedGetMut for Picker<F, A, B>
-where
-    A::Handle: PointerHandle,
-{
+/// A handle from a [`Picker`], which may come from either of its storages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EitherHandle<A, B> {
+    Left(A),
+    Right(B),
+}
+
+unsafe impl<A: Handle, B: Handle> Handle for EitherHandle<A, B> {
+    unsafe fn dangling(align: usize) -> Self { Self::Left(A::dangling(align)) }
+}
+
+unsafe impl<A: PointerHandle, B: PointerHandle> PointerHandle for EitherHandle<A, B> {
+    #[inline]
+    unsafe fn get(self) -> NonNull<u8> {
+        match self {
+            Self::Left(handle) => handle.get(),
+            Self::Right(handle) => handle.get(),
+        }
+    }
+
     #[inline]
-    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle.get_mut() }
+    unsafe fn get_mut(self) -> NonNull<u8> {
+        match self {
+            Self::Left(handle) => handle.get_mut(),
+            Self::Right(handle) => handle.get_mut(),
+        }
+    }
 }
 
-unsafe impl<F: Choose, A: FromPtr, B: FromPtr<Handle = A::Handle>> FromPtr for Picker<F, A, B>
-where
-    A::Handle: PointerHandle,
-{
+fn left<A, B>(MemoryBlock { handle, size }: MemoryBlock<A>) -> MemoryBlock<EitherHandle<A, B>> {
+    MemoryBlock {
+        handle: EitherHandle::Left(handle),
+        size,
+    }
+}
+
+fn right<A, B>(MemoryBlock { handle, size }: MemoryBlock<B>) -> MemoryBlock<EitherHandle<A, B>> {
+    MemoryBlock {
+        handle: EitherHandle::Right(handle),
+        size,
+    }
+}
+
+fn left_ne<A, B>(
+    NonEmptyMemoryBlock { handle, size }: NonEmptyMemoryBlock<A>,
+) -> NonEmptyMemoryBlock<EitherHandle<A, B>> {
+    NonEmptyMemoryBlock {
+        handle: EitherHandle::Left(handle),
+        size,
+    }
+}
+
+fn right_ne<A, B>(
+    NonEmptyMemoryBlock { handle, size }: NonEmptyMemoryBlock<B>,
+) -> NonEmptyMemoryBlock<EitherHandle<A, B>> {
+    NonEmptyMemoryBlock {
+        handle: EitherHandle::Right(handle),
+        size,
+    }
+}
+
+impl<F: Choose, A: Storage, B: Storage> Picker<F, A, B> {
+    // moves the contents of `handle` into `memory_block`, which came from the other storage
+    unsafe fn move_to(
+        &mut self,
+        handle: EitherHandle<A::Handle, B::Handle>,
+        old: Layout,
+        new: Layout,
+        memory_block: MemoryBlock<EitherHandle<A::Handle, B::Handle>>,
+    ) -> MemoryBlock<EitherHandle<A::Handle, B::Handle>> {
+        let old_ptr = self.get(handle);
+        let new_ptr = self.get_mut(memory_block.handle);
+        new_ptr
+            .as_ptr()
+            .copy_from_nonoverlapping(old_ptr.as_ptr(), old.size().min(new.size()));
+        self.deallocate(handle, old);
+        memory_block
+    }
+}
+
+impl<F: Choose, A: SharedStorage, B: SharedStorage> Picker<F, A, B> {
+    unsafe fn shared_move_to(
+        &self,
+        handle: EitherHandle<A::Handle, B::Handle>,
+        old: Layout,
+        new: Layout,
+        memory_block: MemoryBlock<EitherHandle<A::Handle, B::Handle>>,
+    ) -> MemoryBlock<EitherHandle<A::Handle, B::Handle>> {
+        let old_ptr = self.get(handle);
+        let new_ptr = self.shared_get_mut(memory_block.handle);
+        new_ptr
+            .as_ptr()
+            .copy_from_nonoverlapping(old_ptr.as_ptr(), old.size().min(new.size()));
+        self.shared_deallocate(handle, old);
+        memory_block
+    }
+}
+
+unsafe impl<F: Choose, A: SharedGetMut, B: SharedGetMut> SharedGetMut for Picker<F, A, B> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            EitherHandle::Left(handle) => self.left.shared_get_mut(handle),
+            EitherHandle::Right(handle) => self.right.shared_get_mut(handle),
+        }
+    }
+}
+
+unsafe impl<F: Choose, A: FromPtr, B: FromPtr> FromPtr for Picker<F, A, B> {
     unsafe fn from_ptr(&self, ptr: core::ptr::NonNull<u8>, layout: Layout) -> Self::Handle {
         if self.choose.choose(layout) {
-            self.left.from_ptr(ptr, layout)
+            EitherHandle::Left(self.left.from_ptr(ptr, layout))
         } else {
-            self.right.from_ptr(ptr, layout)
+            EitherHandle::Right(self.right.from_ptr(ptr, layout))
         }
     }
 
     unsafe fn from_ptr_mut(&mut self, ptr: core::ptr::NonNull<u8>, layout: Layout) -> Self::Handle {
         if self.choose.choose(layout) {
-            self.left.from_ptr_mut(ptr, layout)
+            EitherHandle::Left(self.left.from_ptr_mut(ptr, layout))
         } else {
-            self.right.from_ptr_mut(ptr, layout)
+            EitherHandle::Right(self.right.from_ptr_mut(ptr, layout))
         }
     }
 }
 
-impl<F: Choose, A: MultiStorage, B: MultiStorage<Handle = A::Handle>> MultiStorage for Picker<F, A, B> where
-    A::Handle: PointerHandle
-{
-}
+impl<F: Choose, A: MultiStorage, B: MultiStorage> MultiStorage for Picker<F, A, B> {}
 
-unsafe impl<F: Choose, A: Storage, B: Storage<Handle = A::Handle>> Storage for Picker<F, A, B>
-where
-    A::Handle: PointerHandle,
-{
-    type Handle = A::Handle;
+unsafe impl<F: Choose, A: Storage, B: Storage> Storage for Picker<F, A, B> {
+    type Handle = EitherHandle<A::Handle, B::Handle>;
 
-    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle.get() }
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            EitherHandle::Left(handle) => self.left.get(handle),
+            EitherHandle::Right(handle) => self.right.get(handle),
+        }
+    }
 
-    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle.get_mut() }
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            EitherHandle::Left(handle) => self.left.get_mut(handle),
+            EitherHandle::Right(handle) => self.right.get_mut(handle),
+        }
+    }
 
-    fn allocate_nonempty(
-        &mut self,
-        layout: crate::NonEmptyLayout,
-    ) -> Result<crate::NonEmptyMemoryBlock<Self::Handle>, crate::AllocErr> {
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout.into()) {
-            self.left.allocate_nonempty(layout)
+            self.left.allocate_nonempty(layout).map(left_ne)
         } else {
-            self.right.allocate_nonempty(layout)
+            self.right.allocate_nonempty(layout).map(right_ne)
         }
     }
 
-    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: crate::NonEmptyLayout) {
-        if self.choose.choose(layout.into()) {
-            self.left.deallocate_nonempty(handle, layout)
-        } else {
-            self.right.deallocate_nonempty(handle, layout)
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match handle {
+            EitherHandle::Left(handle) => self.left.deallocate_nonempty(handle, layout),
+            EitherHandle::Right(handle) => self.right.deallocate_nonempty(handle, layout),
         }
     }
 
-    fn allocate(&mut self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout) {
-            self.left.allocate(layout)
+            self.left.allocate(layout).map(left)
         } else {
-            self.right.allocate(layout)
+            self.right.allocate(layout).map(right)
         }
     }
 
     unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
-        if self.choose.choose(layout) {
-            self.left.deallocate(handle, layout)
-        } else {
-            self.right.deallocate(handle, layout)
+        match handle {
+            EitherHandle::Left(handle) => self.left.deallocate(handle, layout),
+            EitherHandle::Right(handle) => self.right.deallocate(handle, layout),
         }
     }
 
     fn allocate_nonempty_zeroed(
         &mut self,
-        layout: crate::NonEmptyLayout,
-    ) -> Result<crate::NonEmptyMemoryBlock<Self::Handle>, crate::AllocErr> {
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout.into()) {
-            self.left.allocate_nonempty_zeroed(layout)
+            self.left.allocate_nonempty_zeroed(layout).map(left_ne)
         } else {
-            self.right.allocate_nonempty_zeroed(layout)
+            self.right.allocate_nonempty_zeroed(layout).map(right_ne)
         }
     }
 
-    fn allocate_zeroed(&mut self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout) {
-            self.left.allocate_zeroed(layout)
+            self.left.allocate_zeroed(layout).map(left)
         } else {
-            self.right.allocate_zeroed(layout)
+            self.right.allocate_zeroed(layout).map(right)
         }
     }
 }
 
-unsafe impl<F: Choose, A: ResizableStorage, B: ResizableStorage<Handle = A::Handle>> ResizableStorage
-    for Picker<F, A, B>
-where
-    A::Handle: PointerHandle,
-{
+unsafe impl<F: Choose, A: ResizableStorage, B: ResizableStorage> ResizableStorage for Picker<F, A, B> {
     unsafe fn grow(
         &mut self,
         handle: Self::Handle,
         old: Layout,
         new: Layout,
-    ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        match (self.choose.choose(old), self.choose.choose(new)) {
-            (true, true) => self.left.grow(handle, old, new),
-            (false, false) => self.right.grow(handle, old, new),
-            (o, _) => {
-                let memory_block = if o {
-                    self.left.allocate(new)
-                } else {
-                    self.right.allocate(new)
-                };
-                let memory_block = memory_block?;
-                let old_ptr = handle.get();
-                let new_ptr = memory_block.handle.get_mut();
-                new_ptr.as_ptr().copy_from_nonoverlapping(old_ptr.as_ptr(), old.size());
-                if o {
-                    self.right.deallocate(handle, old)
-                } else {
-                    self.left.deallocate(handle, old)
-                }
-                Ok(memory_block)
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (handle, self.choose.choose(new)) {
+            (EitherHandle::Left(handle), true) => self.left.grow(handle, old, new).map(left),
+            (EitherHandle::Right(handle), false) => self.right.grow(handle, old, new).map(right),
+            (handle, _) => {
+                let memory_block = self.allocate(new)?;
+                Ok(self.move_to(handle, old, new, memory_block))
             }
         }
     }
@@ -153,26 +238,13 @@ where
         handle: Self::Handle,
         old: Layout,
         new: Layout,
-    ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        match (self.choose.choose(old), self.choose.choose(new)) {
-            (true, true) => self.left.grow(handle, old, new),
-            (false, false) => self.right.grow(handle, old, new),
-            (o, _) => {
-                let memory_block = if o {
-                    self.left.allocate_zeroed(new)
-                } else {
-                    self.right.allocate_zeroed(new)
-                };
-                let memory_block = memory_block?;
-                let old_ptr = handle.get();
-                let new_ptr = memory_block.handle.get_mut();
-                new_ptr.as_ptr().copy_from_nonoverlapping(old_ptr.as_ptr(), old.size());
-                if o {
-                    self.right.deallocate(handle, old)
-                } else {
-                    self.left.deallocate(handle, old)
-                }
-                Ok(memory_block)
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (handle, self.choose.choose(new)) {
+            (EitherHandle::Left(handle), true) => self.left.grow_zeroed(handle, old, new).map(left),
+            (EitherHandle::Right(handle), false) => self.right.grow_zeroed(handle, old, new).map(right),
+            (handle, _) => {
+                let memory_block = self.allocate_zeroed(new)?;
+                Ok(self.move_to(handle, old, new, memory_block))
             }
         }
     }
@@ -182,122 +254,84 @@ where
         handle: Self::Handle,
         old: Layout,
         new: Layout,
-    ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        match (self.choose.choose(old), self.choose.choose(new)) {
-            (true, true) => self.left.grow(handle, old, new),
-            (false, false) => self.right.grow(handle, old, new),
-            (o, _) => {
-                let memory_block = if o {
-                    self.left.allocate(new)
-                } else {
-                    self.right.allocate(new)
-                };
-                let memory_block = memory_block?;
-                let old_ptr = handle.get();
-                let new_ptr = memory_block.handle.get_mut();
-                new_ptr
-                    .as_ptr()
-                    .copy_from_nonoverlapping(old_ptr.as_ptr(), memory_block.size);
-                if o {
-                    self.right.deallocate(handle, old)
-                } else {
-                    self.left.deallocate(handle, old)
-                }
-                Ok(memory_block)
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (handle, self.choose.choose(new)) {
+            (EitherHandle::Left(handle), true) => self.left.shrink(handle, old, new).map(left),
+            (EitherHandle::Right(handle), false) => self.right.shrink(handle, old, new).map(right),
+            (handle, _) => {
+                let memory_block = self.allocate(new)?;
+                Ok(self.move_to(handle, old, new, memory_block))
             }
         }
     }
 }
 
-unsafe impl<F: Choose, A: SharedStorage, B: SharedStorage<Handle = A::Handle>> SharedStorage for Picker<F, A, B>
-where
-    A::Handle: PointerHandle,
-{
-    fn shared_allocate_nonempty(
-        &self,
-        layout: crate::NonEmptyLayout,
-    ) -> Result<crate::NonEmptyMemoryBlock<Self::Handle>, crate::AllocErr> {
+unsafe impl<F: Choose, A: SharedStorage, B: SharedStorage> SharedStorage for Picker<F, A, B> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout.into()) {
-            self.left.shared_allocate_nonempty(layout)
+            self.left.shared_allocate_nonempty(layout).map(left_ne)
         } else {
-            self.right.shared_allocate_nonempty(layout)
+            self.right.shared_allocate_nonempty(layout).map(right_ne)
         }
     }
 
-    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: crate::NonEmptyLayout) {
-        if self.choose.choose(layout.into()) {
-            self.left.shared_deallocate_nonempty(handle, layout)
-        } else {
-            self.right.shared_deallocate_nonempty(handle, layout)
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match handle {
+            EitherHandle::Left(handle) => self.left.shared_deallocate_nonempty(handle, layout),
+            EitherHandle::Right(handle) => self.right.shared_deallocate_nonempty(handle, layout),
         }
     }
 
-    fn shared_allocate(&self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout) {
-            self.left.shared_allocate(layout)
+            self.left.shared_allocate(layout).map(left)
         } else {
-            self.right.shared_allocate(layout)
+            self.right.shared_allocate(layout).map(right)
         }
     }
 
     unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
-        if self.choose.choose(layout) {
-            self.left.shared_deallocate(handle, layout)
-        } else {
-            self.right.shared_deallocate(handle, layout)
+        match handle {
+            EitherHandle::Left(handle) => self.left.shared_deallocate(handle, layout),
+            EitherHandle::Right(handle) => self.right.shared_deallocate(handle, layout),
         }
     }
 
     fn shared_allocate_nonempty_zeroed(
         &self,
-        layout: crate::NonEmptyLayout,
-    ) -> Result<crate::NonEmptyMemoryBlock<Self::Handle>, crate::AllocErr> {
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout.into()) {
-            self.left.shared_allocate_nonempty_zeroed(layout)
+            self.left.shared_allocate_nonempty_zeroed(layout).map(left_ne)
         } else {
-            self.right.shared_allocate_nonempty_zeroed(layout)
+            self.right.shared_allocate_nonempty_zeroed(layout).map(right_ne)
         }
     }
 
-    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
         if self.choose.choose(layout) {
-            self.left.shared_allocate_zeroed(layout)
+            self.left.shared_allocate_zeroed(layout).map(left)
         } else {
-            self.right.shared_allocate_zeroed(layout)
+            self.right.shared_allocate_zeroed(layout).map(right)
         }
     }
 }
 
-unsafe impl<F: Choose, A: SharedResizableStorage, B: SharedResizableStorage<Handle = A::Handle>> SharedResizableStorage
+unsafe impl<F: Choose, A: SharedResizableStorage, B: SharedResizableStorage> SharedResizableStorage
     for Picker<F, A, B>
-where
-    A::Handle: PointerHandle,
 {
     unsafe fn shared_grow(
         &self,
         handle: Self::Handle,
         old: Layout,
         new: Layout,
-    ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        match (self.choose.choose(old), self.choose.choose(new)) {
-            (true, true) => self.left.shared_grow(handle, old, new),
-            (false, false) => self.right.shared_grow(handle, old, new),
-            (o, _) => {
-                let memory_block = if o {
-                    self.left.shared_allocate(new)
-                } else {
-                    self.right.shared_allocate(new)
-                };
-                let memory_block = memory_block?;
-                let old_ptr = handle.get();
-                let new_ptr = memory_block.handle.get_mut();
-                new_ptr.as_ptr().copy_from_nonoverlapping(old_ptr.as_ptr(), old.size());
-                if o {
-                    self.right.shared_deallocate(handle, old)
-                } else {
-                    self.left.shared_deallocate(handle, old)
-                }
-                Ok(memory_block)
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (handle, self.choose.choose(new)) {
+            (EitherHandle::Left(handle), true) => self.left.shared_grow(handle, old, new).map(left),
+            (EitherHandle::Right(handle), false) => self.right.shared_grow(handle, old, new).map(right),
+            (handle, _) => {
+                let memory_block = self.shared_allocate(new)?;
+                Ok(self.shared_move_to(handle, old, new, memory_block))
             }
         }
     }
@@ -307,26 +341,13 @@ where
         handle: Self::Handle,
         old: Layout,
         new: Layout,
-    ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        match (self.choose.choose(old), self.choose.choose(new)) {
-            (true, true) => self.left.shared_grow(handle, old, new),
-            (false, false) => self.right.shared_grow(handle, old, new),
-            (o, _) => {
-                let memory_block = if o {
-                    self.left.shared_allocate_zeroed(new)
-                } else {
-                    self.right.shared_allocate_zeroed(new)
-                };
-                let memory_block = memory_block?;
-                let old_ptr = handle.get();
-                let new_ptr = memory_block.handle.get_mut();
-                new_ptr.as_ptr().copy_from_nonoverlapping(old_ptr.as_ptr(), old.size());
-                if o {
-                    self.right.shared_deallocate(handle, old)
-                } else {
-                    self.left.shared_deallocate(handle, old)
-                }
-                Ok(memory_block)
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (handle, self.choose.choose(new)) {
+            (EitherHandle::Left(handle), true) => self.left.shared_grow_zeroed(handle, old, new).map(left),
+            (EitherHandle::Right(handle), false) => self.right.shared_grow_zeroed(handle, old, new).map(right),
+            (handle, _) => {
+                let memory_block = self.shared_allocate_zeroed(new)?;
+                Ok(self.shared_move_to(handle, old, new, memory_block))
             }
         }
     }
@@ -336,29 +357,99 @@ where
         handle: Self::Handle,
         old: Layout,
         new: Layout,
-    ) -> Result<crate::MemoryBlock<Self::Handle>, crate::AllocErr> {
-        match (self.choose.choose(old), self.choose.choose(new)) {
-            (true, true) => self.left.shared_grow(handle, old, new),
-            (false, false) => self.right.shared_grow(handle, old, new),
-            (o, _) => {
-                let memory_block = if o {
-                    self.left.shared_allocate(new)
-                } else {
-                    self.right.shared_allocate(new)
-                };
-                let memory_block = memory_block?;
-                let old_ptr = handle.get();
-                let new_ptr = memory_block.handle.get_mut();
-                new_ptr
-                    .as_ptr()
-                    .copy_from_nonoverlapping(old_ptr.as_ptr(), memory_block.size);
-                if o {
-                    self.right.shared_deallocate(handle, old)
-                } else {
-                    self.left.shared_deallocate(handle, old)
-                }
-                Ok(memory_block)
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (handle, self.choose.choose(new)) {
+            (EitherHandle::Left(handle), true) => self.left.shared_shrink(handle, old, new).map(left),
+            (EitherHandle::Right(handle), false) => self.right.shared_shrink(handle, old, new).map(right),
+            (handle, _) => {
+                let memory_block = self.shared_allocate(new)?;
+                Ok(self.shared_move_to(handle, old, new, memory_block))
             }
         }
     }
 }
+
+#[test]
+fn picker_either() {
+    let bump = crate::BumpStorage::<_, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 64);
+    let mut storage = Picker {
+        choose: MaxSize::<16>,
+        left: bump,
+        right: crate::AllocatorStorage::new(std::alloc::System),
+    };
+
+    let small = Layout::new::<[u8; 8]>();
+    let large = Layout::new::<[u8; 64]>();
+
+    unsafe {
+        let memory_block = storage.allocate(small).unwrap();
+        assert!(matches!(memory_block.handle, EitherHandle::Left(_)));
+        storage.get_mut(memory_block.handle).as_ptr().write_bytes(0xab, 8);
+
+        // the allocation moves over to the right storage, which has a different handle type
+        let memory_block = storage.grow(memory_block.handle, small, large).unwrap();
+        assert!(matches!(memory_block.handle, EitherHandle::Right(_)));
+        assert_eq!(storage.get(memory_block.handle).cast::<[u8; 8]>().read(), [0xab; 8]);
+        storage.deallocate(memory_block.handle, large);
+    }
+}
+
+#[test]
+fn picker_resize() {
+    let mut small = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut large = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = Picker {
+        choose: MaxSize::<16>,
+        left: &small,
+        right: &large,
+    };
+
+    let a = Layout::new::<[u8; 4]>();
+    let b = Layout::new::<[u8; 8]>();
+    let c = Layout::new::<[u8; 64]>();
+    let d = Layout::new::<[u8; 128]>();
+
+    unsafe {
+        let memory_block = storage.allocate(a).unwrap();
+        storage
+            .get_mut(memory_block.handle)
+            .cast::<[u8; 4]>()
+            .as_ptr()
+            .write([1, 2, 3, 4]);
+
+        // (left, left), (left, right), (right, right), (right, left), (left, left)
+        let memory_block = storage.grow(memory_block.handle, a, b).unwrap();
+        let memory_block = storage.grow(memory_block.handle, b, c).unwrap();
+        let memory_block = storage.grow(memory_block.handle, c, d).unwrap();
+        let memory_block = storage.shrink(memory_block.handle, d, b).unwrap();
+        let memory_block = storage.shrink(memory_block.handle, b, a).unwrap();
+
+        assert_eq!(storage.get(memory_block.handle).cast::<[u8; 4]>().read(), [1, 2, 3, 4]);
+        storage.deallocate(memory_block.handle, a);
+    }
+
+    small.assert_events(&[
+        crate::Event::Allocate(a),
+        crate::Event::Grow { old: a, new: b },
+        crate::Event::Deallocate(b),
+        crate::Event::Allocate(b),
+        crate::Event::Shrink { old: b, new: a },
+        crate::Event::Deallocate(a),
+    ]);
+    large.assert_events(&[
+        crate::Event::Allocate(c),
+        crate::Event::Grow { old: c, new: d },
+        crate::Event::Deallocate(d),
+    ]);
+
+    crate::storage_conformance!(
+        Picker {
+            choose: MaxSize::<16>,
+            left: &small,
+            right: &large,
+        },
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
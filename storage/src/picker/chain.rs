@@ -0,0 +1,346 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+use super::Choose;
+use crate::{
+    AllocErr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, PointerHandle, ResizableStorage,
+    SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that picks which of many storages to allocate from
+///
+/// `choose` is a tuple of choosers, and `storages` is a tuple with one more storage than there
+/// are choosers. A layout goes to the storage of the first chooser that picks it, or the last
+/// storage if none of them do. This is implemented for up to 8 storages.
+///
+/// Like [`Picker`](crate::Picker), the storage is picked again from the layout on every
+/// deallocation, and resizing moves the allocation if the new layout belongs to another storage.
+pub struct PickerChain<C, S> {
+    pub choose: C,
+    pub storages: S,
+}
+
+macro_rules! picker_chain {
+    ($($F:ident $c:tt)* ; $($S:ident $s:tt)* ; $last:tt) => {
+        impl<$($F: Choose,)* $($S,)*> PickerChain<($($F,)*), ($($S,)*)> {
+            #[inline]
+            fn index(&self, layout: Layout) -> usize {
+                $(if self.choose.$c.choose(layout) {
+                    return $c
+                })*
+
+                $last
+            }
+        }
+
+        unsafe impl<H: PointerHandle, $($F: Choose,)* $($S: SharedGetMut<Handle = H>,)*> SharedGetMut
+            for PickerChain<($($F,)*), ($($S,)*)>
+        {
+            #[inline]
+            unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle.get_mut() }
+        }
+
+        impl<H: PointerHandle, $($F: Choose,)* $($S: MultiStorage<Handle = H>,)*> MultiStorage
+            for PickerChain<($($F,)*), ($($S,)*)>
+        {
+        }
+
+        unsafe impl<H: PointerHandle, $($F: Choose,)* $($S: Storage<Handle = H>,)*> Storage
+            for PickerChain<($($F,)*), ($($S,)*)>
+        {
+            type Handle = H;
+
+            #[inline]
+            unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle.get() }
+
+            #[inline]
+            unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle.get_mut() }
+
+            fn allocate_nonempty(
+                &mut self,
+                layout: NonEmptyLayout,
+            ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout.into()) {
+                    $($s => self.storages.$s.allocate_nonempty(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+
+            unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+                match self.index(layout.into()) {
+                    $($s => self.storages.$s.deallocate_nonempty(handle, layout),)*
+                    _ => core::hint::unreachable_unchecked(),
+                }
+            }
+
+            fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout) {
+                    $($s => self.storages.$s.allocate(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+
+            unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+                match self.index(layout) {
+                    $($s => self.storages.$s.deallocate(handle, layout),)*
+                    _ => core::hint::unreachable_unchecked(),
+                }
+            }
+
+            fn allocate_nonempty_zeroed(
+                &mut self,
+                layout: NonEmptyLayout,
+            ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout.into()) {
+                    $($s => self.storages.$s.allocate_nonempty_zeroed(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+
+            fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout) {
+                    $($s => self.storages.$s.allocate_zeroed(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+        }
+
+        unsafe impl<H: PointerHandle, $($F: Choose,)* $($S: ResizableStorage<Handle = H>,)*> ResizableStorage
+            for PickerChain<($($F,)*), ($($S,)*)>
+        {
+            unsafe fn grow(
+                &mut self,
+                handle: Self::Handle,
+                old: Layout,
+                new: Layout,
+            ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                let (from, to) = (self.index(old), self.index(new));
+                if from == to {
+                    return match from {
+                        $($s => self.storages.$s.grow(handle, old, new),)*
+                        _ => core::hint::unreachable_unchecked(),
+                    }
+                }
+
+                let memory_block = self.allocate(new)?;
+                moved(handle, memory_block.handle, old, new);
+                self.deallocate(handle, old);
+                Ok(memory_block)
+            }
+
+            unsafe fn grow_zeroed(
+                &mut self,
+                handle: Self::Handle,
+                old: Layout,
+                new: Layout,
+            ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                let (from, to) = (self.index(old), self.index(new));
+                if from == to {
+                    return match from {
+                        $($s => self.storages.$s.grow_zeroed(handle, old, new),)*
+                        _ => core::hint::unreachable_unchecked(),
+                    }
+                }
+
+                let memory_block = self.allocate_zeroed(new)?;
+                moved(handle, memory_block.handle, old, new);
+                self.deallocate(handle, old);
+                Ok(memory_block)
+            }
+
+            unsafe fn shrink(
+                &mut self,
+                handle: Self::Handle,
+                old: Layout,
+                new: Layout,
+            ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                let (from, to) = (self.index(old), self.index(new));
+                if from == to {
+                    return match from {
+                        $($s => self.storages.$s.shrink(handle, old, new),)*
+                        _ => core::hint::unreachable_unchecked(),
+                    }
+                }
+
+                let memory_block = self.allocate(new)?;
+                moved(handle, memory_block.handle, old, new);
+                self.deallocate(handle, old);
+                Ok(memory_block)
+            }
+        }
+
+        unsafe impl<H: PointerHandle, $($F: Choose,)* $($S: SharedStorage<Handle = H>,)*> SharedStorage
+            for PickerChain<($($F,)*), ($($S,)*)>
+        {
+            fn shared_allocate_nonempty(
+                &self,
+                layout: NonEmptyLayout,
+            ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout.into()) {
+                    $($s => self.storages.$s.shared_allocate_nonempty(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+
+            unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+                match self.index(layout.into()) {
+                    $($s => self.storages.$s.shared_deallocate_nonempty(handle, layout),)*
+                    _ => core::hint::unreachable_unchecked(),
+                }
+            }
+
+            fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout) {
+                    $($s => self.storages.$s.shared_allocate(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+
+            unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
+                match self.index(layout) {
+                    $($s => self.storages.$s.shared_deallocate(handle, layout),)*
+                    _ => core::hint::unreachable_unchecked(),
+                }
+            }
+
+            fn shared_allocate_nonempty_zeroed(
+                &self,
+                layout: NonEmptyLayout,
+            ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout.into()) {
+                    $($s => self.storages.$s.shared_allocate_nonempty_zeroed(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+
+            fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                match self.index(layout) {
+                    $($s => self.storages.$s.shared_allocate_zeroed(layout),)*
+                    _ => unsafe { core::hint::unreachable_unchecked() },
+                }
+            }
+        }
+
+        unsafe impl<H: PointerHandle, $($F: Choose,)* $($S: SharedResizableStorage<Handle = H>,)*>
+            SharedResizableStorage for PickerChain<($($F,)*), ($($S,)*)>
+        {
+            unsafe fn shared_grow(
+                &self,
+                handle: Self::Handle,
+                old: Layout,
+                new: Layout,
+            ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                let (from, to) = (self.index(old), self.index(new));
+                if from == to {
+                    return match from {
+                        $($s => self.storages.$s.shared_grow(handle, old, new),)*
+                        _ => core::hint::unreachable_unchecked(),
+                    }
+                }
+
+                let memory_block = self.shared_allocate(new)?;
+                moved(handle, memory_block.handle, old, new);
+                self.shared_deallocate(handle, old);
+                Ok(memory_block)
+            }
+
+            unsafe fn shared_grow_zeroed(
+                &self,
+                handle: Self::Handle,
+                old: Layout,
+                new: Layout,
+            ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                let (from, to) = (self.index(old), self.index(new));
+                if from == to {
+                    return match from {
+                        $($s => self.storages.$s.shared_grow_zeroed(handle, old, new),)*
+                        _ => core::hint::unreachable_unchecked(),
+                    }
+                }
+
+                let memory_block = self.shared_allocate_zeroed(new)?;
+                moved(handle, memory_block.handle, old, new);
+                self.shared_deallocate(handle, old);
+                Ok(memory_block)
+            }
+
+            unsafe fn shared_shrink(
+                &self,
+                handle: Self::Handle,
+                old: Layout,
+                new: Layout,
+            ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+                let (from, to) = (self.index(old), self.index(new));
+                if from == to {
+                    return match from {
+                        $($s => self.storages.$s.shared_shrink(handle, old, new),)*
+                        _ => core::hint::unreachable_unchecked(),
+                    }
+                }
+
+                let memory_block = self.shared_allocate(new)?;
+                moved(handle, memory_block.handle, old, new);
+                self.shared_deallocate(handle, old);
+                Ok(memory_block)
+            }
+        }
+    };
+}
+
+// copies the contents of an allocation that is moving to another storage
+unsafe fn moved<H: PointerHandle>(from: H, to: H, old: Layout, new: Layout) {
+    let size = old.size().min(new.size());
+    to.get_mut()
+        .as_ptr()
+        .copy_from_nonoverlapping(from.get().as_ptr(), size);
+}
+
+picker_chain!(F0 0; S0 0 S1 1; 1);
+picker_chain!(F0 0 F1 1; S0 0 S1 1 S2 2; 2);
+picker_chain!(F0 0 F1 1 F2 2; S0 0 S1 1 S2 2 S3 3; 3);
+picker_chain!(F0 0 F1 1 F2 2 F3 3; S0 0 S1 1 S2 2 S3 3 S4 4; 4);
+picker_chain!(F0 0 F1 1 F2 2 F3 3 F4 4; S0 0 S1 1 S2 2 S3 3 S4 4 S5 5; 5);
+picker_chain!(F0 0 F1 1 F2 2 F3 3 F4 4 F5 5; S0 0 S1 1 S2 2 S3 3 S4 4 S5 5 S6 6; 6);
+picker_chain!(F0 0 F1 1 F2 2 F3 3 F4 4 F5 5 F6 6; S0 0 S1 1 S2 2 S3 3 S4 4 S5 5 S6 6 S7 7; 7);
+
+#[test]
+fn picker_chain() {
+    use super::MaxSize;
+
+    let mut tiny = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut small = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut huge = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = PickerChain {
+        choose: (MaxSize::<8>, MaxSize::<64>),
+        storages: (&tiny, &small, &huge),
+    };
+
+    let a = Layout::new::<[u8; 8]>();
+    let b = Layout::new::<[u8; 64]>();
+    let c = Layout::new::<[u8; 256]>();
+
+    unsafe {
+        let memory_block = storage.allocate(a).unwrap();
+        storage.get_mut(memory_block.handle).as_ptr().write_bytes(0xab, 8);
+
+        // the allocation moves on to the next storage, and takes its contents with it
+        let memory_block = storage.grow(memory_block.handle, a, b).unwrap();
+        assert_eq!(storage.get(memory_block.handle).cast::<[u8; 8]>().read(), [0xab; 8]);
+
+        let memory_block = storage.grow(memory_block.handle, b, c).unwrap();
+        storage.deallocate(memory_block.handle, c);
+    }
+
+    tiny.assert_events(&[crate::Event::Allocate(a), crate::Event::Deallocate(a)]);
+    small.assert_events(&[crate::Event::Allocate(b), crate::Event::Deallocate(b)]);
+    huge.assert_events(&[crate::Event::Allocate(c), crate::Event::Deallocate(c)]);
+
+    crate::storage_conformance!(
+        PickerChain {
+            choose: (MaxSize::<8>, MaxSize::<64>),
+            storages: (&tiny, &small, &huge),
+        },
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
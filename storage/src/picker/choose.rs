@@ -15,6 +15,13 @@ pub struct MinSize<const VALUE: usize>;
 pub struct MaxAlign<const VALUE: usize>;
 #[derive(Default, Debug, Clone, Copy)]
 pub struct MinAlign<const VALUE: usize>;
+/// Picks layouts with a size in `LO..HI`
+#[derive(Default, Debug, Clone, Copy)]
+pub struct SizeInRange<const LO: usize, const HI: usize>;
+#[derive(Default, Debug, Clone, Copy)]
+pub struct SizeEq<const VALUE: usize>;
+#[derive(Default, Debug, Clone, Copy)]
+pub struct AlignEq<const VALUE: usize>;
 #[derive(Default, Debug, Clone, Copy)]
 pub struct NotC<T>(pub T);
 #[derive(Default, Debug, Clone, Copy)]
@@ -22,6 +29,27 @@ pub struct AndC<A, B>(pub A, pub B);
 #[derive(Default, Debug, Clone, Copy)]
 pub struct OrC<A, B>(pub A, pub B);
 
+/// A chooser that calls a closure
+#[derive(Debug, Clone, Copy)]
+pub struct FnChoose<F>(F);
+/// A chooser that calls a function pointer, which may be picked at runtime
+#[derive(Debug, Clone, Copy)]
+pub struct DynChoose(fn(Layout) -> bool);
+
+impl<F: Fn(Layout) -> bool + Copy> FnChoose<F> {
+    /// # Safety
+    ///
+    /// `choose` must always give the same answer for the same layout
+    pub const unsafe fn new(choose: F) -> Self { Self(choose) }
+}
+
+impl DynChoose {
+    /// # Safety
+    ///
+    /// `choose` must always give the same answer for the same layout
+    pub const unsafe fn new(choose: fn(Layout) -> bool) -> Self { Self(choose) }
+}
+
 macro_rules! impl_op {
     (AND ($($generics:tt)*) $type:ty) => {
         impl<F: Choose, $($generics)*> BitAnd<F> for $type {
@@ -70,6 +98,11 @@ impl_ops!((const VALUE: usize) MaxSize<VALUE>);
 impl_ops!((const VALUE: usize) MinSize<VALUE>);
 impl_ops!((const VALUE: usize) MaxAlign<VALUE>);
 impl_ops!((const VALUE: usize) MinAlign<VALUE>);
+impl_ops!((const LO: usize, const HI: usize) SizeInRange<LO, HI>);
+impl_ops!((const VALUE: usize) SizeEq<VALUE>);
+impl_ops!((const VALUE: usize) AlignEq<VALUE>);
+impl_ops!((T) FnChoose<T>);
+impl_ops!(() DynChoose);
 impl_ops!((A, B) AndC<A, B>, (AND OR));
 impl_ops!((A, B) OrC<A, B>, (AND OR));
 impl_ops!((A) NotC<A>, (AND OR));
@@ -118,6 +151,21 @@ unsafe impl<const VALUE: usize> Choose for MinAlign<VALUE> {
     fn choose(&self, layout: Layout) -> bool { layout.align() >= VALUE }
 }
 
+unsafe impl<const LO: usize, const HI: usize> Choose for SizeInRange<LO, HI> {
+    #[inline]
+    fn choose(&self, layout: Layout) -> bool { (LO..HI).contains(&layout.size()) }
+}
+
+unsafe impl<const VALUE: usize> Choose for SizeEq<VALUE> {
+    #[inline]
+    fn choose(&self, layout: Layout) -> bool { layout.size() == VALUE }
+}
+
+unsafe impl<const VALUE: usize> Choose for AlignEq<VALUE> {
+    #[inline]
+    fn choose(&self, layout: Layout) -> bool { layout.align() == VALUE }
+}
+
 unsafe impl<A: Choose, B: Choose> Choose for AndC<A, B> {
     #[inline]
     fn choose(&self, layout: Layout) -> bool {
@@ -141,3 +189,38 @@ unsafe impl<A: Choose> Choose for NotC<A> {
         !a.choose(layout)
     }
 }
+
+unsafe impl<F: Fn(Layout) -> bool + Copy> Choose for FnChoose<F> {
+    #[inline]
+    fn choose(&self, layout: Layout) -> bool { (self.0)(layout) }
+}
+
+unsafe impl Choose for DynChoose {
+    #[inline]
+    fn choose(&self, layout: Layout) -> bool { (self.0)(layout) }
+}
+
+#[test]
+fn fn_choose() {
+    let small = unsafe { FnChoose::new(|layout: Layout| layout.size() <= 16) };
+    let aligned = unsafe { DynChoose::new(|layout| layout.align() >= 8) };
+    let choose = small & !aligned;
+
+    assert!(choose.choose(Layout::new::<[u8; 16]>()));
+    assert!(!choose.choose(Layout::new::<[u8; 17]>()));
+    assert!(!choose.choose(Layout::new::<u64>()));
+}
+
+#[test]
+fn exact_choose() {
+    let range = SizeInRange::<8, 16>;
+    assert!(!range.choose(Layout::new::<[u8; 7]>()));
+    assert!(range.choose(Layout::new::<[u8; 8]>()));
+    assert!(range.choose(Layout::new::<[u8; 15]>()));
+    assert!(!range.choose(Layout::new::<[u8; 16]>()));
+
+    let exact = SizeEq::<8> & AlignEq::<8>;
+    assert!(exact.choose(Layout::new::<u64>()));
+    assert!(!exact.choose(Layout::new::<[u8; 8]>()));
+    assert!(!exact.choose(Layout::new::<[u64; 2]>()));
+}
@@ -0,0 +1,325 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+use super::Choose;
+use crate::{
+    AllocErr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that picks which of two storages to allocate from, like [`Picker`](crate::Picker),
+/// but remembers the choice in the handle
+///
+/// Deallocations and resizes are sent to the storage that made the allocation, no matter what
+/// the chooser would say about their layout. So the chooser doesn't have to give the same answer
+/// for every layout an allocation may be deallocated with, and the two storages may pad layouts
+/// differently. Resizing never moves an allocation to the other storage.
+pub struct TaggedPicker<F, A, B> {
+    pub choose: F,
+    pub left: A,
+    pub right: B,
+}
+
+/// A handle from a [`TaggedPicker`], which records which storage it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaggedHandle<H> {
+    pub handle: H,
+    pub left: bool,
+}
+
+unsafe impl<H: Handle> Handle for TaggedHandle<H> {
+    unsafe fn dangling(align: usize) -> Self {
+        Self {
+            handle: H::dangling(align),
+            left: true,
+        }
+    }
+}
+
+fn tagged<H>(left: bool) -> impl FnOnce(MemoryBlock<H>) -> MemoryBlock<TaggedHandle<H>> {
+    move |MemoryBlock { handle, size }| MemoryBlock {
+        handle: TaggedHandle { handle, left },
+        size,
+    }
+}
+
+fn tagged_ne<H>(left: bool) -> impl FnOnce(NonEmptyMemoryBlock<H>) -> NonEmptyMemoryBlock<TaggedHandle<H>> {
+    move |NonEmptyMemoryBlock { handle, size }| NonEmptyMemoryBlock {
+        handle: TaggedHandle { handle, left },
+        size,
+    }
+}
+
+unsafe impl<F: Choose, A: SharedGetMut, B: SharedGetMut<Handle = A::Handle>> SharedGetMut for TaggedPicker<F, A, B> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, TaggedHandle { handle, left }: Self::Handle) -> NonNull<u8> {
+        if left {
+            self.left.shared_get_mut(handle)
+        } else {
+            self.right.shared_get_mut(handle)
+        }
+    }
+}
+
+impl<F: Choose, A: MultiStorage, B: MultiStorage<Handle = A::Handle>> MultiStorage for TaggedPicker<F, A, B> {}
+
+unsafe impl<F: Choose, A: Storage, B: Storage<Handle = A::Handle>> Storage for TaggedPicker<F, A, B> {
+    type Handle = TaggedHandle<A::Handle>;
+
+    #[inline]
+    unsafe fn get(&self, TaggedHandle { handle, left }: Self::Handle) -> NonNull<u8> {
+        if left {
+            self.left.get(handle)
+        } else {
+            self.right.get(handle)
+        }
+    }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, TaggedHandle { handle, left }: Self::Handle) -> NonNull<u8> {
+        if left {
+            self.left.get_mut(handle)
+        } else {
+            self.right.get_mut(handle)
+        }
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout.into()) {
+            self.left.allocate_nonempty(layout).map(tagged_ne(true))
+        } else {
+            self.right.allocate_nonempty(layout).map(tagged_ne(false))
+        }
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, TaggedHandle { handle, left }: Self::Handle, layout: NonEmptyLayout) {
+        if left {
+            self.left.deallocate_nonempty(handle, layout);
+        } else {
+            self.right.deallocate_nonempty(handle, layout);
+        }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout) {
+            self.left.allocate(layout).map(tagged(true))
+        } else {
+            self.right.allocate(layout).map(tagged(false))
+        }
+    }
+
+    unsafe fn deallocate(&mut self, TaggedHandle { handle, left }: Self::Handle, layout: Layout) {
+        if left {
+            self.left.deallocate(handle, layout);
+        } else {
+            self.right.deallocate(handle, layout);
+        }
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout.into()) {
+            self.left.allocate_nonempty_zeroed(layout).map(tagged_ne(true))
+        } else {
+            self.right.allocate_nonempty_zeroed(layout).map(tagged_ne(false))
+        }
+    }
+
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout) {
+            self.left.allocate_zeroed(layout).map(tagged(true))
+        } else {
+            self.right.allocate_zeroed(layout).map(tagged(false))
+        }
+    }
+}
+
+unsafe impl<F: Choose, A: ResizableStorage, B: ResizableStorage<Handle = A::Handle>> ResizableStorage
+    for TaggedPicker<F, A, B>
+{
+    unsafe fn grow(
+        &mut self,
+        TaggedHandle { handle, left }: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if left {
+            self.left.grow(handle, old, new).map(tagged(left))
+        } else {
+            self.right.grow(handle, old, new).map(tagged(left))
+        }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        TaggedHandle { handle, left }: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if left {
+            self.left.grow_zeroed(handle, old, new).map(tagged(left))
+        } else {
+            self.right.grow_zeroed(handle, old, new).map(tagged(left))
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        TaggedHandle { handle, left }: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if left {
+            self.left.shrink(handle, old, new).map(tagged(left))
+        } else {
+            self.right.shrink(handle, old, new).map(tagged(left))
+        }
+    }
+}
+
+unsafe impl<F: Choose, A: SharedStorage, B: SharedStorage<Handle = A::Handle>> SharedStorage for TaggedPicker<F, A, B> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout.into()) {
+            self.left.shared_allocate_nonempty(layout).map(tagged_ne(true))
+        } else {
+            self.right.shared_allocate_nonempty(layout).map(tagged_ne(false))
+        }
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, TaggedHandle { handle, left }: Self::Handle, layout: NonEmptyLayout) {
+        if left {
+            self.left.shared_deallocate_nonempty(handle, layout);
+        } else {
+            self.right.shared_deallocate_nonempty(handle, layout);
+        }
+    }
+
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout) {
+            self.left.shared_allocate(layout).map(tagged(true))
+        } else {
+            self.right.shared_allocate(layout).map(tagged(false))
+        }
+    }
+
+    unsafe fn shared_deallocate(&self, TaggedHandle { handle, left }: Self::Handle, layout: Layout) {
+        if left {
+            self.left.shared_deallocate(handle, layout);
+        } else {
+            self.right.shared_deallocate(handle, layout);
+        }
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout.into()) {
+            self.left.shared_allocate_nonempty_zeroed(layout).map(tagged_ne(true))
+        } else {
+            self.right.shared_allocate_nonempty_zeroed(layout).map(tagged_ne(false))
+        }
+    }
+
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if self.choose.choose(layout) {
+            self.left.shared_allocate_zeroed(layout).map(tagged(true))
+        } else {
+            self.right.shared_allocate_zeroed(layout).map(tagged(false))
+        }
+    }
+}
+
+unsafe impl<F: Choose, A: SharedResizableStorage, B: SharedResizableStorage<Handle = A::Handle>> SharedResizableStorage
+    for TaggedPicker<F, A, B>
+{
+    unsafe fn shared_grow(
+        &self,
+        TaggedHandle { handle, left }: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if left {
+            self.left.shared_grow(handle, old, new).map(tagged(left))
+        } else {
+            self.right.shared_grow(handle, old, new).map(tagged(left))
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        TaggedHandle { handle, left }: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if left {
+            self.left.shared_grow_zeroed(handle, old, new).map(tagged(left))
+        } else {
+            self.right.shared_grow_zeroed(handle, old, new).map(tagged(left))
+        }
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        TaggedHandle { handle, left }: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        if left {
+            self.left.shared_shrink(handle, old, new).map(tagged(left))
+        } else {
+            self.right.shared_shrink(handle, old, new).map(tagged(left))
+        }
+    }
+}
+
+#[test]
+fn tagged_picker() {
+    use super::MaxSize;
+
+    let mut small = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut large = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = TaggedPicker {
+        choose: MaxSize::<16>,
+        left: &small,
+        right: &large,
+    };
+
+    let layout = Layout::new::<[u8; 8]>();
+    let grown = Layout::new::<[u8; 64]>();
+    let memory_block = storage.allocate(layout).unwrap();
+    assert!(memory_block.handle.left);
+
+    unsafe {
+        // the allocation is too large for the left storage now, but it stays there
+        let memory_block = storage.grow(memory_block.handle, layout, grown).unwrap();
+        assert!(memory_block.handle.left);
+        storage.deallocate(memory_block.handle, grown);
+    }
+
+    let memory_block = storage.allocate(grown).unwrap();
+    assert!(!memory_block.handle.left);
+    unsafe { storage.deallocate(memory_block.handle, grown) }
+
+    small.assert_events(&[
+        crate::Event::Allocate(layout),
+        crate::Event::Grow {
+            old: layout,
+            new: grown,
+        },
+        crate::Event::Deallocate(grown),
+    ]);
+    large.assert_events(&[crate::Event::Allocate(grown), crate::Event::Deallocate(grown)]);
+
+    crate::storage_conformance!(
+        TaggedPicker {
+            choose: MaxSize::<16>,
+            left: &small,
+            right: &large,
+        },
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
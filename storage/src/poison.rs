@@ -0,0 +1,342 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+#[cfg(debug_assertions)]
+use core::{cell::UnsafeCell, mem};
+
+#[cfg(debug_assertions)]
+use crate::spin_lock::SpinLock;
+use crate::{
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
+};
+
+// the number of freed blocks that are remembered, to check them when they are allocated again
+#[cfg(debug_assertions)]
+const RECENT: usize = 32;
+// storages often keep their own bookkeeping at the start of free blocks, so that isn't checked
+#[cfg(debug_assertions)]
+const SKIP: usize = 4 * mem::size_of::<usize>();
+
+#[cfg(debug_assertions)]
+struct Recent {
+    // (address, size) of recently freed blocks
+    blocks: [(usize, usize); RECENT],
+    next: usize,
+}
+
+#[cfg(debug_assertions)]
+impl Recent {
+    fn insert(&mut self, addr: usize, size: usize) {
+        let index = self.blocks.iter().position(|&(a, _)| a == addr).unwrap_or_else(|| {
+            let index = self.next;
+            self.next = (self.next + 1) % RECENT;
+            index
+        });
+
+        self.blocks[index] = (addr, size);
+    }
+
+    fn take(&mut self, addr: usize) -> Option<usize> {
+        let block = self.blocks.iter_mut().find(|&&mut (a, size)| a == addr && size != 0)?;
+        Some(mem::take(block).1)
+    }
+}
+
+/// A storage that fills freed memory with [`FREED`](Self::FREED), and newly allocated memory
+/// with [`FRESH`](Self::FRESH)
+///
+/// Reading either pattern where real data is expected points to a use of freed or uninitialized memory.
+/// With [`with_verification`](Self::with_verification), writes to freed blocks are caught as well.
+///
+/// Resizing always moves the allocation, so that the old block is poisoned.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct PoisonStorage<S> {
+    storage: S,
+    #[cfg(debug_assertions)]
+    verify: bool,
+    #[cfg(debug_assertions)]
+    recent: UnsafeCell<Recent>,
+    #[cfg(debug_assertions)]
+    lock: SpinLock,
+}
+
+#[cfg(debug_assertions)]
+unsafe impl<S: Sync> Sync for PoisonStorage<S> {}
+
+impl<S> PoisonStorage<S> {
+    /// The byte that freed memory is filled with
+    pub const FREED: u8 = 0xDE;
+    /// The byte that newly allocated memory is filled with
+    pub const FRESH: u8 = 0xAA;
+
+    pub const fn new(storage: S) -> Self { Self::with_verify(storage, false) }
+
+    /// Like [`new`](Self::new), but in debug builds recently freed blocks are also checked when
+    /// they are allocated again, and writes to them after they were freed cause a panic
+    ///
+    /// Once a block is deallocated it belongs to the underlying storage, which may write its own
+    /// bookkeeping into it, so this is only meaningful over storages that never write to deallocated
+    /// memory (like a [`SingleStackStorage`](crate::SingleStackStorage) or a bump storage),
+    /// over any other storage it can panic on correct code.
+    pub const fn with_verification(storage: S) -> Self { Self::with_verify(storage, true) }
+
+    #[allow(unused_variables)]
+    const fn with_verify(storage: S, verify: bool) -> Self {
+        Self {
+            storage,
+            #[cfg(debug_assertions)]
+            verify,
+            #[cfg(debug_assertions)]
+            recent: UnsafeCell::new(Recent {
+                blocks: [(0, 0); RECENT],
+                next: 0,
+            }),
+            #[cfg(debug_assertions)]
+            lock: SpinLock::new(),
+        }
+    }
+
+    #[allow(clippy::unused_self, clippy::missing_const_for_fn)]
+    unsafe fn check(&self, ptr: NonNull<u8>, size: usize) {
+        #[cfg(debug_assertions)]
+        if self.verify {
+            let freed = {
+                let _guard = self.lock.lock();
+                (*self.recent.get()).take(ptr.as_ptr() as usize)
+            };
+
+            if let Some(freed) = freed {
+                for offset in SKIP..size.min(freed) {
+                    let byte = ptr.as_ptr().add(offset).read();
+                    assert!(
+                        byte == Self::FREED,
+                        "freed memory was written to at offset {} of a {} byte block, found {:#x}",
+                        offset,
+                        freed,
+                        byte
+                    );
+                }
+            }
+        }
+
+        #[cfg(not(debug_assertions))]
+        let _ = (ptr, size);
+    }
+
+    unsafe fn fresh(&self, ptr: NonNull<u8>, size: usize) {
+        self.check(ptr, size);
+        ptr.as_ptr().write_bytes(Self::FRESH, size);
+    }
+
+    #[allow(clippy::unused_self, clippy::missing_const_for_fn)]
+    unsafe fn poison(&self, ptr: NonNull<u8>, size: usize) {
+        ptr.as_ptr().write_bytes(Self::FREED, size);
+
+        #[cfg(debug_assertions)]
+        if self.verify {
+            let _guard = self.lock.lock();
+            (*self.recent.get()).insert(ptr.as_ptr() as usize, size);
+        }
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for PoisonStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for PoisonStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for PoisonStorage<S> {}
+
+unsafe impl<S: Storage> Storage for PoisonStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        unsafe {
+            let ptr = self.storage.get_mut(memory_block.handle);
+            self.fresh(ptr, memory_block.size.get());
+        }
+        Ok(memory_block)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let ptr = self.storage.get_mut(handle);
+        self.poison(ptr, layout.size());
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        unsafe {
+            let ptr = self.storage.get_mut(memory_block.handle);
+            self.check(ptr, memory_block.size.get());
+            ptr.as_ptr().write_bytes(0, memory_block.size.get());
+        }
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: MultiStorage> ResizableStorage for PoisonStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for PoisonStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        unsafe {
+            let ptr = self.storage.shared_get_mut(memory_block.handle);
+            self.fresh(ptr, memory_block.size.get());
+        }
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.poison(self.storage.shared_get_mut(handle), layout.size());
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        unsafe {
+            let ptr = self.storage.shared_get_mut(memory_block.handle);
+            self.check(ptr, memory_block.size.get());
+            ptr.as_ptr().write_bytes(0, memory_block.size.get());
+        }
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<S: SharedStorage + MultiStorage> SharedResizableStorage for PoisonStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn poison() {
+    let mut storage = PoisonStorage::new(crate::SingleStackStorage::<[u8; 64]>::new());
+
+    let layout = Layout::new::<[u8; 64]>();
+    storage.allocate(layout).unwrap();
+
+    unsafe {
+        let ptr = storage.get_mut(()).cast::<[u8; 64]>().as_ptr();
+        assert_eq!(*ptr, [PoisonStorage::<()>::FRESH; 64]);
+        storage.deallocate((), layout);
+        assert_eq!(*ptr, [PoisonStorage::<()>::FREED; 64]);
+    }
+
+    // untouched blocks pass the check when they are allocated again
+    let mut storage = PoisonStorage::with_verification(crate::SingleStackStorage::<[u8; 64]>::new());
+    storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate((), layout) }
+    storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate((), layout) }
+
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    crate::storage_conformance!(PoisonStorage::new(system), resizable, shared, shared_resizable);
+}
+
+#[test]
+fn poison_system() {
+    // the system allocator writes its own bookkeeping into freed blocks, which isn't a use after free
+    let mut storage = PoisonStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let layout = Layout::from_size_align(2008, 8).unwrap();
+    let memory_block = storage.allocate(layout).unwrap();
+    // keeps the block from being merged back into the rest of the heap
+    let guard = storage.allocate(Layout::new::<u64>()).unwrap();
+    unsafe {
+        storage.deallocate(memory_block.handle, layout);
+        let memory_block = storage.allocate(layout).unwrap();
+        storage.deallocate(memory_block.handle, layout);
+        storage.deallocate(guard.handle, Layout::new::<u64>());
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "freed memory was written to"]
+fn poison_use_after_free() {
+    let mut storage = PoisonStorage::with_verification(crate::SingleStackStorage::<[u8; 64]>::new());
+
+    let layout = Layout::new::<[u8; 64]>();
+    storage.allocate(layout).unwrap();
+
+    unsafe {
+        let ptr = storage.get_mut(()).as_ptr();
+        storage.deallocate((), layout);
+        ptr.add(48).write(0);
+    }
+
+    let _ = storage.allocate(layout);
+}
@@ -0,0 +1,168 @@
+use crate::{AllocErr, MultiStorage, SharedStorage};
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+// free slots store the handle of the next free slot in place of the value
+union Slot<T, H: Copy> {
+    _value: ManuallyDrop<T>,
+    _next: Option<H>,
+}
+
+/// A pool of slots for values of type `T`, slots are reused once their [`PoolBox`] is dropped
+///
+/// Many [`PoolBox`]es can be alive at the same time, so the storage must be a [`MultiStorage`]
+pub struct Pool<T, S: SharedStorage + MultiStorage = crate::Global> {
+    storage: S,
+    free: Cell<Option<S::Handle>>,
+    __: PhantomData<T>,
+}
+
+pub struct PoolBox<'a, T, S: SharedStorage + MultiStorage = crate::Global> {
+    pool: &'a Pool<T, S>,
+    handle: S::Handle,
+}
+
+impl<T> Pool<T> {
+    pub const fn new() -> Self { Self::new_in(crate::Global) }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<T, S: SharedStorage + MultiStorage> Pool<T, S> {
+    const LAYOUT: Layout = Layout::new::<Slot<T, S::Handle>>();
+
+    pub const fn new_in(storage: S) -> Self {
+        Self {
+            storage,
+            free: Cell::new(None),
+            __: PhantomData,
+        }
+    }
+
+    pub const fn storage(&self) -> &S { &self.storage }
+
+    pub fn alloc(&self, value: T) -> PoolBox<'_, T, S> { self.try_alloc(value).unwrap_or_else(AllocErr::handle) }
+
+    /// Moves `value` into a free slot, or a new slot from the storage if there are no free slots
+    ///
+    /// # Errors
+    ///
+    /// If there are no free slots and the storage can't allocate a new one
+    pub fn try_alloc(&self, value: T) -> Result<PoolBox<'_, T, S>, AllocErr> {
+        let handle = match self.free.get() {
+            Some(handle) => unsafe {
+                let next = self.storage.get(handle).as_ptr().cast::<Option<S::Handle>>();
+                self.free.set(next.read());
+                handle
+            },
+            None => self.storage.shared_allocate(Self::LAYOUT)?.handle,
+        };
+
+        unsafe { self.storage.shared_get_mut(handle).as_ptr().cast::<T>().write(value) }
+
+        Ok(PoolBox { pool: self, handle })
+    }
+
+    /// The number of slots that are ready to be reused
+    pub fn free_count(&self) -> usize {
+        let mut count = 0;
+        let mut free = self.free.get();
+
+        while let Some(handle) = free {
+            count += 1;
+            free = unsafe { self.storage.get(handle).as_ptr().cast::<Option<S::Handle>>().read() };
+        }
+
+        count
+    }
+
+    /// Returns all free slots to the storage
+    pub fn shrink_to_fit(&mut self) {
+        while let Some(handle) = self.free.get() {
+            unsafe {
+                let next = self.storage.get(handle).as_ptr().cast::<Option<S::Handle>>();
+                self.free.set(next.read());
+                self.storage.deallocate(handle, Self::LAYOUT);
+            }
+        }
+    }
+
+    unsafe fn release(&self, handle: S::Handle) {
+        let next = self.storage.shared_get_mut(handle).as_ptr().cast::<Option<S::Handle>>();
+        next.write(self.free.get());
+        self.free.set(Some(handle));
+    }
+}
+
+impl<T, S: SharedStorage + MultiStorage> Drop for Pool<T, S> {
+    fn drop(&mut self) { self.shrink_to_fit() }
+}
+
+impl<'a, T, S: SharedStorage + MultiStorage> PoolBox<'a, T, S> {
+    pub const fn pool(this: &Self) -> &'a Pool<T, S> { this.pool }
+
+    /// Moves the value out of the pool, the slot is returned to the pool
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            let value = this.pool.storage.get(this.handle).as_ptr().cast::<T>().read();
+            this.pool.release(this.handle);
+            value
+        }
+    }
+}
+
+impl<T, S: SharedStorage + MultiStorage> Drop for PoolBox<'_, T, S> {
+    fn drop(&mut self) {
+        unsafe {
+            let ptr = self.pool.storage.shared_get_mut(self.handle);
+            let _release = crate::scope_guard::ScopeGuard::with_extra(self.handle, |handle| self.pool.release(handle));
+            ptr.as_ptr().cast::<T>().drop_in_place();
+        }
+    }
+}
+
+impl<T, S: SharedStorage + MultiStorage> Deref for PoolBox<'_, T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target { unsafe { &*self.pool.storage.get(self.handle).as_ptr().cast::<T>() } }
+}
+
+impl<T, S: SharedStorage + MultiStorage> DerefMut for PoolBox<'_, T, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.pool.storage.shared_get_mut(self.handle).as_ptr().cast::<T>() }
+    }
+}
+
+impl<T: fmt::Debug, S: SharedStorage + MultiStorage> fmt::Debug for PoolBox<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { T::fmt(self, f) }
+}
+
+#[test]
+fn pool() {
+    let pool = Pool::new_in(crate::LeakCheck::new(crate::AllocatorStorage::new(std::alloc::System)));
+
+    let a = pool.alloc(std::string::String::from("a"));
+    let b = pool.alloc(std::string::String::from("b"));
+    assert_eq!(*a, "a");
+    assert_eq!(*b, "b");
+    assert_eq!(pool.free_count(), 0);
+
+    drop(a);
+    assert_eq!(pool.free_count(), 1);
+
+    let mut c = pool.alloc(std::string::String::from("c"));
+    c.push('c');
+    assert_eq!(pool.free_count(), 0);
+    assert_eq!(PoolBox::into_inner(c), "cc");
+    assert_eq!(PoolBox::into_inner(b), "b");
+    assert_eq!(pool.free_count(), 2);
+}
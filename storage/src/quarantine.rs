@@ -0,0 +1,352 @@
+use core::{alloc::Layout, cell::UnsafeCell, mem, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+// quarantined blocks are linked together through their own memory, from oldest to newest
+struct Node<H> {
+    next: Option<H>,
+    layout: NonEmptyLayout,
+}
+
+struct Queue<H> {
+    head: Option<H>,
+    tail: Option<H>,
+    bytes: usize,
+}
+
+impl<H: Copy> Queue<H> {
+    // pushes the block onto the back of the queue, and detaches the oldest blocks
+    // until the queue fits in `BYTES` again, the detached blocks are still linked together
+    unsafe fn push<const BYTES: usize>(
+        &mut self,
+        node: impl Fn(H) -> *mut Node<H>,
+        handle: H,
+        layout: NonEmptyLayout,
+    ) -> Option<H> {
+        node(handle).write(Node { next: None, layout });
+
+        match self.tail.replace(handle) {
+            Some(tail) => (*node(tail)).next = Some(handle),
+            None => self.head = Some(handle),
+        }
+
+        self.bytes += layout.size();
+
+        let evicted = self.head;
+        let mut last = None;
+
+        while self.bytes > BYTES {
+            let Some(head) = self.head else { break };
+            let head = &mut *node(head);
+            self.bytes -= head.layout.size();
+            last = mem::replace(&mut self.head, head.next);
+        }
+
+        let last = last?;
+        (*node(last)).next = None;
+
+        if self.head.is_none() {
+            self.tail = None;
+        }
+
+        evicted
+    }
+
+    const fn take(&mut self) -> Option<H> {
+        self.tail = None;
+        self.bytes = 0;
+        self.head.take()
+    }
+}
+
+/// A storage that holds on to freed blocks until `BYTES` worth of newer blocks have been freed
+///
+/// Freed blocks wait in a FIFO, and the oldest ones are only given back to the underlying storage
+/// once the quarantine grows past `BYTES`. This delays reuse of freed memory, so use-after-free bugs
+/// are far more likely to touch memory that is still poisoned or guarded by a storage further down.
+///
+/// Every allocation is made large enough to hold the link in the queue.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct QuarantineStorage<S: Storage, const BYTES: usize> {
+    storage: S,
+    queue: UnsafeCell<Queue<S::Handle>>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Storage + Send, const BYTES: usize> Send for QuarantineStorage<S, BYTES> where S::Handle: Send {}
+unsafe impl<S: Storage + Sync, const BYTES: usize> Sync for QuarantineStorage<S, BYTES> where S::Handle: Send {}
+
+impl<S: Storage, const BYTES: usize> QuarantineStorage<S, BYTES> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            queue: UnsafeCell::new(Queue {
+                head: None,
+                tail: None,
+                bytes: 0,
+            }),
+            lock: SpinLock::new(),
+        }
+    }
+
+    /// The number of bytes currently in quarantine
+    pub fn quarantined(&self) -> usize {
+        let _guard = self.lock.lock();
+        unsafe { (*self.queue.get()).bytes }
+    }
+
+    fn padded(layout: Layout) -> Result<NonEmptyLayout, AllocErr> {
+        let node = Layout::new::<Node<S::Handle>>();
+
+        Layout::from_size_align(layout.size().max(node.size()), layout.align().max(node.align()))
+            .ok()
+            .and_then(NonEmptyLayout::new)
+            .ok_or_else(|| AllocErr::new(layout))
+    }
+
+    // the layout was already padded when the block was allocated
+    unsafe fn padded_unchecked(layout: Layout) -> NonEmptyLayout {
+        Self::padded(layout).unwrap_or_else(|_| core::hint::unreachable_unchecked())
+    }
+
+    // frees every block in the list starting at `head`
+    unsafe fn free_list(&mut self, mut head: Option<S::Handle>) {
+        while let Some(handle) = head {
+            let node = self.storage.get_mut(handle).as_ptr().cast::<Node<S::Handle>>().read();
+            head = node.next;
+            self.storage.deallocate_nonempty(handle, node.layout);
+        }
+    }
+
+    fn release_all(&mut self) {
+        let head = self.queue.get_mut().take();
+        unsafe { self.free_list(head) }
+    }
+}
+
+impl<S: SharedStorage, const BYTES: usize> QuarantineStorage<S, BYTES> {
+    unsafe fn shared_free_list(&self, mut head: Option<S::Handle>) {
+        while let Some(handle) = head {
+            let node = self
+                .storage
+                .shared_get_mut(handle)
+                .as_ptr()
+                .cast::<Node<S::Handle>>()
+                .read();
+            head = node.next;
+            self.storage.shared_deallocate_nonempty(handle, node.layout);
+        }
+    }
+
+    fn shared_release_all(&self) {
+        let head = {
+            let _guard = self.lock.lock();
+            unsafe { (*self.queue.get()).take() }
+        };
+
+        unsafe { self.shared_free_list(head) }
+    }
+}
+
+impl<S: Storage, const BYTES: usize> Drop for QuarantineStorage<S, BYTES> {
+    fn drop(&mut self) { self.release_all() }
+}
+
+impl<S: Storage + Flush, const BYTES: usize> Flush for QuarantineStorage<S, BYTES> {
+    fn try_flush(&mut self) -> bool {
+        self.release_all();
+        self.storage.try_flush()
+    }
+
+    fn flush(&mut self) {
+        self.release_all();
+        self.storage.flush();
+    }
+}
+
+impl<S: SharedStorage + SharedFlush, const BYTES: usize> SharedFlush for QuarantineStorage<S, BYTES> {
+    fn try_shared_flush(&self) -> bool {
+        self.shared_release_all();
+        self.storage.try_shared_flush()
+    }
+
+    fn shared_flush(&self) {
+        self.shared_release_all();
+        self.storage.shared_flush();
+    }
+}
+
+unsafe impl<S: FromPtr, const BYTES: usize> FromPtr for QuarantineStorage<S, BYTES> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr(ptr, Self::padded_unchecked(layout).into())
+    }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, Self::padded_unchecked(layout).into())
+    }
+}
+
+unsafe impl<S: SharedGetMut, const BYTES: usize> SharedGetMut for QuarantineStorage<S, BYTES> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage, const BYTES: usize> MultiStorage for QuarantineStorage<S, BYTES> {}
+
+unsafe impl<S: Storage, const BYTES: usize> Storage for QuarantineStorage<S, BYTES> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.allocate_nonempty(Self::padded(layout.into())?)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let layout = Self::padded_unchecked(layout.into());
+        let storage = &self.storage;
+        let evicted = self
+            .queue
+            .get_mut()
+            .push::<BYTES>(|handle| storage.get(handle).as_ptr().cast(), handle, layout);
+        self.free_list(evicted);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.allocate_nonempty_zeroed(Self::padded(layout.into())?)
+    }
+}
+
+// the old block has to go through the quarantine, so resizing always moves the allocation
+unsafe impl<S: MultiStorage, const BYTES: usize> ResizableStorage for QuarantineStorage<S, BYTES> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage, const BYTES: usize> SharedStorage for QuarantineStorage<S, BYTES> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_allocate_nonempty(Self::padded(layout.into())?)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let layout = Self::padded_unchecked(layout.into());
+
+        let evicted = {
+            let _guard = self.lock.lock();
+            (*self.queue.get()).push::<BYTES>(
+                |handle| self.storage.shared_get_mut(handle).as_ptr().cast(),
+                handle,
+                layout,
+            )
+        };
+
+        self.shared_free_list(evicted);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage
+            .shared_allocate_nonempty_zeroed(Self::padded(layout.into())?)
+    }
+}
+
+unsafe impl<S: SharedStorage + MultiStorage, const BYTES: usize> SharedResizableStorage
+    for QuarantineStorage<S, BYTES>
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn quarantine() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = QuarantineStorage::<_, 64>::new(&mock);
+
+    let layout = Layout::new::<[u8; 32]>();
+    let handles = [(); 3].map(|()| storage.allocate(layout).unwrap().handle);
+
+    unsafe {
+        storage.deallocate(handles[0], layout);
+        storage.deallocate(handles[1], layout);
+        assert_eq!(storage.quarantined(), 64);
+        assert_eq!(mock.live_allocations(), 3);
+
+        // the oldest block is released once the quarantine is over budget
+        storage.deallocate(handles[2], layout);
+        assert_eq!(storage.quarantined(), 64);
+        assert_eq!(mock.live_allocations(), 2);
+    }
+
+    drop(storage);
+    assert_eq!(mock.live_allocations(), 0);
+    mock.clear_events();
+
+    crate::storage_conformance!(
+        QuarantineStorage::<_, 256>::new(mock),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
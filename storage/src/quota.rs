@@ -0,0 +1,286 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that fails any allocation that would take it past a byte budget
+///
+/// Allocations are charged the size of their layout, and every memory block reports exactly
+/// that size, so the budget is released by the same amount when the block is deallocated or shrunk.
+/// Optionally, allocations larger than a maximum size are rejected no matter how much of
+/// the budget is left.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct QuotaStorage<S> {
+    storage: S,
+    used: AtomicUsize,
+    limit: usize,
+    max_size: usize,
+}
+
+impl<S> QuotaStorage<S> {
+    pub const fn new(storage: S, limit: usize) -> Self { Self::with_max_size(storage, limit, usize::MAX) }
+
+    pub const fn with_max_size(storage: S, limit: usize, max_size: usize) -> Self {
+        Self {
+            storage,
+            used: AtomicUsize::new(0),
+            limit,
+            max_size,
+        }
+    }
+
+    /// The number of bytes currently charged against the budget
+    pub fn used(&self) -> usize { self.used.load(Ordering::Relaxed) }
+
+    /// The number of bytes that can still be allocated
+    pub fn remaining(&self) -> usize { self.limit - self.used() }
+
+    pub const fn limit(&self) -> usize { self.limit }
+
+    pub const fn max_size(&self) -> usize { self.max_size }
+
+    // charges `size` more bytes against the budget for an allocation that ends up with `layout`
+    fn reserve(&self, layout: Layout, size: usize) -> Result<(), AllocErr> {
+        let limit = self.limit;
+
+        if layout.size() > self.max_size {
+            return Err(AllocErr::new(layout))
+        }
+
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(size).filter(|&used| used <= limit)
+            })
+            .map(drop)
+            .map_err(|_| AllocErr::new(layout))
+    }
+
+    fn release(&self, size: usize) { self.used.fetch_sub(size, Ordering::Relaxed); }
+
+    // gives back the bytes reserved for a grow if it failed
+    fn resized<H>(
+        &self,
+        old: Layout,
+        new: Layout,
+        result: Result<MemoryBlock<H>, AllocErr>,
+    ) -> Result<MemoryBlock<H>, AllocErr> {
+        match result {
+            Ok(memory_block) => Ok(MemoryBlock {
+                handle: memory_block.handle,
+                size: new.size(),
+            }),
+            Err(err) => {
+                self.release(new.size() - old.size());
+                Err(err)
+            }
+        }
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for QuotaStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for QuotaStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for QuotaStorage<S> {}
+
+unsafe impl<S: Storage> Storage for QuotaStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(layout.into(), layout.size())?;
+        self.storage
+            .allocate_nonempty(layout)
+            .map(|memory_block| exact(memory_block, layout))
+            .inspect_err(|_| self.release(layout.size()))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.deallocate_nonempty(handle, layout);
+        self.release(layout.size());
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(layout.into(), layout.size())?;
+        self.storage
+            .allocate_nonempty_zeroed(layout)
+            .map(|memory_block| exact(memory_block, layout))
+            .inspect_err(|_| self.release(layout.size()))
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for QuotaStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(new, new.size() - old.size())?;
+        let result = self.storage.grow(handle, old, new);
+        self.resized(old, new, result)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(new, new.size() - old.size())?;
+        let result = self.storage.grow_zeroed(handle, old, new);
+        self.resized(old, new, result)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shrink(handle, old, new)?;
+        self.release(old.size() - new.size());
+        Ok(MemoryBlock {
+            handle: memory_block.handle,
+            size: new.size(),
+        })
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for QuotaStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(layout.into(), layout.size())?;
+        self.storage
+            .shared_allocate_nonempty(layout)
+            .map(|memory_block| exact(memory_block, layout))
+            .inspect_err(|_| self.release(layout.size()))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.shared_deallocate_nonempty(handle, layout);
+        self.release(layout.size());
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(layout.into(), layout.size())?;
+        self.storage
+            .shared_allocate_nonempty_zeroed(layout)
+            .map(|memory_block| exact(memory_block, layout))
+            .inspect_err(|_| self.release(layout.size()))
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for QuotaStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(new, new.size() - old.size())?;
+        let result = self.storage.shared_grow(handle, old, new);
+        self.resized(old, new, result)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.reserve(new, new.size() - old.size())?;
+        let result = self.storage.shared_grow_zeroed(handle, old, new);
+        self.resized(old, new, result)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_shrink(handle, old, new)?;
+        self.release(old.size() - new.size());
+        Ok(MemoryBlock {
+            handle: memory_block.handle,
+            size: new.size(),
+        })
+    }
+}
+
+// any slack in the block isn't paid for, so it can't be handed out
+fn exact<H>(memory_block: NonEmptyMemoryBlock<H>, layout: NonEmptyLayout) -> NonEmptyMemoryBlock<H> {
+    NonEmptyMemoryBlock {
+        handle: memory_block.handle,
+        size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+    }
+}
+
+#[test]
+fn quota() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = QuotaStorage::with_max_size(system, 64, 48);
+
+    let layout = Layout::new::<[u8; 32]>();
+    let a = storage.allocate(layout).unwrap();
+    assert_eq!(storage.used(), 32);
+
+    // larger than the maximum allocation size
+    assert!(storage.allocate(Layout::new::<[u8; 56]>()).is_err());
+
+    unsafe {
+        let a = storage.grow(a.handle, layout, Layout::new::<[u8; 48]>()).unwrap();
+        assert_eq!(storage.remaining(), 16);
+
+        // over budget
+        assert!(storage.allocate(layout).is_err());
+        assert_eq!(storage.used(), 48);
+
+        let a = storage
+            .shrink(a.handle, Layout::new::<[u8; 48]>(), Layout::new::<[u8; 16]>())
+            .unwrap();
+        let b = storage.allocate(layout).unwrap();
+        assert_eq!(storage.remaining(), 16);
+
+        storage.deallocate(a.handle, Layout::new::<[u8; 16]>());
+        storage.deallocate(b.handle, layout);
+    }
+
+    assert_eq!(storage.used(), 0);
+
+    crate::storage_conformance!(
+        QuotaStorage::new(system, usize::MAX),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -10,7 +10,7 @@ use core::{
 
 use crate::{
     affix::{OffsetHandle, TypedLayoutProvider},
-    AffixStorage, Storage,
+    AffixStorage, AllocErr, Storage, TryCloneIn,
 };
 
 type RcStore<S, I, A> = crate::AffixStorage<TypedLayoutProvider<Counters<I, A>>, TypedLayoutProvider<()>, S>;
@@ -198,7 +198,7 @@ where
     S: Storage + OffsetHandle,
 {
     let store_ptr = Storage::get(storage, handle);
-    let ptr = ptr::from_raw_parts::<T>(store_ptr.as_ptr().cast(), meta);
+    let ptr = ptr::from_raw_parts::<T>(store_ptr.as_ptr().cast::<u8>(), meta);
     let layout = Layout::for_value_raw(ptr);
     let (counters, _) = storage.split(store_ptr, layout);
     let counters = counters.as_ref();
@@ -232,7 +232,7 @@ fn drop_slow<T, I, A, K, S>(
             let storage = scope.extra_mut();
 
             let ptr = Storage::get_mut(storage, handle);
-            let ptr = ptr::from_raw_parts_mut::<T>(ptr.as_ptr().cast(), meta);
+            let ptr = ptr::from_raw_parts_mut::<T>(ptr.as_ptr().cast::<u8>(), meta);
             ptr.drop_in_place();
         }
     } else {
@@ -309,7 +309,7 @@ where
     fn counters(&self) -> &Counters<I, A> {
         unsafe {
             let store_ptr = self.storage.get(self.handle);
-            let ptr = ptr::from_raw_parts::<T>(store_ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts::<T>(store_ptr.as_ptr().cast::<u8>(), self.meta);
             let layout = Layout::for_value_raw(ptr);
             let (counters, _) = self.storage.split(store_ptr, layout);
             &*counters.as_ptr()
@@ -361,6 +361,26 @@ where
     S: Storage + OffsetHandle,
 {
     pub fn new_in(value: T, storage: S) -> Self { crate::boxed::Box::new_in(value, AffixStorage::new(storage)).into() }
+
+    pub fn try_new_in(value: T, storage: S) -> Result<Self, AllocErr> {
+        crate::boxed::Box::try_new_in(value, AffixStorage::new(storage)).map(Into::into)
+    }
+}
+
+impl<I, A, T, S, S2> TryCloneIn<S2> for RefCounted<T, I, A, StrongKind, S>
+where
+    I: DynamicCounter,
+    A: Counter,
+    T: Thin + Clone,
+    S: Storage + OffsetHandle,
+    S2: Storage + OffsetHandle,
+{
+    type Output = RefCounted<T, I, A, StrongKind, S2>;
+
+    fn try_clone_in(&self, storage: S2) -> Result<Self::Output, AllocErr> {
+        let bx = crate::boxed::Box::try_uninit_in(AffixStorage::new(storage))?;
+        Ok(crate::boxed::Box::write(bx, T::clone(self)).into())
+    }
 }
 
 impl<I, A, K, T, S> RefCounted<T, I, A, K, S>
@@ -377,7 +397,7 @@ where
     {
         unsafe {
             let ptr = self.storage.get(self.handle);
-            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts::<T>(ptr.as_ptr().cast::<u8>(), self.meta);
             let ptr: *const U = ptr;
 
             let meta = ptr::metadata(ptr);
@@ -422,7 +442,7 @@ where
     fn deref(&self) -> &Self::Target {
         unsafe {
             let store_ptr = self.storage.get(self.handle);
-            let ptr = ptr::from_raw_parts::<T>(store_ptr.as_ptr().cast(), self.meta);
+            let ptr = ptr::from_raw_parts::<T>(store_ptr.as_ptr().cast::<u8>(), self.meta);
             &*ptr
         }
     }
@@ -437,7 +457,8 @@ fn test() {
     let bx = crate::boxed::Box::try_uninit_in(storage).unwrap();
     let bx = crate::boxed::Box::write(bx, 0);
     let x: Rc<usize, _> = Rc::from(bx);
-    assert_eq!(core::mem::size_of_val(&x), core::mem::size_of::<usize>());
+    // the storage reference, and the handle's offset into the single allocation
+    assert_eq!(core::mem::size_of_val(&x), 2 * core::mem::size_of::<usize>());
     let y = x.clone();
     assert!(crate::boxed::Box::<u8, _>::try_uninit_in(storage).is_err());
     assert_eq!(*y, 0);
@@ -0,0 +1,461 @@
+use core::{alloc::Layout, cell::UnsafeCell, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordOp {
+    Allocate,
+    AllocateZeroed,
+    Deallocate,
+    Grow,
+    GrowZeroed,
+    Shrink,
+}
+
+/// One operation recorded by a [`RecordingStorage`]
+///
+/// Allocations are identified by their address, so a deallocation or resize refers to
+/// the latest successful record whose `addr` is its `old_addr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Record {
+    pub op: RecordOp,
+    /// The requested size, for resizes this is the new size
+    pub size: usize,
+    pub align: usize,
+    /// The size of the allocation that is deallocated or resized
+    pub old_size: usize,
+    pub old_align: usize,
+    /// The address of the allocation that is deallocated or resized, or zero if it is zero-sized
+    pub old_addr: usize,
+    /// The address of the resulting allocation, or zero if it is zero-sized or there isn't one
+    pub addr: usize,
+    /// The size of the resulting memory block, or `None` if the operation failed
+    pub result: Option<usize>,
+}
+
+impl Record {
+    const fn new(op: RecordOp, layout: Layout) -> Self {
+        Self {
+            op,
+            size: layout.size(),
+            align: layout.align(),
+            old_size: 0,
+            old_align: 0,
+            old_addr: 0,
+            addr: 0,
+            result: None,
+        }
+    }
+
+    fn layout(&self) -> Option<Layout> { Layout::from_size_align(self.size, self.align).ok() }
+
+    fn old_layout(&self) -> Option<Layout> { Layout::from_size_align(self.old_size, self.old_align).ok() }
+}
+
+struct Buffer<'a> {
+    records: &'a mut [Record],
+    len: usize,
+    dropped: usize,
+}
+
+/// A storage that records every operation into a caller provided buffer
+///
+/// Once the buffer is full, further operations are only counted, see [`dropped`](Self::dropped).
+/// The recorded trace can be re-executed with a [`ReplayStorage`].
+#[must_use = "storages don't do anything unless they are used"]
+pub struct RecordingStorage<'a, S> {
+    storage: S,
+    buffer: UnsafeCell<Buffer<'a>>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Sync> Sync for RecordingStorage<'_, S> {}
+
+impl<'a, S> RecordingStorage<'a, S> {
+    pub const fn new(storage: S, records: &'a mut [Record]) -> Self {
+        Self {
+            storage,
+            buffer: UnsafeCell::new(Buffer {
+                records,
+                len: 0,
+                dropped: 0,
+            }),
+            lock: SpinLock::new(),
+        }
+    }
+
+    /// The operations that were recorded so far
+    pub fn records(&mut self) -> &[Record] {
+        let buffer = self.buffer.get_mut();
+        &buffer.records[..buffer.len]
+    }
+
+    /// The number of operations that didn't fit in the buffer
+    pub fn dropped(&self) -> usize {
+        let _guard = self.lock.lock();
+        unsafe { (*self.buffer.get()).dropped }
+    }
+
+    fn push(&self, record: Record) {
+        let _guard = self.lock.lock();
+        let buffer = unsafe { &mut *self.buffer.get() };
+
+        if let Some(slot) = buffer.records.get_mut(buffer.len) {
+            *slot = record;
+            buffer.len += 1;
+        } else {
+            buffer.dropped += 1;
+        }
+    }
+}
+
+impl<S: Storage> RecordingStorage<'_, S> {
+    unsafe fn addr(&self, handle: S::Handle, layout: Layout) -> usize {
+        if layout.size() == 0 {
+            0
+        } else {
+            self.storage.get(handle).as_ptr() as usize
+        }
+    }
+
+    fn allocated(
+        &self,
+        op: RecordOp,
+        layout: NonEmptyLayout,
+        result: Result<NonEmptyMemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<NonEmptyMemoryBlock<S::Handle>, AllocErr> {
+        let mut record = Record::new(op, layout.into());
+
+        if let Ok(memory_block) = &result {
+            record.addr = unsafe { self.addr(memory_block.handle, layout.into()) };
+            record.result = Some(memory_block.size.get());
+        }
+
+        self.push(record);
+        result
+    }
+
+    unsafe fn deallocated(&self, handle: S::Handle, layout: NonEmptyLayout) {
+        self.push(Record {
+            old_size: layout.size(),
+            old_align: layout.align(),
+            old_addr: self.addr(handle, layout.into()),
+            ..Record::new(RecordOp::Deallocate, layout.into())
+        });
+    }
+
+    fn resized(
+        &self,
+        op: RecordOp,
+        old: Layout,
+        old_addr: usize,
+        new: Layout,
+        result: Result<MemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+        let mut record = Record {
+            old_size: old.size(),
+            old_align: old.align(),
+            old_addr,
+            ..Record::new(op, new)
+        };
+
+        if let Ok(memory_block) = &result {
+            record.addr = unsafe { self.addr(memory_block.handle, new) };
+            record.result = Some(memory_block.size);
+        }
+
+        self.push(record);
+        result
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for RecordingStorage<'_, S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for RecordingStorage<'_, S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for RecordingStorage<'_, S> {}
+
+unsafe impl<S: Storage> Storage for RecordingStorage<'_, S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.allocate_nonempty(layout);
+        self.allocated(RecordOp::Allocate, layout, result)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.deallocated(handle, layout);
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.allocate_nonempty_zeroed(layout);
+        self.allocated(RecordOp::AllocateZeroed, layout, result)
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for RecordingStorage<'_, S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let old_addr = self.addr(handle, old);
+        let result = self.storage.grow(handle, old, new);
+        self.resized(RecordOp::Grow, old, old_addr, new, result)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let old_addr = self.addr(handle, old);
+        let result = self.storage.grow_zeroed(handle, old, new);
+        self.resized(RecordOp::GrowZeroed, old, old_addr, new, result)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let old_addr = self.addr(handle, old);
+        let result = self.storage.shrink(handle, old, new);
+        self.resized(RecordOp::Shrink, old, old_addr, new, result)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for RecordingStorage<'_, S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocated(
+            RecordOp::Allocate,
+            layout,
+            self.storage.shared_allocate_nonempty(layout),
+        )
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.deallocated(handle, layout);
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.shared_allocate_nonempty_zeroed(layout);
+        self.allocated(RecordOp::AllocateZeroed, layout, result)
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for RecordingStorage<'_, S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let old_addr = self.addr(handle, old);
+        let result = self.storage.shared_grow(handle, old, new);
+        self.resized(RecordOp::Grow, old, old_addr, new, result)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let old_addr = self.addr(handle, old);
+        let result = self.storage.shared_grow_zeroed(handle, old, new);
+        self.resized(RecordOp::GrowZeroed, old, old_addr, new, result)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let old_addr = self.addr(handle, old);
+        let result = self.storage.shared_shrink(handle, old, new);
+        self.resized(RecordOp::Shrink, old, old_addr, new, result)
+    }
+}
+
+/// Re-executes a trace recorded by a [`RecordingStorage`] against another storage
+///
+/// The handles that are created while replaying are kept in a caller provided buffer, with one slot
+/// per record. Whatever is still live at the end of the trace is deallocated, so replaying never leaks.
+pub struct ReplayStorage<S> {
+    pub storage: S,
+}
+
+impl<S: ResizableStorage> ReplayStorage<S> {
+    pub const fn new(storage: S) -> Self { Self { storage } }
+
+    /// Replays every record in `trace`
+    ///
+    /// # Errors
+    ///
+    /// Returns the index of the first record that didn't have the same outcome as when it was recorded,
+    /// the rest of the trace is skipped
+    ///
+    /// # Panics
+    ///
+    /// if there are fewer slots in `handles` than records in `trace`
+    pub fn replay(&mut self, trace: &[Record], handles: &mut [Option<S::Handle>]) -> Result<(), usize> {
+        assert!(
+            trace.len() <= handles.len(),
+            "there are {} records, but only {} handle slots",
+            trace.len(),
+            handles.len()
+        );
+
+        let handles = &mut handles[..trace.len()];
+        handles.fill(None);
+
+        let diverged = (0..trace.len()).find(|&index| !self.step(trace, handles, index));
+
+        for (handle, record) in handles.iter_mut().zip(trace) {
+            if let (Some(handle), Some(layout)) = (handle.take(), record.layout()) {
+                unsafe { self.storage.deallocate(handle, layout) }
+            }
+        }
+
+        diverged.map_or(Ok(()), Err)
+    }
+
+    // replays the record at `index`, and returns whether it had the same outcome as when it was recorded
+    fn step(&mut self, trace: &[Record], handles: &mut [Option<S::Handle>], index: usize) -> bool {
+        let record = &trace[index];
+        let Some(layout) = record.layout() else { return false };
+
+        let result = match record.op {
+            RecordOp::Allocate => self.storage.allocate(layout),
+            RecordOp::AllocateZeroed => self.storage.allocate_zeroed(layout),
+            RecordOp::Deallocate | RecordOp::Grow | RecordOp::GrowZeroed | RecordOp::Shrink => {
+                let Some(old) = record.old_layout() else { return false };
+
+                // the latest successful record that created the allocation, zero-sized allocations weren't recorded
+                let source = if old.size() == 0 {
+                    None
+                } else {
+                    let Some(source) = trace[..index]
+                        .iter()
+                        .rposition(|source| source.result.is_some() && source.addr == record.old_addr)
+                    else {
+                        return false
+                    };
+                    Some(source)
+                };
+
+                let handle = match source {
+                    None => unsafe { Handle::dangling(old.align()) },
+                    Some(source) => match handles[source].take() {
+                        Some(handle) => handle,
+                        None => return false,
+                    },
+                };
+
+                if record.op == RecordOp::Deallocate {
+                    unsafe { self.storage.deallocate(handle, old) }
+                    return true
+                }
+
+                let result = unsafe {
+                    match record.op {
+                        RecordOp::Grow => self.storage.grow(handle, old, layout),
+                        RecordOp::GrowZeroed => self.storage.grow_zeroed(handle, old, layout),
+                        // only shrinks are left
+                        _ => self.storage.shrink(handle, old, layout),
+                    }
+                };
+
+                // a failed resize leaves the old allocation where it was
+                if let (Err(_), Some(source)) = (&result, source) {
+                    handles[source] = Some(handle);
+                }
+
+                result
+            }
+        };
+
+        if let Ok(memory_block) = &result {
+            if layout.size() != 0 {
+                handles[index] = Some(memory_block.handle);
+            }
+        }
+
+        result.is_ok() == record.result.is_some()
+    }
+}
+
+#[test]
+fn record() {
+    let layout = Layout::new::<[u8; 32]>();
+    let mut records = [Record::new(RecordOp::Allocate, layout); 8];
+
+    let mut recorded = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = RecordingStorage::new(&mut recorded, &mut records);
+
+    let a = storage.allocate(layout).unwrap();
+    let b = storage.allocate_zeroed(layout).unwrap();
+
+    unsafe {
+        let a = storage.grow(a.handle, layout, Layout::new::<[u8; 64]>()).unwrap();
+        storage.deallocate(b.handle, layout);
+        storage
+            .shrink(a.handle, Layout::new::<[u8; 64]>(), Layout::new::<[u8; 16]>())
+            .unwrap();
+    }
+
+    // the last allocation is never deallocated
+    let trace = storage.records().to_vec();
+    assert_eq!(storage.dropped(), 0);
+    assert_eq!(trace.len(), 5);
+
+    let mut replayed = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut handles = [None; 8];
+    ReplayStorage::new(&replayed).replay(&trace, &mut handles).unwrap();
+
+    assert_eq!(replayed.live_allocations(), 0);
+    let mut events = recorded.events().to_vec();
+    events.push(crate::Event::Deallocate(Layout::new::<[u8; 16]>()));
+    replayed.assert_events(&events);
+
+    let mut records = std::vec![Record::new(RecordOp::Allocate, layout); 256];
+    crate::storage_conformance!(
+        RecordingStorage::new(crate::AllocatorStorage::new(std::alloc::System), &mut records),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -0,0 +1,230 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    os, spin_lock::SpinLock, AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A bump storage over a large reserved range of address space
+///
+/// Pages are only committed as the bump pointer reaches them, so the reservation
+/// can be much larger than the memory that's actually used, and allocations never move
+#[must_use = "storages don't do anything unless they are used"]
+pub struct ReserveCommitStorage {
+    start: NonNull<u8>,
+    reserved: usize,
+    committed: AtomicUsize,
+    offset: AtomicUsize,
+    lock: SpinLock,
+}
+
+unsafe impl Send for ReserveCommitStorage {}
+unsafe impl Sync for ReserveCommitStorage {}
+
+impl ReserveCommitStorage {
+    pub fn new(reserve: usize) -> Self { Self::try_new(reserve).unwrap_or_else(AllocErr::handle) }
+
+    /// # Panics
+    ///
+    /// if `Layout::from_size_align(reserve, page_size)` returns Err
+    pub fn try_new(reserve: usize) -> Result<Self, AllocErr> {
+        let layout = Layout::from_size_align(reserve, os::page_size()).unwrap();
+        let reserved = os::pages(reserve).ok_or_else(|| AllocErr::new(layout))?;
+        let start = unsafe { os::reserve(reserved) }.ok_or_else(|| AllocErr::new(layout))?;
+
+        Ok(Self {
+            start,
+            reserved,
+            committed: AtomicUsize::new(0),
+            offset: AtomicUsize::new(0),
+            lock: SpinLock::new(),
+        })
+    }
+
+    /// The size of the reserved address space
+    pub const fn reserved(&self) -> usize { self.reserved }
+
+    /// The number of bytes that have been committed so far
+    pub fn committed(&self) -> usize { self.committed.load(Ordering::Relaxed) }
+
+    pub fn remaining_space(&self) -> usize { self.reserved - self.offset.load(Ordering::Relaxed) }
+
+    // must be called with exclusive access to the offsets
+    unsafe fn bump(&self, layout: Layout) -> Option<NonEmptyMemoryBlock<NonNull<u8>>> {
+        // the reservation is only page aligned
+        if layout.align() > os::page_size() {
+            return None
+        }
+
+        let offset = self.offset.load(Ordering::Relaxed);
+        let start = offset.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+
+        if end > self.reserved {
+            return None
+        }
+
+        let committed = self.committed.load(Ordering::Relaxed);
+
+        if end > committed {
+            // commit at least as much as is already committed, so that
+            // the number of commits is logarithmic in the memory used
+            let target = os::pages(end.max(committed * 2))?.min(self.reserved);
+
+            if !os::commit(
+                NonNull::new_unchecked(self.start.as_ptr().add(committed)),
+                target - committed,
+            ) {
+                return None
+            }
+
+            self.committed.store(target, Ordering::Relaxed);
+        }
+
+        self.offset.store(end, Ordering::Relaxed);
+
+        Some(NonEmptyMemoryBlock {
+            handle: NonNull::new_unchecked(self.start.as_ptr().add(start)),
+            size: NonZeroUsize::new_unchecked(end - start),
+        })
+    }
+}
+
+impl Drop for ReserveCommitStorage {
+    fn drop(&mut self) { unsafe { os::unmap(self.start, self.reserved) } }
+}
+
+unsafe impl FromPtr for ReserveCommitStorage {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl OffsetHandle for ReserveCommitStorage {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for ReserveCommitStorage {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedGetMut for ReserveCommitStorage {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl MultiStorage for ReserveCommitStorage {}
+
+unsafe impl Storage for ReserveCommitStorage {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        unsafe { self.bump(layout.into()) }.ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl ResizableStorage for ReserveCommitStorage {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for ReserveCommitStorage {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let _guard = self.lock.lock();
+        unsafe { self.bump(layout.into()) }.ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl SharedResizableStorage for ReserveCommitStorage {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn reserve_commit() {
+    let mut storage = ReserveCommitStorage::new(1 << 30);
+    let page_size = os::page_size();
+    assert_eq!(storage.committed(), 0);
+
+    let first = storage.allocate(Layout::new::<u64>()).unwrap().handle;
+    unsafe { first.cast::<u64>().as_ptr().write(10) }
+    assert_eq!(storage.committed(), page_size);
+
+    let big = Layout::from_size_align(page_size * 3, 8).unwrap();
+    let second = storage.allocate(big).unwrap().handle;
+    unsafe { second.as_ptr().write_bytes(1, big.size()) }
+    assert_eq!(storage.committed(), page_size * 4);
+
+    // allocations never move as more memory is committed
+    assert_eq!(unsafe { first.cast::<u64>().as_ptr().read() }, 10);
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
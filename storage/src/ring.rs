@@ -0,0 +1,345 @@
+use core::{alloc::Layout, cell::UnsafeCell, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const ALIGN: usize = 16;
+// every block starts with its size, padded so that the user's memory is aligned
+const HEADER: usize = ALIGN;
+// set in the header of blocks that were freed, but not yet reclaimed
+const FREE: usize = 1;
+
+// blocks are allocated at `head`, and reclaimed from `tail` once they are freed
+#[derive(Clone, Copy)]
+struct Cursor {
+    head: usize,
+    tail: usize,
+    used: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Region(*mut u8);
+
+impl Region {
+    const unsafe fn header(self, block: usize) -> *mut usize { self.0.add(block).cast() }
+}
+
+impl Cursor {
+    fn block_size(layout: Layout) -> Option<usize> {
+        // this is necessary so that the storage can be moved
+        // between allocation and getting the pointer
+        if ALIGN < layout.align() {
+            return None
+        }
+
+        Some(layout.size().checked_add(HEADER + ALIGN - 1)? & !(ALIGN - 1))
+    }
+
+    unsafe fn allocate(&mut self, region: Region, capacity: usize, layout: Layout) -> Option<(usize, usize)> {
+        let size = Self::block_size(layout)?;
+
+        if self.used == 0 {
+            self.head = 0;
+            self.tail = 0;
+        }
+
+        let block = if self.head > self.tail || self.used == 0 {
+            if size <= capacity - self.head {
+                self.head
+            } else if size <= self.tail {
+                // skip the rest of the region, it is reclaimed when the tail reaches it
+                let rest = capacity - self.head;
+                if rest != 0 {
+                    region.header(self.head).write(rest | FREE);
+                    self.used += rest;
+                }
+                0
+            } else {
+                return None
+            }
+        } else if size <= self.tail - self.head {
+            self.head
+        } else {
+            return None
+        };
+
+        region.header(block).write(size);
+        self.head = block + size;
+        self.used += size;
+
+        Some((block + HEADER, size - HEADER))
+    }
+
+    unsafe fn deallocate(&mut self, region: Region, capacity: usize, offset: usize) {
+        *region.header(offset - HEADER) |= FREE;
+
+        while self.used != 0 {
+            let header = region.header(self.tail).read();
+
+            if header & FREE == 0 {
+                break
+            }
+
+            let size = header & !FREE;
+            self.used -= size;
+            self.tail += size;
+
+            if self.tail == capacity {
+                self.tail = 0;
+            }
+        }
+    }
+}
+
+/// A storage that allocates from a circular region
+///
+/// Space is reclaimed once the oldest allocations are freed, so this works
+/// best when allocations are freed in roughly the same order they were allocated in.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct RingStorage<S: Storage> {
+    storage: S,
+    start: S::Handle,
+    layout: Layout,
+    capacity: usize,
+    cursor: UnsafeCell<Cursor>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Storage + Sync> Sync for RingStorage<S> where S::Handle: Sync {}
+
+#[derive(Clone, Copy)]
+pub struct RingHandle(usize);
+
+unsafe impl Handle for RingHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl RingHandle {
+    #[must_use = "`RingHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+}
+
+impl<S: Storage> RingStorage<S> {
+    pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
+
+    /// # Panics
+    ///
+    /// if `Layout::from_size_align(space, 16)` returns Err
+    pub fn try_new(mut storage: S, space: usize) -> Result<Self, AllocErr> {
+        let layout = Layout::from_size_align(space, ALIGN).unwrap();
+        let memory_block = storage.allocate(layout)?;
+
+        Ok(Self {
+            storage,
+            start: memory_block.handle,
+            layout,
+            capacity: memory_block.size & !(ALIGN - 1),
+            cursor: UnsafeCell::new(Cursor {
+                head: 0,
+                tail: 0,
+                used: 0,
+            }),
+            lock: SpinLock::new(),
+        })
+    }
+
+    pub const fn capacity(&self) -> usize { self.capacity }
+
+    /// The number of bytes that are allocated, or freed but not yet reclaimed
+    pub fn used(&self) -> usize {
+        let _guard = self.lock.lock();
+        unsafe { (*self.cursor.get()).used }
+    }
+}
+
+impl<S: Storage> Drop for RingStorage<S> {
+    fn drop(&mut self) { unsafe { self.storage.deallocate(self.start, self.layout) } }
+}
+
+unsafe impl<S: Storage> OffsetHandle for RingStorage<S> {
+    unsafe fn offset(&mut self, RingHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        RingHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedOffsetHandle for RingStorage<S> {
+    unsafe fn shared_offset(&self, RingHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        RingHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: Storage> FromPtr for RingStorage<S> {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        let origin = self.storage.get(self.start);
+        RingHandle(ptr.as_ptr().offset_from(origin.as_ptr()) as usize)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for RingStorage<S> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.shared_get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+}
+
+impl<S: SharedGetMut> MultiStorage for RingStorage<S> {}
+
+unsafe impl<S: Storage> Storage for RingStorage<S> {
+    type Handle = RingHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let region = Region(unsafe { self.storage.get_mut(self.start).as_ptr() });
+
+        unsafe { self.cursor.get_mut().allocate(region, self.capacity, layout.into()) }
+            .map(|(offset, size)| NonEmptyMemoryBlock {
+                handle: RingHandle(offset),
+                size: unsafe { NonZeroUsize::new_unchecked(size) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, RingHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let region = Region(self.storage.get_mut(self.start).as_ptr());
+        self.cursor.get_mut().deallocate(region, self.capacity, offset);
+    }
+}
+
+unsafe impl<S: SharedGetMut> ResizableStorage for RingStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedStorage for RingStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let _guard = self.lock.lock();
+        let region = Region(unsafe { self.storage.shared_get_mut(self.start).as_ptr() });
+
+        unsafe { (*self.cursor.get()).allocate(region, self.capacity, layout.into()) }
+            .map(|(offset, size)| NonEmptyMemoryBlock {
+                handle: RingHandle(offset),
+                size: unsafe { NonZeroUsize::new_unchecked(size) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, RingHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let _guard = self.lock.lock();
+        let region = Region(self.storage.shared_get_mut(self.start).as_ptr());
+        (*self.cursor.get()).deallocate(region, self.capacity, offset);
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedResizableStorage for RingStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn ring() {
+    let mut storage = RingStorage::new(crate::AllocatorStorage::new(std::alloc::System), 256);
+
+    let layout = Layout::new::<[u8; 48]>();
+    let blocks = [(); 4].map(|()| storage.allocate(layout).unwrap().handle);
+    assert!(storage.allocate(Layout::new::<u8>()).is_err());
+
+    unsafe {
+        // the oldest allocation is still live, so nothing can be reclaimed
+        storage.deallocate(blocks[1], layout);
+        assert_eq!(storage.used(), 256);
+        assert!(storage.allocate(Layout::new::<u8>()).is_err());
+
+        storage.deallocate(blocks[0], layout);
+        assert_eq!(storage.used(), 128);
+
+        let big = Layout::new::<[u8; 100]>();
+        let wrapped = storage.allocate(big).unwrap().handle;
+        assert_eq!(wrapped.0, HEADER);
+
+        storage.deallocate(blocks[2], layout);
+        storage.deallocate(blocks[3], layout);
+        storage.deallocate(wrapped, big);
+        assert_eq!(storage.used(), 0);
+    }
+
+    let storage = RingStorage::new(crate::AllocatorStorage::new(std::alloc::System), 1 << 16);
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
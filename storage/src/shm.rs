@@ -0,0 +1,266 @@
+use core::{
+    alloc::Layout,
+    mem,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::io;
+
+use crate::{
+    os, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+// the start of the region holds the end of the last allocation, it's zero in a new region
+const HEADER: usize = mem::size_of::<AtomicUsize>();
+
+/// A bump storage over a named shared memory region,
+/// using POSIX shared memory on unix and named file mappings on windows
+///
+/// Handles are offsets from the start of the region, so processes that map the
+/// same region at different addresses can exchange handles. The bump pointer is
+/// kept inside the region, so every process allocates from the same memory.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct ShmStorage {
+    start: NonNull<u8>,
+    len: usize,
+}
+
+unsafe impl Send for ShmStorage {}
+unsafe impl Sync for ShmStorage {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShmHandle(usize);
+
+unsafe impl Handle for ShmHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl ShmHandle {
+    /// A handle to the memory at `offset` bytes from the start of the region
+    pub const fn new(offset: usize) -> Self { Self(offset) }
+
+    #[must_use = "`ShmHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+
+    /// The offset of this allocation from the start of the region
+    pub const fn offset(self) -> usize { self.0 }
+}
+
+impl ShmStorage {
+    /// Maps the first `len` bytes of the shared memory region called `name`,
+    /// creating it if it doesn't exist yet
+    ///
+    /// # Errors
+    ///
+    /// If the region can't be created or mapped
+    pub fn open(name: &str, len: usize) -> io::Result<Self> {
+        if len < HEADER {
+            return Err(io::ErrorKind::InvalidInput.into())
+        }
+
+        let start = unsafe { os::map_shared(name, len) }.ok_or_else(io::Error::last_os_error)?;
+
+        Ok(Self { start, len })
+    }
+
+    /// Removes the name of the shared memory region called `name`,
+    /// so that it's freed once every process unmaps it
+    ///
+    /// On windows this does nothing, because named regions are freed as soon as they are unmapped
+    ///
+    /// # Errors
+    ///
+    /// If the name couldn't be removed
+    pub fn unlink(name: &str) -> io::Result<()> {
+        if os::unlink_shared(name) {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub const fn len(&self) -> usize { self.len }
+
+    pub const fn is_empty(&self) -> bool { self.len == HEADER }
+
+    /// The number of bytes from the start of the region that are allocated, by any process
+    pub fn used(&self) -> usize { self.top().load(Ordering::Relaxed).max(HEADER) }
+
+    fn top(&self) -> &AtomicUsize { unsafe { &*self.start.as_ptr().cast() } }
+
+    // the new end of the allocation, if it can be placed at `offset`
+    fn bump(&self, offset: usize, layout: Layout) -> Option<(usize, usize)> {
+        // the mapping is only page aligned
+        if layout.align() > os::page_size() {
+            return None
+        }
+
+        let offset = offset.max(HEADER);
+        let start = offset.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        (end <= self.len).then_some((start, end))
+    }
+}
+
+impl Drop for ShmStorage {
+    fn drop(&mut self) { unsafe { os::unmap_file(self.start, self.len) } }
+}
+
+unsafe impl OffsetHandle for ShmStorage {
+    unsafe fn offset(&mut self, ShmHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        ShmHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for ShmStorage {
+    unsafe fn shared_offset(&self, ShmHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        ShmHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl FromPtr for ShmStorage {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        ShmHandle(ptr.as_ptr().offset_from(self.start.as_ptr()) as usize)
+    }
+}
+
+unsafe impl SharedGetMut for ShmStorage {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
+}
+
+impl MultiStorage for ShmStorage {}
+
+unsafe impl Storage for ShmStorage {
+    type Handle = ShmHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        NonNull::new_unchecked(self.start.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
+
+    // other processes may be allocating at the same time, so this always goes through the shared path
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty(layout)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl ResizableStorage for ShmStorage {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for ShmStorage {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let layout = Layout::from(layout);
+
+        let mut start = 0;
+        let mut end = 0;
+        self.top()
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |offset| {
+                (start, end) = self.bump(offset, layout)?;
+                Some(end)
+            })
+            .map_err(|_| AllocErr::new(layout))?;
+
+        Ok(NonEmptyMemoryBlock {
+            handle: ShmHandle(start),
+            size: unsafe { NonZeroUsize::new_unchecked(end - start) },
+        })
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, _: NonEmptyLayout) {}
+}
+
+unsafe impl SharedResizableStorage for ShmStorage {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn shm() {
+    let name = std::format!("storage-shm-{}", std::process::id());
+
+    // two mappings of the same region stand in for two processes
+    let mut first = ShmStorage::open(&name, 1 << 16).unwrap();
+    let second = ShmStorage::open(&name, 1 << 16).unwrap();
+    ShmStorage::unlink(&name).unwrap();
+    assert_ne!(first.start, second.start);
+
+    let handle = first.allocate(Layout::new::<u64>()).unwrap().handle;
+    assert_eq!(handle.offset(), HEADER);
+    unsafe { first.get_mut(handle).cast::<u64>().as_ptr().write(10) }
+    assert_eq!(unsafe { second.get(handle).cast::<u64>().as_ptr().read() }, 10);
+
+    // both mappings bump the same pointer
+    let other = second.shared_allocate(Layout::new::<u64>()).unwrap().handle;
+    assert_eq!(other.offset(), HEADER + 8);
+    assert_eq!(first.used(), HEADER + 16);
+
+    drop(second);
+    crate::storage_conformance!(first, resizable, shared, shared_resizable);
+}
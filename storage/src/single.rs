@@ -9,25 +9,107 @@ use core::{
 };
 
 use crate::{
-    AllocErr, FromPtr, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle, SharedGetMut,
-    SharedOffsetHandle, SharedStorage, Storage,
+    AlignGuarantee, AllocErr, FromPtr, Handle, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    SharedGetMut, SharedOffsetHandle, SharedStorage, Storage,
 };
 
+/// A `T` that is aligned to at least `ALIGN` bytes
+#[repr(C)]
+pub struct Aligned<T, const ALIGN: usize>
+where
+    Alignment<ALIGN>: ValidAlignment,
+{
+    align: [<Alignment<ALIGN> as ValidAlignment>::Align; 0],
+    pub value: T,
+}
+
+/// A [`SingleStackStorage`] whose buffer is aligned to at least `ALIGN` bytes
+pub type AlignedSingleStackStorage<T, const ALIGN: usize> = SingleStackStorage<Aligned<T, ALIGN>>;
+
+/// An alignment that can be used with [`Aligned`]
+pub struct Alignment<const ALIGN: usize>;
+
+/// Implemented for every [`Alignment`] that is a power of two, from 1 up to 2<sup>29</sup>
+pub trait ValidAlignment {
+    /// A zero-sized type with this alignment
+    type Align;
+}
+
+macro_rules! alignments {
+    ($($name:ident = $align:literal)*) => {$(
+        #[doc(hidden)]
+        #[repr(align($align))]
+        pub struct $name;
+
+        impl ValidAlignment for Alignment<$align> {
+            type Align = $name;
+        }
+    )*};
+}
+
+alignments! {
+    Align1 = 1 Align2 = 2 Align4 = 4 Align8 = 8 Align16 = 16 Align32 = 32 Align64 = 64 Align128 = 128
+    Align256 = 256 Align512 = 512 Align1024 = 1024 Align2048 = 2048 Align4096 = 4096 Align8192 = 8192
+    Align16384 = 16384 Align32768 = 32768 Align65536 = 65536 Align131072 = 131_072 Align262144 = 262_144
+    Align524288 = 524_288 Align1048576 = 1_048_576 Align2097152 = 2_097_152 Align4194304 = 4_194_304
+    Align8388608 = 8_388_608 Align16777216 = 16_777_216 Align33554432 = 33_554_432
+    Align67108864 = 67_108_864 Align134217728 = 134_217_728 Align268435456 = 268_435_456
+    Align536870912 = 536_870_912
+}
+
+impl<T, const ALIGN: usize> Aligned<T, ALIGN>
+where
+    Alignment<ALIGN>: ValidAlignment,
+{
+    pub const fn new(value: T) -> Self { Self { align: [], value } }
+}
+
 pub struct SingleStackStorage<T> {
     memory: UnsafeCell<MaybeUninit<T>>,
     allocated: AtomicBool,
 }
 pub struct OffsetSingleStackStorage<T> {
     storage: SingleStackStorage<T>,
-    offset: UnsafeCell<isize>,
+}
+
+/// A handle to the allocation in an [`OffsetSingleStackStorage`] or an
+/// [`OffsetSingleRefStorage`](crate::OffsetSingleRefStorage)
+///
+/// Each handle carries its own offset from the start of the buffer, so handles
+/// that were offset by different amounts can be used side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetSingleHandle(isize);
+
+unsafe impl Handle for OffsetSingleHandle {
+    // real offsets never get anywhere close to `isize::MIN`, so the alignment is stored just above it
+    unsafe fn dangling(align: usize) -> Self { Self(isize::MIN.wrapping_add_unsigned(align)) }
+}
+
+impl OffsetSingleHandle {
+    pub(crate) const START: Self = Self(0);
+
+    #[must_use = "`OffsetSingleHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 < isize::MIN / 2 }
+
+    /// The offset of this handle from the start of the buffer
+    pub const fn offset(self) -> isize { self.0 }
+
+    #[inline]
+    pub(crate) const unsafe fn get(self, start: *mut u8) -> NonNull<u8> {
+        if self.is_dangling() {
+            NonNull::new_unchecked(self.0.wrapping_sub(isize::MIN).cast_unsigned() as *mut u8)
+        } else {
+            NonNull::new_unchecked(start.offset(self.0))
+        }
+    }
+
+    #[inline]
+    pub(crate) const fn add(self, offset: isize) -> Self { Self(self.0.wrapping_add(offset)) }
 }
 
 unsafe impl<T> Send for SingleStackStorage<T> {}
 unsafe impl<T> Sync for SingleStackStorage<T> {}
 
-unsafe impl<T> Send for OffsetSingleStackStorage<T> {}
-unsafe impl<T> Sync for OffsetSingleStackStorage<T> {}
-
 impl<T> SingleStackStorage<T> {
     pub const fn new() -> Self {
         Self {
@@ -43,12 +125,7 @@ impl<T> SingleStackStorage<T> {
         }
     }
 
-    pub const fn offsetable(self) -> OffsetSingleStackStorage<T> {
-        OffsetSingleStackStorage {
-            offset: UnsafeCell::new(0),
-            storage: self,
-        }
-    }
+    pub const fn offsetable(self) -> OffsetSingleStackStorage<T> { OffsetSingleStackStorage { storage: self } }
 }
 
 impl<T> SingleStackStorage<T> {
@@ -71,6 +148,11 @@ unsafe impl<T> SharedGetMut for SingleStackStorage<T> {
     unsafe fn shared_get_mut(&self, _: Self::Handle) -> NonNull<u8> { NonNull::new_unchecked(self.memory.get()).cast() }
 }
 
+// there is only one allocation, and it is always at the start of the `T`
+unsafe impl<T> AlignGuarantee for SingleStackStorage<T> {
+    const ALIGN: usize = mem::align_of::<T>();
+}
+
 unsafe impl<T> Storage for SingleStackStorage<T> {
     type Handle = ();
 
@@ -152,73 +234,118 @@ unsafe impl<T> SharedStorage for SingleStackStorage<T> {
 }
 
 unsafe impl<T> SharedGetMut for OffsetSingleStackStorage<T> {
-    unsafe fn shared_get_mut(&self, _: Self::Handle) -> NonNull<u8> { self.get(()) }
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
 }
 
 unsafe impl<T> OffsetHandle for OffsetSingleStackStorage<T> {
-    unsafe fn offset(&mut self, _: Self::Handle, offset: isize) -> Self::Handle { self.offset.get().write(offset) }
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle { handle.add(offset) }
 }
 
 unsafe impl<T> SharedOffsetHandle for OffsetSingleStackStorage<T> {
-    unsafe fn shared_offset(&self, _: Self::Handle, offset: isize) -> Self::Handle { self.offset.get().write(offset) }
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle { handle.add(offset) }
 }
 
 unsafe impl<T> Storage for OffsetSingleStackStorage<T> {
-    type Handle = ();
+    type Handle = OffsetSingleHandle;
 
     #[inline]
-    unsafe fn get(&self, _: Self::Handle) -> NonNull<u8> {
-        NonNull::new_unchecked(
-            self.storage
-                .memory
-                .get()
-                .cast::<u8>()
-                .offset(self.offset.get().read())
-                .cast::<T>(),
-        )
-        .cast()
-    }
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle.get(self.storage.memory.get().cast()) }
 
     #[inline]
-    unsafe fn get_mut(&mut self, _: Self::Handle) -> NonNull<u8> { self.get(()) }
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
 
     #[inline]
     fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.allocate_nonempty(layout)
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        Ok(NonEmptyMemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
     fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.allocate(layout)
+        let memory_block = self.storage.allocate(layout)?;
+        Ok(MemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
-    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
-        self.storage.deallocate_nonempty(handle, layout)
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.deallocate_nonempty((), layout);
     }
 
     #[inline]
-    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) { self.storage.deallocate(handle, layout) }
+    unsafe fn deallocate(&mut self, _: Self::Handle, layout: Layout) { self.storage.deallocate((), layout) }
 }
 
 unsafe impl<T> SharedStorage for OffsetSingleStackStorage<T> {
     #[inline]
     fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.shared_allocate_nonempty(layout)
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        Ok(NonEmptyMemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
     fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.shared_allocate(layout)
+        let memory_block = self.storage.shared_allocate(layout)?;
+        Ok(MemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
-    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
-        self.storage.shared_deallocate_nonempty(handle, layout)
+    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.shared_deallocate_nonempty((), layout);
     }
 
     #[inline]
-    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
-        self.storage.shared_deallocate(handle, layout)
+    unsafe fn shared_deallocate(&self, _: Self::Handle, layout: Layout) { self.storage.shared_deallocate((), layout) }
+}
+
+#[test]
+fn aligned_single_stack() {
+    let mut storage = AlignedSingleStackStorage::<[u8; 64], 4096>::new();
+    assert_eq!(mem::align_of_val(&storage), 4096);
+
+    let layout = Layout::from_size_align(64, 4096).unwrap();
+    let memory_block = storage.allocate(layout).unwrap();
+    // the buffer is padded out to its alignment
+    assert_eq!(memory_block.size, 4096);
+
+    let ptr = unsafe { storage.get_mut(()) };
+    assert_eq!(ptr.as_ptr() as usize % 4096, 0);
+    unsafe { storage.deallocate((), layout) }
+
+    let storage = AlignedSingleStackStorage::init(Aligned::<_, 16>::new(0xdead_beef_u32));
+    assert_eq!(unsafe { storage.get(()).cast::<u32>().read() }, 0xdead_beef);
+}
+
+#[test]
+fn offset_single_stack() {
+    let storage = SingleStackStorage::<[u64; 4]>::new().offsetable();
+    let memory_block = storage.shared_allocate(Layout::new::<[u64; 4]>()).unwrap();
+
+    unsafe {
+        let start = storage.get(memory_block.handle);
+        let a = storage.shared_offset(memory_block.handle, 8);
+        let b = storage.shared_offset(a, 16);
+
+        // offsetting one handle doesn't move any of the others
+        assert_eq!(storage.get(memory_block.handle), start);
+        assert_eq!(storage.get(a), NonNull::new_unchecked(start.as_ptr().add(8)));
+        assert_eq!(storage.get(b), NonNull::new_unchecked(start.as_ptr().add(24)));
+
+        let dangling = OffsetSingleHandle::dangling(64);
+        assert!(dangling.is_dangling());
+        assert_eq!(storage.get(dangling).as_ptr() as usize, 64);
+
+        storage.shared_deallocate(storage.shared_offset(b, -24), Layout::new::<[u64; 4]>());
     }
 }
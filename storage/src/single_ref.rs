@@ -5,12 +5,13 @@ use core::{
     mem::MaybeUninit,
     num::NonZeroUsize,
     ptr::{self, NonNull},
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 
 use crate::{
-    AllocErr, FromPtr, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle, SharedGetMut,
-    SharedOffsetHandle, SharedStorage, Storage,
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    OffsetSingleHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage,
+    Storage,
 };
 
 pub struct SingleRefStorage<'a, T> {
@@ -19,14 +20,24 @@ pub struct SingleRefStorage<'a, T> {
 }
 pub struct OffsetSingleRefStorage<'a, T> {
     storage: SingleRefStorage<'a, T>,
-    offset: UnsafeCell<isize>,
+}
+
+/// A storage that bump allocates any number of allocations from a borrowed slice
+///
+/// Allocations are handed out from the start of the slice upwards, and only the most recent
+/// allocation can be deallocated to reclaim its space, everything else is reclaimed by
+/// [`reset`](Self::reset) or by dropping the storage.
+pub struct MultiRefStorage<'a, T> {
+    memory: &'a UnsafeCell<[MaybeUninit<T>]>,
+    // the start of the free space, in bytes from the start of the slice
+    offset: AtomicUsize,
 }
 
 unsafe impl<T> Send for SingleRefStorage<'_, T> {}
 unsafe impl<T> Sync for SingleRefStorage<'_, T> {}
 
-unsafe impl<T> Send for OffsetSingleRefStorage<'_, T> {}
-unsafe impl<T> Sync for OffsetSingleRefStorage<'_, T> {}
+unsafe impl<T> Send for MultiRefStorage<'_, T> {}
+unsafe impl<T> Sync for MultiRefStorage<'_, T> {}
 
 impl<'a, T> SingleRefStorage<'a, T> {
     pub fn new(memory: &'a mut [MaybeUninit<T>]) -> Self {
@@ -36,12 +47,7 @@ impl<'a, T> SingleRefStorage<'a, T> {
         }
     }
 
-    pub const fn offsetable(self) -> OffsetSingleRefStorage<'a, T> {
-        OffsetSingleRefStorage {
-            offset: UnsafeCell::new(0),
-            storage: self,
-        }
-    }
+    pub const fn offsetable(self) -> OffsetSingleRefStorage<'a, T> { OffsetSingleRefStorage { storage: self } }
 }
 
 impl<T> SingleRefStorage<'_, T> {
@@ -146,73 +152,280 @@ unsafe impl<T> SharedStorage for SingleRefStorage<'_, T> {
 }
 
 unsafe impl<T> SharedGetMut for OffsetSingleRefStorage<'_, T> {
-    unsafe fn shared_get_mut(&self, _: Self::Handle) -> NonNull<u8> { self.get(()) }
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
 }
 
 unsafe impl<T> OffsetHandle for OffsetSingleRefStorage<'_, T> {
-    unsafe fn offset(&mut self, _: Self::Handle, offset: isize) -> Self::Handle { self.offset.get().write(offset) }
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle { handle.add(offset) }
 }
 
 unsafe impl<T> SharedOffsetHandle for OffsetSingleRefStorage<'_, T> {
-    unsafe fn shared_offset(&self, _: Self::Handle, offset: isize) -> Self::Handle { self.offset.get().write(offset) }
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle { handle.add(offset) }
 }
 
 unsafe impl<T> Storage for OffsetSingleRefStorage<'_, T> {
-    type Handle = ();
+    type Handle = OffsetSingleHandle;
 
     #[inline]
-    unsafe fn get(&self, _: Self::Handle) -> NonNull<u8> {
-        NonNull::new_unchecked(
-            self.storage
-                .memory
-                .get()
-                .cast::<u8>()
-                .offset(self.offset.get().read())
-                .cast::<T>(),
-        )
-        .cast()
-    }
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle.get(self.storage.memory.get().cast()) }
 
     #[inline]
-    unsafe fn get_mut(&mut self, _: Self::Handle) -> NonNull<u8> { self.get(()) }
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.get(handle) }
 
     #[inline]
     fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.allocate_nonempty(layout)
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        Ok(NonEmptyMemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
     fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.allocate(layout)
+        let memory_block = self.storage.allocate(layout)?;
+        Ok(MemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
-    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
-        self.storage.deallocate_nonempty(handle, layout)
+    unsafe fn deallocate_nonempty(&mut self, _: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.deallocate_nonempty((), layout);
     }
 
     #[inline]
-    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) { self.storage.deallocate(handle, layout) }
+    unsafe fn deallocate(&mut self, _: Self::Handle, layout: Layout) { self.storage.deallocate((), layout) }
 }
 
 unsafe impl<T> SharedStorage for OffsetSingleRefStorage<'_, T> {
     #[inline]
     fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.shared_allocate_nonempty(layout)
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        Ok(NonEmptyMemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
     fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
-        self.storage.shared_allocate(layout)
+        let memory_block = self.storage.shared_allocate(layout)?;
+        Ok(MemoryBlock {
+            handle: OffsetSingleHandle::START,
+            size: memory_block.size,
+        })
     }
 
     #[inline]
-    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
-        self.storage.shared_deallocate_nonempty(handle, layout)
+    unsafe fn shared_deallocate_nonempty(&self, _: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.shared_deallocate_nonempty((), layout);
     }
 
     #[inline]
-    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
-        self.storage.shared_deallocate(handle, layout)
+    unsafe fn shared_deallocate(&self, _: Self::Handle, layout: Layout) { self.storage.shared_deallocate((), layout) }
+}
+
+impl<'a, T> MultiRefStorage<'a, T> {
+    pub const fn new(memory: &'a mut [MaybeUninit<T>]) -> Self {
+        Self {
+            memory: UnsafeCell::from_mut(memory),
+            offset: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T> MultiRefStorage<'_, T> {
+    /// The number of bytes that can still be allocated, before any padding for alignment
+    ///
+    /// While the storage is shared this is only a snapshot, other threads may allocate at any time
+    pub fn remaining(&self) -> usize { self.capacity() - self.offset.load(Ordering::Relaxed) }
+
+    /// Frees everything that was allocated from this storage
+    ///
+    /// # Safety
+    ///
+    /// handles to allocations made before the reset must not be used
+    pub unsafe fn reset(&mut self) { *self.offset.get_mut() = 0; }
+
+    const fn capacity(&self) -> usize {
+        let len: usize = ptr::metadata(self.memory.get());
+        mem::size_of::<T>() * len
     }
+
+    const fn start(&self) -> NonNull<u8> { unsafe { NonNull::new_unchecked(self.memory.get().cast()) } }
+
+    fn bump(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<NonNull<u8>>, AllocErr> {
+        let start = self.start();
+        let mut offset = self.offset.load(Ordering::Relaxed);
+
+        loop {
+            let aligned = unsafe { start.as_ptr().add(offset) }.align_offset(layout.align());
+            let end = offset
+                .checked_add(aligned)
+                .and_then(|begin| begin.checked_add(layout.size()))
+                .filter(|&end| end <= self.capacity())
+                .ok_or_else(|| AllocErr::new(layout.into()))?;
+
+            match self
+                .offset
+                .compare_exchange_weak(offset, end, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => {
+                    return Ok(NonEmptyMemoryBlock {
+                        handle: unsafe { NonNull::new_unchecked(start.as_ptr().add(offset + aligned)) },
+                        size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+                    })
+                }
+                Err(current) => offset = current,
+            }
+        }
+    }
+
+    // only the most recent allocation can be reclaimed
+    fn unbump(&self, handle: NonNull<u8>, layout: NonEmptyLayout) {
+        let begin = handle.as_ptr() as usize - self.start().as_ptr() as usize;
+        let _ = self
+            .offset
+            .compare_exchange(begin + layout.size(), begin, Ordering::Relaxed, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<T> FromPtr for MultiRefStorage<'_, T> {
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl<T> SharedGetMut for MultiRefStorage<'_, T> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl<T> MultiStorage for MultiRefStorage<'_, T> {}
+
+unsafe impl<T> Storage for MultiRefStorage<'_, T> {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.bump(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.unbump(handle, layout);
+    }
+}
+
+unsafe impl<T> SharedStorage for MultiRefStorage<'_, T> {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.bump(layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.unbump(handle, layout);
+    }
+}
+
+unsafe impl<T> ResizableStorage for MultiRefStorage<'_, T> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<T> SharedResizableStorage for MultiRefStorage<'_, T> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn multi_ref() {
+    let mut memory = [MaybeUninit::<u64>::uninit(); 16];
+    let mut storage = MultiRefStorage::new(&mut memory);
+    assert_eq!(storage.remaining(), 128);
+
+    let layout = Layout::new::<[u32; 3]>();
+    let a = storage.allocate(layout).unwrap();
+    let b = storage.allocate(Layout::new::<u64>()).unwrap();
+    // `b` is aligned past the end of `a`
+    assert_eq!(storage.remaining(), 128 - 24);
+    assert_eq!(
+        unsafe {
+            storage
+                .get(b.handle)
+                .as_ptr()
+                .offset_from(storage.get(a.handle).as_ptr())
+        },
+        16
+    );
+
+    unsafe {
+        // `a` isn't the most recent allocation, so its space can't be reclaimed yet
+        storage.deallocate(a.handle, layout);
+        assert_eq!(storage.remaining(), 128 - 24);
+
+        storage.deallocate(b.handle, Layout::new::<u64>());
+        assert_eq!(storage.remaining(), 128 - 16);
+
+        storage.reset();
+    }
+
+    assert_eq!(storage.remaining(), 128);
+    assert!(storage.allocate(Layout::new::<[u64; 17]>()).is_err());
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
 }
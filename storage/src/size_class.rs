@@ -0,0 +1,341 @@
+use core::{alloc::Layout, cell::UnsafeCell, mem, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const MIN_CLASS: usize = 16;
+
+#[must_use = "storages don't do anything unless they are used"]
+pub struct SizeClassStorage<S: Storage, const CLASSES: usize> {
+    storage: S,
+    // each free block stores an `Option<S::Handle>` to the next free block in it's size class
+    heads: UnsafeCell<[Option<S::Handle>; CLASSES]>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Storage + Send, const CLASSES: usize> Send for SizeClassStorage<S, CLASSES> where S::Handle: Send {}
+unsafe impl<S: Storage + Sync, const CLASSES: usize> Sync for SizeClassStorage<S, CLASSES> where S::Handle: Send {}
+
+impl<S: Storage, const CLASSES: usize> SizeClassStorage<S, CLASSES> {
+    const LINK_FITS: () = assert!(
+        mem::size_of::<Option<S::Handle>>() <= MIN_CLASS && mem::align_of::<Option<S::Handle>>() <= MIN_CLASS,
+        "handles must fit in the smallest size class"
+    );
+
+    pub const fn new(storage: S) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::LINK_FITS;
+
+        Self {
+            storage,
+            heads: UnsafeCell::new([None; CLASSES]),
+            lock: SpinLock::new(),
+        }
+    }
+
+    pub const fn class_size(class: usize) -> usize { MIN_CLASS << class }
+
+    fn class(layout: Layout) -> Option<usize> {
+        // zero-sized layouts are never allocated
+        if layout.size() == 0 || layout.align() > MIN_CLASS {
+            return None
+        }
+
+        let size = layout.size().max(MIN_CLASS).checked_next_power_of_two()?;
+        let class = (size.trailing_zeros() - MIN_CLASS.trailing_zeros()) as usize;
+        if class < CLASSES {
+            Some(class)
+        } else {
+            None
+        }
+    }
+
+    const fn class_layout(class: usize) -> NonEmptyLayout {
+        unsafe { NonEmptyLayout::new_unchecked(Layout::from_size_align_unchecked(Self::class_size(class), MIN_CLASS)) }
+    }
+
+    const fn memory_block(handle: S::Handle, class: usize) -> NonEmptyMemoryBlock<S::Handle> {
+        NonEmptyMemoryBlock {
+            handle,
+            size: unsafe { NonZeroUsize::new_unchecked(Self::class_size(class)) },
+        }
+    }
+
+    // must be called with exclusive access to `head`
+    unsafe fn pop(&self, head: &mut Option<S::Handle>) -> Option<S::Handle> {
+        let handle = (*head)?;
+        let link: *mut Option<S::Handle> = self.storage.get(handle).as_ptr().cast();
+        *head = link.read();
+        Some(handle)
+    }
+
+    // must be called with exclusive access to `head`
+    const unsafe fn push(ptr: NonNull<u8>, head: &mut Option<S::Handle>, handle: S::Handle) {
+        let link: *mut Option<S::Handle> = ptr.as_ptr().cast();
+        link.write(head.replace(handle));
+    }
+
+    fn shallow_flush(&mut self) {
+        for class in 0..CLASSES {
+            let mut head = mem::take(&mut self.heads.get_mut()[class]);
+            while let Some(handle) = unsafe { self.pop(&mut head) } {
+                unsafe { self.storage.deallocate_nonempty(handle, Self::class_layout(class)) }
+            }
+        }
+    }
+
+    fn shared_shallow_flush(&self)
+    where
+        S: SharedStorage,
+    {
+        for class in 0..CLASSES {
+            let mut head = {
+                let _guard = self.lock.lock();
+                unsafe { mem::take(&mut (*self.heads.get())[class]) }
+            };
+
+            while let Some(handle) = unsafe { self.pop(&mut head) } {
+                unsafe {
+                    self.storage
+                        .shared_deallocate_nonempty(handle, Self::class_layout(class));
+                }
+            }
+        }
+    }
+}
+
+impl<S: Storage, const CLASSES: usize> Drop for SizeClassStorage<S, CLASSES> {
+    fn drop(&mut self) { self.shallow_flush() }
+}
+
+impl<S: Storage + Flush, const CLASSES: usize> Flush for SizeClassStorage<S, CLASSES> {
+    fn try_flush(&mut self) -> bool {
+        self.shallow_flush();
+        self.storage.try_flush()
+    }
+
+    fn flush(&mut self) {
+        self.shallow_flush();
+        self.storage.flush();
+    }
+}
+
+impl<S: SharedStorage + SharedFlush, const CLASSES: usize> SharedFlush for SizeClassStorage<S, CLASSES> {
+    fn try_shared_flush(&self) -> bool {
+        self.shared_shallow_flush();
+        self.storage.try_shared_flush()
+    }
+
+    fn shared_flush(&self) {
+        self.shared_shallow_flush();
+        self.storage.shared_flush();
+    }
+}
+
+unsafe impl<S: FromPtr, const CLASSES: usize> FromPtr for SizeClassStorage<S, CLASSES> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const CLASSES: usize> SharedGetMut for SizeClassStorage<S, CLASSES> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage, const CLASSES: usize> MultiStorage for SizeClassStorage<S, CLASSES> {}
+
+unsafe impl<S: Storage, const CLASSES: usize> Storage for SizeClassStorage<S, CLASSES> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let Some(class) = Self::class(layout.into()) else {
+            return self.storage.allocate_nonempty(layout)
+        };
+
+        let handle = unsafe { self.pop(&mut (*self.heads.get())[class]) };
+
+        if let Some(handle) = handle {
+            Ok(Self::memory_block(handle, class))
+        } else {
+            let memory_block = self.storage.allocate_nonempty(Self::class_layout(class))?;
+            Ok(Self::memory_block(memory_block.handle, class))
+        }
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match Self::class(layout.into()) {
+            Some(class) => Self::push(self.storage.get_mut(handle), &mut self.heads.get_mut()[class], handle),
+            None => self.storage.deallocate_nonempty(handle, layout),
+        }
+    }
+}
+
+unsafe impl<S: ResizableStorage + MultiStorage, const CLASSES: usize> ResizableStorage
+    for SizeClassStorage<S, CLASSES>
+{
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old), Some(new)) if old == new => Ok(Self::memory_block(handle, new).into()),
+            (None, None) if old.size() != 0 => self.storage.grow(handle, old, new),
+            _ => crate::defaults::grow(self, handle, old, new),
+        }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old_class), Some(new_class)) if old_class == new_class => {
+                let ptr = self.storage.get_mut(handle).as_ptr();
+                ptr.add(old.size())
+                    .write_bytes(0, Self::class_size(new_class) - old.size());
+                Ok(Self::memory_block(handle, new_class).into())
+            }
+            (None, None) if old.size() != 0 => self.storage.grow_zeroed(handle, old, new),
+            _ => crate::defaults::grow_zeroed(self, handle, old, new),
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old), Some(new)) if old == new => Ok(Self::memory_block(handle, new).into()),
+            (None, None) if new.size() != 0 => self.storage.shrink(handle, old, new),
+            _ => crate::defaults::shrink(self, handle, old, new),
+        }
+    }
+}
+
+unsafe impl<S: SharedStorage, const CLASSES: usize> SharedStorage for SizeClassStorage<S, CLASSES> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let Some(class) = Self::class(layout.into()) else {
+            return self.storage.shared_allocate_nonempty(layout)
+        };
+
+        let handle = {
+            let _guard = self.lock.lock();
+            unsafe { self.pop(&mut (*self.heads.get())[class]) }
+        };
+
+        if let Some(handle) = handle {
+            Ok(Self::memory_block(handle, class))
+        } else {
+            let memory_block = self.storage.shared_allocate_nonempty(Self::class_layout(class))?;
+            Ok(Self::memory_block(memory_block.handle, class))
+        }
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match Self::class(layout.into()) {
+            Some(class) => {
+                let ptr = self.storage.shared_get_mut(handle);
+                let _guard = self.lock.lock();
+                Self::push(ptr, &mut (*self.heads.get())[class], handle);
+            }
+            None => self.storage.shared_deallocate_nonempty(handle, layout),
+        }
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + MultiStorage, const CLASSES: usize> SharedResizableStorage
+    for SizeClassStorage<S, CLASSES>
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old), Some(new)) if old == new => Ok(Self::memory_block(handle, new).into()),
+            (None, None) if old.size() != 0 => self.storage.shared_grow(handle, old, new),
+            _ => crate::defaults::grow(self, handle, old, new),
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old_class), Some(new_class)) if old_class == new_class => {
+                let ptr = self.storage.shared_get_mut(handle).as_ptr();
+                ptr.add(old.size())
+                    .write_bytes(0, Self::class_size(new_class) - old.size());
+                Ok(Self::memory_block(handle, new_class).into())
+            }
+            (None, None) if old.size() != 0 => self.storage.shared_grow_zeroed(handle, old, new),
+            _ => crate::defaults::grow_zeroed(self, handle, old, new),
+        }
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old), Some(new)) if old == new => Ok(Self::memory_block(handle, new).into()),
+            (None, None) if new.size() != 0 => self.storage.shared_shrink(handle, old, new),
+            _ => crate::defaults::shrink(self, handle, old, new),
+        }
+    }
+}
+
+#[test]
+fn size_class() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = SizeClassStorage::<_, 4>::new(&mock);
+
+    let small = Layout::new::<[u8; 20]>();
+    let memory_block = storage.allocate(small).unwrap();
+    assert_eq!(memory_block.size, 32);
+    unsafe { storage.deallocate(memory_block.handle, small) }
+
+    // the block is reused from the free list
+    let memory_block = storage.allocate(Layout::new::<[u8; 32]>()).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, Layout::new::<[u8; 32]>()) }
+
+    // too large for any size class
+    let large = Layout::new::<[u8; 1000]>();
+    let memory_block = storage.allocate(large).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, large) }
+
+    drop(storage);
+    mock.assert_events(&[
+        crate::Event::Allocate(Layout::from_size_align(32, 16).unwrap()),
+        crate::Event::Allocate(large),
+        crate::Event::Deallocate(large),
+        crate::Event::Deallocate(Layout::from_size_align(32, 16).unwrap()),
+    ]);
+
+    crate::storage_conformance!(SizeClassStorage::<_, 4>::new(mock), resizable, shared, shared_resizable);
+}
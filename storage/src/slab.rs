@@ -0,0 +1,349 @@
+use core::{
+    alloc::Layout,
+    mem,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const NIL: usize = usize::MAX;
+
+#[must_use = "storages don't do anything unless they are used"]
+pub struct SlabStorage<S: Storage, const BLOCK: usize, const ALIGN: usize> {
+    storage: S,
+    start: S::Handle,
+    capacity: usize,
+    // the offset of the first free block, free blocks store the offset of the next free block
+    free: AtomicUsize,
+    // the index of the first block that was never allocated
+    uninit: AtomicUsize,
+    lock: SpinLock,
+}
+
+#[derive(Clone, Copy)]
+pub struct SlabHandle(usize);
+
+unsafe impl Handle for SlabHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl SlabHandle {
+    #[must_use = "`SlabHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+}
+
+impl<S: Storage, const BLOCK: usize, const ALIGN: usize> SlabStorage<S, BLOCK, ALIGN> {
+    const REGION_ALIGN: usize = if ALIGN > mem::align_of::<usize>() {
+        ALIGN
+    } else {
+        mem::align_of::<usize>()
+    };
+    const STRIDE: usize = {
+        let size = if BLOCK > mem::size_of::<usize>() {
+            BLOCK
+        } else {
+            mem::size_of::<usize>()
+        };
+        (size + Self::REGION_ALIGN - 1) & !(Self::REGION_ALIGN - 1)
+    };
+
+    pub fn new(storage: S, capacity: usize) -> Self {
+        Self::try_new(storage, capacity).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// # Panics
+    ///
+    /// if `ALIGN` is not a power of two, or if the region for `capacity` blocks overflows
+    pub fn try_new(mut storage: S, capacity: usize) -> Result<Self, AllocErr> {
+        let size = Self::STRIDE.checked_mul(capacity).unwrap();
+        let layout = Layout::from_size_align(size, Self::REGION_ALIGN).unwrap();
+        let memory_block = storage.allocate(layout)?;
+
+        Ok(Self {
+            storage,
+            start: memory_block.handle,
+            capacity,
+            free: AtomicUsize::new(NIL),
+            uninit: AtomicUsize::new(0),
+            lock: SpinLock::new(),
+        })
+    }
+
+    pub const fn capacity(&self) -> usize { self.capacity }
+
+    const fn fits(layout: Layout) -> bool { layout.size() <= BLOCK && layout.align() <= ALIGN }
+
+    const fn region(&self) -> Layout {
+        unsafe { Layout::from_size_align_unchecked(Self::STRIDE * self.capacity, Self::REGION_ALIGN) }
+    }
+
+    // must be called with exclusive access to the free list
+    unsafe fn pop(&self, base: NonNull<u8>) -> Option<usize> {
+        let free = self.free.load(Ordering::Relaxed);
+
+        if free != NIL {
+            let link: *mut usize = base.as_ptr().add(free).cast();
+            let next = link.read();
+            self.free.store(next, Ordering::Relaxed);
+            return Some(free)
+        }
+
+        let uninit = self.uninit.load(Ordering::Relaxed);
+
+        if uninit == self.capacity {
+            None
+        } else {
+            self.uninit.store(uninit + 1, Ordering::Relaxed);
+            Some(uninit * Self::STRIDE)
+        }
+    }
+
+    // must be called with exclusive access to the free list
+    unsafe fn push(&self, base: NonNull<u8>, offset: usize) {
+        let free = self.free.load(Ordering::Relaxed);
+        let link: *mut usize = base.as_ptr().add(offset).cast();
+        link.write(free);
+        self.free.store(offset, Ordering::Relaxed);
+    }
+
+    fn memory_block(
+        offset: Option<usize>,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<SlabHandle>, AllocErr> {
+        offset
+            .map(|offset| NonEmptyMemoryBlock {
+                handle: SlabHandle(offset),
+                size: unsafe { NonZeroUsize::new_unchecked(Self::STRIDE) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn resize<T: Storage<Handle = SlabHandle>>(
+        storage: &mut T,
+        handle: SlabHandle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<SlabHandle>, AllocErr> {
+        if !Self::fits(new) {
+            Err(AllocErr::new(new))
+        } else if old.size() == 0 {
+            storage.allocate(new)
+        } else if new.size() == 0 {
+            storage.deallocate(handle, old);
+            Ok(MemoryBlock {
+                handle: SlabHandle::dangling(new.align()),
+                size: 0,
+            })
+        } else {
+            Ok(MemoryBlock {
+                handle,
+                size: Self::STRIDE,
+            })
+        }
+    }
+}
+
+impl<S: Storage, const BLOCK: usize, const ALIGN: usize> Drop for SlabStorage<S, BLOCK, ALIGN> {
+    fn drop(&mut self) {
+        let region = self.region();
+        unsafe { self.storage.deallocate(self.start, region) }
+    }
+}
+
+unsafe impl<S: Storage, const BLOCK: usize, const ALIGN: usize> OffsetHandle for SlabStorage<S, BLOCK, ALIGN> {
+    unsafe fn offset(&mut self, SlabHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        SlabHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: SharedGetMut, const BLOCK: usize, const ALIGN: usize> SharedOffsetHandle
+    for SlabStorage<S, BLOCK, ALIGN>
+{
+    unsafe fn shared_offset(&self, SlabHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        SlabHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: Storage, const BLOCK: usize, const ALIGN: usize> FromPtr for SlabStorage<S, BLOCK, ALIGN> {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        let origin = self.storage.get(self.start);
+        SlabHandle(ptr.as_ptr().offset_from(origin.as_ptr()) as usize)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const BLOCK: usize, const ALIGN: usize> SharedGetMut for SlabStorage<S, BLOCK, ALIGN> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.shared_get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+}
+
+impl<S: SharedGetMut, const BLOCK: usize, const ALIGN: usize> MultiStorage for SlabStorage<S, BLOCK, ALIGN> {}
+
+unsafe impl<S: Storage, const BLOCK: usize, const ALIGN: usize> Storage for SlabStorage<S, BLOCK, ALIGN> {
+    type Handle = SlabHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !Self::fits(layout.into()) {
+            return Err(AllocErr::new(layout.into()))
+        }
+
+        let offset = unsafe {
+            let base = self.storage.get_mut(self.start);
+            self.pop(base)
+        };
+
+        Self::memory_block(offset, layout)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, SlabHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let base = self.storage.get_mut(self.start);
+        self.push(base, offset);
+    }
+}
+
+unsafe impl<S: Storage, const BLOCK: usize, const ALIGN: usize> ResizableStorage for SlabStorage<S, BLOCK, ALIGN> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = Self::resize(self, handle, old, new)?;
+        let ptr = self.get_mut(memory_block.handle).as_ptr();
+        ptr.add(old.size()).write_bytes(0, memory_block.size - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedGetMut, const BLOCK: usize, const ALIGN: usize> SharedStorage for SlabStorage<S, BLOCK, ALIGN> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        if !Self::fits(layout.into()) {
+            return Err(AllocErr::new(layout.into()))
+        }
+
+        let offset = unsafe {
+            let base = self.storage.shared_get_mut(self.start);
+            let _guard = self.lock.lock();
+            self.pop(base)
+        };
+
+        Self::memory_block(offset, layout)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, SlabHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let base = self.storage.shared_get_mut(self.start);
+        let _guard = self.lock.lock();
+        self.push(base, offset);
+    }
+}
+
+unsafe impl<S: SharedGetMut, const BLOCK: usize, const ALIGN: usize> SharedResizableStorage
+    for SlabStorage<S, BLOCK, ALIGN>
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(&mut &*self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = Self::resize(&mut &*self, handle, old, new)?;
+        let ptr = self.shared_get_mut(memory_block.handle).as_ptr();
+        ptr.add(old.size()).write_bytes(0, memory_block.size - old.size());
+        Ok(memory_block)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        Self::resize(&mut &*self, handle, old, new)
+    }
+}
+
+#[test]
+fn slab() {
+    let mut storage = SlabStorage::<_, 24, 8>::new(crate::AllocatorStorage::new(std::alloc::System), 4);
+
+    let layout = Layout::new::<[u64; 3]>();
+    let blocks = [(); 4].map(|()| storage.allocate(layout).unwrap());
+    assert!(storage.allocate(Layout::new::<u8>()).is_err());
+    assert!(storage.allocate(Layout::new::<[u64; 4]>()).is_err());
+    assert!(storage.allocate(Layout::new::<u128>()).is_err());
+
+    unsafe {
+        storage.deallocate(blocks[1].handle, layout);
+        let small = storage.allocate(Layout::new::<u8>()).unwrap();
+        assert_eq!(small.handle.0, blocks[1].handle.0);
+        storage.deallocate(small.handle, Layout::new::<u8>());
+
+        for index in [0, 2, 3] {
+            storage.deallocate(blocks[index].handle, layout);
+        }
+    }
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
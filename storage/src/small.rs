@@ -0,0 +1,346 @@
+use core::{alloc::Layout, mem, mem::MaybeUninit, ptr::NonNull};
+
+use crate::{
+    AllocErr, FromPtr, Handle, MemoryBlock, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, SingleStackStorage, Storage,
+};
+
+/// `N` inline bytes, aligned enough for most types
+#[repr(C, align(16))]
+pub struct InlineBytes<const N: usize>([MaybeUninit<u8>; N]);
+
+/// A [`SpillStorage`] with `N` inline bytes
+pub type SmallStorage<const N: usize, S = crate::Global> = SpillStorage<InlineBytes<N>, S>;
+
+/// A storage that puts one allocation in an inline `I`, like [`SingleStackStorage`],
+/// and spills everything that doesn't fit into `S`
+///
+/// This is the building block for `SmallVec` and `SmallBox` style containers. Handles
+/// record where their allocation lives, so the storage may be moved while the inline
+/// allocation is live, and an inline allocation is moved into `S` if it grows too large.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct SpillStorage<I, S> {
+    inline: SingleStackStorage<I>,
+    spill: S,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpillHandle<H> {
+    Inline,
+    Spilled(H),
+}
+
+unsafe impl<H: Handle> Handle for SpillHandle<H> {
+    unsafe fn dangling(align: usize) -> Self { Self::Spilled(H::dangling(align)) }
+}
+
+impl<H> SpillHandle<H> {
+    pub const fn is_inline(&self) -> bool { matches!(self, Self::Inline) }
+}
+
+impl<I, S: Default> Default for SpillStorage<I, S> {
+    fn default() -> Self { Self::new(S::default()) }
+}
+
+impl<I, S> SpillStorage<I, S> {
+    pub const fn new(spill: S) -> Self {
+        Self {
+            inline: SingleStackStorage::new(),
+            spill,
+        }
+    }
+
+    pub const fn spill(&self) -> &S { &self.spill }
+
+    const fn fits_inline(layout: Layout) -> bool {
+        mem::size_of::<I>() >= layout.size() && mem::align_of::<I>() >= layout.align()
+    }
+
+    const fn inline_block<H>() -> MemoryBlock<SpillHandle<H>> {
+        MemoryBlock {
+            handle: SpillHandle::Inline,
+            size: mem::size_of::<I>(),
+        }
+    }
+}
+
+fn spilled<H>(memory_block: MemoryBlock<H>) -> MemoryBlock<SpillHandle<H>> {
+    MemoryBlock {
+        handle: SpillHandle::Spilled(memory_block.handle),
+        size: memory_block.size,
+    }
+}
+
+fn spilled_nonempty<H>(memory_block: NonEmptyMemoryBlock<H>) -> NonEmptyMemoryBlock<SpillHandle<H>> {
+    NonEmptyMemoryBlock {
+        handle: SpillHandle::Spilled(memory_block.handle),
+        size: memory_block.size,
+    }
+}
+
+unsafe impl<I, S: FromPtr> FromPtr for SpillStorage<I, S> {
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        if ptr == self.inline.get(()) {
+            SpillHandle::Inline
+        } else {
+            SpillHandle::Spilled(self.spill.from_ptr(ptr, layout))
+        }
+    }
+
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        if ptr == self.inline.get_mut(()) {
+            SpillHandle::Inline
+        } else {
+            SpillHandle::Spilled(self.spill.from_ptr_mut(ptr, layout))
+        }
+    }
+}
+
+unsafe impl<I, S: SharedGetMut> SharedGetMut for SpillStorage<I, S> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            SpillHandle::Inline => self.inline.shared_get_mut(()),
+            SpillHandle::Spilled(handle) => self.spill.shared_get_mut(handle),
+        }
+    }
+}
+
+unsafe impl<I, S: Storage> Storage for SpillStorage<I, S> {
+    type Handle = SpillHandle<S::Handle>;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            SpillHandle::Inline => self.inline.get(()),
+            SpillHandle::Spilled(handle) => self.spill.get(handle),
+        }
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        match handle {
+            SpillHandle::Inline => self.inline.get_mut(()),
+            SpillHandle::Spilled(handle) => self.spill.get_mut(handle),
+        }
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.inline
+            .allocate_nonempty(layout)
+            .map(|memory_block| NonEmptyMemoryBlock {
+                handle: SpillHandle::Inline,
+                size: memory_block.size,
+            })
+            .or_else(|_| self.spill.allocate_nonempty(layout).map(spilled_nonempty))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match handle {
+            SpillHandle::Inline => self.inline.deallocate_nonempty((), layout),
+            SpillHandle::Spilled(handle) => self.spill.deallocate_nonempty(handle, layout),
+        }
+    }
+}
+
+impl<I, S: ResizableStorage> SpillStorage<I, S> {
+    // moves the inline allocation into the spill storage
+    unsafe fn spill_inline(
+        &mut self,
+        old: Layout,
+        new: Layout,
+        zeroed: bool,
+    ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+        let memory_block = if zeroed {
+            self.spill.allocate_zeroed(new)?
+        } else {
+            self.spill.allocate(new)?
+        };
+
+        let ptr = self.spill.get_mut(memory_block.handle);
+        ptr.as_ptr()
+            .copy_from_nonoverlapping(self.inline.get(()).as_ptr(), old.size().min(new.size()));
+        self.inline.deallocate((), old);
+
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<I, S: ResizableStorage> ResizableStorage for SpillStorage<I, S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match handle {
+            // there is nothing to keep, so the allocation may move inline
+            _ if old.size() == 0 => self.allocate(new),
+            SpillHandle::Inline if Self::fits_inline(new) => Ok(Self::inline_block()),
+            SpillHandle::Inline => self.spill_inline(old, new, false).map(spilled),
+            SpillHandle::Spilled(handle) => self.spill.grow(handle, old, new).map(spilled),
+        }
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match handle {
+            _ if old.size() == 0 => self.allocate_zeroed(new),
+            SpillHandle::Inline if Self::fits_inline(new) => {
+                let ptr = self.inline.get_mut(()).as_ptr();
+                ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+                Ok(Self::inline_block())
+            }
+            SpillHandle::Inline => self.spill_inline(old, new, true).map(spilled),
+            SpillHandle::Spilled(handle) => self.spill.grow_zeroed(handle, old, new).map(spilled),
+        }
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match handle {
+            SpillHandle::Inline if new.size() == 0 => {
+                self.inline.deallocate((), old);
+                Ok(MemoryBlock {
+                    handle: Handle::dangling(new.align()),
+                    size: 0,
+                })
+            }
+            SpillHandle::Inline if Self::fits_inline(new) => Ok(Self::inline_block()),
+            SpillHandle::Inline => self.spill_inline(old, new, false).map(spilled),
+            SpillHandle::Spilled(handle) => self.spill.shrink(handle, old, new).map(spilled),
+        }
+    }
+}
+
+unsafe impl<I, S: SharedStorage> SharedStorage for SpillStorage<I, S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.inline
+            .shared_allocate_nonempty(layout)
+            .map(|memory_block| NonEmptyMemoryBlock {
+                handle: SpillHandle::Inline,
+                size: memory_block.size,
+            })
+            .or_else(|_| self.spill.shared_allocate_nonempty(layout).map(spilled_nonempty))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match handle {
+            SpillHandle::Inline => self.inline.shared_deallocate_nonempty((), layout),
+            SpillHandle::Spilled(handle) => self.spill.shared_deallocate_nonempty(handle, layout),
+        }
+    }
+}
+
+impl<I, S: SharedResizableStorage> SpillStorage<I, S> {
+    unsafe fn shared_spill_inline(
+        &self,
+        old: Layout,
+        new: Layout,
+        zeroed: bool,
+    ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+        let memory_block = if zeroed {
+            self.spill.shared_allocate_zeroed(new)?
+        } else {
+            self.spill.shared_allocate(new)?
+        };
+
+        let ptr = self.spill.shared_get_mut(memory_block.handle);
+        ptr.as_ptr()
+            .copy_from_nonoverlapping(self.inline.get(()).as_ptr(), old.size().min(new.size()));
+        self.inline.shared_deallocate((), old);
+
+        Ok(memory_block)
+    }
+}
+
+unsafe impl<I, S: SharedResizableStorage> SharedResizableStorage for SpillStorage<I, S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match handle {
+            _ if old.size() == 0 => self.shared_allocate(new),
+            SpillHandle::Inline if Self::fits_inline(new) => Ok(Self::inline_block()),
+            SpillHandle::Inline => self.shared_spill_inline(old, new, false).map(spilled),
+            SpillHandle::Spilled(handle) => self.spill.shared_grow(handle, old, new).map(spilled),
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match handle {
+            _ if old.size() == 0 => self.shared_allocate_zeroed(new),
+            SpillHandle::Inline if Self::fits_inline(new) => {
+                let ptr = self.inline.shared_get_mut(()).as_ptr();
+                ptr.add(old.size()).write_bytes(0, new.size() - old.size());
+                Ok(Self::inline_block())
+            }
+            SpillHandle::Inline => self.shared_spill_inline(old, new, true).map(spilled),
+            SpillHandle::Spilled(handle) => self.spill.shared_grow_zeroed(handle, old, new).map(spilled),
+        }
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match handle {
+            SpillHandle::Inline if new.size() == 0 => {
+                self.inline.shared_deallocate((), old);
+                Ok(MemoryBlock {
+                    handle: Handle::dangling(new.align()),
+                    size: 0,
+                })
+            }
+            SpillHandle::Inline if Self::fits_inline(new) => Ok(Self::inline_block()),
+            SpillHandle::Inline => self.shared_spill_inline(old, new, false).map(spilled),
+            SpillHandle::Spilled(handle) => self.spill.shared_shrink(handle, old, new).map(spilled),
+        }
+    }
+}
+
+#[test]
+fn small() {
+    let mut storage = SmallStorage::<32, _>::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let small = Layout::new::<[u64; 2]>();
+    let big = Layout::new::<[u64; 16]>();
+
+    let memory_block = storage.allocate(small).unwrap();
+    assert!(memory_block.handle.is_inline());
+    let spilled = storage.allocate(small).unwrap();
+    assert!(!spilled.handle.is_inline());
+    unsafe { storage.deallocate(spilled.handle, small) }
+
+    unsafe {
+        storage
+            .get_mut(memory_block.handle)
+            .cast::<[u64; 2]>()
+            .as_ptr()
+            .write([1, 2]);
+        let memory_block = storage.grow(memory_block.handle, small, big).unwrap();
+        assert!(!memory_block.handle.is_inline());
+        assert_eq!(*storage.get(memory_block.handle).cast::<[u64; 2]>().as_ptr(), [1, 2]);
+        storage.deallocate(memory_block.handle, big);
+    }
+
+    // the inline buffer was freed when the allocation spilled
+    assert!(storage.allocate(small).unwrap().handle.is_inline());
+
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    crate::storage_conformance!(SmallStorage::<64, _>::new(system), resizable, shared, shared_resizable);
+}
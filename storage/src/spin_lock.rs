@@ -0,0 +1,54 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::backoff::Backoff;
+
+pub struct SpinLock {
+    locked: AtomicBool,
+}
+
+pub struct SpinLockGuard<'a> {
+    lock: &'a SpinLock,
+}
+
+impl SpinLock {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    #[inline]
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_>> {
+        if self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            Some(SpinLockGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn lock(&self) -> SpinLockGuard<'_> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard
+            }
+
+            let backoff = Backoff::new();
+            while self.locked.load(Ordering::Relaxed) {
+                if !backoff.spin() {
+                    core::hint::spin_loop();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SpinLockGuard<'_> {
+    #[inline]
+    fn drop(&mut self) { self.lock.locked.store(false, Ordering::Release) }
+}
@@ -0,0 +1,263 @@
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+    str,
+};
+
+use crate::{boxed::Box, vec::Vec, AllocErr, ResizableStorage, Storage, TryCloneIn};
+
+/// A UTF-8 string stored in a [`Vec<u8, S>`](Vec)
+pub struct String<S: Storage = crate::Global> {
+    vec: Vec<u8, S>,
+}
+
+impl String {
+    pub const fn new() -> Self { Self { vec: Vec::new() } }
+
+    pub fn with_capacity(capacity: usize) -> Self { Self::with_capacity_in(capacity, crate::Global) }
+}
+
+impl<S: Storage> String<S> {
+    pub fn new_in(storage: S) -> Self {
+        Self {
+            vec: Vec::new_in(storage),
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, storage: S) -> Self {
+        Self::try_with_capacity_in(capacity, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_with_capacity_in(capacity: usize, storage: S) -> Result<Self, AllocErr<S>> {
+        Ok(Self {
+            vec: Vec::try_with_capacity_in(capacity, storage)?,
+        })
+    }
+
+    pub fn from_str_in(string: &str, storage: S) -> Self {
+        Self::try_from_str_in(string, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_from_str_in(string: &str, storage: S) -> Result<Self, AllocErr> {
+        Ok(Self {
+            vec: Vec::try_from_copy_slice_in(string.as_bytes(), storage)?,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// If `vec` isn't valid UTF-8, it's returned along with the error
+    pub fn from_utf8(vec: Vec<u8, S>) -> Result<Self, (Vec<u8, S>, str::Utf8Error)> {
+        match str::from_utf8(&vec) {
+            Ok(_) => Ok(Self { vec }),
+            Err(err) => Err((vec, err)),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `vec` must be valid UTF-8
+    pub const unsafe fn from_utf8_unchecked(vec: Vec<u8, S>) -> Self { Self { vec } }
+
+    pub fn into_bytes(self) -> Vec<u8, S> { self.vec }
+
+    pub fn as_str(&self) -> &str { unsafe { str::from_utf8_unchecked(&self.vec) } }
+
+    pub fn as_mut_str(&mut self) -> &mut str { unsafe { str::from_utf8_unchecked_mut(&mut self.vec) } }
+
+    pub fn as_bytes(&self) -> &[u8] { &self.vec }
+
+    /// # Safety
+    ///
+    /// the bytes must still be valid UTF-8 once the borrow ends
+    pub const unsafe fn as_mut_vec(&mut self) -> &mut Vec<u8, S> { &mut self.vec }
+
+    pub fn len(&self) -> usize { self.vec.len() }
+
+    pub fn is_empty(&self) -> bool { self.vec.is_empty() }
+
+    pub fn capacity(&self) -> usize { self.vec.capacity() }
+
+    pub fn clear(&mut self) { self.vec.clear() }
+
+    /// # Panics
+    ///
+    /// if `new_len` isn't on a char boundary
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            assert!(
+                self.is_char_boundary(new_len),
+                "new length (is {}) should be on a char boundary",
+                new_len
+            );
+            self.vec.truncate(new_len);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.chars().next_back()?;
+        self.vec.truncate(self.len() - ch.len_utf8());
+        Some(ch)
+    }
+}
+
+impl<S: ResizableStorage> String<S> {
+    pub fn reserve(&mut self, additional: usize) { self.vec.reserve(additional) }
+
+    /// Converts the string into a boxed `str`, shrinking the allocation to fit
+    pub fn into_boxed_str(self) -> Box<str, S> { unsafe { Box::from_utf8_unchecked(self.vec.into_boxed_slice()) } }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocErr> { self.vec.try_reserve(additional) }
+
+    pub fn push(&mut self, ch: char) { self.try_push(ch).unwrap_or_else(AllocErr::handle) }
+
+    pub fn try_push(&mut self, ch: char) -> Result<(), AllocErr> { self.try_push_str(ch.encode_utf8(&mut [0; 4])) }
+
+    pub fn push_str(&mut self, string: &str) { self.try_push_str(string).unwrap_or_else(AllocErr::handle) }
+
+    /// Appends `string` to the end
+    ///
+    /// If the string can't grow, it's left unchanged
+    pub fn try_push_str(&mut self, string: &str) -> Result<(), AllocErr> {
+        self.vec.try_extend_from_copy_slice(string.as_bytes())
+    }
+}
+
+impl<S: Storage + Default> Default for String<S> {
+    fn default() -> Self { Self::new_in(S::default()) }
+}
+
+impl<S: ResizableStorage> String<S> {
+    pub fn format_in(storage: S, args: fmt::Arguments<'_>) -> Self {
+        Self::try_format_in(storage, args).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Formats `args` into a new string, see [`format_in`](crate::format_in)
+    ///
+    /// # Panics
+    ///
+    /// if a formatting trait implementation returns an error
+    pub fn try_format_in(storage: S, args: fmt::Arguments<'_>) -> Result<Self, AllocErr> {
+        if let Some(string) = args.as_str() {
+            return Self::try_from_str_in(string, storage)
+        }
+
+        let mut writer = Writer {
+            string: Self::new_in(storage),
+            error: None,
+        };
+
+        match fmt::write(&mut writer, args) {
+            Ok(()) => Ok(writer.string),
+            Err(fmt::Error) => Err(writer
+                .error
+                .unwrap_or_else(|| panic!("a formatting trait implementation returned an error"))),
+        }
+    }
+}
+
+// keeps the allocation error, since `fmt::Error` can't carry it
+struct Writer<S: Storage> {
+    string: String<S>,
+    error: Option<AllocErr>,
+}
+
+impl<S: ResizableStorage> fmt::Write for Writer<S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.string.try_push_str(s).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+impl<S: Storage> Deref for String<S> {
+    type Target = str;
+
+    fn deref(&self) -> &str { self.as_str() }
+}
+
+impl<S: Storage> DerefMut for String<S> {
+    fn deref_mut(&mut self) -> &mut str { self.as_mut_str() }
+}
+
+impl<S: Storage> fmt::Display for String<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { str::fmt(self, f) }
+}
+
+impl<S: Storage> fmt::Debug for String<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { <str as fmt::Debug>::fmt(self, f) }
+}
+
+impl<S: ResizableStorage> fmt::Write for String<S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result { self.try_push_str(s).map_err(|_| fmt::Error) }
+
+    fn write_char(&mut self, c: char) -> fmt::Result { self.try_push(c).map_err(|_| fmt::Error) }
+}
+
+impl<S: Storage, S2: Storage> PartialEq<String<S2>> for String<S> {
+    fn eq(&self, other: &String<S2>) -> bool { self.as_str() == other.as_str() }
+}
+
+impl<S: Storage> Eq for String<S> {}
+
+impl<S: Storage> PartialEq<str> for String<S> {
+    fn eq(&self, other: &str) -> bool { self.as_str() == other }
+}
+
+impl<S: Storage> PartialEq<&str> for String<S> {
+    fn eq(&self, other: &&str) -> bool { self.as_str() == *other }
+}
+
+impl<S: Storage, S2: Storage> TryCloneIn<S2> for String<S> {
+    type Output = String<S2>;
+
+    fn try_clone_in(&self, storage: S2) -> Result<Self::Output, AllocErr> { String::try_from_str_in(self, storage) }
+}
+
+#[test]
+fn string() {
+    use core::fmt::Write;
+
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut string = String::from_str_in("hello", &mock);
+    string.push_str(", ");
+    string.push('w');
+    string.push('ö');
+    write!(string, "rld {}", 42).unwrap();
+    assert_eq!(string, "hello, wörld 42");
+    assert_eq!(
+        std::format!("{string} {string:?}"),
+        "hello, wörld 42 \"hello, wörld 42\""
+    );
+
+    assert_eq!(string.pop(), Some('2'));
+    string.truncate(10);
+    assert_eq!(string, "hello, wö");
+    assert_eq!(string.pop(), Some('ö'));
+    assert_eq!(string.len(), 8);
+    assert_eq!(&*string.into_boxed_str(), "hello, w");
+
+    let bytes = Vec::from_slice_in(&[0xff, 0xfe], &mock);
+    assert!(String::from_utf8(bytes).is_err());
+
+    // a bump arena keeps what fits
+    let mut string = String::new_in(crate::BumpStorage::<_, 1>::new(&mock, 8));
+    string.push_str("abcd");
+    assert!(string.try_push_str("efghijk").is_err());
+    assert_eq!(string, "abcd");
+}
+
+#[test]
+fn format_in() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let string = crate::format_in!(&mock, "{}-{:03}-{:?}", "a", 7, 'c');
+    assert_eq!(string, "a-007-'c'");
+    assert_eq!(crate::format_in!(&mock, "plain"), "plain");
+
+    let bump = crate::BumpStorage::<_, 1>::new(&mock, 8);
+    assert!(String::try_format_in(&bump, format_args!("{}", "too long to fit")).is_err());
+    assert_eq!(String::try_format_in(&bump, format_args!("{}", 1234)).unwrap(), "1234");
+}
@@ -0,0 +1,358 @@
+#![allow(clippy::missing_panics_doc)]
+
+use core::{alloc::Layout, ptr::NonNull};
+
+use crate::{ResizableStorage, SharedResizableStorage, SharedStorage, Storage};
+
+const LAYOUTS: &[(usize, usize)] = &[
+    (0, 1),
+    (0, 8),
+    (1, 1),
+    (3, 1),
+    (8, 8),
+    (24, 8),
+    (16, 16),
+    (100, 4),
+    (64, 64),
+    (1024, 32),
+];
+
+fn layouts() -> impl Iterator<Item = Layout> {
+    LAYOUTS
+        .iter()
+        .map(|&(size, align)| Layout::from_size_align(size, align).unwrap())
+}
+
+const unsafe fn fill(ptr: NonNull<u8>, size: usize, byte: u8) { ptr.as_ptr().write_bytes(byte, size) }
+
+unsafe fn check(ptr: NonNull<u8>, range: core::ops::Range<usize>, byte: u8, what: &str) {
+    for i in range {
+        let found = ptr.as_ptr().add(i).read();
+        assert!(
+            found == byte,
+            "{}: expected {:#x} at offset {}, found {:#x}",
+            what,
+            byte,
+            i,
+            found
+        );
+    }
+}
+
+fn check_aligned(ptr: NonNull<u8>, layout: Layout) {
+    assert!(
+        (ptr.as_ptr() as usize).is_multiple_of(layout.align()),
+        "allocation for {:?} is not aligned: {:p}",
+        layout,
+        ptr
+    );
+}
+
+/// Runs all checks that only need exclusive access
+///
+/// Allocation failures are allowed, only successful allocations are checked
+pub fn storage<S: Storage>(storage: &mut S) {
+    alignment(storage);
+    stable_handles(storage);
+    zeroed(storage);
+}
+
+/// Runs all checks for the resizing operations
+pub fn resizable<S: ResizableStorage>(storage: &mut S) {
+    grow(storage);
+    grow_zeroed(storage);
+    shrink(storage);
+}
+
+/// Runs all checks for the shared operations, and checks
+/// that they interoperate with the exclusive operations
+pub fn shared<S: SharedStorage>(storage: &mut S) {
+    for layout in layouts() {
+        if let Ok(memory_block) = storage.shared_allocate(layout) {
+            assert!(
+                memory_block.size >= layout.size(),
+                "allocation for {:?} is too small",
+                layout
+            );
+
+            unsafe {
+                let ptr = storage.shared_get_mut(memory_block.handle);
+                check_aligned(ptr, layout);
+                assert_eq!(
+                    storage.get(memory_block.handle),
+                    ptr,
+                    "`get` and `shared_get_mut` disagree"
+                );
+                fill(ptr, layout.size(), 0xa5);
+                storage.deallocate(memory_block.handle, layout);
+            }
+        }
+
+        if let Ok(memory_block) = storage.allocate(layout) {
+            unsafe {
+                let ptr = storage.get_mut(memory_block.handle);
+                assert_eq!(
+                    storage.shared_get_mut(memory_block.handle),
+                    ptr,
+                    "`get_mut` and `shared_get_mut` disagree"
+                );
+                storage.shared_deallocate(memory_block.handle, layout);
+            }
+        }
+
+        if let Ok(memory_block) = storage.allocate(layout) {
+            unsafe {
+                let ptr = storage.get_mut(memory_block.handle);
+                fill(ptr, memory_block.size, 0xff);
+                storage.shared_deallocate(memory_block.handle, layout);
+            }
+        }
+
+        if let Ok(memory_block) = storage.shared_allocate_zeroed(layout) {
+            unsafe {
+                let ptr = storage.shared_get_mut(memory_block.handle);
+                check(ptr, 0..memory_block.size, 0, "shared_allocate_zeroed");
+                storage.shared_deallocate(memory_block.handle, layout);
+            }
+        }
+    }
+}
+
+/// Runs all checks for the shared resizing operations
+pub fn shared_resizable<S: SharedResizableStorage>(storage: &mut S) {
+    for (old, new) in resize_pairs() {
+        if let Ok(memory_block) = storage.shared_allocate(old) {
+            unsafe {
+                fill(storage.shared_get_mut(memory_block.handle), old.size(), 0x3c);
+
+                match storage.shared_grow(memory_block.handle, old, new) {
+                    Ok(memory_block) => {
+                        let ptr = storage.shared_get_mut(memory_block.handle);
+                        check_aligned(ptr, new);
+                        check(ptr, 0..old.size(), 0x3c, "shared_grow");
+                        storage.shared_deallocate(memory_block.handle, new);
+                    }
+                    Err(_) => storage.shared_deallocate(memory_block.handle, old),
+                }
+            }
+        }
+
+        if let Ok(memory_block) = storage.shared_allocate(old) {
+            unsafe {
+                fill(storage.shared_get_mut(memory_block.handle), old.size(), 0x3c);
+
+                match storage.shared_grow_zeroed(memory_block.handle, old, new) {
+                    Ok(memory_block) => {
+                        let ptr = storage.shared_get_mut(memory_block.handle);
+                        check(ptr, 0..old.size(), 0x3c, "shared_grow_zeroed");
+                        check(ptr, old.size()..new.size(), 0, "shared_grow_zeroed");
+                        storage.shared_deallocate(memory_block.handle, new);
+                    }
+                    Err(_) => storage.shared_deallocate(memory_block.handle, old),
+                }
+            }
+        }
+
+        if let Ok(memory_block) = storage.shared_allocate(new) {
+            unsafe {
+                fill(storage.shared_get_mut(memory_block.handle), new.size(), 0xc3);
+
+                match storage.shared_shrink(memory_block.handle, new, old) {
+                    Ok(memory_block) => {
+                        let ptr = storage.shared_get_mut(memory_block.handle);
+                        check_aligned(ptr, old);
+                        check(ptr, 0..old.size(), 0xc3, "shared_shrink");
+                        storage.shared_deallocate(memory_block.handle, old);
+                    }
+                    Err(_) => storage.shared_deallocate(memory_block.handle, new),
+                }
+            }
+        }
+    }
+}
+
+pub fn alignment<S: Storage>(storage: &mut S) {
+    for layout in layouts() {
+        if let Ok(memory_block) = storage.allocate(layout) {
+            assert!(
+                memory_block.size >= layout.size(),
+                "allocation for {:?} is too small",
+                layout
+            );
+
+            unsafe {
+                let ptr = storage.get_mut(memory_block.handle);
+                check_aligned(ptr, layout);
+                assert_eq!(storage.get(memory_block.handle), ptr, "`get` and `get_mut` disagree");
+                fill(ptr, layout.size(), 0xa5);
+                storage.deallocate(memory_block.handle, layout);
+            }
+        }
+    }
+}
+
+pub fn stable_handles<S: Storage>(storage: &mut S) {
+    let layout = Layout::new::<[u64; 2]>();
+
+    let first = match storage.allocate(layout) {
+        Ok(memory_block) => memory_block.handle,
+        Err(_) => return,
+    };
+
+    unsafe {
+        fill(storage.get_mut(first), layout.size(), 0xa5);
+
+        for other in layouts() {
+            if let Ok(memory_block) = storage.allocate(other) {
+                fill(storage.get_mut(memory_block.handle), other.size(), 0x5a);
+                check(
+                    storage.get(first),
+                    0..layout.size(),
+                    0xa5,
+                    "allocate clobbered a live allocation",
+                );
+                storage.deallocate(memory_block.handle, other);
+                check(
+                    storage.get(first),
+                    0..layout.size(),
+                    0xa5,
+                    "deallocate clobbered a live allocation",
+                );
+            }
+        }
+
+        storage.deallocate(first, layout);
+    }
+}
+
+pub fn zeroed<S: Storage>(storage: &mut S) {
+    for layout in layouts() {
+        if let Ok(memory_block) = storage.allocate(layout) {
+            unsafe {
+                fill(storage.get_mut(memory_block.handle), memory_block.size, 0xff);
+                storage.deallocate(memory_block.handle, layout);
+            }
+        }
+
+        if let Ok(memory_block) = storage.allocate_zeroed(layout) {
+            unsafe {
+                let ptr = storage.get_mut(memory_block.handle);
+                check_aligned(ptr, layout);
+                check(ptr, 0..memory_block.size, 0, "allocate_zeroed");
+                storage.deallocate(memory_block.handle, layout);
+            }
+        }
+    }
+}
+
+fn resize_pairs() -> impl Iterator<Item = (Layout, Layout)> {
+    layouts().flat_map(|old| {
+        layouts().filter_map(move |new| {
+            if old.align() == new.align() && old.size() < new.size() {
+                Some((old, new))
+            } else {
+                None
+            }
+        })
+    })
+}
+
+pub fn grow<S: ResizableStorage>(storage: &mut S) {
+    for (old, new) in resize_pairs() {
+        if let Ok(memory_block) = storage.allocate(old) {
+            unsafe {
+                fill(storage.get_mut(memory_block.handle), old.size(), 0x3c);
+
+                match storage.grow(memory_block.handle, old, new) {
+                    Ok(memory_block) => {
+                        assert!(memory_block.size >= new.size(), "grow to {:?} is too small", new);
+                        let ptr = storage.get_mut(memory_block.handle);
+                        check_aligned(ptr, new);
+                        check(ptr, 0..old.size(), 0x3c, "grow");
+                        storage.deallocate(memory_block.handle, new);
+                    }
+                    Err(_) => storage.deallocate(memory_block.handle, old),
+                }
+            }
+        }
+    }
+}
+
+pub fn grow_zeroed<S: ResizableStorage>(storage: &mut S) {
+    for (old, new) in resize_pairs() {
+        if let Ok(memory_block) = storage.allocate(old) {
+            unsafe {
+                fill(storage.get_mut(memory_block.handle), old.size(), 0x3c);
+
+                match storage.grow_zeroed(memory_block.handle, old, new) {
+                    Ok(memory_block) => {
+                        let ptr = storage.get_mut(memory_block.handle);
+                        check_aligned(ptr, new);
+                        check(ptr, 0..old.size(), 0x3c, "grow_zeroed");
+                        check(ptr, old.size()..new.size(), 0, "grow_zeroed");
+                        storage.deallocate(memory_block.handle, new);
+                    }
+                    Err(_) => storage.deallocate(memory_block.handle, old),
+                }
+            }
+        }
+    }
+}
+
+pub fn shrink<S: ResizableStorage>(storage: &mut S) {
+    for (new, old) in resize_pairs() {
+        if let Ok(memory_block) = storage.allocate(old) {
+            unsafe {
+                fill(storage.get_mut(memory_block.handle), old.size(), 0xc3);
+
+                match storage.shrink(memory_block.handle, old, new) {
+                    Ok(memory_block) => {
+                        assert!(memory_block.size >= new.size(), "shrink to {:?} is too small", new);
+                        let ptr = storage.get_mut(memory_block.handle);
+                        check_aligned(ptr, new);
+                        check(ptr, 0..new.size(), 0xc3, "shrink");
+                        storage.deallocate(memory_block.handle, new);
+                    }
+                    Err(_) => storage.deallocate(memory_block.handle, old),
+                }
+            }
+        }
+    }
+}
+
+/// Checks that zero-sized layouts can be allocated, resized, and deallocated
+///
+/// Unlike the other checks, these must succeed, since zero-sized allocations don't need any memory
+pub fn zero_sized<S: ResizableStorage>(storage: &mut S) {
+    let unit = Layout::new::<()>();
+    let aligned = Layout::new::<[u64; 0]>();
+
+    unsafe {
+        let memory_block = storage.allocate(unit).unwrap();
+        assert_eq!(memory_block.size, 0);
+        let memory_block = storage.grow(memory_block.handle, unit, aligned).unwrap();
+        let memory_block = storage.grow_zeroed(memory_block.handle, aligned, aligned).unwrap();
+        let memory_block = storage.shrink(memory_block.handle, aligned, unit).unwrap();
+        storage.deallocate(memory_block.handle, unit);
+    }
+}
+
+#[test]
+fn conformance() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+
+    crate::storage_conformance!(crate::SingleStackStorage::<[u64; 32]>::new(), shared);
+    crate::storage_conformance!(system, resizable, shared, shared_resizable);
+    crate::storage_conformance!(
+        crate::FreeListStorage::new(core::num::NonZeroUsize::new(8).unwrap(), system),
+        resizable,
+        shared,
+        shared_resizable
+    );
+    crate::storage_conformance!(
+        crate::AffixStorage::<crate::TypedLayoutProvider<u32>, crate::TypedLayoutProvider<u16>, _>::new(system),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -0,0 +1,431 @@
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const MIN_CLASS: usize = 16;
+const MAGAZINE_SIZE: usize = 16;
+// the number of blocks moved between a magazine and the underlying storage at once
+const BATCH: usize = MAGAZINE_SIZE / 2;
+const SHARDS: usize = 8;
+
+// every thread is assigned a shard round-robin, so threads rarely share one
+fn shard_index() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    std::thread_local! {
+        static INDEX: usize = NEXT.fetch_add(1, Ordering::Relaxed) % SHARDS;
+    }
+
+    // thread locals may already be gone while a thread is exiting
+    INDEX.try_with(|&index| index).unwrap_or(0)
+}
+
+struct Magazine<H> {
+    handles: [MaybeUninit<H>; MAGAZINE_SIZE],
+    len: usize,
+}
+
+impl<H: Copy> Magazine<H> {
+    const fn new() -> Self {
+        Self {
+            handles: [MaybeUninit::uninit(); MAGAZINE_SIZE],
+            len: 0,
+        }
+    }
+
+    fn pop(&mut self) -> Option<H> {
+        self.len = self.len.checked_sub(1)?;
+        Some(unsafe { self.handles[self.len].assume_init() })
+    }
+
+    fn push(&mut self, handle: H) -> Result<(), H> {
+        let slot = self.handles.get_mut(self.len).ok_or(handle)?;
+        slot.write(handle);
+        self.len += 1;
+        Ok(())
+    }
+}
+
+struct Shard<H, const CLASSES: usize> {
+    magazines: UnsafeCell<[Magazine<H>; CLASSES]>,
+    lock: SpinLock,
+}
+
+impl<H: Copy, const CLASSES: usize> Shard<H, CLASSES> {
+    const fn new() -> Self {
+        Self {
+            magazines: UnsafeCell::new([const { Magazine::new() }; CLASSES]),
+            lock: SpinLock::new(),
+        }
+    }
+}
+
+/// A storage that keeps small free blocks in per-thread magazines
+///
+/// Small layouts are rounded up to one of `CLASSES` power of two size classes, starting at 16 bytes.
+/// Blocks are only moved to and from the underlying storage in batches, when a magazine runs empty
+/// or overflows, so threads mostly stay out of each other's way.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct ThreadCachedStorage<S: Storage, const CLASSES: usize> {
+    storage: S,
+    shards: [Shard<S::Handle, CLASSES>; SHARDS],
+}
+
+unsafe impl<S: Storage + Send, const CLASSES: usize> Send for ThreadCachedStorage<S, CLASSES> where S::Handle: Send {}
+unsafe impl<S: Storage + Sync, const CLASSES: usize> Sync for ThreadCachedStorage<S, CLASSES> where S::Handle: Send {}
+
+impl<S: Storage, const CLASSES: usize> ThreadCachedStorage<S, CLASSES> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            shards: [const { Shard::new() }; SHARDS],
+        }
+    }
+
+    pub const fn class_size(class: usize) -> usize { MIN_CLASS << class }
+
+    fn class(layout: Layout) -> Option<usize> {
+        // zero-sized layouts are never allocated
+        if layout.size() == 0 || layout.align() > MIN_CLASS {
+            return None
+        }
+
+        let size = layout.size().max(MIN_CLASS).checked_next_power_of_two()?;
+        let class = (size.trailing_zeros() - MIN_CLASS.trailing_zeros()) as usize;
+        (class < CLASSES).then_some(class)
+    }
+
+    const fn class_layout(class: usize) -> NonEmptyLayout {
+        unsafe { NonEmptyLayout::new_unchecked(Layout::from_size_align_unchecked(Self::class_size(class), MIN_CLASS)) }
+    }
+
+    const fn memory_block(handle: S::Handle, class: usize) -> NonEmptyMemoryBlock<S::Handle> {
+        NonEmptyMemoryBlock {
+            handle,
+            size: unsafe { NonZeroUsize::new_unchecked(Self::class_size(class)) },
+        }
+    }
+
+    fn shallow_flush(&mut self) {
+        for shard in &mut self.shards {
+            for (class, magazine) in shard.magazines.get_mut().iter_mut().enumerate() {
+                while let Some(handle) = magazine.pop() {
+                    unsafe { self.storage.deallocate_nonempty(handle, Self::class_layout(class)) }
+                }
+            }
+        }
+    }
+}
+
+impl<S: SharedStorage, const CLASSES: usize> ThreadCachedStorage<S, CLASSES> {
+    fn shared_shallow_flush(&self) {
+        for shard in &self.shards {
+            for class in 0..CLASSES {
+                let mut magazine = {
+                    let _guard = shard.lock.lock();
+                    let magazine = unsafe { &mut (*shard.magazines.get())[class] };
+                    core::mem::replace(magazine, Magazine::new())
+                };
+
+                while let Some(handle) = magazine.pop() {
+                    unsafe {
+                        self.storage
+                            .shared_deallocate_nonempty(handle, Self::class_layout(class));
+                    }
+                }
+            }
+        }
+    }
+
+    fn allocate_class(&self, class: usize) -> Result<NonEmptyMemoryBlock<S::Handle>, AllocErr> {
+        let shard = &self.shards[shard_index()];
+
+        let handle = {
+            let _guard = shard.lock.lock();
+            unsafe { (*shard.magazines.get())[class].pop() }
+        };
+
+        if let Some(handle) = handle {
+            return Ok(Self::memory_block(handle, class))
+        }
+
+        // the magazine is empty, so refill it with a batch from the underlying storage
+        let layout = Self::class_layout(class);
+        let handle = self.storage.shared_allocate_nonempty(layout)?.handle;
+        let mut batch = Magazine::new();
+
+        for _ in 1..BATCH {
+            let Ok(memory_block) = self.storage.shared_allocate_nonempty(layout) else {
+                break
+            };
+            let _ = batch.push(memory_block.handle);
+        }
+
+        let mut overflow = {
+            let _guard = shard.lock.lock();
+            let magazine = unsafe { &mut (*shard.magazines.get())[class] };
+
+            while let Some(handle) = batch.pop() {
+                if let Err(handle) = magazine.push(handle) {
+                    let _ = batch.push(handle);
+                    break
+                }
+            }
+
+            batch
+        };
+
+        while let Some(handle) = overflow.pop() {
+            unsafe { self.storage.shared_deallocate_nonempty(handle, layout) }
+        }
+
+        Ok(Self::memory_block(handle, class))
+    }
+
+    unsafe fn deallocate_class(&self, handle: S::Handle, class: usize) {
+        let shard = &self.shards[shard_index()];
+
+        let mut batch = {
+            let _guard = shard.lock.lock();
+            let magazine = &mut (*shard.magazines.get())[class];
+
+            let Err(handle) = magazine.push(handle) else { return };
+
+            // the magazine is full, so half of it goes back to the underlying storage
+            let mut batch = Magazine::new();
+            for _ in 0..BATCH {
+                if let Some(handle) = magazine.pop() {
+                    let _ = batch.push(handle);
+                }
+            }
+            let _ = magazine.push(handle);
+
+            batch
+        };
+
+        while let Some(handle) = batch.pop() {
+            self.storage
+                .shared_deallocate_nonempty(handle, Self::class_layout(class));
+        }
+    }
+}
+
+impl<S: Storage, const CLASSES: usize> Drop for ThreadCachedStorage<S, CLASSES> {
+    fn drop(&mut self) { self.shallow_flush() }
+}
+
+impl<S: Storage + Flush, const CLASSES: usize> Flush for ThreadCachedStorage<S, CLASSES> {
+    fn try_flush(&mut self) -> bool {
+        self.shallow_flush();
+        self.storage.try_flush()
+    }
+
+    fn flush(&mut self) {
+        self.shallow_flush();
+        self.storage.flush();
+    }
+}
+
+impl<S: SharedStorage + SharedFlush, const CLASSES: usize> SharedFlush for ThreadCachedStorage<S, CLASSES> {
+    fn try_shared_flush(&self) -> bool {
+        self.shared_shallow_flush();
+        self.storage.try_shared_flush()
+    }
+
+    fn shared_flush(&self) {
+        self.shared_shallow_flush();
+        self.storage.shared_flush();
+    }
+}
+
+unsafe impl<S: FromPtr + SharedStorage, const CLASSES: usize> FromPtr for ThreadCachedStorage<S, CLASSES> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedStorage, const CLASSES: usize> SharedGetMut for ThreadCachedStorage<S, CLASSES> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage + SharedStorage, const CLASSES: usize> MultiStorage for ThreadCachedStorage<S, CLASSES> {}
+
+unsafe impl<S: SharedStorage, const CLASSES: usize> Storage for ThreadCachedStorage<S, CLASSES> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.shared_deallocate_nonempty(handle, layout);
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + MultiStorage, const CLASSES: usize> ResizableStorage
+    for ThreadCachedStorage<S, CLASSES>
+{
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        self.shared_shrink(handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage, const CLASSES: usize> SharedStorage for ThreadCachedStorage<S, CLASSES> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let Some(class) = Self::class(layout.into()) else {
+            return self.storage.shared_allocate_nonempty(layout)
+        };
+
+        self.allocate_class(class)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        match Self::class(layout.into()) {
+            Some(class) => self.deallocate_class(handle, class),
+            None => self.storage.shared_deallocate_nonempty(handle, layout),
+        }
+    }
+}
+
+unsafe impl<S: SharedResizableStorage + MultiStorage, const CLASSES: usize> SharedResizableStorage
+    for ThreadCachedStorage<S, CLASSES>
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old), Some(new)) if old == new => Ok(Self::memory_block(handle, new).into()),
+            (None, None) if old.size() != 0 => self.storage.shared_grow(handle, old, new),
+            _ => crate::defaults::grow(self, handle, old, new),
+        }
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old_class), Some(new_class)) if old_class == new_class => {
+                let ptr = self.storage.shared_get_mut(handle).as_ptr();
+                ptr.add(old.size())
+                    .write_bytes(0, Self::class_size(new_class) - old.size());
+                Ok(Self::memory_block(handle, new_class).into())
+            }
+            (None, None) if old.size() != 0 => self.storage.shared_grow_zeroed(handle, old, new),
+            _ => crate::defaults::grow_zeroed(self, handle, old, new),
+        }
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        match (Self::class(old), Self::class(new)) {
+            (Some(old), Some(new)) if old == new => Ok(Self::memory_block(handle, new).into()),
+            (None, None) if new.size() != 0 => self.storage.shared_shrink(handle, old, new),
+            _ => crate::defaults::shrink(self, handle, old, new),
+        }
+    }
+}
+
+#[test]
+fn thread_cache() {
+    use std::vec::Vec;
+
+    fn churn<S: SharedStorage>(storage: &ThreadCachedStorage<S, 4>) {
+        let layout = Layout::new::<[u8; 24]>();
+
+        for _ in 0..10 {
+            let handles = (0..40)
+                .map(|_| storage.shared_allocate(layout).unwrap().handle)
+                .collect::<Vec<_>>();
+
+            for handle in handles {
+                unsafe { storage.shared_deallocate(handle, layout) }
+            }
+        }
+    }
+
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let storage = ThreadCachedStorage::new(&mock);
+    churn(&storage);
+    drop(storage);
+
+    // most blocks were reused from the magazines, and all of them were given back
+    let events = mock.events();
+    let allocated = events
+        .iter()
+        .filter(|event| matches!(event, crate::Event::Allocate(_)))
+        .count();
+    let deallocated = events
+        .iter()
+        .filter(|event| matches!(event, crate::Event::Deallocate(_)))
+        .count();
+    assert_eq!(allocated, deallocated);
+    assert!(allocated < 10 * 40);
+    drop(events);
+    mock.clear_events();
+
+    crate::storage_conformance!(
+        ThreadCachedStorage::<_, 4>::new(mock),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
@@ -0,0 +1,267 @@
+use core::{alloc::Layout, cell::Cell, marker::PhantomData, ptr::NonNull};
+
+use crate::{
+    AllocErr, FromPtr, Global, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, OffsetHandle,
+    ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+pub type ThreadStorageImp = &'static dyn SharedResizableStorage<Handle = NonNull<u8>>;
+
+std::thread_local! {
+    static THREAD_STORAGE: Cell<Option<ThreadStorageImp>> = const { Cell::new(None) };
+}
+
+/// A storage that allocates from the storage set for the current thread by [`set_thread_storage`],
+/// or from [`Global`] if there isn't one
+///
+/// Memory allocated on one thread must be deallocated on the same thread, so `ThreadLocalGlobal`
+/// is neither `Send` nor `Sync`, and neither is anything that holds one.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ThreadLocalGlobal {
+    not_thread_safe: PhantomData<*const ()>,
+}
+
+impl ThreadLocalGlobal {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            not_thread_safe: PhantomData,
+        }
+    }
+}
+
+/// Sets the storage that [`ThreadLocalGlobal`] uses on the current thread
+///
+/// Returns false if the current thread already has a storage, or if [`ThreadLocalGlobal`] was
+/// already used on the current thread. The first use without a storage settles on [`Global`],
+/// so that memory handed out before this call is never freed into a different storage.
+pub fn set_thread_storage(storage: ThreadStorageImp) -> bool {
+    THREAD_STORAGE.with(|thread_storage| {
+        if thread_storage.get().is_some() {
+            return false
+        }
+
+        thread_storage.set(Some(storage));
+        true
+    })
+}
+
+#[inline]
+fn thread_storage() -> ThreadStorageImp {
+    THREAD_STORAGE.with(|thread_storage| {
+        thread_storage.get().unwrap_or_else(|| {
+            thread_storage.set(Some(&Global));
+            &Global
+        })
+    })
+}
+
+unsafe impl FromPtr for ThreadLocalGlobal {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl SharedGetMut for ThreadLocalGlobal {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl MultiStorage for ThreadLocalGlobal {}
+
+unsafe impl OffsetHandle for ThreadLocalGlobal {
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for ThreadLocalGlobal {
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl Storage for ThreadLocalGlobal {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        thread_storage().deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn allocate(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&mut self, handle: Self::Handle, layout: Layout) {
+        thread_storage().deallocate(handle, layout);
+    }
+
+    #[inline]
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate_nonempty_zeroed(layout)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&mut self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate_zeroed(layout)
+    }
+}
+
+unsafe impl ResizableStorage for ThreadLocalGlobal {
+    #[inline]
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().shrink(handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for ThreadLocalGlobal {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate_nonempty(layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        thread_storage().deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn shared_allocate(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn shared_deallocate(&self, handle: Self::Handle, layout: Layout) {
+        thread_storage().deallocate(handle, layout);
+    }
+
+    #[inline]
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate_nonempty_zeroed(layout)
+    }
+
+    #[inline]
+    fn shared_allocate_zeroed(&self, layout: Layout) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().allocate_zeroed(layout)
+    }
+}
+
+unsafe impl SharedResizableStorage for ThreadLocalGlobal {
+    #[inline]
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().grow(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().grow_zeroed(handle, old, new)
+    }
+
+    #[inline]
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        thread_storage().shrink(handle, old, new)
+    }
+}
+
+#[test]
+fn thread_local_global() {
+    std::thread::spawn(|| {
+        let arena: &'static _ = std::boxed::Box::leak(std::boxed::Box::new(crate::LeakCheck::new(
+            crate::AllocatorStorage::new(std::alloc::System),
+        )));
+
+        assert!(set_thread_storage(arena));
+        assert!(!set_thread_storage(arena));
+
+        let layout = Layout::new::<u64>();
+        let mut storage = ThreadLocalGlobal::new();
+        let memory_block = storage.allocate(layout).unwrap();
+        assert_eq!(arena.live_allocations(), 1);
+        unsafe { storage.deallocate(memory_block.handle, layout) }
+        assert_eq!(arena.live_allocations(), 0);
+
+        crate::storage_conformance!(ThreadLocalGlobal::new(), resizable, shared, shared_resizable);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn thread_local_global_after_use() {
+    std::thread::spawn(|| {
+        let arena: &'static _ = std::boxed::Box::leak(std::boxed::Box::new(crate::LeakCheck::new(
+            crate::AllocatorStorage::new(std::alloc::System),
+        )));
+
+        let layout = Layout::new::<u64>();
+        let mut storage = ThreadLocalGlobal::new();
+        let memory_block = storage.allocate(layout);
+        assert!(!set_thread_storage(arena));
+
+        if let Ok(memory_block) = memory_block {
+            unsafe { storage.deallocate(memory_block.handle, layout) }
+        }
+        assert_eq!(arena.live_allocations(), 0);
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,452 @@
+use core::{alloc::Layout, mem, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const ALIGN_LOG2: u32 = 4;
+const ALIGN: usize = 1 << ALIGN_LOG2;
+
+const SL_LOG2: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+
+const FL_SHIFT: u32 = SL_LOG2 + ALIGN_LOG2;
+const FL_COUNT: usize = (usize::BITS - FL_SHIFT + 1) as usize;
+const SMALL_BLOCK: usize = 1 << FL_SHIFT;
+
+const HEADER: usize = mem::size_of::<BlockHeader>();
+const MIN_BLOCK: usize = HEADER + mem::size_of::<FreeLinks>();
+
+const FREE: usize = 1;
+const NIL: usize = usize::MAX;
+
+// all blocks are addressed by their offset from the start of the pool
+// and the control structure lives at the start of the pool
+#[repr(C, align(16))]
+struct Control {
+    fl_bitmap: usize,
+    sl_bitmap: [usize; FL_COUNT],
+    heads: [[usize; SL_COUNT]; FL_COUNT],
+}
+
+#[repr(C, align(16))]
+struct BlockHeader {
+    prev_phys: usize,
+    // the low bit is set if the block is free
+    size: usize,
+}
+
+// stored just after the header of free blocks
+struct FreeLinks {
+    next: usize,
+    prev: usize,
+}
+
+const fn log2(size: usize) -> u32 { usize::BITS - 1 - size.leading_zeros() }
+
+const fn mapping(size: usize) -> (usize, usize) {
+    if size < SMALL_BLOCK {
+        (0, size >> ALIGN_LOG2)
+    } else {
+        let fl = log2(size);
+        let sl = (size >> (fl - SL_LOG2)) ^ SL_COUNT;
+        ((fl - FL_SHIFT + 1) as usize, sl)
+    }
+}
+
+// rounds up `size` to the next size class, so that any block
+// in that size class can fit `size`
+fn mapping_search(size: usize) -> Option<(usize, usize)> {
+    if size < SMALL_BLOCK {
+        Some(mapping(size))
+    } else {
+        let round = (1 << (log2(size) - SL_LOG2)) - 1;
+        size.checked_add(round).map(mapping)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Pool(*mut u8);
+
+impl Pool {
+    unsafe fn init(self, size: usize) -> Option<()> {
+        let first = mem::size_of::<Control>();
+        let size = (size & !(ALIGN - 1))
+            .checked_sub(first + HEADER)
+            .filter(|&size| size >= MIN_BLOCK)?;
+
+        self.control().write(Control {
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            heads: [[NIL; SL_COUNT]; FL_COUNT],
+        });
+
+        self.header(first).write(BlockHeader { prev_phys: NIL, size });
+        // a used, empty, block to mark the end of the pool
+        self.header(first + size).write(BlockHeader {
+            prev_phys: first,
+            size: 0,
+        });
+        self.insert(first);
+
+        Some(())
+    }
+
+    const fn control(self) -> *mut Control { self.0.cast() }
+
+    const unsafe fn header(self, block: usize) -> *mut BlockHeader { self.0.add(block).cast() }
+
+    const unsafe fn links(self, block: usize) -> *mut FreeLinks { self.0.add(block + HEADER).cast() }
+
+    unsafe fn size(self, block: usize) -> usize { (*self.header(block)).size & !FREE }
+
+    unsafe fn is_free(self, block: usize) -> bool { (*self.header(block)).size & FREE != 0 }
+
+    unsafe fn set_size(self, block: usize, size: usize) {
+        (*self.header(block)).size = size;
+        (*self.header(block + size)).prev_phys = block;
+    }
+
+    unsafe fn insert(self, block: usize) {
+        let (fl, sl) = mapping(self.size(block));
+        let control = self.control();
+
+        let head = (*control).heads[fl][sl];
+        self.links(block).write(FreeLinks { next: head, prev: NIL });
+        if head != NIL {
+            (*self.links(head)).prev = block;
+        }
+
+        (*control).heads[fl][sl] = block;
+        (*control).fl_bitmap |= 1 << fl;
+        (*control).sl_bitmap[fl] |= 1 << sl;
+        (*self.header(block)).size |= FREE;
+    }
+
+    unsafe fn remove(self, block: usize) {
+        let (fl, sl) = mapping(self.size(block));
+        let FreeLinks { next, prev } = self.links(block).read();
+
+        if next != NIL {
+            (*self.links(next)).prev = prev;
+        }
+
+        if prev == NIL {
+            let control = self.control();
+            (*control).heads[fl][sl] = next;
+
+            if next == NIL {
+                (*control).sl_bitmap[fl] &= !(1 << sl);
+                if (*control).sl_bitmap[fl] == 0 {
+                    (*control).fl_bitmap &= !(1 << fl);
+                }
+            }
+        } else {
+            (*self.links(prev)).next = next;
+        }
+
+        (*self.header(block)).size &= !FREE;
+    }
+
+    unsafe fn find(self, size: usize) -> Option<usize> {
+        let (mut fl, sl) = mapping_search(size)?;
+        let control = self.control();
+
+        let mut sl_map = (*control).sl_bitmap[fl] & (!0 << sl);
+        if sl_map == 0 {
+            let fl_map = (*control).fl_bitmap & (!0 << fl << 1);
+            if fl_map == 0 {
+                return None
+            }
+
+            fl = fl_map.trailing_zeros() as usize;
+            sl_map = (*control).sl_bitmap[fl];
+        }
+
+        Some((*control).heads[fl][sl_map.trailing_zeros() as usize])
+    }
+
+    // `block` must be in use, and the next block must not be in a free list
+    unsafe fn release(self, mut block: usize) {
+        let mut size = self.size(block);
+
+        let next = block + size;
+        if self.is_free(next) {
+            self.remove(next);
+            size += self.size(next);
+        }
+
+        let prev = (*self.header(block)).prev_phys;
+        if prev != NIL && self.is_free(prev) {
+            self.remove(prev);
+            size += self.size(prev);
+            block = prev;
+        }
+
+        self.set_size(block, size);
+        self.insert(block);
+    }
+
+    // `block` must be in use
+    unsafe fn split(self, block: usize, size: usize) {
+        let total = self.size(block);
+
+        if total - size >= MIN_BLOCK {
+            let rest = block + size;
+            self.set_size(block, size);
+            self.header(rest).write(BlockHeader {
+                prev_phys: block,
+                size: 0,
+            });
+            self.set_size(rest, total - size);
+            self.release(rest);
+        }
+    }
+
+    fn block_size(layout: Layout) -> Option<usize> {
+        // this is necessary so that the storage can be moved
+        // between allocation and getting the pointer
+        if ALIGN < layout.align() {
+            return None
+        }
+
+        let size = layout.size().checked_add(HEADER + ALIGN - 1)? & !(ALIGN - 1);
+        Some(size.max(MIN_BLOCK))
+    }
+
+    unsafe fn allocate(self, layout: Layout) -> Option<(usize, usize)> {
+        let size = Self::block_size(layout)?;
+        let block = self.find(size)?;
+        self.remove(block);
+        self.split(block, size);
+        Some((block + HEADER, self.size(block) - HEADER))
+    }
+
+    unsafe fn deallocate(self, offset: usize) { self.release(offset - HEADER) }
+}
+
+#[must_use = "storages don't do anything unless they are used"]
+pub struct TlsfStorage<S: Storage> {
+    storage: S,
+    start: S::Handle,
+    layout: Layout,
+    lock: SpinLock,
+}
+
+#[derive(Clone, Copy)]
+pub struct TlsfHandle(usize);
+
+unsafe impl Handle for TlsfHandle {
+    // offsets are never larger than `isize::MAX`, so we can store the dangling pointer directly
+    unsafe fn dangling(align: usize) -> Self { Self(!align) }
+}
+
+impl TlsfHandle {
+    #[must_use = "`TlsfHandle::is_dangling` should be used"]
+    pub const fn is_dangling(self) -> bool { self.0 > isize::MAX as usize }
+}
+
+impl<S: Storage> TlsfStorage<S> {
+    pub fn new(storage: S, space: usize) -> Self { Self::try_new(storage, space).unwrap_or_else(AllocErr::handle) }
+
+    /// # Panics
+    ///
+    /// if `Layout::from_size_align(space, 16)` returns Err
+    pub fn try_new(mut storage: S, space: usize) -> Result<Self, AllocErr> {
+        let layout = Layout::from_size_align(space, ALIGN).unwrap();
+        let memory_block = storage.allocate(layout)?;
+
+        unsafe {
+            let pool = Pool(storage.get_mut(memory_block.handle).as_ptr());
+            if pool.init(memory_block.size).is_none() {
+                storage.deallocate(memory_block.handle, layout);
+                return Err(AllocErr::new(layout))
+            }
+        }
+
+        Ok(Self {
+            storage,
+            start: memory_block.handle,
+            layout,
+            lock: SpinLock::new(),
+        })
+    }
+
+    unsafe fn pool_mut(&mut self) -> Pool { Pool(self.storage.get_mut(self.start).as_ptr()) }
+}
+
+impl<S: Storage> Drop for TlsfStorage<S> {
+    fn drop(&mut self) { unsafe { self.storage.deallocate(self.start, self.layout) } }
+}
+
+unsafe impl<S: Storage> OffsetHandle for TlsfStorage<S> {
+    unsafe fn offset(&mut self, TlsfHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        TlsfHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedOffsetHandle for TlsfStorage<S> {
+    unsafe fn shared_offset(&self, TlsfHandle(handle): Self::Handle, offset: isize) -> Self::Handle {
+        let offset = offset.to_ne_bytes();
+        let offset = usize::from_ne_bytes(offset);
+        TlsfHandle(handle.wrapping_add(offset))
+    }
+}
+
+unsafe impl<S: Storage> FromPtr for TlsfStorage<S> {
+    #[inline]
+    #[allow(clippy::cast_sign_loss)]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle {
+        let origin = self.storage.get(self.start);
+        TlsfHandle(ptr.as_ptr().offset_from(origin.as_ptr()) as usize)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for TlsfStorage<S> {
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.shared_get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+}
+
+impl<S: SharedGetMut> MultiStorage for TlsfStorage<S> {}
+
+unsafe impl<S: Storage> Storage for TlsfStorage<S> {
+    type Handle = TlsfHandle;
+
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> {
+        if handle.is_dangling() {
+            return NonNull::new_unchecked(!handle.0 as *mut u8)
+        }
+
+        let ptr = self.storage.get_mut(self.start);
+        NonNull::new_unchecked(ptr.as_ptr().add(handle.0))
+    }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        unsafe { self.pool_mut().allocate(layout.into()) }
+            .map(|(offset, size)| NonEmptyMemoryBlock {
+                handle: TlsfHandle(offset),
+                size: unsafe { NonZeroUsize::new_unchecked(size) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, TlsfHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        self.pool_mut().deallocate(offset);
+    }
+}
+
+unsafe impl<S: SharedGetMut> ResizableStorage for TlsfStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedStorage for TlsfStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let _guard = self.lock.lock();
+        let pool = Pool(unsafe { self.storage.shared_get_mut(self.start).as_ptr() });
+
+        unsafe { pool.allocate(layout.into()) }
+            .map(|(offset, size)| NonEmptyMemoryBlock {
+                handle: TlsfHandle(offset),
+                size: unsafe { NonZeroUsize::new_unchecked(size) },
+            })
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, TlsfHandle(offset): Self::Handle, _: NonEmptyLayout) {
+        let _guard = self.lock.lock();
+        Pool(self.storage.shared_get_mut(self.start).as_ptr()).deallocate(offset);
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedResizableStorage for TlsfStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn tlsf() {
+    let mut storage = TlsfStorage::new(crate::AllocatorStorage::new(std::alloc::System), 1 << 16);
+
+    let layouts = [8, 24, 300, 1000, 16, 5000, 64].map(|size| Layout::from_size_align(size, 8).unwrap());
+    let handles = layouts.map(|layout| storage.allocate(layout).unwrap().handle);
+    for (&handle, &layout) in handles.iter().zip(&layouts).step_by(2) {
+        unsafe { storage.deallocate(handle, layout) }
+    }
+    for (&handle, &layout) in handles.iter().zip(&layouts).skip(1).step_by(2) {
+        unsafe { storage.deallocate(handle, layout) }
+    }
+
+    // everything was coalesced back into a single block
+    let big = Layout::from_size_align(48_000, 16).unwrap();
+    let memory_block = storage.allocate(big).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, big) }
+
+    crate::storage_conformance!(storage, resizable, shared, shared_resizable);
+}
@@ -0,0 +1,269 @@
+use core::{alloc::Layout, fmt::Debug, ptr::NonNull};
+
+use tracing::{trace, trace_span};
+
+use crate::{
+    AllocErr, Flush, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage,
+    SharedFlush, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that reports everything it does to [`tracing`]
+///
+/// Every operation runs inside of a `trace` level span named after it, with the layout
+/// and handle as fields, and the result is recorded as an event inside of that span.
+/// So anything the underlying storage reports is nested under the request that caused it.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct TracedStorage<S> {
+    storage: S,
+}
+
+impl<S> TracedStorage<S> {
+    pub const fn new(storage: S) -> Self { Self { storage } }
+}
+
+fn allocated<H: Debug>(result: Result<NonEmptyMemoryBlock<H>, AllocErr>) -> Result<NonEmptyMemoryBlock<H>, AllocErr> {
+    if let Ok(memory_block) = &result {
+        trace!(handle = ?memory_block.handle, size = memory_block.size.get(), "allocated");
+    } else {
+        trace!("failed");
+    }
+
+    result
+}
+
+fn resized<H: Debug>(result: Result<MemoryBlock<H>, AllocErr>) -> Result<MemoryBlock<H>, AllocErr> {
+    if let Ok(memory_block) = &result {
+        trace!(handle = ?memory_block.handle, size = memory_block.size, "resized");
+    } else {
+        trace!("failed");
+    }
+
+    result
+}
+
+impl<S: Flush> Flush for TracedStorage<S> {
+    fn try_flush(&mut self) -> bool {
+        trace_span!("try_flush").in_scope(|| {
+            let flushed = self.storage.try_flush();
+            trace!(flushed);
+            flushed
+        })
+    }
+
+    fn flush(&mut self) { trace_span!("flush").in_scope(|| self.storage.flush()) }
+}
+
+impl<S: SharedFlush> SharedFlush for TracedStorage<S> {
+    fn try_shared_flush(&self) -> bool {
+        trace_span!("try_flush").in_scope(|| {
+            let flushed = self.storage.try_shared_flush();
+            trace!(flushed);
+            flushed
+        })
+    }
+
+    fn shared_flush(&self) { trace_span!("flush").in_scope(|| self.storage.shared_flush()) }
+}
+
+unsafe impl<S: FromPtr> FromPtr for TracedStorage<S>
+where
+    S::Handle: Debug,
+{
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for TracedStorage<S>
+where
+    S::Handle: Debug,
+{
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for TracedStorage<S> where S::Handle: Debug {}
+
+unsafe impl<S: Storage> Storage for TracedStorage<S>
+where
+    S::Handle: Debug,
+{
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!("allocate", size = layout.size(), align = layout.align())
+            .in_scope(|| allocated(self.storage.allocate_nonempty(layout)))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        trace_span!("deallocate", ?handle, size = layout.size(), align = layout.align())
+            .in_scope(|| self.storage.deallocate_nonempty(handle, layout));
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!("allocate_zeroed", size = layout.size(), align = layout.align())
+            .in_scope(|| allocated(self.storage.allocate_nonempty_zeroed(layout)))
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for TracedStorage<S>
+where
+    S::Handle: Debug,
+{
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!(
+            "grow",
+            ?handle,
+            old_size = old.size(),
+            new_size = new.size(),
+            align = new.align()
+        )
+        .in_scope(|| resized(self.storage.grow(handle, old, new)))
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!(
+            "grow_zeroed",
+            ?handle,
+            old_size = old.size(),
+            new_size = new.size(),
+            align = new.align()
+        )
+        .in_scope(|| resized(self.storage.grow_zeroed(handle, old, new)))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!(
+            "shrink",
+            ?handle,
+            old_size = old.size(),
+            new_size = new.size(),
+            align = new.align()
+        )
+        .in_scope(|| resized(self.storage.shrink(handle, old, new)))
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for TracedStorage<S>
+where
+    S::Handle: Debug,
+{
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!("allocate", size = layout.size(), align = layout.align())
+            .in_scope(|| allocated(self.storage.shared_allocate_nonempty(layout)))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        trace_span!("deallocate", ?handle, size = layout.size(), align = layout.align())
+            .in_scope(|| self.storage.shared_deallocate_nonempty(handle, layout));
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!("allocate_zeroed", size = layout.size(), align = layout.align())
+            .in_scope(|| allocated(self.storage.shared_allocate_nonempty_zeroed(layout)))
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for TracedStorage<S>
+where
+    S::Handle: Debug,
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!(
+            "grow",
+            ?handle,
+            old_size = old.size(),
+            new_size = new.size(),
+            align = new.align()
+        )
+        .in_scope(|| resized(self.storage.shared_grow(handle, old, new)))
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!(
+            "grow_zeroed",
+            ?handle,
+            old_size = old.size(),
+            new_size = new.size(),
+            align = new.align()
+        )
+        .in_scope(|| resized(self.storage.shared_grow_zeroed(handle, old, new)))
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        trace_span!(
+            "shrink",
+            ?handle,
+            old_size = old.size(),
+            new_size = new.size(),
+            align = new.align()
+        )
+        .in_scope(|| resized(self.storage.shared_shrink(handle, old, new)))
+    }
+}
+
+#[test]
+fn traced() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut storage = TracedStorage::new(crate::DeferredFreeStorage::new(crate::FlushBarrier::new(&mock)));
+
+    let layout = Layout::new::<[u8; 32]>();
+    let memory_block = storage.allocate(layout).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, layout) }
+    assert_eq!(mock.live_allocations(), 1);
+
+    // flushes are passed through to the underlying storage
+    storage.flush();
+    assert_eq!(mock.live_allocations(), 0);
+
+    drop(storage);
+    mock.clear_events();
+
+    crate::storage_conformance!(TracedStorage::new(mock), resizable, shared, shared_resizable);
+}
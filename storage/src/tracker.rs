@@ -0,0 +1,66 @@
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::{Ref, RefCell},
+    ptr::NonNull,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    pub ptr: NonNull<u8>,
+    pub layout: Layout,
+    pub size: usize,
+}
+
+/// Keeps track of all live allocations of a storage by their address
+///
+/// zero-sized allocations are dangling, so they are never tracked
+pub struct Tracker {
+    live: RefCell<Vec<Allocation>>,
+}
+
+impl Tracker {
+    pub const fn new() -> Self {
+        Self {
+            live: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn live(&self) -> Ref<'_, [Allocation]> { Ref::map(self.live.borrow(), Vec::as_slice) }
+
+    pub fn track(&self, ptr: NonNull<u8>, layout: Layout, size: usize) {
+        if layout.size() != 0 {
+            self.live.borrow_mut().push(Allocation { ptr, layout, size });
+        }
+    }
+
+    pub fn retrack(&self, allocation: Allocation) { self.live.borrow_mut().push(allocation) }
+
+    /// # Panics
+    ///
+    /// if `ptr` isn't a live allocation, or if it was allocated with a layout that doesn't fit `layout`
+    pub fn untrack(&self, ptr: NonNull<u8>, layout: Layout) -> Option<Allocation> {
+        if layout.size() == 0 {
+            return None
+        }
+
+        let mut live = self.live.borrow_mut();
+        let index = live.iter().position(|allocation| allocation.ptr == ptr);
+        let index = index.unwrap_or_else(|| panic!("{:p} is not a live allocation (double free?)", ptr));
+        let allocation = live.swap_remove(index);
+
+        // the layout may be anywhere between the requested and the returned size, but must have the same alignment
+        assert!(
+            layout.align() == allocation.layout.align()
+                && allocation.layout.size() <= layout.size()
+                && layout.size() <= allocation.size,
+            "{:p} was allocated with {:?} (size: {}), but deallocated with {:?}",
+            ptr,
+            allocation.layout,
+            allocation.size,
+            layout
+        );
+
+        Some(allocation)
+    }
+}
@@ -0,0 +1,366 @@
+use alloc::vec::Vec;
+use core::{alloc::Layout, cell::UnsafeCell, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    ResizableStorage, SharedGetMut, SharedResizableStorage, SharedStorage, Storage,
+};
+
+// the number of freed handles that are remembered, to tell double frees apart from unknown handles
+const RECENT: usize = 32;
+
+#[derive(Clone, Copy)]
+struct Live<H> {
+    handle: H,
+    layout: Layout,
+    size: usize,
+}
+
+struct State<H> {
+    live: Vec<Live<H>>,
+    freed: [Option<H>; RECENT],
+    next: usize,
+}
+
+impl<H: Copy + PartialEq> State<H> {
+    fn track(&mut self, live: Live<H>) {
+        if let Some(freed) = self.freed.iter_mut().find(|freed| **freed == Some(live.handle)) {
+            *freed = None;
+        }
+
+        self.live.push(live);
+    }
+
+    fn untrack(&mut self, handle: H, layout: Layout) -> Live<H> {
+        let Some(index) = self.live.iter().position(|live| live.handle == handle) else {
+            assert!(
+                !self.freed.contains(&Some(handle)),
+                "deallocated with {:?}, but the handle was already deallocated (double free)",
+                layout
+            );
+            panic!("deallocated with {:?}, but the handle was never allocated", layout)
+        };
+
+        let live = self.live.swap_remove(index);
+
+        // the layout may be anywhere between the requested and the returned size, but must have the same alignment
+        assert!(
+            layout.align() == live.layout.align() && live.layout.size() <= layout.size() && layout.size() <= live.size,
+            "allocated with {:?} (size: {}), but deallocated with {:?}",
+            live.layout,
+            live.size,
+            layout
+        );
+
+        live
+    }
+
+    const fn freed(&mut self, handle: H) {
+        self.freed[self.next] = Some(handle);
+        self.next = (self.next + 1) % RECENT;
+    }
+}
+
+/// A storage that checks every handle and layout that is passed to it, and panics if
+/// they couldn't have come from a live allocation
+///
+/// This catches double frees, handles that were never allocated by this storage, and allocations that are
+/// deallocated or resized with a layout that doesn't fit them. The layout fits if it has the same alignment
+/// as the one it was allocated with, and its size is between the requested and the returned size.
+///
+/// Every live allocation is recorded, so this is meant for debug builds and tests.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct ValidatingStorage<S: Storage> {
+    storage: S,
+    state: UnsafeCell<State<S::Handle>>,
+    lock: SpinLock,
+}
+
+unsafe impl<S: Storage + Send> Send for ValidatingStorage<S> where S::Handle: Send {}
+unsafe impl<S: Storage + Sync> Sync for ValidatingStorage<S> where S::Handle: Send {}
+
+impl<S: Storage> ValidatingStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            state: UnsafeCell::new(State {
+                live: Vec::new(),
+                freed: [None; RECENT],
+                next: 0,
+            }),
+            lock: SpinLock::new(),
+        }
+    }
+
+    /// The number of allocations that haven't been deallocated yet
+    pub fn live_allocations(&self) -> usize {
+        let _guard = self.lock.lock();
+        unsafe { (*self.state.get()).live.len() }
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut State<S::Handle>) -> R) -> R {
+        let _guard = self.lock.lock();
+        f(unsafe { &mut *self.state.get() })
+    }
+}
+
+impl<S: Storage> ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    fn allocated(
+        &self,
+        layout: NonEmptyLayout,
+        result: Result<NonEmptyMemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<NonEmptyMemoryBlock<S::Handle>, AllocErr> {
+        if let Ok(memory_block) = &result {
+            self.with_state(|state| {
+                state.track(Live {
+                    handle: memory_block.handle,
+                    layout: layout.into(),
+                    size: memory_block.size.get(),
+                });
+            });
+        }
+
+        result
+    }
+
+    fn deallocated(&self, handle: S::Handle, layout: NonEmptyLayout) {
+        self.with_state(|state| {
+            state.untrack(handle, layout.into());
+            state.freed(handle);
+        });
+    }
+
+    // zero-sized allocations are dangling, so they are never tracked
+    fn resizing(&self, handle: S::Handle, old: Layout) -> Option<Live<S::Handle>> {
+        if old.size() == 0 {
+            None
+        } else {
+            Some(self.with_state(|state| state.untrack(handle, old)))
+        }
+    }
+
+    fn resized(
+        &self,
+        old: Option<Live<S::Handle>>,
+        new: Layout,
+        result: Result<MemoryBlock<S::Handle>, AllocErr>,
+    ) -> Result<MemoryBlock<S::Handle>, AllocErr> {
+        self.with_state(|state| match &result {
+            Ok(memory_block) => {
+                if let Some(old) = old {
+                    state.freed(old.handle);
+                }
+
+                if new.size() != 0 {
+                    state.track(Live {
+                        handle: memory_block.handle,
+                        layout: new,
+                        size: memory_block.size,
+                    });
+                }
+            }
+            Err(_) => {
+                if let Some(old) = old {
+                    state.track(old);
+                }
+            }
+        });
+
+        result
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for ValidatingStorage<S> where S::Handle: PartialEq {}
+
+unsafe impl<S: Storage> Storage for ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.allocate_nonempty(layout);
+        self.allocated(layout, result)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.deallocated(handle, layout);
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let result = self.storage.allocate_nonempty_zeroed(layout);
+        self.allocated(layout, result)
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let live = self.resizing(handle, old);
+        let result = self.storage.grow(handle, old, new);
+        self.resized(live, new, result)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let live = self.resizing(handle, old);
+        let result = self.storage.grow_zeroed(handle, old, new);
+        self.resized(live, new, result)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let live = self.resizing(handle, old);
+        let result = self.storage.shrink(handle, old, new);
+        self.resized(live, new, result)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocated(layout, self.storage.shared_allocate_nonempty(layout))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.deallocated(handle, layout);
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.allocated(layout, self.storage.shared_allocate_nonempty_zeroed(layout))
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for ValidatingStorage<S>
+where
+    S::Handle: PartialEq,
+{
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let live = self.resizing(handle, old);
+        self.resized(live, new, self.storage.shared_grow(handle, old, new))
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let live = self.resizing(handle, old);
+        self.resized(live, new, self.storage.shared_grow_zeroed(handle, old, new))
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let live = self.resizing(handle, old);
+        self.resized(live, new, self.storage.shared_shrink(handle, old, new))
+    }
+}
+
+#[test]
+fn validating() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = ValidatingStorage::new(system);
+
+    let layout = Layout::new::<[u8; 32]>();
+    let memory_block = storage.allocate(layout).unwrap();
+    assert_eq!(storage.live_allocations(), 1);
+
+    unsafe {
+        let memory_block = storage
+            .grow(memory_block.handle, layout, Layout::new::<[u8; 64]>())
+            .unwrap();
+        storage.deallocate(memory_block.handle, Layout::new::<[u8; 64]>());
+    }
+
+    assert_eq!(storage.live_allocations(), 0);
+
+    crate::storage_conformance!(ValidatingStorage::new(system), resizable, shared, shared_resizable);
+}
+
+#[test]
+#[should_panic = "already deallocated (double free)"]
+fn validating_double_free() {
+    let mut storage = ValidatingStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let layout = Layout::new::<u32>();
+    let memory_block = storage.allocate(layout).unwrap();
+
+    unsafe {
+        storage.deallocate(memory_block.handle, layout);
+        storage.deallocate(memory_block.handle, layout);
+    }
+}
+
+#[test]
+#[should_panic = "but deallocated with"]
+fn validating_layout_mismatch() {
+    let mut storage = ValidatingStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let memory_block = storage.allocate(Layout::new::<u32>()).unwrap();
+    unsafe { storage.deallocate(memory_block.handle, Layout::new::<u64>()) }
+}
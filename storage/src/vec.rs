@@ -1,6 +1,14 @@
-use core::{intrinsics::assume, mem::MaybeUninit};
+use core::{
+    alloc::Layout,
+    intrinsics::assume,
+    iter::{FromIterator, FusedIterator},
+    mem,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+    ptr, slice,
+};
 
-use crate::{boxed::Box, AllocErr, ResizableStorage, Storage};
+use crate::{boxed::Box, scope_guard::ScopeGuard, AllocErr, ResizableStorage, SpillStorage, Storage, TryCloneIn};
 
 pub struct Vec<T, S: Storage = crate::Global> {
     len: usize,
@@ -16,6 +24,29 @@ impl<T> Vec<T> {
     }
 
     pub fn with_capacity(capacity: usize) -> Self { Self::with_capacity_in(capacity, crate::Global) }
+
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, AllocErr> {
+        Self::try_with_capacity_in(capacity, crate::Global).map_err(|err| AllocErr::new(err.0))
+    }
+}
+
+/// A [`Vec`] that keeps up to `N` elements inline, and spills into `S` once it grows past that
+pub type InlineVec<T, const N: usize, S = crate::Global> = Vec<T, SpillStorage<[T; N], S>>;
+
+impl<T, const N: usize> InlineVec<T, N> {
+    pub fn new_inline() -> Self { Self::new_inline_in(crate::Global) }
+}
+
+impl<T, const N: usize, S: Storage> InlineVec<T, N, S> {
+    /// Creates an empty vector with space for `N` elements inline
+    pub fn new_inline_in(spill: S) -> Self { Self::with_capacity_in(N, SpillStorage::new(spill)) }
+
+    /// Whether the elements are stored inline, rather than in the spill storage
+    pub const fn is_inline(&self) -> bool { Box::handle(&self.raw).is_inline() }
+}
+
+impl<T> FromIterator<T> for Vec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self { Self::from_iter_in(iter, crate::Global) }
 }
 
 impl<T, S: Storage> Vec<T, S> {
@@ -37,6 +68,20 @@ impl<T, S: Storage> Vec<T, S> {
         })
     }
 
+    pub fn from_array_in<const N: usize>(array: [T; N], storage: S) -> Self {
+        Self::try_from_array_in(array, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_from_array_in<const N: usize>(array: [T; N], storage: S) -> Result<Self, AllocErr> {
+        let mut vec = Self::try_with_capacity_in(N, storage).map_err(|err| AllocErr::new(err.0))?;
+
+        for value in array {
+            unsafe { vec.push_unchecked(value) }
+        }
+
+        Ok(vec)
+    }
+
     pub fn is_empty(&self) -> bool { self.len == 0 }
 
     #[inline]
@@ -94,24 +139,453 @@ impl<T, S: Storage> Vec<T, S> {
             Some(unsafe { self.pop_unchecked() })
         }
     }
+
+    pub fn as_ptr(&self) -> *const T { self.raw.as_ptr().cast() }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T { self.raw.as_mut_ptr().cast() }
+
+    pub fn as_slice(&self) -> &[T] { unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) } }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] { unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len()) } }
+
+    pub fn clear(&mut self) { self.truncate(0) }
+
+    pub fn truncate(&mut self, len: usize) {
+        let old_len = self.len();
+        if len >= old_len {
+            return
+        }
+
+        // the length is set first, so if a destructor panics the rest are leaked instead of dropped twice
+        self.len = len;
+        unsafe {
+            let tail = ptr::slice_from_raw_parts_mut(self.as_mut_ptr().add(len), old_len - len);
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    /// # Panics
+    ///
+    /// if `index >= self.len()`
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        self.try_remove(index)
+            .unwrap_or_else(|| panic!("removal index (is {}) should be < len (is {})", index, len))
+    }
+
+    pub fn try_remove(&mut self, index: usize) -> Option<T> {
+        let len = self.len();
+        if index >= len {
+            return None
+        }
+
+        unsafe {
+            let hole = self.as_mut_ptr().add(index);
+            let value = hole.read();
+            hole.copy_from(hole.add(1), len - index - 1);
+            self.len = len - 1;
+            Some(value)
+        }
+    }
+
+    /// # Panics
+    ///
+    /// if `index >= self.len()`
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        self.try_swap_remove(index)
+            .unwrap_or_else(|| panic!("swap_remove index (is {}) should be < len (is {})", index, len))
+    }
+
+    pub fn try_swap_remove(&mut self, index: usize) -> Option<T> {
+        let len = self.len();
+        if index >= len {
+            return None
+        }
+
+        unsafe {
+            let ptr = self.as_mut_ptr();
+            let value = ptr.add(index).read();
+            ptr.add(index).copy_from(ptr.add(len - 1), 1);
+            self.len = len - 1;
+            Some(value)
+        }
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) { self.retain_mut(|value| f(value)) }
+
+    pub fn retain_mut(&mut self, mut f: impl FnMut(&mut T) -> bool) {
+        let mut compact = Compact::new(self, 0);
+
+        while compact.read < compact.len {
+            unsafe {
+                let ptr = compact.vec.as_mut_ptr();
+                let current = ptr.add(compact.read);
+                let keep = f(&mut *current);
+                compact.read += 1;
+
+                if keep {
+                    ptr.add(compact.write).copy_from(current, 1);
+                    compact.write += 1;
+                } else {
+                    current.drop_in_place();
+                }
+            }
+        }
+    }
+
+    pub fn dedup_by_key<K: PartialEq>(&mut self, mut key: impl FnMut(&mut T) -> K) {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements that `same_bucket` says are the same, keeping the first one
+    ///
+    /// `same_bucket` is passed the element that may be removed first, and the one it would be a duplicate of second
+    pub fn dedup_by(&mut self, mut same_bucket: impl FnMut(&mut T, &mut T) -> bool) {
+        if self.len() <= 1 {
+            return
+        }
+
+        let mut compact = Compact::new(self, 1);
+
+        while compact.read < compact.len {
+            unsafe {
+                let ptr = compact.vec.as_mut_ptr();
+                let current = ptr.add(compact.read);
+                let duplicate = same_bucket(&mut *current, &mut *ptr.add(compact.write - 1));
+                compact.read += 1;
+
+                if duplicate {
+                    current.drop_in_place();
+                } else {
+                    ptr.add(compact.write).copy_from(current, 1);
+                    compact.write += 1;
+                }
+            }
+        }
+    }
+    /// Removes the elements in `range`, and returns an iterator over them
+    ///
+    /// The elements after `range` are moved back when the iterator is dropped, along with
+    /// any elements in `range` that the iterator didn't yield. If the iterator is leaked,
+    /// the elements in and after `range` may be leaked as well.
+    ///
+    /// # Panics
+    ///
+    /// if the start of `range` is after the end, or the end is after `self.len()`
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T, S> {
+        let len = self.len();
+
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start
+                .checked_add(1)
+                .unwrap_or_else(|| panic!("attempted to drain from after maximum usize")),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end
+                .checked_add(1)
+                .unwrap_or_else(|| panic!("attempted to drain up to maximum usize")),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+
+        assert!(start <= end, "drain index starts at {} but ends at {}", start, end);
+        assert!(end <= len, "drain end index (is {}) should be <= len (is {})", end, len);
+
+        // the drained elements and the tail are owned by the `Drain` until it's dropped
+        self.len = start;
+
+        Drain {
+            vec: self,
+            front: start,
+            back: end,
+            tail_start: end,
+            tail_len: len - end,
+        }
+    }
+
+    /// Moves the elements from `at` on into a new vector in `storage`
+    ///
+    /// # Panics
+    ///
+    /// if `at > self.len()`
+    pub fn split_off_in<S2: Storage>(&mut self, at: usize, storage: S2) -> Vec<T, S2> {
+        self.try_split_off_in(at, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Moves the elements from `at` on into a new vector in `storage`
+    ///
+    /// If the new vector can't be allocated, `self` is left unchanged
+    ///
+    /// # Panics
+    ///
+    /// if `at > self.len()`
+    pub fn try_split_off_in<S2: Storage>(&mut self, at: usize, storage: S2) -> Result<Vec<T, S2>, AllocErr> {
+        let len = self.len();
+        assert!(at <= len, "split_off index (is {}) should be <= len (is {})", at, len);
+
+        let tail_len = len - at;
+        let mut tail = Vec::<T, S2>::try_with_capacity_in(tail_len, storage).map_err(|err| AllocErr::new(err.0))?;
+
+        unsafe {
+            tail.as_mut_ptr()
+                .copy_from_nonoverlapping(self.as_ptr().add(at), tail_len);
+            self.len = at;
+            tail.set_len(tail_len);
+        }
+
+        Ok(tail)
+    }
+
+    /// Moves the elements from `at` on into a new vector in a clone of this vector's storage
+    ///
+    /// # Panics
+    ///
+    /// if `at > self.len()`
+    #[must_use = "use `truncate` if you don't need the split off elements"]
+    pub fn split_off(&mut self, at: usize) -> Self
+    where
+        S: Clone,
+    {
+        self.try_split_off(at).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Moves the elements from `at` on into a new vector in a clone of this vector's storage
+    ///
+    /// If the new vector can't be allocated, `self` is left unchanged
+    ///
+    /// # Panics
+    ///
+    /// if `at > self.len()`
+    pub fn try_split_off(&mut self, at: usize) -> Result<Self, AllocErr>
+    where
+        S: Clone,
+    {
+        let storage = Box::storage(&self.raw).clone();
+        self.try_split_off_in(at, storage)
+    }
+}
+
+/// An iterator that removes a range of elements from a [`Vec`], created by [`Vec::drain`]
+pub struct Drain<'a, T, S: Storage = crate::Global> {
+    vec: &'a mut Vec<T, S>,
+    front: usize,
+    back: usize,
+    tail_start: usize,
+    tail_len: usize,
+}
+
+impl<T, S: Storage> Drain<'_, T, S> {
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.vec.as_ptr().add(self.front), self.back - self.front) }
+    }
+}
+
+impl<T, S: Storage> Iterator for Drain<'_, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None
+        }
+
+        let value = unsafe { self.vec.as_ptr().add(self.front).read() };
+        self.front += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T, S: Storage> DoubleEndedIterator for Drain<'_, T, S> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None
+        }
+
+        self.back -= 1;
+        Some(unsafe { self.vec.as_ptr().add(self.back).read() })
+    }
+}
+
+impl<T, S: Storage> ExactSizeIterator for Drain<'_, T, S> {}
+
+impl<T, S: Storage> FusedIterator for Drain<'_, T, S> {}
+
+impl<T, S: Storage> Drop for Drain<'_, T, S> {
+    fn drop(&mut self) {
+        let remaining =
+            unsafe { ptr::slice_from_raw_parts_mut(self.vec.as_mut_ptr().add(self.front), self.back - self.front) };
+        self.front = self.back;
+
+        let tail_start = self.tail_start;
+        let tail_len = self.tail_len;
+        let vec = &mut *self.vec;
+
+        // move the tail back even if one of the remaining elements panics while being dropped
+        let _guard = ScopeGuard::new(move || unsafe {
+            let start = vec.len();
+            let ptr = vec.as_mut_ptr();
+            ptr.add(start).copy_from(ptr.add(tail_start), tail_len);
+            vec.len = start + tail_len;
+        });
+
+        unsafe { ptr::drop_in_place(remaining) }
+    }
+}
+
+// removes elements in place, the elements before `write` are kept, and the ones from `read` on
+// haven't been looked at yet. When dropped, the elements that weren't looked at are moved back
+// to close the gap, which only matters if a closure or destructor panicked part way through
+struct Compact<'a, T, S: Storage> {
+    vec: &'a mut Vec<T, S>,
+    read: usize,
+    write: usize,
+    len: usize,
+}
+
+impl<'a, T, S: Storage> Compact<'a, T, S> {
+    fn new(vec: &'a mut Vec<T, S>, start: usize) -> Self {
+        let len = vec.len();
+        // the elements between `write` and `read` are already gone, so if this
+        // doesn't get dropped, the rest of the elements are leaked instead
+        vec.len = 0;
+        Self {
+            vec,
+            read: start,
+            write: start,
+            len,
+        }
+    }
+}
+
+impl<T, S: Storage> Drop for Compact<'_, T, S> {
+    fn drop(&mut self) {
+        unsafe {
+            let ptr = self.vec.as_mut_ptr();
+            ptr.add(self.write).copy_from(ptr.add(self.read), self.len - self.read);
+            self.vec.len = self.write + (self.len - self.read);
+        }
+    }
+}
+
+impl<T, S: Storage> Drop for Vec<T, S> {
+    fn drop(&mut self) { self.clear() }
+}
+
+impl<T, S: Storage> Deref for Vec<T, S> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] { self.as_slice() }
+}
+
+impl<T, S: Storage> DerefMut for Vec<T, S> {
+    fn deref_mut(&mut self) -> &mut [T] { self.as_mut_slice() }
+}
+
+impl<T: PartialEq, S: Storage> Vec<T, S> {
+    pub fn dedup(&mut self) { self.dedup_by(|a, b| a == b) }
+}
+
+impl<T: Clone, S: Storage> Vec<T, S> {
+    pub fn from_elem_in(elem: T, len: usize, storage: S) -> Self {
+        Self::try_from_elem_in(elem, len, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Creates a vector with `len` clones of `elem`
+    pub fn try_from_elem_in(elem: T, len: usize, storage: S) -> Result<Self, AllocErr> {
+        let mut vec = Self::try_with_capacity_in(len, storage).map_err(|err| AllocErr::new(err.0))?;
+
+        if len != 0 {
+            unsafe {
+                for _ in 1..len {
+                    vec.push_unchecked(elem.clone());
+                }
+
+                vec.push_unchecked(elem);
+            }
+        }
+
+        Ok(vec)
+    }
+
+    pub fn from_slice_in(slice: &[T], storage: S) -> Self {
+        Self::try_from_slice_in(slice, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_from_slice_in(slice: &[T], storage: S) -> Result<Self, AllocErr> {
+        let mut vec = Self::try_with_capacity_in(slice.len(), storage).map_err(|err| AllocErr::new(err.0))?;
+        unsafe { vec.extend_from_slice_unchecked(slice) }
+        Ok(vec)
+    }
+
+    /// # Safety
+    ///
+    /// `self` must have space for at least `slice.len()` more elements
+    unsafe fn extend_from_slice_unchecked(&mut self, slice: &[T]) {
+        let ptr = self.as_mut_ptr();
+        // the length is written back at the end, or if a clone panics, so the ones before it are kept
+        let mut len = ScopeGuard::with_extra((self.len, &mut self.len), |(len, vec_len)| *vec_len = len);
+
+        for value in slice {
+            let (len, _) = len.extra_mut();
+            ptr.add(*len).write(value.clone());
+            *len += 1;
+        }
+    }
+}
+
+impl<T: Copy, S: Storage> Vec<T, S> {
+    pub fn from_copy_slice_in(slice: &[T], storage: S) -> Self {
+        Self::try_from_copy_slice_in(slice, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    /// Creates a vector with a copy of `slice`, copying all of the elements at once
+    pub fn try_from_copy_slice_in(slice: &[T], storage: S) -> Result<Self, AllocErr> {
+        let mut vec = Self::try_with_capacity_in(slice.len(), storage).map_err(|err| AllocErr::new(err.0))?;
+        unsafe { vec.extend_from_copy_slice_unchecked(slice) }
+        Ok(vec)
+    }
+
+    /// # Safety
+    ///
+    /// `self` must have space for at least `slice.len()` more elements
+    unsafe fn extend_from_copy_slice_unchecked(&mut self, slice: &[T]) {
+        let len = self.len;
+        self.as_mut_ptr()
+            .add(len)
+            .copy_from_nonoverlapping(slice.as_ptr(), slice.len());
+        self.len = len + slice.len();
+    }
 }
 
 impl<T, S: ResizableStorage> Vec<T, S> {
     #[cold]
     #[inline(never)]
-    pub fn try_reserve_slow(&mut self, new_capacity: usize) -> Result<(), AllocErr> { self.raw.try_grow(new_capacity) }
+    pub fn try_reserve_slow(&mut self, new_capacity: usize) -> Result<(), AllocErr> {
+        if new_capacity > Self::max_capacity() {
+            return Err(Self::capacity_overflow())
+        }
+
+        self.raw.try_grow(new_capacity)
+    }
 
     #[inline]
     pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocErr> {
         let len = self.len();
         if self.capacity().wrapping_sub(len) < additional {
-            self.try_reserve_slow(len.wrapping_add(additional))?
+            self.try_reserve_slow(len.saturating_add(additional))?;
         }
         unsafe {
             assume(len == self.len());
-            assume(self.len() < self.capacity());
             let remaining = self.raw[len..].len();
-            assume(remaining <= additional);
+            assume(remaining >= additional);
             Ok(())
         }
     }
@@ -119,11 +593,587 @@ impl<T, S: ResizableStorage> Vec<T, S> {
     #[inline]
     pub fn reserve(&mut self, additional: usize) { self.try_reserve(additional).unwrap_or_else(AllocErr::handle) }
 
-    pub fn push(&mut self, value: T) {
-        if self.len().wrapping_add(1) == self.capacity() {
-            self.reserve(1);
+    // grows to at least twice the capacity, so that pushing one element at a time
+    // doesn't have to resize the allocation every time
+    #[cold]
+    #[inline(never)]
+    fn try_grow_amortized(&mut self, additional: usize) -> Result<(), AllocErr> {
+        let max_capacity = Self::max_capacity();
+        let required = self.len().saturating_add(additional);
+        if required > max_capacity {
+            return Err(Self::capacity_overflow())
+        }
+
+        let new_capacity = required.max(self.capacity().saturating_mul(2)).max(4).min(max_capacity);
+        self.raw.try_grow(new_capacity)
+    }
+
+    fn max_capacity() -> usize { isize::MAX.cast_unsigned() / mem::size_of::<T>().max(1) }
+
+    // there's no layout for a capacity that's too large, so this reports the layout of a single element
+    const fn capacity_overflow() -> AllocErr { AllocErr::new(Layout::new::<T>()) }
+
+    pub fn push(&mut self, value: T) { self.try_push_grow(value).unwrap_or_else(AllocErr::handle) }
+
+    /// Pushes `value`, growing the vector if there isn't space for it (unlike [`Vec::try_push`])
+    ///
+    /// If the vector can't grow, `value` is returned in the error
+    pub fn try_push_grow(&mut self, value: T) -> Result<(), AllocErr<T>> {
+        if self.len() == self.capacity() {
+            if let Err(err) = self.try_grow_amortized(1) {
+                return Err(err.with(value))
+            }
         }
 
         unsafe { self.push_unchecked(value) }
+        Ok(())
+    }
+
+    pub fn shrink_to_fit(&mut self) { self.shrink_to(0) }
+
+    pub fn try_shrink_to_fit(&mut self) -> Result<(), AllocErr> { self.try_shrink_to(0) }
+
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.try_shrink_to(min_capacity).unwrap_or_else(AllocErr::handle);
+    }
+
+    /// Shrinks the capacity to `min_capacity`, or the length if that's larger
+    ///
+    /// Does nothing if the capacity is already smaller
+    pub fn try_shrink_to(&mut self, min_capacity: usize) -> Result<(), AllocErr> {
+        let new_capacity = self.len().max(min_capacity);
+        if self.capacity() > new_capacity {
+            self.raw.try_shrink(new_capacity)?;
+        }
+
+        Ok(())
+    }
+
+    /// # Panics
+    ///
+    /// if `index > self.len()`
+    pub fn insert(&mut self, index: usize, value: T) { self.try_insert(index, value).unwrap_or_else(AllocErr::handle) }
+
+    /// If the vector can't grow, `value` is returned in the error
+    ///
+    /// # Panics
+    ///
+    /// if `index > self.len()`
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), AllocErr<T>> {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            len
+        );
+
+        if len == self.capacity() {
+            if let Err(err) = self.try_grow_amortized(1) {
+                return Err(err.with(value))
+            }
+        }
+
+        unsafe {
+            let hole = self.as_mut_ptr().add(index);
+            hole.add(1).copy_from(hole, len - index);
+            hole.write(value);
+            self.len = len + 1;
+        }
+
+        Ok(())
+    }
+
+    /// Converts the vector into a boxed slice, shrinking the allocation to fit the elements
+    pub fn into_boxed_slice(self) -> Box<[T], S> { self.try_into_boxed_slice().unwrap_or_else(AllocErr::handle) }
+
+    /// Converts the vector into a boxed slice, shrinking the allocation to fit the elements
+    ///
+    /// If the allocation can't be shrunk, the vector is returned in the error
+    pub fn try_into_boxed_slice(mut self) -> Result<Box<[T], S>, AllocErr<Self>> {
+        let len = self.len();
+        if let Err(err) = self.try_shrink_to_fit() {
+            return Err(err.with(self))
+        }
+
+        // the storage may have given back more space than was asked for, but
+        // the allocation still fits a layout with exactly `len` elements
+        let this = ManuallyDrop::new(self);
+        let raw = unsafe { ptr::read(&raw const this.raw) };
+        let (handle, _, storage) = Box::into_raw_parts(raw);
+        Ok(unsafe { Box::from_raw_parts(handle, len, storage) })
+    }
+
+    pub fn from_iter_in<I: IntoIterator<Item = T>>(iter: I, storage: S) -> Self {
+        Self::try_from_iter_in(iter, storage).unwrap_or_else(AllocErr::handle)
+    }
+
+    pub fn try_from_iter_in<I: IntoIterator<Item = T>>(iter: I, storage: S) -> Result<Self, AllocErr> {
+        let mut vec = Self::new_in(storage);
+        vec.try_extend(iter)?;
+        Ok(vec)
+    }
+
+    /// Pushes every element of `iter`
+    ///
+    /// If the vector can't grow, everything that fit stays in the vector and the rest of `iter` is dropped
+    pub fn try_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), AllocErr> {
+        let iter = iter.into_iter();
+        // reserving up front is only an optimization, whatever fits is still pushed if it fails
+        let _ = self.try_reserve(iter.size_hint().0);
+
+        for value in iter {
+            if self.len() == self.capacity() {
+                self.try_grow_amortized(1)?;
+            }
+
+            unsafe { self.push_unchecked(value) }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Clone, S: ResizableStorage> Vec<T, S> {
+    pub fn extend_from_slice(&mut self, slice: &[T]) {
+        self.try_extend_from_slice(slice).unwrap_or_else(AllocErr::handle);
+    }
+
+    /// Clones every element of `slice` onto the end of the vector
+    ///
+    /// If the vector can't grow, it's left unchanged
+    pub fn try_extend_from_slice(&mut self, slice: &[T]) -> Result<(), AllocErr> {
+        self.try_reserve(slice.len())?;
+        unsafe { self.extend_from_slice_unchecked(slice) }
+        Ok(())
+    }
+
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        self.try_resize(new_len, value).unwrap_or_else(AllocErr::handle);
     }
+
+    /// Resizes the vector to `new_len`, either by removing elements from the end,
+    /// or by filling the new space with clones of `value`
+    ///
+    /// If the vector can't grow, it's left unchanged
+    pub fn try_resize(&mut self, new_len: usize, value: T) -> Result<(), AllocErr> {
+        let len = self.len();
+        if new_len <= len {
+            self.truncate(new_len);
+            return Ok(())
+        }
+
+        self.try_reserve(new_len - len)?;
+
+        unsafe {
+            for _ in len + 1..new_len {
+                self.push_unchecked(value.clone());
+            }
+
+            self.push_unchecked(value);
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Copy, S: ResizableStorage> Vec<T, S> {
+    pub fn extend_from_copy_slice(&mut self, slice: &[T]) {
+        self.try_extend_from_copy_slice(slice).unwrap_or_else(AllocErr::handle);
+    }
+
+    /// Copies all of the elements of `slice` onto the end of the vector at once
+    ///
+    /// If the vector can't grow, it's left unchanged
+    pub fn try_extend_from_copy_slice(&mut self, slice: &[T]) -> Result<(), AllocErr> {
+        self.try_reserve(slice.len())?;
+        unsafe { self.extend_from_copy_slice_unchecked(slice) }
+        Ok(())
+    }
+}
+
+impl<T, S: Storage> From<Box<[T], S>> for Vec<T, S> {
+    fn from(boxed: Box<[T], S>) -> Self {
+        let (handle, len, storage) = Box::into_raw_parts(boxed);
+        Self {
+            len,
+            raw: unsafe { Box::from_raw_parts(handle, len, storage) },
+        }
+    }
+}
+
+impl<T, S: ResizableStorage> From<Vec<T, S>> for Box<[T], S> {
+    fn from(vec: Vec<T, S>) -> Self { vec.into_boxed_slice() }
+}
+
+impl<T, S: ResizableStorage> Extend<T> for Vec<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) { self.try_extend(iter).unwrap_or_else(AllocErr::handle) }
+}
+
+impl<T: Clone, S: Storage, S2: Storage> TryCloneIn<S2> for Vec<T, S> {
+    type Output = Vec<T, S2>;
+
+    fn try_clone_in(&self, storage: S2) -> Result<Self::Output, AllocErr> { Vec::try_from_slice_in(self, storage) }
+}
+
+#[test]
+fn vec_from_iter() {
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = Vec::from_iter_in(0..10_u32, &mock);
+    assert_eq!(vec.len(), 10);
+    vec.extend((10..100).filter(|x| x % 2 == 0));
+    assert_eq!(vec.len(), 55);
+    assert!(vec.try_extend(Some(100)).is_ok());
+
+    let expected = (0..10).chain((10..101).step_by(2)).rev();
+    assert!(core::iter::from_fn(|| vec.try_pop()).eq(expected));
+    drop(vec);
+
+    assert_eq!(mock.live_allocations(), 0);
+    mock.clear_events();
+
+    // a vector that can't grow still takes everything that fits
+    let mut vec = Vec::<u32, _>::new_in(crate::BumpStorage::<_, 4>::new(&mock, 16));
+    assert!(vec.try_extend(0..8).is_err());
+    assert_eq!(vec.len(), 4);
+}
+
+#[test]
+fn vec_drop() {
+    use core::cell::Cell;
+
+    struct Counted<'a>(&'a Cell<usize>);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    fn check<S: ResizableStorage>(storage: S) {
+        let drops = Cell::new(0);
+        let mut vec = Vec::new_in(storage);
+        for _ in 0..10 {
+            vec.push(Counted(&drops));
+        }
+
+        drop(vec.try_pop());
+        assert_eq!(drops.get(), 1);
+        vec.clear();
+        assert_eq!(drops.get(), 10);
+        assert!(vec.is_empty());
+
+        vec.push(Counted(&drops));
+        vec.push(Counted(&drops));
+        drop(vec);
+        assert_eq!(drops.get(), 12);
+    }
+
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    check(&mock);
+    assert_eq!(mock.live_allocations(), 0);
+    mock.clear_events();
+
+    check(crate::AllocatorStorage::new(std::alloc::System));
+    check(crate::BumpStorage::<_, 8>::new(&mock, 1024));
+    check(crate::SmallStorage::<128, _>::new(&mock));
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn vec_elements() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut vec = Vec::from_iter_in(0..10_u32, &mock);
+
+    vec.insert(0, 100);
+    vec.insert(11, 200);
+    vec.insert(5, 300);
+    assert_eq!(*vec, [100, 0, 1, 2, 3, 300, 4, 5, 6, 7, 8, 9, 200]);
+
+    assert_eq!(vec.remove(5), 300);
+    assert_eq!(vec.swap_remove(0), 100);
+    assert_eq!(vec.try_remove(11), None);
+    assert_eq!(vec.try_swap_remove(11), None);
+    assert_eq!(*vec, [200, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    vec.retain(|x| x % 3 != 0);
+    assert_eq!(*vec, [200, 1, 2, 4, 5, 7, 8]);
+
+    vec.truncate(3);
+    assert_eq!(*vec, [200, 1, 2]);
+
+    vec.extend([2, 2, 3, 3, 3, 2, 8, 9, 10]);
+    vec.dedup();
+    assert_eq!(*vec, [200, 1, 2, 3, 2, 8, 9, 10]);
+    vec.dedup_by_key(|x| *x / 4);
+    assert_eq!(*vec, [200, 1, 8]);
+}
+
+#[test]
+fn vec_try_insert() {
+    let mut vec = Vec::<u32, _>::new_in(crate::NoOpStorage);
+    assert_eq!(vec.try_insert(0, 1).map_err(AllocErr::defuse), Err(1));
+    assert!(vec.is_empty());
+}
+
+#[test]
+fn vec_retain_panic() {
+    use core::cell::Cell;
+    use std::rc::Rc;
+
+    struct Counted(Rc<Cell<usize>>, u32);
+
+    impl Drop for Counted {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+
+    let mut vec = Vec::new_in(crate::AllocatorStorage::new(std::alloc::System));
+    vec.extend((0..6).map(|i| Counted(drops.clone(), i)));
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        vec.retain(|value| {
+            assert!(value.1 != 3);
+            value.1 % 2 == 0
+        })
+    }));
+    assert!(result.is_err());
+
+    // 1 was removed before the panic, and the rest are still there
+    assert_eq!(drops.get(), 1);
+    assert_eq!(
+        vec.iter().map(|value| value.1).collect::<std::vec::Vec<_>>(),
+        [0, 2, 3, 4, 5]
+    );
+    drop(vec);
+    assert_eq!(drops.get(), 6);
+}
+
+#[test]
+fn vec_drain() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let mut vec = Vec::from_iter_in(0..10_u32, &mock);
+
+    let mut drain = vec.drain(2..6);
+    assert_eq!(drain.len(), 4);
+    assert_eq!(drain.next(), Some(2));
+    assert_eq!(drain.next_back(), Some(5));
+    assert_eq!(drain.as_slice(), [3, 4]);
+    drop(drain);
+    assert_eq!(*vec, [0, 1, 6, 7, 8, 9]);
+
+    assert!(vec.drain(..=1).eq([0, 1]));
+    assert!(vec.drain(4..).eq(None));
+    assert_eq!(*vec, [6, 7, 8, 9]);
+
+    let tail = vec.split_off(1);
+    assert_eq!(*vec, [6]);
+    assert_eq!(*tail, [7, 8, 9]);
+
+    let moved = vec.split_off_in(0, crate::AllocatorStorage::new(std::alloc::System));
+    assert!(vec.is_empty());
+    assert_eq!(*moved, [6]);
+    drop((vec, tail));
+
+    let mut vec = Vec::from_iter_in(0..4_u32, &mock);
+    assert!(vec
+        .try_split_off_in(1, crate::BumpStorage::<_, 4>::new(&mock, 8))
+        .is_err());
+    assert_eq!(*vec, [0, 1, 2, 3]);
+
+    drop(vec);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn vec_drain_panic() {
+    use core::cell::Cell;
+    use std::rc::Rc;
+
+    struct PanicOnDrop(Rc<Cell<usize>>, u32);
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+            assert!(self.1 != 3);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+
+    let mut vec = Vec::new_in(crate::AllocatorStorage::new(std::alloc::System));
+    vec.extend((0..8).map(|i| PanicOnDrop(drops.clone(), i)));
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(vec.drain(1..5))));
+    assert!(result.is_err());
+
+    // the rest of the drained elements were still dropped, and the tail was moved back
+    assert_eq!(drops.get(), 4);
+    assert_eq!(
+        vec.iter().map(|value| value.1).collect::<std::vec::Vec<_>>(),
+        [0, 5, 6, 7]
+    );
+    drop(vec);
+    assert_eq!(drops.get(), 8);
+}
+
+#[test]
+fn vec_boxed_slice() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = Vec::with_capacity_in(16, &mock);
+    vec.extend(0..10_u32);
+    let boxed = vec.into_boxed_slice();
+    assert_eq!(*boxed, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    let mut vec = Box::into_vec(boxed);
+    assert_eq!(vec.capacity(), 10);
+    vec.push(10);
+    let boxed = Box::from(vec);
+    assert_eq!(boxed.len(), 11);
+
+    let vec = Vec::from(boxed);
+    assert_eq!(*vec, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    drop(vec);
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn vec_from_slice() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = Vec::from_slice_in(b"hello", &mock);
+    vec.extend_from_slice(b", world");
+    assert_eq!(*vec, *b"hello, world");
+
+    vec.resize(3, 0);
+    assert_eq!(*vec, *b"hel");
+    vec.resize(5, b'!');
+    assert_eq!(*vec, *b"hel!!");
+
+    let mut strings = Vec::from_slice_in(&[std::string::String::from("a")], &mock);
+    strings.extend_from_slice(&[std::string::String::from("b"), std::string::String::from("c")]);
+    strings.resize(5, std::string::String::from("d"));
+    assert_eq!(*strings, ["a", "b", "c", "d", "d"]);
+
+    let mut vec = Vec::<u32, _>::new_in(crate::BumpStorage::<_, 4>::new(&mock, 16));
+    vec.extend_from_slice(&[1, 2]);
+    assert!(vec.try_extend_from_slice(&[3, 4, 5]).is_err());
+    assert!(vec.try_resize(5, 0).is_err());
+    assert_eq!(*vec, [1, 2]);
+}
+
+#[test]
+fn vec_from_copy_slice() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = Vec::from_copy_slice_in(b"hello", &mock);
+    assert_eq!(vec.len(), 5);
+    vec.extend_from_copy_slice(b", world");
+    assert_eq!(vec.len(), 12);
+    assert_eq!(*vec, *b"hello, world");
+
+    let mut vec = Vec::<u32, _>::new_in(crate::BumpStorage::<_, 4>::new(&mock, 16));
+    vec.extend_from_copy_slice(&[1, 2]);
+    assert!(vec.try_extend_from_copy_slice(&[3, 4, 5]).is_err());
+    assert_eq!(*vec, [1, 2]);
+}
+
+#[test]
+fn vec_extend_from_slice_panic() {
+    struct PanicOnClone(bool);
+
+    impl Clone for PanicOnClone {
+        fn clone(&self) -> Self {
+            assert!(!self.0);
+            Self(self.0)
+        }
+    }
+
+    let mut vec = Vec::new_in(crate::AllocatorStorage::new(std::alloc::System));
+    let slice = [PanicOnClone(false), PanicOnClone(false), PanicOnClone(true)];
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vec.extend_from_slice(&slice)));
+    assert!(result.is_err());
+
+    // the elements that were cloned before the panic are kept
+    assert_eq!(vec.len(), 2);
+}
+
+#[test]
+fn vec_try() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = Vec::<u32, _>::with_capacity_in(2, &mock);
+    assert_eq!(vec.try_push(0), Ok(()));
+    assert_eq!(vec.try_push(1), Ok(()));
+    assert_eq!(vec.try_push(2), Err(2));
+    assert!(vec.try_push_grow(2).is_ok());
+    assert!(vec.try_reserve(usize::MAX).is_err());
+
+    assert!(vec.capacity() > 3);
+    assert!(vec.try_shrink_to(8).is_ok());
+    assert!(vec.try_shrink_to_fit().is_ok());
+    assert_eq!(vec.capacity(), 3);
+    assert_eq!(*vec, [0, 1, 2]);
+    drop(vec);
+
+    let mut vec = Vec::<u32, _>::new_in(crate::BumpStorage::<_, 4>::new(&mock, 16));
+    for i in 0..4 {
+        assert!(vec.try_push_grow(i).is_ok());
+    }
+    assert_eq!(vec.try_push_grow(4).map_err(AllocErr::defuse), Err(4));
+    assert_eq!(*vec, [0, 1, 2, 3]);
+}
+
+#[test]
+fn vec_in_macros() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let empty: Vec<u32, _> = crate::vec_in![; &mock];
+    assert!(empty.is_empty());
+
+    let zeros = crate::vec_in![0_u8; 64; &mock];
+    assert_eq!(*zeros, [0; 64]);
+
+    let vec = crate::vec_in![1, 2, 3,; &mock];
+    assert_eq!(*vec, [1, 2, 3]);
+
+    let single = crate::vec_in![std::string::String::from("a"); &mock];
+    assert_eq!(*single, ["a"]);
+
+    let strings = crate::vec_in![std::string::String::from("b"); 3; &mock];
+    assert_eq!(*strings, ["b", "b", "b"]);
+
+    let boxed = crate::boxed_slice_in![7_u16; 5; &mock];
+    assert_eq!(*boxed, [7; 5]);
+
+    drop((empty, zeros, vec, single, strings, boxed));
+    assert_eq!(mock.live_allocations(), 0);
+}
+
+#[test]
+fn inline_vec() {
+    let mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+
+    let mut vec = InlineVec::<u32, 4, _>::new_inline_in(&mock);
+    assert_eq!(vec.capacity(), 4);
+    vec.extend(0..4);
+    assert!(vec.is_inline());
+    assert_eq!(mock.live_allocations(), 0);
+
+    // the inline elements move with the vector
+    let mut vec = *std::boxed::Box::new(vec);
+    assert_eq!(*vec, [0, 1, 2, 3]);
+
+    vec.push(4);
+    assert!(!vec.is_inline());
+    assert_eq!(mock.live_allocations(), 1);
+    assert_eq!(*vec, [0, 1, 2, 3, 4]);
+
+    vec.retain(|x| x % 2 == 0);
+    assert_eq!(vec.remove(0), 0);
+    assert_eq!(*vec, [2, 4]);
+    drop(vec);
+    assert_eq!(mock.live_allocations(), 0);
 }
@@ -0,0 +1,208 @@
+use core::{alloc::Layout, arch::wasm32, cell::UnsafeCell, num::NonZeroUsize, ptr::NonNull};
+
+use crate::{
+    spin_lock::SpinLock, AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock,
+    OffsetHandle, ResizableStorage, SharedGetMut, SharedOffsetHandle, SharedResizableStorage, SharedStorage, Storage,
+};
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+// allocations are bumped from `top`, and linear memory is grown once `end` is reached
+struct Cursor {
+    top: usize,
+    end: usize,
+}
+
+impl Cursor {
+    fn bump(&mut self, layout: Layout) -> Option<NonEmptyMemoryBlock<NonNull<u8>>> {
+        let mask = layout.align() - 1;
+        let mut start = self.top.checked_add(mask)? & !mask;
+        let mut end = start.checked_add(layout.size())?;
+
+        if end > self.end {
+            self.grow(layout)?;
+            start = self.top.checked_add(mask)? & !mask;
+            end = start.checked_add(layout.size())?;
+        }
+
+        self.top = end;
+
+        Some(NonEmptyMemoryBlock {
+            handle: unsafe { NonNull::new_unchecked(start as *mut u8) },
+            size: unsafe { NonZeroUsize::new_unchecked(end - start) },
+        })
+    }
+
+    fn grow(&mut self, layout: Layout) -> Option<()> {
+        // enough pages for the allocation, even if something else grew memory since the last call
+        let size = layout.size().checked_add(layout.align() - 1)?;
+        let pages = size.div_ceil(PAGE_SIZE);
+        let old_pages = wasm32::memory_grow::<0>(pages);
+
+        if old_pages == usize::MAX {
+            return None
+        }
+
+        let start = old_pages * PAGE_SIZE;
+
+        // the new pages aren't next to the old ones, so the rest of the old ones is lost
+        if start != self.end {
+            self.top = start;
+        }
+
+        self.end = start + pages * PAGE_SIZE;
+        Some(())
+    }
+
+    fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        // only the last allocation can be reclaimed
+        if ptr.as_ptr() as usize + layout.size() == self.top {
+            self.top = ptr.as_ptr() as usize;
+        }
+    }
+}
+
+/// A bump storage over wasm's linear memory, which is grown with `memory.grow` as needed
+///
+/// Only the most recent allocation can be reclaimed, so this is meant for small, short lived
+/// programs, as a `#[global_allocator]` through [`StorageGlobalAlloc`](crate::StorageGlobalAlloc)
+#[must_use = "storages don't do anything unless they are used"]
+pub struct WasmMemoryStorage {
+    cursor: UnsafeCell<Cursor>,
+    lock: SpinLock,
+}
+
+unsafe impl Sync for WasmMemoryStorage {}
+
+impl Default for WasmMemoryStorage {
+    fn default() -> Self { Self::new() }
+}
+
+impl WasmMemoryStorage {
+    pub const fn new() -> Self {
+        Self {
+            cursor: UnsafeCell::new(Cursor { top: 0, end: 0 }),
+            lock: SpinLock::new(),
+        }
+    }
+
+    /// The current size of linear memory, including memory that this storage didn't allocate
+    pub fn size() -> usize { wasm32::memory_size::<0>() * PAGE_SIZE }
+}
+
+unsafe impl FromPtr for WasmMemoryStorage {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, _: Layout) -> Self::Handle { ptr }
+}
+
+unsafe impl OffsetHandle for WasmMemoryStorage {
+    #[inline]
+    unsafe fn offset(&mut self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedOffsetHandle for WasmMemoryStorage {
+    #[inline]
+    unsafe fn shared_offset(&self, handle: Self::Handle, offset: isize) -> Self::Handle {
+        NonNull::new_unchecked(handle.as_ptr().offset(offset))
+    }
+}
+
+unsafe impl SharedGetMut for WasmMemoryStorage {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+}
+
+impl MultiStorage for WasmMemoryStorage {}
+
+unsafe impl Storage for WasmMemoryStorage {
+    type Handle = NonNull<u8>;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { handle }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.cursor
+            .get_mut()
+            .bump(layout.into())
+            .ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.cursor.get_mut().deallocate(handle, layout.into());
+    }
+}
+
+unsafe impl ResizableStorage for WasmMemoryStorage {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl SharedStorage for WasmMemoryStorage {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let _guard = self.lock.lock();
+        unsafe { (*self.cursor.get()).bump(layout.into()) }.ok_or_else(|| AllocErr::new(layout.into()))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        let _guard = self.lock.lock();
+        (*self.cursor.get()).deallocate(handle, layout.into());
+    }
+}
+
+unsafe impl SharedResizableStorage for WasmMemoryStorage {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
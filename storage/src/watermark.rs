@@ -0,0 +1,230 @@
+use core::{
+    alloc::Layout,
+    num::NonZeroUsize,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
+};
+
+/// A storage that keeps track of how many bytes are live, and the most that were live at once
+///
+/// Allocations are counted by the size of their layout, and every memory block reports exactly
+/// that size, so deallocating or shrinking it takes back the same amount.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct WatermarkStorage<S> {
+    storage: S,
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl<S> WatermarkStorage<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of bytes that are currently live
+    pub fn current(&self) -> usize { self.current.load(Ordering::Relaxed) }
+
+    /// The most bytes that were live at once since the storage was created,
+    /// or since the last call to [`reset_peak`](Self::reset_peak)
+    pub fn peak(&self) -> usize { self.peak.load(Ordering::Relaxed) }
+
+    /// Lowers the peak to the number of bytes that are currently live
+    pub fn reset_peak(&self) { self.peak.store(self.current(), Ordering::Relaxed) }
+
+    fn add(&self, size: usize) {
+        let current = self.current.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn sub(&self, size: usize) { self.current.fetch_sub(size, Ordering::Relaxed); }
+
+    fn allocated<H>(&self, layout: NonEmptyLayout, memory_block: NonEmptyMemoryBlock<H>) -> NonEmptyMemoryBlock<H> {
+        self.add(layout.size());
+        NonEmptyMemoryBlock {
+            handle: memory_block.handle,
+            size: unsafe { NonZeroUsize::new_unchecked(layout.size()) },
+        }
+    }
+
+    fn resized<H>(&self, old: Layout, new: Layout, memory_block: MemoryBlock<H>) -> MemoryBlock<H> {
+        if new.size() > old.size() {
+            self.add(new.size() - old.size());
+        } else {
+            self.sub(old.size() - new.size());
+        }
+
+        MemoryBlock {
+            handle: memory_block.handle,
+            size: new.size(),
+        }
+    }
+}
+
+unsafe impl<S: FromPtr> FromPtr for WatermarkStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for WatermarkStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for WatermarkStorage<S> {}
+
+unsafe impl<S: Storage> Storage for WatermarkStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_nonempty(layout)?;
+        Ok(self.allocated(layout, memory_block))
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.deallocate_nonempty(handle, layout);
+        self.sub(layout.size());
+    }
+
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.allocate_nonempty_zeroed(layout)?;
+        Ok(self.allocated(layout, memory_block))
+    }
+}
+
+unsafe impl<S: ResizableStorage> ResizableStorage for WatermarkStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.grow(handle, old, new)?;
+        Ok(self.resized(old, new, memory_block))
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.grow_zeroed(handle, old, new)?;
+        Ok(self.resized(old, new, memory_block))
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shrink(handle, old, new)?;
+        Ok(self.resized(old, new, memory_block))
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for WatermarkStorage<S> {
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_nonempty(layout)?;
+        Ok(self.allocated(layout, memory_block))
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        self.storage.shared_deallocate_nonempty(handle, layout);
+        self.sub(layout.size());
+    }
+
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_allocate_nonempty_zeroed(layout)?;
+        Ok(self.allocated(layout, memory_block))
+    }
+}
+
+unsafe impl<S: SharedResizableStorage> SharedResizableStorage for WatermarkStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_grow(handle, old, new)?;
+        Ok(self.resized(old, new, memory_block))
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_grow_zeroed(handle, old, new)?;
+        Ok(self.resized(old, new, memory_block))
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        let memory_block = self.storage.shared_shrink(handle, old, new)?;
+        Ok(self.resized(old, new, memory_block))
+    }
+}
+
+#[test]
+fn watermark() {
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    let mut storage = WatermarkStorage::new(system);
+
+    let layout = Layout::new::<[u8; 32]>();
+    let a = storage.allocate(layout).unwrap();
+    let b = storage.allocate(layout).unwrap();
+    assert_eq!(storage.peak(), 64);
+
+    unsafe {
+        let a = storage.grow(a.handle, layout, Layout::new::<[u8; 48]>()).unwrap();
+        assert_eq!(storage.current(), 80);
+
+        storage.deallocate(b.handle, layout);
+        assert_eq!(storage.current(), 48);
+        // the peak survives the free
+        assert_eq!(storage.peak(), 80);
+
+        storage.reset_peak();
+        assert_eq!(storage.peak(), 48);
+
+        storage.deallocate(a.handle, Layout::new::<[u8; 48]>());
+    }
+
+    assert_eq!(storage.current(), 0);
+    assert_eq!(storage.peak(), 48);
+
+    crate::storage_conformance!(WatermarkStorage::new(system), resizable, shared, shared_resizable);
+}
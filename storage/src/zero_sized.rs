@@ -1,8 +1,8 @@
 use core::{alloc::Layout, marker::PhantomData, ptr::NonNull};
 
 use crate::{
-    AllocErr, FromPtr, Handle, MemoryBlock, ResizableStorage, SharedGetMut, SharedResizableStorage, SharedStorage,
-    Storage,
+    AllocErr, FromPtr, Handle, MemoryBlock, MultiStorage, ResizableStorage, SharedGetMut, SharedResizableStorage,
+    SharedStorage, Storage,
 };
 
 const MAX_ALIGN: usize = 1 << 29;
@@ -25,6 +25,8 @@ unsafe impl<H: Handle> SharedGetMut for ZeroSizedStorage<H> {
     unsafe fn shared_get_mut(&self, _: Self::Handle) -> NonNull<u8> { DANGLING }
 }
 
+impl<H: Handle> MultiStorage for ZeroSizedStorage<H> {}
+
 unsafe impl<H: Handle> Storage for ZeroSizedStorage<H> {
     type Handle = H;
 
@@ -177,3 +179,54 @@ unsafe impl<H: Handle> SharedResizableStorage for ZeroSizedStorage<H> {
         self.shared_allocate(new)
     }
 }
+
+#[test]
+fn zero_sized() {
+    let mut storage = ZeroSizedStorage::<crate::BumpHandle>::new();
+    assert!(storage.allocate(Layout::new::<u8>()).is_err());
+
+    let memory_block = storage.allocate(Layout::new::<[u64; 0]>()).unwrap();
+    assert_eq!(memory_block.size, 0);
+    unsafe { storage.deallocate(memory_block.handle, Layout::new::<[u64; 0]>()) }
+
+    crate::storage_conformance!(
+        ZeroSizedStorage::<crate::BumpHandle>::new(),
+        resizable,
+        shared,
+        shared_resizable
+    );
+}
+
+#[test]
+fn zero_sized_combinators() {
+    type Zst = ZeroSizedStorage<NonNull<u8>>;
+
+    crate::storage_conformance!(Zst::new(), zero_sized);
+    crate::storage_conformance!(&Zst::new(), zero_sized);
+    crate::storage_conformance!(crate::Pad::<_, 0, 8>::new(Zst::new()), zero_sized);
+    crate::storage_conformance!(crate::ZeroizeStorage::new(Zst::new()), zero_sized);
+    crate::storage_conformance!(crate::PoisonStorage::new(Zst::new()), zero_sized);
+    crate::storage_conformance!(crate::DeferredFreeStorage::new(Zst::new()), zero_sized);
+    crate::storage_conformance!(crate::QuarantineStorage::<_, 64>::new(Zst::new()), zero_sized);
+
+    // the defaults don't touch the underlying storage for zero-sized layouts
+    let mut mock = crate::MockStorage::new(crate::AllocatorStorage::new(std::alloc::System));
+    let layout = Layout::new::<[u32; 4]>();
+
+    {
+        let mut storage = crate::ZeroizeStorage::new(&mock);
+        let memory_block = storage.allocate(layout).unwrap();
+
+        unsafe {
+            let memory_block = storage
+                .shrink(memory_block.handle, layout, Layout::new::<()>())
+                .unwrap();
+            let memory_block = storage
+                .grow(memory_block.handle, Layout::new::<()>(), Layout::new::<()>())
+                .unwrap();
+            storage.deallocate(memory_block.handle, Layout::new::<()>());
+        }
+    }
+
+    mock.assert_events(&[crate::Event::Allocate(layout), crate::Event::Deallocate(layout)]);
+}
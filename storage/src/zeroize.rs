@@ -0,0 +1,164 @@
+use core::{alloc::Layout, intrinsics, ptr::NonNull};
+
+use crate::{
+    AllocErr, FromPtr, MemoryBlock, MultiStorage, NonEmptyLayout, NonEmptyMemoryBlock, ResizableStorage, SharedGetMut,
+    SharedResizableStorage, SharedStorage, Storage,
+};
+
+// volatile, so the writes can't be optimized out even though the memory is never read again
+unsafe fn scrub(ptr: NonNull<u8>, size: usize) { intrinsics::volatile_set_memory(ptr.as_ptr(), 0, size) }
+
+/// A storage that overwrites every block with zeros before it is freed
+///
+/// Resizing always moves the allocation, so that the old block can be scrubbed
+/// before it goes back to the underlying storage.
+#[must_use = "storages don't do anything unless they are used"]
+pub struct ZeroizeStorage<S> {
+    storage: S,
+}
+
+impl<S> ZeroizeStorage<S> {
+    pub const fn new(storage: S) -> Self { Self { storage } }
+}
+
+unsafe impl<S: FromPtr> FromPtr for ZeroizeStorage<S> {
+    #[inline]
+    unsafe fn from_ptr(&self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle { self.storage.from_ptr(ptr, layout) }
+
+    #[inline]
+    unsafe fn from_ptr_mut(&mut self, ptr: NonNull<u8>, layout: Layout) -> Self::Handle {
+        self.storage.from_ptr_mut(ptr, layout)
+    }
+}
+
+unsafe impl<S: SharedGetMut> SharedGetMut for ZeroizeStorage<S> {
+    #[inline]
+    unsafe fn shared_get_mut(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.shared_get_mut(handle) }
+}
+
+impl<S: MultiStorage> MultiStorage for ZeroizeStorage<S> {}
+
+unsafe impl<S: Storage> Storage for ZeroizeStorage<S> {
+    type Handle = S::Handle;
+
+    #[inline]
+    unsafe fn get(&self, handle: Self::Handle) -> NonNull<u8> { self.storage.get(handle) }
+
+    #[inline]
+    unsafe fn get_mut(&mut self, handle: Self::Handle) -> NonNull<u8> { self.storage.get_mut(handle) }
+
+    #[inline]
+    fn allocate_nonempty(&mut self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.allocate_nonempty(layout)
+    }
+
+    unsafe fn deallocate_nonempty(&mut self, handle: Self::Handle, layout: NonEmptyLayout) {
+        scrub(self.storage.get_mut(handle), layout.size());
+        self.storage.deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn allocate_nonempty_zeroed(
+        &mut self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<S: MultiStorage> ResizableStorage for ZeroizeStorage<S> {
+    unsafe fn grow(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn grow_zeroed(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shrink(
+        &mut self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        // the vacated tail is scrubbed along with the rest of the old block
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+unsafe impl<S: SharedStorage> SharedStorage for ZeroizeStorage<S> {
+    #[inline]
+    fn shared_allocate_nonempty(&self, layout: NonEmptyLayout) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_allocate_nonempty(layout)
+    }
+
+    unsafe fn shared_deallocate_nonempty(&self, handle: Self::Handle, layout: NonEmptyLayout) {
+        scrub(self.storage.shared_get_mut(handle), layout.size());
+        self.storage.shared_deallocate_nonempty(handle, layout);
+    }
+
+    #[inline]
+    fn shared_allocate_nonempty_zeroed(
+        &self,
+        layout: NonEmptyLayout,
+    ) -> Result<NonEmptyMemoryBlock<Self::Handle>, AllocErr> {
+        self.storage.shared_allocate_nonempty_zeroed(layout)
+    }
+}
+
+unsafe impl<S: SharedStorage + MultiStorage> SharedResizableStorage for ZeroizeStorage<S> {
+    unsafe fn shared_grow(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow(self, handle, old, new)
+    }
+
+    unsafe fn shared_grow_zeroed(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::grow_zeroed(self, handle, old, new)
+    }
+
+    unsafe fn shared_shrink(
+        &self,
+        handle: Self::Handle,
+        old: Layout,
+        new: Layout,
+    ) -> Result<MemoryBlock<Self::Handle>, AllocErr> {
+        crate::defaults::shrink(self, handle, old, new)
+    }
+}
+
+#[test]
+fn zeroize() {
+    let mut storage = ZeroizeStorage::new(crate::SingleStackStorage::<[u64; 4]>::new());
+
+    let layout = Layout::new::<[u64; 4]>();
+    storage.allocate(layout).unwrap();
+
+    unsafe {
+        let ptr = storage.get_mut(()).cast::<[u64; 4]>().as_ptr();
+        ptr.write([u64::MAX; 4]);
+        storage.deallocate((), layout);
+        assert_eq!(*ptr, [0; 4]);
+    }
+
+    let system = crate::AllocatorStorage::new(std::alloc::System);
+    crate::storage_conformance!(ZeroizeStorage::new(system), resizable, shared, shared_resizable);
+}